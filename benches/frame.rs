@@ -0,0 +1,76 @@
+// how long the overlay's frames take to generate on a 4K output, the first frame has to be done
+// well within 50 ms -- run with `cargo bench --bench frame`
+//
+// the daemon is a binary, so the modules that draw the overlay are included directly
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use wayland_client::protocol::{wl_output::Transform, wl_shm::Format};
+
+#[allow(dead_code)]
+#[path = "../src/config.rs"]
+mod config;
+#[allow(dead_code)]
+#[path = "../src/fonts.rs"]
+mod fonts;
+#[allow(dead_code)]
+#[path = "../src/layout.rs"]
+mod layout;
+#[allow(dead_code)]
+#[path = "../src/palette.rs"]
+mod palette;
+#[allow(dead_code)]
+#[path = "../src/render.rs"]
+mod render;
+#[allow(dead_code)]
+#[path = "../src/resources.rs"]
+mod resources;
+
+use layout::Layout;
+use render::Canvas;
+
+const WIDTH: u32 = 3840;
+const HEIGHT: u32 = 2160;
+const BREAK_DURATION: u64 = 300;
+
+fn frames(c: &mut Criterion) {
+    // the configuration of the user running the benchmark, e.g. to include their icon
+    let config = config::load_configuration().expect("The configuration could not be loaded!");
+    let mut layout = Layout::new(&config, true);
+
+    c.bench_function("first frame", |b| {
+        b.iter(|| {
+            let mut canvas = Canvas::new(WIDTH, HEIGHT, 0xFF000000);
+            layout.render(&mut canvas, black_box(BREAK_DURATION));
+            canvas.dim(0.1);
+            canvas.region_bytes(Format::Xrgb8888, Transform::Normal, canvas.full_rect())
+        })
+    });
+
+    c.bench_function("first frame, rotated output", |b| {
+        b.iter(|| {
+            let mut canvas = Canvas::new(WIDTH, HEIGHT, 0xFF000000);
+            layout.render(&mut canvas, black_box(BREAK_DURATION));
+            canvas.dim(0.1);
+            canvas.region_bytes(Format::Xrgb8888, Transform::_90, canvas.full_rect())
+        })
+    });
+
+    // only the countdown changes from one second to the next
+    let mut canvas = Canvas::new(WIDTH, HEIGHT, 0xFF000000);
+    layout.render(&mut canvas, BREAK_DURATION);
+    let mut remaining = BREAK_DURATION;
+    c.bench_function("countdown frame", |b| {
+        b.iter(|| {
+            let previous = canvas.pixels().to_vec();
+            remaining = remaining.checked_sub(1).unwrap_or(BREAK_DURATION);
+            layout.render(&mut canvas, black_box(remaining));
+            canvas
+                .changed_rect(&previous)
+                .map(|rect| canvas.region_bytes(Format::Xrgb8888, Transform::Normal, rect))
+        })
+    });
+}
+
+criterion_group!(benches, frames);
+criterion_main!(benches);
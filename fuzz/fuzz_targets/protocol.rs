@@ -0,0 +1,25 @@
+// feeds arbitrary datagrams to the daemon's parser, everything a message reaches before the
+// scheduler acts on it: the request id, the batch and the durations of its commands
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wlbreaktime::protocol::{self, MAX_DURATION_SECONDS};
+
+fuzz_target!(|bytes: &[u8]| {
+    let Ok((_, commands)) = protocol::parse_request(bytes) else {
+        return;
+    };
+    for command in commands {
+        let argument = command.split_once(' ').map_or("", |(_, argument)| argument);
+        // the daemon adds the durations to its clocks, so they must stay within bounds
+        for seconds in [
+            protocol::parse_duration(argument),
+            protocol::parse_seconds(argument),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            assert!(seconds <= MAX_DURATION_SECONDS);
+        }
+    }
+});
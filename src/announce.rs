@@ -0,0 +1,58 @@
+// announcements for screen reader users
+//
+// screen readers like Orca read out incoming notifications, so every phase change is sent as an
+// urgent, transient notification -- it's shown even when the notification daemon is set to do not
+// disturb and it doesn't clutter the notification history
+#[cfg(feature = "notifications")]
+use notify_rust::{Hint, Urgency};
+use wlbreaktime::duration::Seconds;
+
+#[cfg(feature = "notifications")]
+const CATEGORY: &str = "x-wlbreaktime.announcement";
+
+#[cfg(feature = "notifications")]
+pub fn announce(message: &str) {
+    if !crate::capabilities::get().notifications {
+        println!("[announce]: {message}");
+        return;
+    }
+
+    // the configured urgency doesn't apply, announcements are always urgent
+    let result = crate::notify::notification()
+        .summary(message)
+        .urgency(Urgency::Critical)
+        .hint(Hint::Category(CATEGORY.to_string()))
+        .hint(Hint::Transient(true))
+        // the break chime is already playing, if enabled
+        .hint(Hint::SuppressSound(true))
+        .show();
+
+    if let Err(err) = result {
+        println!("The announcement '{message}' could not be sent! The error: {err}");
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn announce(message: &str) {
+    println!("[announce]: {message}");
+}
+
+/*
+ * formats seconds the way they should be read out, e.g. "1 minute and 20 seconds"
+ */
+pub fn spoken_duration(duration: Seconds) -> String {
+    let plural = |count: u64, unit: &str| {
+        if count == 1 {
+            format!("{count} {unit}")
+        } else {
+            format!("{count} {unit}s")
+        }
+    };
+
+    let (minutes, seconds) = duration.split();
+    match (minutes.as_minutes(), seconds) {
+        (0, seconds) => plural(seconds, "second"),
+        (minutes, 0) => plural(minutes, "minute"),
+        (minutes, seconds) => plural(minutes, "minute") + " and " + &plural(seconds, "second"),
+    }
+}
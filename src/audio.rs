@@ -0,0 +1,351 @@
+use std::{
+    cell::RefCell,
+    f32::consts::TAU,
+    path::PathBuf,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rodio::{
+    DeviceTrait, OutputStream, OutputStreamHandle, Sink, Source,
+    cpal::{self, traits::HostTrait},
+};
+
+use crate::resources;
+
+const CHIME_SAMPLE_RATE: u32 = 44100;
+// two descending tones (E5 and C5), each fading out
+const CHIME_FREQUENCIES: [f32; 2] = [659.25, 523.25];
+const CHIME_TONE_SECONDS: f32 = 0.6;
+const CHIME_DECAY: f32 = 6.0;
+const CHIME_VOLUME: f32 = 0.4;
+// two short rising tones (C5 and E5), quieter than the break sound
+const CUE_FREQUENCIES: [f32; 2] = [523.25, 659.25];
+const CUE_TONE_SECONDS: f32 = 0.25;
+const CUE_VOLUME: f32 = 0.15;
+// the longest wait for the sounds to be played out, e.g. before the daemon exits
+pub(crate) const PLAYED_TIMEOUT: Duration = Duration::from_secs(5);
+const PLAYED_POLL_INTERVAL: Duration = Duration::from_millis(20);
+// the theme's tick is played for each of the last seconds of a break
+const TICK_SECONDS: u64 = 5;
+
+// rodio's stream can't be sent to other threads, so the output lives on the daemon's main thread
+// -- set once at startup, if an output is available
+thread_local! {
+    static OUTPUT: RefCell<Option<AudioOutput>> = const { RefCell::new(None) };
+}
+// the handle of the main thread's stream, the other threads play on it (e.g. the wayland thread,
+// which fires the timers during a break)
+static SHARED_HANDLE: Mutex<Option<OutputStreamHandle>> = Mutex::new(None);
+// every sound gets a sink of its own, which stops the sound when it's dropped -- so they are kept
+// until they are played out
+static PLAYING: Mutex<Vec<Sink>> = Mutex::new(Vec::new());
+// set once at startup, without it the built-in sounds are played
+static THEME: OnceLock<PathBuf> = OnceLock::new();
+// whether the current break is counted down with ticks, i.e. sounds are played during it
+static TICKING: AtomicBool = AtomicBool::new(false);
+
+/*
+ * what a sound stands for
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Cue {
+    BreakStart,
+    BreakEnd,
+    // the notification announcing the break is shown
+    PreBreak,
+    // one of the last seconds of a break, only if the theme has a tick
+    Tick,
+    // a timer started through the helper is up
+    Timer,
+}
+
+impl Cue {
+    /*
+     * the name of the cue's file in a sound theme, timers always get the chime
+     */
+    fn file_name(self) -> Option<&'static str> {
+        match self {
+            Cue::PreBreak => Some("pre_break"),
+            Cue::BreakStart => Some("break_start"),
+            Cue::BreakEnd => Some("break_end"),
+            Cue::Tick => Some("tick"),
+            Cue::Timer => None,
+        }
+    }
+}
+
+pub(crate) fn set_output(output: AudioOutput) {
+    OUTPUT.with_borrow_mut(|current| *current = Some(output));
+}
+
+pub(crate) fn set_theme(theme: &Option<String>) {
+    if let Some(theme) = theme
+        && THEME.set(PathBuf::from(theme)).is_err()
+    {
+        println!("The sound theme was already set!");
+    }
+}
+
+/*
+ * set for every break, ticks are only played while sounds are
+ */
+pub(crate) fn set_ticking(ticking: bool) {
+    TICKING.store(ticking, Ordering::Relaxed);
+}
+
+/*
+ * plays the theme's tick for the last seconds of a break
+ */
+pub(crate) fn tick(remaining_seconds: u64) {
+    if TICKING.load(Ordering::Relaxed)
+        && remaining_seconds <= TICK_SECONDS
+        && let Err(err) = play(Cue::Tick)
+    {
+        println!("The tick could not be played! The error: {err}");
+    }
+}
+
+/*
+ * plays the cue on the output, if there is one
+ */
+pub(crate) fn play(cue: Cue) -> Result<(), Box<dyn std::error::Error>> {
+    let played = OUTPUT.with_borrow_mut(|output| output.as_mut().map(|output| output.play(cue)));
+    match played {
+        Some(result) => result,
+        // not on the main thread, its stream isn't reopened from here
+        None => match SHARED_HANDLE.lock().unwrap().as_ref() {
+            Some(stream_handle) => play_on(stream_handle, cue),
+            None => Ok(()),
+        },
+    }
+}
+
+/*
+ * waits until every sound is played out, but at most for the timeout -- e.g. so the chime at the
+ * end of a break isn't cut off by the monitors waking up or by the daemon exiting
+ */
+pub(crate) fn wait_until_played(timeout: Duration) {
+    let started = Instant::now();
+    loop {
+        {
+            let mut playing = PLAYING.lock().unwrap();
+            playing.retain(|sink| !sink.empty());
+            if playing.is_empty() {
+                return;
+            }
+        }
+        if started.elapsed() >= timeout {
+            println!("The sounds were not played out within {timeout:?}, they are cut off.");
+            return;
+        }
+        thread::sleep(PLAYED_POLL_INTERVAL);
+    }
+}
+
+/*
+ * the output the sounds are played on -- if a device was configured but is not available (e.g. a
+ * Bluetooth headset that is turned off), the default output is used until the device appears
+ */
+pub(crate) struct AudioOutput {
+    device_name: Option<String>,
+    // the stream needs to be kept alive for the handle to work
+    stream: Option<(OutputStream, OutputStreamHandle)>,
+    on_chosen_device: bool,
+}
+
+impl AudioOutput {
+    pub(crate) fn new(device_name: Option<String>) -> AudioOutput {
+        let mut output = AudioOutput {
+            device_name,
+            stream: None,
+            on_chosen_device: false,
+        };
+        output.open();
+        output
+    }
+
+    pub(crate) fn available(&mut self) -> bool {
+        self.handle().is_some()
+    }
+
+    /*
+     * returns the handle of the chosen device if it's available, otherwise the default output's
+     */
+    fn handle(&mut self) -> Option<&OutputStreamHandle> {
+        if self.stream.is_none() || (self.device_name.is_some() && !self.on_chosen_device) {
+            self.open();
+        }
+        self.stream.as_ref().map(|(_, handle)| handle)
+    }
+
+    /*
+     * fails if no device is available, e.g. after the headphones were unplugged
+     */
+    pub(crate) fn play(&mut self, cue: Cue) -> Result<(), Box<dyn std::error::Error>> {
+        match self.handle() {
+            Some(stream_handle) => play_on(stream_handle, cue),
+            None => Err("no audio device is available".into()),
+        }
+    }
+
+    fn open(&mut self) {
+        if let Some(name) = &self.device_name {
+            match find_device(name) {
+                Some(device) => match OutputStream::try_from_device(&device) {
+                    Ok(stream) => {
+                        println!("Playing sounds on the audio device '{name}'.");
+                        *SHARED_HANDLE.lock().unwrap() = Some(stream.1.clone());
+                        self.stream = Some(stream);
+                        self.on_chosen_device = true;
+                        return;
+                    }
+                    Err(err) => println!("The audio device '{name}' could not be opened: {err}"),
+                },
+                None => println!("The audio device '{name}' is not available."),
+            }
+        }
+
+        // a working default stream is kept until the chosen device appears
+        if self.stream.is_none() {
+            match OutputStream::try_default() {
+                Ok(stream) => {
+                    *SHARED_HANDLE.lock().unwrap() = Some(stream.1.clone());
+                    self.stream = Some(stream);
+                }
+                Err(err) => println!("The default audio device could not be opened: {err}"),
+            }
+        }
+    }
+}
+
+fn play_on(stream_handle: &OutputStreamHandle, cue: Cue) -> Result<(), Box<dyn std::error::Error>> {
+    let sink = Sink::try_new(stream_handle)?;
+    // the sounds are decoded once and shared with every playback
+    let themed = THEME
+        .get()
+        .zip(cue.file_name())
+        .and_then(|(theme, name)| resources::theme_file(theme, name));
+    match themed.map(|path| resources::sound(Some(&path))) {
+        Some(Ok(sound)) => sink.append(sound),
+        Some(Err(err)) => {
+            println!(
+                "The theme's sound could not be decoded, playing the built-in one instead! The error: {err}"
+            );
+            append_built_in(&sink, cue);
+        }
+        None => append_built_in(&sink, cue),
+    }
+
+    let mut playing = PLAYING.lock().unwrap();
+    playing.retain(|sink| !sink.empty());
+    playing.push(sink);
+    Ok(())
+}
+
+/*
+ * the built-in theme: the gong when the break starts and ends, chimes for everything else and no
+ * tick
+ */
+fn append_built_in(sink: &Sink, cue: Cue) {
+    match cue {
+        Cue::PreBreak => sink.append(Chime::pre_break()),
+        Cue::Timer => sink.append(Chime::new()),
+        Cue::Tick => {}
+        Cue::BreakStart | Cue::BreakEnd => match resources::sound(None) {
+            Ok(sound) => sink.append(sound),
+            Err(err) => {
+                println!(
+                    "The sound could not be decoded, playing a chime instead! The error: {err}"
+                );
+                sink.append(Chime::new());
+            }
+        },
+    }
+}
+
+fn find_device(name: &str) -> Option<cpal::Device> {
+    let host = cpal::default_host();
+    let mut devices = match host.output_devices() {
+        Ok(devices) => devices,
+        Err(err) => {
+            println!("The audio devices could not be enumerated! The error: {err}");
+            return None;
+        }
+    };
+    devices.find(|device| device.name().is_ok_and(|device_name| device_name == name))
+}
+
+/*
+ * a synthesized chime, used for timers, when no sound asset can be played and as the pre-break
+ * cue
+ */
+pub(crate) struct Chime {
+    frequencies: &'static [f32],
+    tone_seconds: f32,
+    volume: f32,
+    sample: u32,
+}
+
+impl Chime {
+    pub(crate) fn new() -> Chime {
+        Chime {
+            frequencies: &CHIME_FREQUENCIES,
+            tone_seconds: CHIME_TONE_SECONDS,
+            volume: CHIME_VOLUME,
+            sample: 0,
+        }
+    }
+
+    pub(crate) fn pre_break() -> Chime {
+        Chime {
+            frequencies: &CUE_FREQUENCIES,
+            tone_seconds: CUE_TONE_SECONDS,
+            volume: CUE_VOLUME,
+            sample: 0,
+        }
+    }
+
+    fn seconds(&self) -> f32 {
+        self.frequencies.len() as f32 * self.tone_seconds
+    }
+}
+
+impl Iterator for Chime {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.sample >= (self.seconds() * CHIME_SAMPLE_RATE as f32) as u32 {
+            return None;
+        }
+
+        let time = self.sample as f32 / CHIME_SAMPLE_RATE as f32;
+        let tone = ((time / self.tone_seconds) as usize).min(self.frequencies.len() - 1);
+        let tone_time = time - tone as f32 * self.tone_seconds;
+        let envelope = (-CHIME_DECAY * tone_time).exp();
+        self.sample += 1;
+        Some((TAU * self.frequencies[tone] * tone_time).sin() * envelope * self.volume)
+    }
+}
+
+impl Source for Chime {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        CHIME_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.seconds()))
+    }
+}
@@ -0,0 +1,307 @@
+// command batches, e.g. "skip && set 45"
+//
+// the commands of a batch are run in order, so commands that are left when one of them ends the
+// phase are run by the next phase -- every batched command gets its own response, so the sender
+// knows how each of them went
+use std::{collections::VecDeque, io, os::unix::net::UnixDatagram, path::PathBuf, sync::Mutex};
+
+use wlbreaktime::{datagram, protocol};
+
+static PENDING: Mutex<VecDeque<Request>> = Mutex::new(VecDeque::new());
+
+pub(crate) struct Request {
+    pub(crate) id: u32,
+    pub(crate) path: PathBuf,
+    // the sender's user as told by the kernel
+    pub(crate) uid: Option<u32>,
+    pub(crate) command: String,
+    // commands that don't respond on their own are acknowledged when they are part of a batch, or
+    // when the sender asks for it
+    pub(crate) acknowledged: bool,
+}
+
+/*
+ * the next command, either left over from a batch or read from the socket
+ * None if the message was dropped, e.g. because it had no request id
+ */
+pub(crate) fn receive(socket: &UnixDatagram, phase: &str) -> io::Result<Option<Request>> {
+    if let Some(request) = PENDING.lock().unwrap().pop_front() {
+        return Ok(Some(request));
+    }
+
+    let datagram = datagram::receive(socket, protocol::MAX_REQUEST_SIZE)?;
+    // not every command needs a response, however it simplifies things if unbound sockets are not
+    // accepted -- a misbehaving client must not take the daemon down with it, so such messages are
+    // only dropped
+    let Some(path) = datagram.address.as_pathname() else {
+        println!("[{phase}]: Ignoring a message from an unbound socket, it can't be answered.");
+        return Ok(None);
+    };
+    let path = path.to_path_buf();
+    if datagram.truncated() {
+        println!(
+            "[{phase}]: Ignoring a message of {} bytes from {path:?}, requests have at most {} bytes.",
+            datagram.size,
+            protocol::MAX_REQUEST_SIZE
+        );
+        // answered, so the requester doesn't wait in vain -- the id is at the start
+        if let Some((id, _)) = protocol::split_id(&String::from_utf8_lossy(&datagram.bytes))
+            && let Err(err) = datagram::send_response(socket, id, "request too long", &path)
+        {
+            println!("[{phase}]: The refusal could not be sent to {path:?}! The error: {err}");
+        }
+        return Ok(None);
+    }
+    let (id, commands) = match protocol::parse_request(&datagram.bytes) {
+        Ok(request) => request,
+        Err(reason) => {
+            println!("[{phase}]: Ignoring {reason} from {path:?}.");
+            return Ok(None);
+        }
+    };
+
+    let acknowledged = commands.len() > 1 || protocol::wants_acknowledgment(&datagram.bytes);
+    let mut pending = PENDING.lock().unwrap();
+    pending.extend(commands.into_iter().map(|command| Request {
+        id,
+        path: path.clone(),
+        uid: datagram.uid,
+        command: command.to_string(),
+        acknowledged,
+    }));
+    Ok(pending.pop_front())
+}
+
+/*
+ * true if commands of a batch are left, they have to be run before reading the socket again
+ */
+pub(crate) fn pending() -> bool {
+    !PENDING.lock().unwrap().is_empty()
+}
+
+/*
+ * puts the request back in front of the pending ones, the next phase runs it first
+ */
+pub(crate) fn requeue(request: Request) {
+    PENDING.lock().unwrap().push_front(request);
+}
+
+/*
+ * answers the request on the socket it came from -- a client that is gone by now (e.g. a helper
+ * that timed out) doesn't concern the others, so failures are only logged
+ */
+pub(crate) fn respond(socket: &UnixDatagram, request: &Request, payload: &str) {
+    if let Err(err) = datagram::send_response(socket, request.id, payload, &request.path) {
+        println!(
+            "The response to '{}' could not be sent to {:?}! The error: {err}",
+            request.command, request.path
+        );
+    }
+}
+
+pub(crate) fn acknowledge(socket: &UnixDatagram, request: &Request, result: &str) {
+    if request.acknowledged {
+        respond(socket, request, result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    // the pending commands are global, so the tests must not run at the same time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /*
+     * a fresh directory for the sockets of a single test
+     */
+    fn socket_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("wlbreaktime-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn bind(path: &Path) -> UnixDatagram {
+        let socket = UnixDatagram::bind(path).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        socket
+    }
+
+    fn read(socket: &UnixDatagram) -> String {
+        let datagram = datagram::receive(socket, usize::MAX).unwrap();
+        String::from_utf8(datagram.bytes).unwrap()
+    }
+
+    /*
+     * answers the given number of commands with the command itself, like `get` or `status` are
+     * answered by the daemon
+     */
+    fn serve(daemon: &UnixDatagram, count: usize) {
+        for _ in 0..count {
+            let request = receive(daemon, "test").unwrap().unwrap();
+            respond(daemon, &request, &request.command);
+        }
+    }
+
+    #[test]
+    fn concurrent_clients_get_their_own_responses() {
+        let _serial = SERIAL.lock().unwrap();
+        let dir = socket_dir("concurrent");
+        let daemon_path = dir.join("daemon.socket");
+        let daemon = bind(&daemon_path);
+
+        let clients: Vec<_> = (1..=8)
+            .map(|id| {
+                let dir = dir.clone();
+                let daemon_path = daemon_path.clone();
+                thread::spawn(move || {
+                    let client = bind(&dir.join(format!("client-{id}.socket")));
+                    let command = format!("get {id}");
+                    client
+                        .send_to(protocol::request(id, &command).as_bytes(), &daemon_path)
+                        .unwrap();
+                    assert_eq!(read(&client), protocol::response(id, &command));
+                })
+            })
+            .collect();
+        serve(&daemon, clients.len());
+        for client in clients {
+            client.join().unwrap();
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn batches_are_not_mixed_with_other_clients() {
+        let _serial = SERIAL.lock().unwrap();
+        let dir = socket_dir("batches");
+        let daemon_path = dir.join("daemon.socket");
+        let daemon = bind(&daemon_path);
+        let first = bind(&dir.join("first.socket"));
+        let second = bind(&dir.join("second.socket"));
+
+        // both requests are waiting before the daemon reads any of them
+        first
+            .send_to(
+                protocol::request(1, "skip && status").as_bytes(),
+                &daemon_path,
+            )
+            .unwrap();
+        second
+            .send_to(protocol::request(2, "get").as_bytes(), &daemon_path)
+            .unwrap();
+        serve(&daemon, 3);
+
+        assert_eq!(read(&first), "1 skip");
+        assert_eq!(read(&first), "1 status");
+        assert_eq!(read(&second), "2 get");
+        assert!(!pending());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    proptest::proptest! {
+        // batches of any length and content are run in order, every command answered on its own
+        #[test]
+        fn batches_are_run_in_order(commands in proptest::collection::vec("[a-z0-9]{1,8}( [a-z0-9]{1,8})?", 1..10)) {
+            let _serial = SERIAL.lock().unwrap();
+            let dir = socket_dir("order");
+            let daemon_path = dir.join("daemon.socket");
+            let daemon = bind(&daemon_path);
+            let client = bind(&dir.join("client.socket"));
+
+            let batch = commands.join(protocol::BATCH_SEPARATOR);
+            client
+                .send_to(protocol::request(7, &batch).as_bytes(), &daemon_path)
+                .unwrap();
+            serve(&daemon, commands.len());
+            for command in &commands {
+                proptest::prop_assert_eq!(read(&client), protocol::response(7, command));
+            }
+            proptest::prop_assert!(!pending());
+
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn misbehaving_clients_are_ignored() {
+        let _serial = SERIAL.lock().unwrap();
+        let dir = socket_dir("misbehaving");
+        let daemon_path = dir.join("daemon.socket");
+        let daemon = bind(&daemon_path);
+        let client = bind(&dir.join("client.socket"));
+
+        // neither can be answered, so they are dropped instead of taking the daemon down
+        let unbound = UnixDatagram::unbound().unwrap();
+        unbound
+            .send_to(protocol::request(1, "get").as_bytes(), &daemon_path)
+            .unwrap();
+        assert!(receive(&daemon, "test").unwrap().is_none());
+        client.send_to(&[], &daemon_path).unwrap();
+        assert!(receive(&daemon, "test").unwrap().is_none());
+
+        // a client that is gone before its response is sent doesn't concern the others
+        let gone_path = dir.join("gone.socket");
+        let gone = bind(&gone_path);
+        gone.send_to(protocol::request(2, "get").as_bytes(), &daemon_path)
+            .unwrap();
+        drop(gone);
+        fs::remove_file(gone_path).unwrap();
+        client
+            .send_to(protocol::request(3, "status").as_bytes(), &daemon_path)
+            .unwrap();
+        serve(&daemon, 2);
+        assert_eq!(read(&client), "3 status");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn long_messages_are_not_cut_off() {
+        let _serial = SERIAL.lock().unwrap();
+        let dir = socket_dir("long");
+        let daemon_path = dir.join("daemon.socket");
+        let daemon = bind(&daemon_path);
+        let client = bind(&dir.join("client.socket"));
+
+        let too_long = format!("get {}", "x".repeat(protocol::MAX_REQUEST_SIZE));
+        client
+            .send_to(protocol::request(4, &too_long).as_bytes(), &daemon_path)
+            .unwrap();
+        assert!(receive(&daemon, "test").unwrap().is_none());
+        assert_eq!(read(&client), "4 request too long");
+
+        // multibyte characters across the chunks' borders
+        let payload = "äbc".repeat(protocol::CHUNK_SIZE);
+        client
+            .send_to(protocol::request(5, "stats").as_bytes(), &daemon_path)
+            .unwrap();
+        let request = receive(&daemon, "test").unwrap().unwrap();
+        respond(&daemon, &request, &payload);
+        let mut received = String::new();
+        loop {
+            let chunk = read(&client);
+            let (id, more, part) = protocol::split_chunk(&chunk).unwrap();
+            assert_eq!(id, 5);
+            received.push_str(part);
+            if !more {
+                break;
+            }
+        }
+        assert_eq!(received, payload);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
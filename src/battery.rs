@@ -0,0 +1,38 @@
+// the battery level, read from UPower's display device which combines all batteries
+#[cfg(feature = "dbus")]
+use zbus::blocking::{Connection, Proxy};
+
+// UPower's state while the batteries are discharging
+#[cfg(feature = "dbus")]
+const STATE_DISCHARGING: u32 = 2;
+
+/*
+ * returns the battery percentage while running on battery, None on AC power, without a battery or
+ * if UPower is not available
+ */
+pub fn discharging_percentage() -> Option<f64> {
+    #[cfg(feature = "dbus")]
+    match read_display_device() {
+        Ok(percentage) => return percentage,
+        Err(err) => println!("The battery level could not be read! The error: {err}"),
+    }
+    None
+}
+
+#[cfg(feature = "dbus")]
+fn read_display_device() -> Result<Option<f64>, zbus::Error> {
+    let connection = Connection::system()?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.UPower",
+        "/org/freedesktop/UPower/devices/DisplayDevice",
+        "org.freedesktop.UPower.Device",
+    )?;
+
+    if !proxy.get_property::<bool>("IsPresent")?
+        || proxy.get_property::<u32>("State")? != STATE_DISCHARGING
+    {
+        return Ok(None);
+    }
+    Ok(Some(proxy.get_property::<f64>("Percentage")?))
+}
@@ -1,119 +1,546 @@
 use core::str;
-use std::io::ErrorKind;
-use std::os::unix::net::UnixDatagram;
+use std::fmt;
+use std::io::{ErrorKind, Read};
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::path::Path;
+use std::process::{Command, ExitCode};
+use std::time::Duration;
 
+use jiff::{SignedDuration, Timestamp, Zoned, tz::TimeZone};
 use std::{env, fs};
-const SOCKET_NAME: &str = "wlbreaktime.socket";
-const HELPER_SOCKET_NAME: &str = "wlbreaktime-helper.socket";
+use wlbreaktime::{
+    datagram,
+    duration::Seconds,
+    keybinds::{self, Compositor},
+    migration, paths, protocol, units,
+};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+// only the defaults are used, to generate the default config file
+#[allow(dead_code)]
+#[path = "../config.rs"]
+mod config;
+
+/*
+ * why the helper failed, each with an exit code of its own so scripts can tell them apart -- 0 is
+ * success, any other error is reported like a usage error
+ */
+#[derive(Debug)]
+enum Failure {
+    // 1, e.g. an unknown command or a malformed duration
+    Usage(String),
+    // 2, the daemon isn't running or its socket can't be reached
+    Unreachable(String),
+    // 3, the daemon refused the command, e.g. a skip of a break that can't be skipped
+    Rejected(String),
+    // 4, the daemon didn't answer in time
+    Timeout(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Usage(_) => 1,
+            Failure::Unreachable(_) => 2,
+            Failure::Rejected(_) => 3,
+            Failure::Timeout(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Usage(message)
+            | Failure::Unreachable(message)
+            | Failure::Rejected(message)
+            | Failure::Timeout(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+fn usage(message: impl Into<String>) -> Box<dyn std::error::Error> {
+    Box::new(Failure::Usage(message.into()))
+}
+
+fn rejected(message: impl Into<String>) -> Box<dyn std::error::Error> {
+    Box::new(Failure::Rejected(message.into()))
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            let code = err
+                .downcast_ref::<Failure>()
+                .map_or(1, |failure| failure.exit_code());
+            ExitCode::from(code)
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     // --get -g -> get remaining time
     //  -> --get [ minutes ] // optional minutes parameter to shorten output to ##m
     // --set -s -> set remaining time
+    //  -> --set [ --persist ] minutes|<n>s|<n>m // e.g. 90s, for testing and demos
+    //  -> --persist // the work interval is written into the user's config file as well
     // --reset -r -> reset timer
     // --break -b -> start a break
+    //  -> --break [ minutes ] // optional duration of the ad-hoc break
     // --skip -k -> skip the break
+    // --focus -f -> no breaks until the focus session is over
+    //  -> --focus minutes
+    // --status -t -> get the current phase and its remaining time
+    // --doctor -d -> check the setup and print what needs to be fixed
+    // --stats -a -> breaks taken and skipped since the daemon started, by reason
+    // --quit -q -> save the schedule and stop the daemon
+    // --restart -e -> save the schedule and restart the daemon
+    // --batch -c -> run several commands in order, e.g. batch "skip && set 45"
+    // --migrate-config -m -> write config files of older versions migrated next to the originals
+    // --init-config -i -> write the default config file, if there is none yet
+    // --check-config -> read the config files like the daemon and list what's wrong with them
+    // --install-service -n -> write and enable the systemd user units of the daemon
+    //  -> --install-service [ --system ] // the system units of multi-user machines instead, as root
+    // --session -o -> start the daemon of this session through the system units
+    // --debug dump -> the daemon's internal state as JSON, for bug reports
+    // --waybar -w -> the status as JSON for a waybar custom module, colored by the phase
+    // --bindinfo -> the recommended keybindings
+    // --install-keybinds -> registers the recommended keybindings with the running compositor
+    // --timer -m -> a reminder independent of the breaks
+    //  -> --timer duration [ message ] // e.g. timer 20m "Tea is ready"
+    // --toggle -> switches a part on or off until the daemon stops
+    //  -> --toggle [ --persist ] popup|sound|notification|monitors // --persist writes it to the config
     let mut args = env::args();
+    // only timers take a third argument, their message, and set and toggle their flag
+    let max_args = match env::args().nth(1).as_deref() {
+        Some("timer" | "set" | "toggle") => 4,
+        _ => 3,
+    };
     // TODO: provide a description of possible arguments
     if args.len() < 2 {
-        println!("No arguments provided!");
-        return Ok(());
-    } else if args.len() > 3 {
-        println!("Too many arguments!");
-        return Ok(());
+        return Err(usage("No arguments provided!"));
+    } else if args.len() > max_args {
+        return Err(usage("Too many arguments!"));
     }
     args.next().unwrap(); // generally contains the program's name, but this is not a given
     let arg = args.next().unwrap();
     let mut minutes = None;
+    // the duration of `break` and `focus`, for the confirmation
+    let mut duration = None;
+    let mut batch = None;
     let mut short = false;
 
     match arg.as_str() {
         "set" => {
-            let m = args.next().expect("no duration to set to provided!");
-            m.parse::<u16>()
-                .expect("Second argument '{m:?}' is no valid duration!");
+            let mut m = args
+                .next()
+                .ok_or_else(|| usage("no duration to set to provided!"))?;
+            let persist = m == protocol::PERSIST_FLAG;
+            if persist {
+                m = args
+                    .next()
+                    .ok_or_else(|| usage("no duration to set to provided!"))?;
+            }
+            if args.next().is_some() {
+                return Err(usage(
+                    "Too many arguments! usage: set [--persist] <duration>",
+                ));
+            }
+            protocol::parse_duration(&m)
+                .ok_or_else(|| usage(format!("Second argument '{m:?}' is no valid duration!")))?;
+            minutes = Some(if persist {
+                format!("{} {m}", protocol::PERSIST_FLAG)
+            } else {
+                m
+            });
+        }
+        "get" => match args.next() {
+            None => {}
+            Some(s) if s == "--minutes" => {
+                short = true;
+            }
+            Some(_) => return Err(usage("Incorrect second argument. usage: get [--minutes]")),
+        },
+        "break" => {
+            // optional duration of an ad-hoc break
+            if let Some(m) = args.next() {
+                duration = Some(protocol::parse_duration(&m).ok_or_else(|| {
+                    usage(format!("Second argument '{m:?}' is no valid duration!"))
+                })?);
+                minutes = Some(m);
+            }
+        }
+        "focus" => {
+            let m = args
+                .next()
+                .ok_or_else(|| usage("no duration of the focus session provided!"))?;
+            duration =
+                Some(protocol::parse_duration(&m).ok_or_else(|| {
+                    usage(format!("Second argument '{m:?}' is no valid duration!"))
+                })?);
             minutes = Some(m);
         }
-        "get" => {
-            let m = args.next();
-            match m {
-                None => {}
-                Some(s) if s == "--minutes" => {
-                    short = true;
-                }
-                Some(s) if s != "--minutes" => {
-                    assert!(
-                        args.next().is_none(),
-                        "Incorrect second argument. usage: get [--minutes]"
-                    );
-                }
-                Some(_) => {} //impossible to reach, since args are always strings?
+        "timer" => {
+            let duration = args
+                .next()
+                .ok_or_else(|| usage("no duration of the timer provided!"))?;
+            // the daemon checks the duration, like in the config files, e.g. 20m or 90s
+            let command = match args.next() {
+                Some(message) => format!("{duration} {message}"),
+                None => duration,
+            };
+            minutes = Some(command);
+        }
+        "toggle" => {
+            let syntax = "usage: toggle [--persist] popup|sound|notification|monitors";
+            let mut name = args
+                .next()
+                .ok_or_else(|| usage(format!("nothing to toggle provided! {syntax}")))?;
+            let persist = name == protocol::PERSIST_FLAG;
+            if persist {
+                name = args
+                    .next()
+                    .ok_or_else(|| usage(format!("nothing to toggle provided! {syntax}")))?;
+            }
+            if config::Toggle::from_name(&name).is_none() {
+                return Err(usage(format!("Unknown toggle '{name}'! {syntax}")));
+            }
+            minutes = Some(if persist {
+                format!("{} {name}", protocol::PERSIST_FLAG)
+            } else {
+                name
+            });
+        }
+        "debug" => {
+            let what = args
+                .next()
+                .ok_or_else(|| usage("nothing to debug provided! usage: debug dump|decisions"))?;
+            if what != "dump" && what != "decisions" {
+                return Err(usage(format!(
+                    "Unknown debug command '{what}'! usage: debug dump|decisions"
+                )));
             }
+            minutes = Some(what);
+        }
+        "batch" => {
+            let commands = args
+                .next()
+                .filter(|commands| !commands.trim().is_empty())
+                .ok_or_else(|| {
+                    usage("no commands to run provided! usage: batch \"skip && set 45\"")
+                })?;
+            batch = Some(commands);
+        }
+        "reset" | "skip" | "status" | "waybar" | "doctor" | "stats" | "bindinfo" | "quit"
+        | "restart" => {
+            no_second_argument(&mut args)?;
+        }
+        // only the config files are involved, so the daemon doesn't need to run
+        "migrate-config" => {
+            no_second_argument(&mut args)?;
+            return migrate_config();
+        }
+        "init-config" => {
+            no_second_argument(&mut args)?;
+            return init_config();
         }
-        "break" | "reset" | "skip" => {
-            assert!(args.next().is_none(), "did not expect a second argument!");
+        "check-config" => {
+            no_second_argument(&mut args)?;
+            return check_config();
+        }
+        "install-service" => {
+            let system = match args.next() {
+                None => false,
+                Some(s) if s == "--system" => true,
+                Some(s) => {
+                    return Err(usage(format!(
+                        "Unknown argument '{s}'! usage: install-service [--system]"
+                    )));
+                }
+            };
+            return install_service(system);
+        }
+        "session" => {
+            no_second_argument(&mut args)?;
+            return start_session();
+        }
+        "install-keybinds" => {
+            no_second_argument(&mut args)?;
+            return install_keybinds();
         }
         _ => {
-            println!(
-                "Incorrect first argument! Please provide one of the following arguments: break|set|reset|get|skip"
-            );
-            return Ok(());
+            return Err(usage(
+                "Incorrect first argument! Please provide one of the following arguments: break|set|reset|get|skip|focus|timer|toggle|status|waybar|doctor|stats|bindinfo|debug|quit|restart|batch|migrate-config|init-config|check-config|install-service|session|install-keybinds",
+            ));
         }
     }
 
-    let runtime_dir = env::var("XDG_RUNTIME_DIR")?;
+    let socket_path = paths::socket_path()?;
+    let helper_socket_path = paths::helper_socket_path()?;
 
-    let result = UnixDatagram::bind(runtime_dir.clone() + "/" + HELPER_SOCKET_NAME);
-    let socket;
-
-    match result {
+    let result = UnixDatagram::bind(&helper_socket_path);
+    let socket = match result {
         Err(err) if err.kind() == ErrorKind::AddrInUse => {
-            // the helper probably crashed the last time it ran and the socket is still linked, so
-            // it needs to be unlinked before trying again
-            fs::remove_file(runtime_dir.clone() + "/" + HELPER_SOCKET_NAME)?;
-            socket = UnixDatagram::bind(runtime_dir.clone() + "/" + HELPER_SOCKET_NAME)
-                .expect("Unable to bind socket even on second attempt!");
+            // a helper with the same process id probably crashed and its socket is still linked,
+            // so it needs to be unlinked before trying again
+            fs::remove_file(&helper_socket_path)?;
+            UnixDatagram::bind(&helper_socket_path)?
         }
         Err(err) => {
-            let kind = err.kind();
-            panic!("Unable to bind socket because of error '{err:?}' with ErrorKind '{kind}'!");
+            return Err(format!("Unable to bind the helper's socket! The error: {err}").into());
         }
-        Ok(s) => socket = s,
-    }
+        Ok(s) => s,
+    };
+    let _unlink = Unlink(&helper_socket_path);
+    // a wedged daemon must not hang the helper (e.g. in a status bar) forever
+    let timeout = config::load_silently().map_or(config::defaults().helper_timeout, |config| {
+        config.helper_timeout
+    });
+    socket.set_read_timeout((!timeout.is_zero()).then(|| timeout.as_duration()))?;
 
-    // send first argument
-    let result = socket.send_to(arg.as_bytes(), runtime_dir.clone() + "/" + SOCKET_NAME);
+    if arg == "doctor" {
+        return doctor(&socket, &socket_path);
+    }
 
-    match result {
-        Err(err) if err.kind() == ErrorKind::NotFound => {
-            panic!("Breaktime does not seem to be running!"); // socket is not available
-        }
-        Err(err) => panic!("Error '{err}' unexpectedly occured while sending a message!"),
-        Ok(_) => {
-            // everything is fine, do nothing
+    // the command and its argument are sent in a single message, a batch is sent as it is
+    let command = match (&minutes, &batch) {
+        (_, Some(commands)) => commands.clone(),
+        (Some(m), None) => format!("{arg} {m}"),
+        (None, None) => arg.clone(),
+    };
+    let id = protocol::request_id();
+    // the commands that don't respond on their own are acknowledged, so a refusal is noticed
+    let acknowledged = matches!(arg.as_str(), "reset" | "skip" | "break" | "focus")
+        || minutes
+            .as_deref()
+            .is_some_and(|m| arg == "set" && !protocol::split_persist(m).0);
+    let request = if acknowledged {
+        protocol::acknowledged_request(id, &command)
+    } else {
+        protocol::request(id, &command)
+    };
+    if request.len() > protocol::MAX_REQUEST_SIZE {
+        return Err(usage(format!(
+            "The command is longer than {} bytes, the daemon would refuse it!",
+            protocol::MAX_REQUEST_SIZE
+        )));
+    }
+    send(&socket, &socket_path, &request)?;
+    if acknowledged {
+        match receive_response(&socket, id)?.as_str() {
+            "ok" => {}
+            reason => {
+                return Err(rejected(format!(
+                    "The daemon refused '{command}': {reason}"
+                )));
+            }
         }
     }
 
     match arg.as_str() {
         "set" => {
-            let time = minutes.unwrap();
-            socket.send_to(time.as_bytes(), runtime_dir.clone() + "/" + SOCKET_NAME)?;
-            println!("Remaining time set to {time} minutes!");
+            let argument = minutes.unwrap();
+            let (persist, time) = protocol::split_persist(&argument);
+            // only a persisted interval is answered, after the file was written
+            if persist {
+                match receive_response(&socket, id)?.as_str() {
+                    "ok" => println!("The work interval was written to the config file."),
+                    result @ ("ignored" | "not during a break" | "invalid duration") => {
+                        return Err(rejected(format!(
+                            "The remaining time was not set: {result}"
+                        )));
+                    }
+                    // the timer was set nevertheless
+                    result => println!("The work interval was {result}"),
+                }
+            }
+            let seconds = protocol::parse_duration(time).unwrap();
+            if seconds < config::MIN_WORK_INTERVAL {
+                println!(
+                    "Remaining time set to {}, the shortest work interval!",
+                    spoken(config::MIN_WORK_INTERVAL)
+                );
+            } else {
+                println!("Remaining time set to {}!", spoken(seconds));
+            }
         }
         "get" => {
-            let mut buffer = [0; 30];
-            let bytes_read = socket.recv(&mut buffer)?;
-            let string_read = str::from_utf8(&buffer[..bytes_read])?;
-            let seconds = string_read.parse::<u64>().unwrap();
+            let string_read = receive_or_resend(&socket, &socket_path, &request, id)?;
+            // the seconds are followed by when the phase ends and the phase, older daemons only
+            // answered during work
+            let mut parts = string_read.split(' ');
+            let seconds = Seconds::new(parts.next().unwrap_or_default().parse::<u64>()?);
+            let until = match parts.nth(1) {
+                Some("break") => "the break is over",
+                Some("focus") => "the focus session is over",
+                _ => "the next break",
+            };
+            let (minutes, rest) = seconds.split();
             if short {
-                let minutes = seconds / 60;
-                println!("{minutes}m");
-            } else if seconds > 60 {
-                let minutes = seconds / 60;
-                let rest = seconds % 60;
-                println!("{minutes} minutes and {rest} seconds remain until the next break!");
+                println!("{}m", minutes.as_minutes());
+            } else if seconds > Seconds::new(60) {
+                println!(
+                    "{} minutes and {rest} seconds remain until {until}!",
+                    minutes.as_minutes()
+                );
             } else {
-                println!("{string_read} seconds remain until the next break!");
+                println!("{} seconds remain until {until}!", seconds.as_secs());
+            }
+        }
+        "break" => {
+            if let Some(duration) = duration {
+                println!("Started a break of {}!", spoken(duration));
+            }
+        }
+        "focus" => {
+            println!(
+                "Focus session started, no breaks for the next {}!",
+                spoken(duration.unwrap())
+            );
+        }
+        "timer" => match receive_response(&socket, id)?.as_str() {
+            "ok" => println!("Timer started!"),
+            result => return Err(rejected(format!("The timer was not started: {result}"))),
+        },
+        "toggle" => {
+            let argument = minutes.unwrap();
+            let (_, name) = protocol::split_persist(&argument);
+            let response = receive_response(&socket, id)?;
+            match response.split_once(' ') {
+                None => println!("Toggled {name} {response} until the daemon stops!"),
+                Some((state, "persisted")) => {
+                    println!("Toggled {name} {state} and wrote it to the config file!")
+                }
+                Some((state, result)) => {
+                    return Err(rejected(format!(
+                        "Toggled {name} {state}, but it was {result}"
+                    )));
+                }
+            }
+        }
+        "status" => {
+            let string_read = receive_or_resend(&socket, &socket_path, &request, id)?;
+            let mut parts = string_read.split(' ');
+            let (Some(phase), Some(seconds), Some(capabilities)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(format!("Received a malformed status '{string_read}'!").into());
+            };
+            // breaks are followed by their reason, work time by the offset of the jitter, every
+            // phase ends with when it's over
+            let seconds = Seconds::new(seconds.parse::<u64>()?);
+            let (minutes, rest) = seconds.split();
+            let remaining = format!("{} minutes and {rest} seconds", minutes.as_minutes());
+            match phase {
+                "work" => {
+                    let jitter = parts.next();
+                    let at = time_of_day(parts.next(), seconds)?;
+                    println!("Working, {remaining} remain until the next break at {at}!");
+                    match jitter.and_then(|jitter| jitter.parse::<i64>().ok()) {
+                        Some(jitter) if jitter != 0 => println!(
+                            "The jitter moved it by {}{} minutes and {} seconds.",
+                            if jitter < 0 { "-" } else { "+" },
+                            jitter.unsigned_abs() / 60,
+                            jitter.unsigned_abs() % 60
+                        ),
+                        _ => {}
+                    }
+                }
+                "focus" => {
+                    let at = time_of_day(parts.next(), seconds)?;
+                    println!("Focus session active, {remaining} remain until the break at {at}!")
+                }
+                "paused" => {
+                    let at = time_of_day(parts.next(), seconds)?;
+                    println!(
+                        "Paused without input, {remaining} remain until the next break, at {at} if work resumes now!"
+                    )
+                }
+                "break" => {
+                    let reason = parts.next();
+                    let at = time_of_day(parts.next(), seconds)?;
+                    match reason {
+                        Some(reason) => println!(
+                            "On a {reason} break, {remaining} remain until work resumes at {at}!"
+                        ),
+                        None => {
+                            println!("On a break, {remaining} remain until work resumes at {at}!")
+                        }
+                    }
+                }
+                _ => println!("Unknown phase '{phase}', {remaining} remain!"),
+            }
+            println!("Active capabilities: {capabilities}");
+            // e.g. "sound=off,popup=on", older daemons don't send them
+            if let Some(toggled) = parts.next().filter(|toggled| *toggled != "none") {
+                println!(
+                    "Toggled until the daemon stops: {}",
+                    toggled.replace('=', " ").replace(',', ", ")
+                );
+            }
+            // the break time skipped today, older daemons don't send it either
+            if let Some(owed) = parts
+                .next()
+                .and_then(|owed| owed.parse::<u64>().ok())
+                .filter(|owed| *owed > 0)
+            {
+                let (minutes, rest) = Seconds::new(owed).split();
+                println!(
+                    "Break debt: {} minutes and {rest} seconds of skipped breaks are not made up yet.",
+                    minutes.as_minutes()
+                );
+            }
+        }
+        "bindinfo" => {
+            for line in receive_response(&socket, id)?.lines() {
+                let Some((keys, command)) = line.split_once('=') else {
+                    continue;
+                };
+                println!("{keys}: {command}");
+            }
+            println!("'wlbreaktime-helper install-keybinds' registers them with your compositor.");
+        }
+        // printed as it is, so it can be piped into jq or attached to a bug report
+        "waybar" => println!(
+            "{}",
+            receive_or_resend(&socket, &socket_path, &request, id)?
+        ),
+        "debug" => println!("{}", receive_response(&socket, id)?),
+        "quit" | "restart" => match receive_response(&socket, id)?.as_str() {
+            "ok" if arg == "quit" => println!("The daemon saved its schedule and quits!"),
+            "ok" => println!("The daemon saved its schedule and restarts!"),
+            _ => {
+                return Err(rejected(format!(
+                    "The daemon refused to {arg}, it runs as another user!"
+                )));
+            }
+        },
+        "batch" => {
+            // every command of the batch is answered on its own, in order
+            let commands = batch.unwrap();
+            for command in protocol::split_batch(&commands) {
+                let result = receive_response(&socket, id)?;
+                println!("{command}: {result}");
+            }
+        }
+        "stats" => {
+            let string_read = receive_response(&socket, id)?;
+            for line in string_read.lines() {
+                let Some((name, count)) = line.split_once('=') else {
+                    continue;
+                };
+                match name {
+                    "scheduled" => println!("Scheduled breaks: {count}"),
+                    "scheduled_skipped" => println!("Skipped scheduled breaks: {count}"),
+                    "manual" => println!("Manual breaks: {count}"),
+                    "manual_skipped" => println!("Skipped manual breaks: {count}"),
+                    "taken_today" => println!("Breaks taken today: {count}"),
+                    "debt" => println!("Skipped break time not made up today: {count} seconds"),
+                    _ => println!("{name}: {count}"),
+                }
             }
         }
         _ => {
@@ -121,6 +548,477 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    fs::remove_file(runtime_dir + "/" + HELPER_SOCKET_NAME)?; // unlink socket
+    Ok(())
+}
+
+fn no_second_argument(args: &mut env::Args) -> Result<(), Box<dyn std::error::Error>> {
+    match args.next() {
+        Some(_) => Err(usage("did not expect a second argument!")),
+        None => Ok(()),
+    }
+}
+
+/*
+ * unlinks the helper's socket once the helper is done, even if it panicked -- every helper binds
+ * its own, so they would pile up otherwise
+ */
+struct Unlink<'a>(&'a str);
+
+impl Drop for Unlink<'_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.0);
+    }
+}
+
+/*
+ * the local time of day of the timestamp the daemon sent, e.g. "14:35" -- daemons that don't send
+ * one yet are assumed to end the phase after the remaining seconds
+ */
+fn time_of_day(
+    timestamp: Option<&str>,
+    seconds: Seconds,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let at = match timestamp.and_then(|timestamp| timestamp.parse::<i64>().ok()) {
+        Some(timestamp) => Timestamp::from_second(timestamp)?.to_zoned(TimeZone::system()),
+        None => Zoned::now().checked_add(SignedDuration::from_secs(seconds.as_secs() as i64))?,
+    };
+    Ok(at.strftime("%H:%M").to_string())
+}
+
+/*
+ * the duration in minutes if it's a whole number of them, in seconds otherwise, e.g. "90 seconds"
+ */
+fn spoken(duration: Seconds) -> String {
+    let (minutes, _) = duration.split();
+    if duration.is_whole_minutes() {
+        format!("{} minutes", minutes.as_minutes())
+    } else {
+        format!("{} seconds", duration.as_secs())
+    }
+}
+
+/*
+ * waits for the response like receive_response, but sends the request once more if it times out --
+ * only for requests that can be answered twice without harm, e.g. `get`, a datagram that got lost
+ * on a busy daemon is no reason to give up
+ */
+fn receive_or_resend(
+    socket: &UnixDatagram,
+    socket_path: &str,
+    request: &str,
+    id: u32,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match receive_response(socket, id) {
+        Err(err) if matches!(err.downcast_ref::<Failure>(), Some(Failure::Timeout(_))) => {
+            send(socket, socket_path, request)?;
+            receive_response(socket, id)
+        }
+        result => result,
+    }
+}
+
+/*
+ * sends the request to the daemon, failing with Failure::Unreachable if it doesn't run
+ */
+fn send(
+    socket: &UnixDatagram,
+    socket_path: &str,
+    request: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Err(err) = socket.send_to(request.as_bytes(), socket_path) {
+        let message = match err.kind() {
+            // the socket is not available, or nobody is listening on it
+            ErrorKind::NotFound | ErrorKind::ConnectionRefused => {
+                "Breaktime does not seem to be running!".to_string()
+            }
+            _ => format!("The daemon could not be reached! The error: {err}"),
+        };
+        return Err(Box::new(Failure::Unreachable(message)));
+    }
+    Ok(())
+}
+
+/*
+ * waits for the daemon's response to the request with the given id, responses to other requests
+ * (e.g. of a previous helper that crashed) are dropped
+ */
+fn receive_response(socket: &UnixDatagram, id: u32) -> Result<String, Box<dyn std::error::Error>> {
+    // long responses (e.g. the debug dump) come in several chunks
+    let mut payload = String::new();
+    loop {
+        let datagram = match datagram::receive(socket, usize::MAX) {
+            Ok(datagram) => datagram,
+            // the socket's read timeout passed
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                let timeout = socket.read_timeout()?.unwrap_or_default();
+                return Err(Box::new(Failure::Timeout(format!(
+                    "The daemon did not respond within {}.",
+                    Seconds::from(timeout).suffixed()
+                ))));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let string_read = str::from_utf8(&datagram.bytes)?;
+        match protocol::split_chunk(string_read) {
+            Some((response_id, more, part)) if response_id == id => {
+                payload.push_str(part);
+                if !more {
+                    return Ok(payload);
+                }
+            }
+            _ => eprintln!("Dropping the unexpected response '{string_read}'."),
+        }
+    }
+}
+
+/*
+ * writes a migrated copy of every config file of an older version next to it, the originals are
+ * left alone, so the changes can be reviewed before replacing them
+ */
+fn migrate_config() -> Result<(), Box<dyn std::error::Error>> {
+    for path in paths::config_paths()? {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => {
+                println!("The config file '{path}' could not be read: {err}");
+                continue;
+            }
+        };
+
+        let migrated = migration::migrate(&content);
+        if migrated.applied.is_empty() {
+            println!(
+                "The config file '{path}' is up to date (config_version={}).",
+                migrated.version
+            );
+            continue;
+        }
+
+        let copy = format!("{path}.migrated");
+        match fs::write(&copy, &migrated.content) {
+            Ok(()) => {
+                println!(
+                    "The config file '{path}' was migrated to config_version={} and written to '{copy}':",
+                    migration::CONFIG_VERSION
+                );
+                for description in migrated.applied {
+                    println!(" - {description}");
+                }
+            }
+            Err(err) => println!("The migrated copy of '{path}' could not be written: {err}"),
+        }
+    }
+    Ok(())
+}
+
+/*
+ * writes the default config file with every key commented out, an existing file is never
+ * overwritten
+ */
+fn init_config() -> Result<(), Box<dyn std::error::Error>> {
+    let path = paths::user_config_path()?;
+    if Path::new(&path).exists() {
+        println!("The config file '{path}' already exists, it was left alone.");
+        return Ok(());
+    }
+
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, config::default_config_file())?;
+    println!("The default config file was written to '{path}'.");
+    Ok(())
+}
+
+/*
+ * reads the config files like the daemon does and reports what it would ignore or clamp, each
+ * problem is printed as it's found
+ */
+fn check_config() -> Result<(), Box<dyn std::error::Error>> {
+    let warnings = config::check_configuration()?;
+    if warnings.is_empty() {
+        println!("The config is fine.");
+    } else {
+        println!(
+            "{} problem(s) found, the daemon goes on with what it can read.",
+            warnings.len()
+        );
+    }
+    Ok(())
+}
+
+/*
+ * writes the systemd user units for the daemon next to this helper, then reloads systemd and enables
+ * them -- units written before are replaced, changes belong into drop-ins
+ */
+fn install_service(system: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let daemon_path = env::current_exe()?.with_file_name("wlbreaktime-daemon");
+    if !daemon_path.exists() {
+        println!(
+            "The daemon was not found at '{}', it's expected next to the helper!",
+            daemon_path.display()
+        );
+        return Ok(());
+    }
+    let Some(daemon_path) = daemon_path.to_str() else {
+        println!(
+            "The daemon's path '{}' is no valid UTF-8!",
+            daemon_path.display()
+        );
+        return Ok(());
+    };
+
+    if system {
+        write_units(paths::SYSTEMD_SYSTEM_DIR, units::system(daemon_path))?;
+        systemctl("--system", &["daemon-reload"])?;
+        systemctl("--system", &["enable", "--now", units::SESSION_SOCKET_UNIT])?;
+        println!(
+            "Every session starts its own daemon by running 'wlbreaktime-helper session', e.g. from the compositor's autostart."
+        );
+        return Ok(());
+    }
+
+    write_units(&paths::systemd_user_dir()?, units::all(daemon_path))?;
+    systemctl("--user", &["daemon-reload"])?;
+    systemctl(
+        "--user",
+        &[
+            "enable",
+            units::SOCKET_UNIT,
+            units::EVENTS_SOCKET_UNIT,
+            units::SERVICE_UNIT,
+        ],
+    )?;
+    println!(
+        "The daemon starts with the next graphical session, or right away with 'systemctl --user start {}'.",
+        units::SERVICE_UNIT
+    );
+    Ok(())
+}
+
+/*
+ * writes the recommended keybindings next to the compositor's config and registers them right away
+ * where the compositor allows it -- the compositor's own config is left alone, it only needs to
+ * include the written file to keep them
+ */
+fn install_keybinds() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(compositor) = Compositor::detect() else {
+        println!(
+            "No supported compositor (niri, sway or Hyprland) is running, these are the binds:"
+        );
+        println!("{}", keybinds::list("wlbreaktime-helper"));
+        return Ok(());
+    };
+    let helper_path = env::current_exe()?;
+    let Some(helper_path) = helper_path.to_str() else {
+        println!(
+            "The helper's path '{}' is no valid UTF-8!",
+            helper_path.display()
+        );
+        return Ok(());
+    };
+
+    let path = compositor.snippet_path()?;
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, compositor.snippet(helper_path))?;
+    println!(
+        "Wrote the keybindings for {} to '{path}'.",
+        compositor.name()
+    );
+
+    for bind in &keybinds::BINDS {
+        let Some(command) = compositor.register_command(bind, helper_path) else {
+            continue;
+        };
+        let status = Command::new(&command[0]).args(&command[1..]).status()?;
+        if !status.success() {
+            println!("'{}' failed with {status}!", command.join(" "));
+        }
+    }
+    if compositor != Compositor::Niri {
+        println!(
+            "They work right away, until {} is restarted.",
+            compositor.name()
+        );
+    }
+    println!(
+        "To keep them, add this line to {}'s config: {}",
+        compositor.name(),
+        compositor.include_line(&path)
+    );
+    Ok(())
+}
+
+fn write_units(
+    dir: &str,
+    units: Vec<(&'static str, String)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    for (name, content) in units {
+        let path = format!("{dir}/{name}");
+        let replaced = Path::new(&path).exists();
+        fs::write(&path, content)?;
+        if replaced {
+            println!("Replaced '{path}'.");
+        } else {
+            println!("Wrote '{path}'.");
+        }
+    }
+    Ok(())
+}
+
+/*
+ * the scope is either --user or --system
+ */
+fn systemctl(scope: &str, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("systemctl").arg(scope).args(args).status()?;
+    if !status.success() {
+        return Err(format!(
+            "'systemctl {scope} {}' failed with {status}",
+            args.join(" ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/*
+ * asks the system units for a daemon of this session, it's started as this user and with this
+ * session's Wayland display
+ */
+fn start_session() -> Result<(), Box<dyn std::error::Error>> {
+    let mut connection = match UnixStream::connect(paths::SESSION_SOCKET_PATH) {
+        Ok(connection) => connection,
+        Err(err) => {
+            return Err(Box::new(Failure::Unreachable(format!(
+                "Unable to reach '{}', are the system units installed ('install-service --system')? The error: {err}",
+                paths::SESSION_SOCKET_PATH
+            ))));
+        }
+    };
+    connection.set_read_timeout(Some(Duration::from_secs(10)))?;
+    // the daemon replies once it took over the session and closes the connection
+    let mut reply = String::new();
+    match connection.read_to_string(&mut reply) {
+        Ok(_) if !reply.is_empty() => print!("{reply}"),
+        Ok(_) => {
+            return Err(Box::new(Failure::Timeout(
+                "The daemon did not reply, see 'journalctl -u wlbreaktime-session@*'.".to_string(),
+            )));
+        }
+        Err(err) => {
+            return Err(Box::new(Failure::Timeout(format!(
+                "No reply from the daemon! The error: {err}"
+            ))));
+        }
+    }
+    Ok(())
+}
+
+/*
+ * checks the daemon and the environment it runs in, every problem is printed with a hint on how to
+ * fix it
+ */
+fn doctor(socket: &UnixDatagram, socket_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let report = |ok: bool, message: &str, hint: &str| {
+        if ok {
+            println!("[ok] {message}");
+        } else {
+            println!("[!!] {message} -- {hint}");
+        }
+    };
+
+    let socket_exists = Path::new(socket_path).exists();
+    report(
+        socket_exists,
+        &format!("The daemon's socket exists at {socket_path}"),
+        "start wlbreaktime-daemon or enable wlbreaktime.socket",
+    );
+    if !socket_exists {
+        return Err(Box::new(Failure::Unreachable(
+            "The daemon is not running.".to_string(),
+        )));
+    }
+
+    let id = protocol::request_id();
+    let request = |command: &str| -> Result<String, Box<dyn std::error::Error>> {
+        send(socket, socket_path, &protocol::request(id, command))?;
+        receive_response(socket, id)
+    };
+
+    let hello = request("hello");
+    let responds = hello.as_ref().is_ok_and(|response| response == "hello");
+    report(
+        responds,
+        "The daemon responds",
+        "check its log, e.g. `journalctl --user -u wlbreaktime`, and restart it",
+    );
+    if !responds {
+        return Err(hello
+            .err()
+            .unwrap_or_else(|| "The daemon answered something else than hello.".into()));
+    }
+
+    let Ok(diagnostics) = request("diag") else {
+        report(false, "The daemon reports its health", "update the daemon");
+        return Ok(());
+    };
+    for line in diagnostics.lines() {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        match name {
+            "wayland" => report(
+                value == "yes",
+                "The Wayland compositor provides the needed globals",
+                "start the daemon inside your Wayland session, the overlay is disabled",
+            ),
+            // only of interest if it's used, without Wayland
+            "x11" => {
+                if value == "yes" {
+                    report(
+                        true,
+                        "The overlay is shown through X11, without Wayland",
+                        "",
+                    );
+                }
+            }
+            "notifications" => report(
+                value == "yes",
+                "A notification daemon is running",
+                "install and start one (e.g. mako), notifications are only logged",
+            ),
+            "audio" => report(
+                value == "yes",
+                "An audio output is usable",
+                "check audio_device= in the config and your sound server, sounds are disabled",
+            ),
+            "journald" => report(
+                value == "yes",
+                "The log goes to the journal",
+                "the daemon was not started by systemd, its output goes to wherever it was started",
+            ),
+            "input_grab" if value == "off" => {
+                report(true, "Strict breaks don't grab the input devices", "")
+            }
+            "input_grab" => report(
+                value == "yes",
+                "The input devices can be grabbed for strict breaks",
+                "add the user to the input group (and log in again), otherwise only the compositor keeps the input from escaping",
+            ),
+            // only informational, an empty cache just means there was no break yet
+            "cache" => report(true, &format!("Decoded assets are cached: {value}"), ""),
+            "config" => report(
+                value == "ok",
+                "The configuration parses cleanly",
+                &format!("fix the configuration: {value}"),
+            ),
+            _ => println!("[??] {name}: {value}"),
+        }
+    }
+
     Ok(())
 }
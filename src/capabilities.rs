@@ -0,0 +1,70 @@
+// what the system the daemon runs on offers, probed once at startup
+//
+// unavailable components are disabled instead of failing later on, e.g. without a notification
+// daemon the notifications end up in the log
+use std::sync::{
+    OnceLock,
+    atomic::{AtomicBool, Ordering},
+};
+
+static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+// set once the wayland thread crashed, the connection is gone for good
+static WAYLAND_LOST: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Capabilities {
+    pub(crate) wayland: bool,
+    // the overlay is shown through X11 instead, only without Wayland
+    pub(crate) x11: bool,
+    pub(crate) audio: bool,
+    pub(crate) notifications: bool,
+    pub(crate) journald: bool,
+    // keyboards and pointers can be grabbed through evdev
+    pub(crate) input_grab: bool,
+}
+
+impl Capabilities {
+    /*
+     * the active capabilities as a comma-separated list, e.g. "wayland,audio"
+     */
+    pub(crate) fn active(&self) -> String {
+        let names = [
+            (self.wayland, "wayland"),
+            (self.x11, "x11"),
+            (self.audio, "audio"),
+            (self.notifications, "notifications"),
+            (self.journald, "journald"),
+            (self.input_grab, "input_grab"),
+        ]
+        .iter()
+        .filter(|(active, _)| *active)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(",")
+        }
+    }
+}
+
+pub(crate) fn set(capabilities: Capabilities) {
+    if CAPABILITIES.set(capabilities).is_err() {
+        println!("The capabilities were already probed!");
+    }
+}
+
+/*
+ * before the probing is done, nothing is considered available
+ */
+pub(crate) fn get() -> Capabilities {
+    let mut capabilities = CAPABILITIES.get().copied().unwrap_or_default();
+    capabilities.wayland &= !WAYLAND_LOST.load(Ordering::Relaxed);
+    capabilities
+}
+
+#[cfg(feature = "popup")]
+pub(crate) fn lose_wayland() {
+    WAYLAND_LOST.store(true, Ordering::Relaxed);
+}
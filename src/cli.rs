@@ -0,0 +1,58 @@
+// the daemon's command line, e.g. `wlbreaktime-daemon --break-interval 10m --no-popup`
+//
+// the options take precedence over the config files and the environment, `--config` reads a
+// single file instead of the usual ones, e.g. to try something out
+use clap::{Arg, ArgAction, Command};
+
+use wlbreaktime::{duration::Seconds, protocol, units::SYSTEM_SESSION_FLAG};
+
+use crate::config::CommandLine;
+
+pub(crate) fn parse() -> CommandLine {
+    let matches = Command::new("wlbreaktime-daemon")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Reminds you to take breaks, with an overlay that blocks the screen")
+        .arg(
+            Arg::new("break-interval")
+                .long("break-interval")
+                .visible_alias("work-interval")
+                .value_name("DURATION")
+                .value_parser(parse_duration)
+                .help("The time between breaks, in seconds or suffixed with 's' or 'm'"),
+        )
+        .arg(
+            Arg::new("no-popup")
+                .long("no-popup")
+                .action(ArgAction::SetTrue)
+                .help("Don't show the overlay during breaks"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .help("Read this config file instead of the usual ones"),
+        )
+        .arg(
+            Arg::new(SYSTEM_SESSION_FLAG)
+                .long(SYSTEM_SESSION_FLAG)
+                .action(ArgAction::SetTrue)
+                .hide(true)
+                .help("Serve the session of the user connected to the system's session socket"),
+        )
+        .get_matches();
+
+    CommandLine {
+        config_file: matches.get_one::<String>("config").cloned(),
+        break_interval: matches.get_one::<Seconds>("break-interval").copied(),
+        show_popup: matches.get_flag("no-popup").then_some(false),
+        system_session: matches.get_flag(SYSTEM_SESSION_FLAG),
+    }
+}
+
+/*
+ * a duration like in the config files, e.g. 30m or 1800s -- also used for the timers
+ */
+pub(crate) fn parse_duration(value: &str) -> Result<Seconds, String> {
+    protocol::parse_seconds(value)
+        .ok_or_else(|| format!("'{value}' is no duration, e.g. 30m or 1800s"))
+}
@@ -0,0 +1,252 @@
+// reconciliation of the monotonic clock with the wall clock and the boot time clock
+//
+// all timers run on the monotonic clock, only features bound to the time of the day (weekday
+// overrides, the fixed break) need the wall clock -- when it jumps (NTP corrections, manual
+// changes, timezone changes or a suspension), those features have to be re-evaluated
+//
+// the monotonic clock (CLOCK_MONOTONIC) usually stands still while the system is suspended, the
+// boot time clock (CLOCK_BOOTTIME) doesn't: the difference between them is how long it slept. on
+// kernels whose monotonic clock keeps running, there is no difference and suspensions are only
+// noticed through the interrupted reads
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use jiff::Zoned;
+
+// smaller differences are regular NTP adjustments
+const JUMP_THRESHOLD_SECONDS: f64 = 30.0;
+// smaller differences between the monotonic and the boot time clock are no suspension
+const SUSPEND_THRESHOLD: Duration = Duration::from_millis(500);
+
+pub(crate) struct WallClock {
+    monotonic: Instant,
+    wall: SystemTime,
+    // the local timezone's offset from UTC
+    offset_seconds: i32,
+}
+
+impl WallClock {
+    pub(crate) fn new() -> WallClock {
+        WallClock::at(Instant::now(), SystemTime::now(), local_offset())
+    }
+
+    fn at(monotonic: Instant, wall: SystemTime, offset_seconds: i32) -> WallClock {
+        WallClock {
+            monotonic,
+            wall,
+            offset_seconds,
+        }
+    }
+
+    /*
+     * returns by how many seconds the local time jumped since the last check, None if it didn't
+     */
+    pub(crate) fn check(&mut self) -> Option<f64> {
+        self.check_at(Instant::now(), SystemTime::now(), local_offset())
+    }
+
+    fn check_at(
+        &mut self,
+        monotonic: Instant,
+        wall: SystemTime,
+        offset_seconds: i32,
+    ) -> Option<f64> {
+        let wall_elapsed = match wall.duration_since(self.wall) {
+            Ok(elapsed) => elapsed.as_secs_f64(),
+            Err(err) => -err.duration().as_secs_f64(),
+        };
+        let monotonic_elapsed = monotonic.duration_since(self.monotonic).as_secs_f64();
+        let jump = wall_elapsed - monotonic_elapsed + (offset_seconds - self.offset_seconds) as f64;
+
+        // comparing against the last check only, so small adjustments don't add up
+        *self = WallClock::at(monotonic, wall, offset_seconds);
+        (jump.abs() >= JUMP_THRESHOLD_SECONDS).then_some(jump)
+    }
+}
+
+pub(crate) struct SuspendClock {
+    monotonic: Duration,
+    boottime: Duration,
+}
+
+impl SuspendClock {
+    pub(crate) fn new() -> SuspendClock {
+        SuspendClock {
+            monotonic: clock_time(libc::CLOCK_MONOTONIC),
+            boottime: clock_time(libc::CLOCK_BOOTTIME),
+        }
+    }
+
+    /*
+     * returns how long the system was suspended since the last check, None if it wasn't
+     */
+    pub(crate) fn check(&mut self) -> Option<Duration> {
+        self.check_at(
+            clock_time(libc::CLOCK_MONOTONIC),
+            clock_time(libc::CLOCK_BOOTTIME),
+        )
+    }
+
+    fn check_at(&mut self, monotonic: Duration, boottime: Duration) -> Option<Duration> {
+        let slept = boottime
+            .saturating_sub(self.boottime)
+            .saturating_sub(monotonic.saturating_sub(self.monotonic));
+        self.monotonic = monotonic;
+        self.boottime = boottime;
+        (slept >= SUSPEND_THRESHOLD).then_some(slept)
+    }
+}
+
+fn clock_time(clock: libc::clockid_t) -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // can't fail for the clocks used here, they exist since Linux 2.6.39
+    unsafe { libc::clock_gettime(clock, &mut time) };
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/*
+ * the wall-clock time of a deadline on the monotonic clock as seconds since the epoch, e.g. for
+ * widgets that show "next break at 14:35" instead of a countdown
+ */
+pub(crate) fn timestamp(deadline: Instant) -> u64 {
+    timestamp_at(deadline, Instant::now(), SystemTime::now())
+}
+
+/*
+ * the timestamp of the deadline the duration from now -- one too far off for the clocks is reported
+ * as the furthest there is
+ */
+pub(crate) fn timestamp_in(duration: Duration) -> u64 {
+    Instant::now()
+        .checked_add(duration)
+        .map_or(u64::MAX, timestamp)
+}
+
+fn timestamp_at(deadline: Instant, monotonic: Instant, wall: SystemTime) -> u64 {
+    // deadlines in the past are reported as now
+    let Some(at) = wall.checked_add(deadline.saturating_duration_since(monotonic)) else {
+        return u64::MAX;
+    };
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn local_offset() -> i32 {
+    Zoned::now().offset().seconds()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn clock() -> (WallClock, Instant, SystemTime) {
+        let monotonic = Instant::now();
+        let wall = SystemTime::now();
+        (WallClock::at(monotonic, wall, 3600), monotonic, wall)
+    }
+
+    #[test]
+    fn no_jump_when_both_clocks_advance() {
+        let (mut clock, monotonic, wall) = clock();
+        let elapsed = Duration::from_secs(600);
+        assert_eq!(
+            clock.check_at(monotonic + elapsed, wall + elapsed, 3600),
+            None
+        );
+    }
+
+    #[test]
+    fn small_adjustments_are_ignored() {
+        let (mut clock, monotonic, wall) = clock();
+        let elapsed = Duration::from_secs(600);
+        let adjusted = wall + elapsed + Duration::from_secs(2);
+        assert_eq!(clock.check_at(monotonic + elapsed, adjusted, 3600), None);
+    }
+
+    #[test]
+    fn forward_jump() {
+        let (mut clock, monotonic, wall) = clock();
+        let elapsed = Duration::from_secs(60);
+        let jumped = wall + elapsed + Duration::from_secs(3600);
+        assert_eq!(
+            clock.check_at(monotonic + elapsed, jumped, 3600),
+            Some(3600.0)
+        );
+    }
+
+    #[test]
+    fn backward_jump() {
+        let (mut clock, monotonic, wall) = clock();
+        let jumped = wall - Duration::from_secs(120);
+        assert_eq!(clock.check_at(monotonic, jumped, 3600), Some(-120.0));
+    }
+
+    #[test]
+    fn timezone_change() {
+        let (mut clock, monotonic, wall) = clock();
+        assert_eq!(clock.check_at(monotonic, wall, 7200), Some(3600.0));
+    }
+
+    #[test]
+    fn jump_is_only_reported_once() {
+        let (mut clock, monotonic, wall) = clock();
+        let jumped = wall + Duration::from_secs(3600);
+        assert!(clock.check_at(monotonic, jumped, 3600).is_some());
+
+        let elapsed = Duration::from_secs(60);
+        assert_eq!(
+            clock.check_at(monotonic + elapsed, jumped + elapsed, 3600),
+            None
+        );
+    }
+
+    #[test]
+    fn suspension_is_the_difference_of_the_clocks() {
+        let mut clock = SuspendClock {
+            monotonic: Duration::from_secs(100),
+            boottime: Duration::from_secs(150),
+        };
+        assert_eq!(
+            clock.check_at(Duration::from_secs(160), Duration::from_millis(610_250)),
+            Some(Duration::from_millis(400_250))
+        );
+        // only reported once
+        assert_eq!(
+            clock.check_at(Duration::from_secs(170), Duration::from_millis(620_250)),
+            None
+        );
+    }
+
+    #[test]
+    fn no_suspension_when_both_clocks_advance() {
+        let mut clock = SuspendClock {
+            monotonic: Duration::from_secs(100),
+            boottime: Duration::from_secs(100),
+        };
+        let jitter = Duration::from_millis(100);
+        assert_eq!(
+            clock.check_at(Duration::from_secs(700), Duration::from_secs(700) + jitter),
+            None
+        );
+    }
+
+    #[test]
+    fn timestamp_of_deadline() {
+        let monotonic = Instant::now();
+        let wall = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let deadline = monotonic + Duration::from_secs(300);
+        assert_eq!(timestamp_at(deadline, monotonic, wall), 1_000_300);
+    }
+
+    #[test]
+    fn timestamp_of_passed_deadline() {
+        let deadline = Instant::now();
+        let wall = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let monotonic = deadline + Duration::from_secs(5);
+        assert_eq!(timestamp_at(deadline, monotonic, wall), 1_000_000);
+    }
+}
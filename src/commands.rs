@@ -0,0 +1,179 @@
+// what the commands do in the phase they arrive in -- a command sent just before the phase changed
+// (e.g. `break` while the break already started, or a skip from a notification that's still shown
+// after the break) reaches the other loop, so every command needs an answer in every phase
+//
+// most commands are run the same way in every phase, the ones that only make sense in some of them
+// are refused in the others, with the reason as the answer. like the commands that change the
+// schedule, the refusals are only answered when they're batched or acknowledged, or when the helper
+// waits for them
+use std::os::unix::net::UnixDatagram;
+
+use wlbreaktime::protocol;
+
+use crate::batch;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum State {
+    // work time, including focus sessions and pauses
+    Work,
+    // the seconds between the notification announcing a break and the break itself
+    LeadIn,
+    Break,
+}
+
+impl State {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            State::Work => "work",
+            State::LeadIn => "lead-in",
+            State::Break => "break",
+        }
+    }
+}
+
+/*
+ * why the command is refused in the state, None if it's run
+ */
+pub(crate) fn refusal(state: State, command: &str) -> Option<&'static str> {
+    let (name, _) = command.split_once(' ').unwrap_or((command, ""));
+    match (state, name) {
+        // includes the skips from the notification
+        (State::Work, "skip") => Some("no break to skip"),
+        (State::Break, "break") => Some("already on a break"),
+        // the timer starts over after the break anyway
+        (State::Break, "focus" | "reset" | "set") => Some("not during a break"),
+        _ => None,
+    }
+}
+
+/*
+ * whether the command moves the next break, during the lead-in it's run by the next work time
+ * instead, so the announced break doesn't start -- a plain `break` only starts it right away
+ */
+pub(crate) fn moves_break(command: &str) -> bool {
+    let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+    match name {
+        // with its own duration
+        "break" => !argument.is_empty(),
+        "focus" | "reset" | "set" => true,
+        _ => false,
+    }
+}
+
+/*
+ * answers a refused command -- a persisted interval is always answered, the helper waits for it
+ */
+pub(crate) fn refuse(socket: &UnixDatagram, request: &batch::Request, state: State, reason: &str) {
+    let command = &*request.command;
+    let awaited = command
+        .strip_prefix("set ")
+        .is_some_and(|argument| protocol::split_persist(argument).0);
+    if awaited {
+        batch::respond(socket, request, reason);
+    } else {
+        batch::acknowledge(socket, request, reason);
+    }
+    println!("[{}]: Ignored '{command}', {reason}.", state.name());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every command of the protocol, with an argument where it takes one
+    const COMMANDS: [&str; 24] = [
+        "break",
+        "break 5",
+        "focus 30",
+        "set 45",
+        "set --persist 45",
+        "reset",
+        "get",
+        "skip",
+        "skip notification",
+        "timer 20m Tea",
+        "toggle sound",
+        "status",
+        "waybar",
+        "hello",
+        "diag",
+        "debug dump",
+        "debug decisions",
+        "stats",
+        "bindinfo",
+        "quit",
+        "restart",
+        "idle",
+        "active",
+        "session",
+    ];
+
+    #[test]
+    fn break_during_break_is_refused() {
+        assert_eq!(refusal(State::Break, "break"), Some("already on a break"));
+        assert_eq!(
+            refusal(State::Break, "break 10"),
+            Some("already on a break")
+        );
+        assert_eq!(refusal(State::Work, "break"), None);
+    }
+
+    #[test]
+    fn skip_during_work_is_refused() {
+        assert_eq!(refusal(State::Work, "skip"), Some("no break to skip"));
+        assert_eq!(
+            refusal(State::Work, "skip notification"),
+            Some("no break to skip")
+        );
+        assert_eq!(refusal(State::Break, "skip"), None);
+        assert_eq!(refusal(State::LeadIn, "skip"), None);
+    }
+
+    #[test]
+    fn lead_in_runs_everything() {
+        for command in COMMANDS {
+            assert_eq!(refusal(State::LeadIn, command), None);
+        }
+    }
+
+    #[test]
+    fn postponing_commands_move_the_break() {
+        for command in ["break 5", "focus 30", "set 5", "set --persist 5", "reset"] {
+            assert!(moves_break(command), "'{command}' doesn't move the break");
+        }
+        for command in ["break", "skip", "get", "toggle sound", "timer 5m"] {
+            assert!(!moves_break(command), "'{command}' moves the break");
+        }
+    }
+
+    #[test]
+    fn schedule_is_only_changed_during_work() {
+        for command in ["focus 30", "reset", "set 45", "set --persist 45"] {
+            assert_eq!(refusal(State::Work, command), None);
+            assert_eq!(refusal(State::Break, command), Some("not during a break"));
+        }
+    }
+
+    // a command that races the phase change is run by one of the phases at least
+    #[test]
+    fn no_command_is_refused_everywhere() {
+        for command in COMMANDS {
+            assert!(
+                [State::Work, State::LeadIn, State::Break]
+                    .iter()
+                    .any(|state| refusal(*state, command).is_none()),
+                "'{command}' is refused in every phase"
+            );
+        }
+    }
+
+    #[test]
+    fn informational_commands_run_everywhere() {
+        for command in [
+            "get", "status", "waybar", "hello", "diag", "stats", "bindinfo", "quit", "restart",
+        ] {
+            assert_eq!(refusal(State::Work, command), None);
+            assert_eq!(refusal(State::Break, command), None);
+        }
+    }
+}
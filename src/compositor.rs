@@ -0,0 +1,201 @@
+// the compositor specific parts of a break: turning the monitors off and on again, and keeping the
+// workspaces where they were
+//
+// when the monitors are powered off, outputs can disconnect and their workspaces move to the
+// remaining ones -- so the layout is saved before and restored after the break. only niri is
+// supported so far, other compositors (e.g. sway or Hyprland) get their own backend
+use std::{
+    env,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+// the outputs come back one after another after powering the monitors on
+const OUTPUTS_TIMEOUT: Duration = Duration::from_secs(5);
+const OUTPUTS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub(crate) trait Compositor {
+    fn power_off_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    fn power_on_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    /*
+     * remembers which output every workspace is on and which ones are shown
+     */
+    fn save_workspaces(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    /*
+     * moves the saved workspaces back to their outputs and shows them again
+     */
+    fn restore_workspaces(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/*
+ * talks to niri through `niri msg`
+ */
+pub(crate) struct Niri {
+    workspaces: Vec<Workspace>,
+}
+
+#[derive(Debug, Clone)]
+struct Workspace {
+    id: u64,
+    // the position on its output, starting at 1
+    idx: u64,
+    output: Option<String>,
+    is_active: bool,
+    is_focused: bool,
+}
+
+impl Workspace {
+    fn from_json(value: &Value) -> Option<Workspace> {
+        Some(Workspace {
+            id: value["id"].as_u64()?,
+            idx: value["idx"].as_u64()?,
+            output: value["output"].as_str().map(str::to_string),
+            is_active: value["is_active"].as_bool().unwrap_or(false),
+            is_focused: value["is_focused"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+impl Compositor for Niri {
+    fn power_off_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        niri_action(&["power-off-monitors"])
+    }
+
+    fn power_on_monitors(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        niri_action(&["power-on-monitors"])
+    }
+
+    fn save_workspaces(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.workspaces = niri_workspaces()?;
+        Ok(())
+    }
+
+    fn restore_workspaces(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let saved = std::mem::take(&mut self.workspaces);
+        if saved.is_empty() {
+            return Ok(());
+        }
+        let mut current = wait_for_outputs(&saved)?;
+
+        // every move shifts the indices, so the workspaces are read again after each one -- the
+        // indices only refer to the focused output, so the moved workspace's output is focused
+        for _ in 0..saved.len() {
+            let Some((workspace, output)) = current.iter().find_map(|workspace| {
+                let output = saved
+                    .iter()
+                    .find(|saved| saved.id == workspace.id)?
+                    .output
+                    .as_ref()?;
+                // outputs that didn't come back keep the workspaces that were moved to the others
+                let present = current
+                    .iter()
+                    .any(|other| other.output.as_ref() == Some(output));
+                (present && workspace.output.as_ref() != Some(output))
+                    .then_some((workspace, output))
+            }) else {
+                break;
+            };
+            println!(
+                "Moving the workspace {} back to the output {output}.",
+                workspace.id
+            );
+            if let Some(current_output) = &workspace.output {
+                niri_action(&["focus-monitor", current_output])?;
+            }
+            niri_action(&[
+                "move-workspace-to-monitor",
+                "--reference",
+                &workspace.idx.to_string(),
+                output,
+            ])?;
+            current = niri_workspaces()?;
+        }
+
+        // the workspaces that were shown, the focused one last so it keeps the focus
+        let mut active: Vec<&Workspace> = saved.iter().filter(|saved| saved.is_active).collect();
+        active.sort_by_key(|saved| saved.is_focused);
+        for workspace in active {
+            let Some(output) = &workspace.output else {
+                continue;
+            };
+            let Some(idx) = current
+                .iter()
+                .find(|current| {
+                    current.id == workspace.id && current.output.as_ref() == Some(output)
+                })
+                .map(|current| current.idx)
+            else {
+                continue;
+            };
+            niri_action(&["focus-monitor", output])?;
+            niri_action(&["focus-workspace", &idx.to_string()])?;
+        }
+        Ok(())
+    }
+}
+
+/*
+ * the current workspaces once all outputs of the saved ones are back, or when the timeout is over
+ */
+fn wait_for_outputs(saved: &[Workspace]) -> Result<Vec<Workspace>, Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    loop {
+        let current = niri_workspaces()?;
+        let all_present = saved
+            .iter()
+            .filter_map(|saved| saved.output.as_ref())
+            .all(|output| {
+                current
+                    .iter()
+                    .any(|current| current.output.as_ref() == Some(output))
+            });
+        if all_present {
+            return Ok(current);
+        }
+        if started.elapsed() >= OUTPUTS_TIMEOUT {
+            println!("Not all outputs came back, their workspaces stay where they are.");
+            return Ok(current);
+        }
+        thread::sleep(OUTPUTS_POLL_INTERVAL);
+    }
+}
+
+fn niri_workspaces() -> Result<Vec<Workspace>, Box<dyn std::error::Error>> {
+    let output = Command::new("niri")
+        .args(["msg", "--json", "workspaces"])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("'niri msg workspaces' failed with {}", output.status).into());
+    }
+    let value: Value = serde_json::from_slice(&output.stdout)?;
+    let workspaces = value
+        .as_array()
+        .ok_or("niri did not send a list of workspaces")?;
+    Ok(workspaces.iter().filter_map(Workspace::from_json).collect())
+}
+
+fn niri_action(args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("niri")
+        .args(["msg", "action"])
+        .args(args)
+        .status()?;
+    if !status.success() {
+        return Err(format!("'niri msg action {}' failed with {status}", args.join(" ")).into());
+    }
+    Ok(())
+}
+
+/*
+ * the backend of the running compositor, None if it's not supported
+ */
+pub(crate) fn detect() -> Option<Box<dyn Compositor>> {
+    if env::var_os("NIRI_SOCKET").is_some() {
+        return Some(Box::new(Niri {
+            workspaces: Vec::new(),
+        }));
+    }
+    None
+}
@@ -1,118 +1,2326 @@
 use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
     env::{self, VarError},
     fs,
     io::ErrorKind,
+    path::Path,
+    sync::{Mutex, OnceLock},
 };
 
+use jiff::{
+    Zoned,
+    civil::{Time, Weekday},
+};
 use regex::Regex;
+use wlbreaktime::{
+    duration::{Minutes, Seconds},
+    migration, paths, protocol,
+};
 
-const CONFIG_PATH: &str = "wlbreaktime/config";
+// e.g. WLBREAKTIME_WORK_INTERVAL=10m overrides work_interval in every config file
+const ENV_PREFIX: &str = "WLBREAKTIME_";
+// a line only applying on some weekdays, e.g. "Sat,Sun: work_interval=60m"
+const SCHEDULE_OVERRIDE: &str =
+    r"^\s*((?:Mon|Tue|Wed|Thu|Fri|Sat|Sun)(?:\s*,\s*(?:Mon|Tue|Wed|Thu|Fri|Sat|Sun))*)\s*:(.*)$";
+// a section only applying on some machines, e.g. "[host:worklaptop]" or
+// "[env:WAYLAND_DISPLAY=wayland-1]" -- it lasts until the next one, keys may follow on the same line
+const CONDITIONAL_SECTION: &str = r"^\s*\[([^\]]*)\](.*)$";
+const HOSTNAME_PATH: &str = "/proc/sys/kernel/hostname";
+
+// set once at startup, the daemon's command line takes precedence over everything else
+static COMMAND_LINE: OnceLock<CommandLine> = OnceLock::new();
+// what was wrong with the config the last time it was loaded, `wlbreaktime-helper check-config`
+// lists it
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+thread_local! {
+    // the keys whose values couldn't be read, collected instead of reported while the environment
+    // is read, see read_environment_overrides
+    static UNREADABLE: RefCell<Option<HashSet<String>>> = const { RefCell::new(None) };
+    // set while the weekday overrides are applied (they were already checked when they were
+    // loaded) and while the helper loads its settings
+    static SILENT: Cell<bool> = const { Cell::new(false) };
+    // set while the daemon counts the warnings for `diag`, they are collected but not logged again
+    static UNLOGGED: Cell<bool> = const { Cell::new(false) };
+}
 
-const DEFAULT_BREAK_DURATION_SECONDS: u64 = 80;
-const DEFAULT_BREAK_INTERVAL_SECONDS: u64 = 1800;
+const DEFAULT_BREAK_DURATION: Seconds = Seconds::new(80);
+// a break of no time at all would be over before the overlay is shown
+const MIN_BREAK_DURATION: Seconds = Seconds::new(10);
+const DEFAULT_BREAK_INTERVAL: Seconds = Seconds::new(1800);
+const DEFAULT_BREAK_INTERVAL_JITTER: Seconds = Seconds::new(0);
+// shorter work intervals are meant for testing and demos, but a typo shouldn't start a break
+// every second
+pub const MIN_WORK_INTERVAL: Seconds = Seconds::new(10);
+const DEFAULT_MIN_WORK_BETWEEN_BREAKS: Seconds = Seconds::new(300);
+const DEFAULT_GRACE_PERIOD: Seconds = Seconds::new(3);
+const DEFAULT_AUTO_PAUSE_AFTER: Seconds = Seconds::new(0);
 const DEFAULT_SHOW_POPUP: bool = true;
+const DEFAULT_POPUP: PopupMode = PopupMode::Auto;
 const DEFAULT_PLAY_SOUND: bool = true;
+const DEFAULT_PRE_BREAK_SOUND: bool = false;
 const DEFAULT_SHOW_NOTIFICATION: bool = true;
 const DEFAULT_TURN_OFF_MONITORS: bool = false;
+const DEFAULT_ON_RESUME: OnResume = OnResume::Reset;
+const DEFAULT_SUSPEND_COUNTS_AS: SuspendCountsAs = SuspendCountsAs::Pause;
+const DEFAULT_RESUME_AFTER_IDLE: Seconds = Seconds::new(10 * 60);
+const DEFAULT_REQUEST_AUTOSTART: bool = false;
+const DEFAULT_POPUP_ALIGN: Align = Align::Center;
+const DEFAULT_POPUP_MARGIN: u32 = 64;
+const DEFAULT_STRICT: bool = false;
+const DEFAULT_GRAB_INPUT: bool = false;
+const DEFAULT_EXTEND_BREAK_ON_ACTIVITY: bool = false;
+const DEFAULT_SKIP_FROM: [SkipSource; 3] = [
+    SkipSource::Cli,
+    SkipSource::Overlay,
+    SkipSource::Notification,
+];
+const DEFAULT_STRICT_SKIP_FROM: [SkipSource; 1] = [SkipSource::Cli];
+const DEFAULT_HIGH_CONTRAST: bool = false;
+const DEFAULT_THEME: Theme = Theme::Auto;
+const DEFAULT_PHASE_PALETTE: PhasePalette = PhasePalette::Default;
+const DEFAULT_REDUCE_MOTION: bool = false;
+const DEFAULT_ANNOUNCE_PHASES: bool = false;
+const DEFAULT_GAMMA_SHIFT: bool = false;
+const DEFAULT_GAMMA_TEMPERATURE: u32 = 3400;
+const DEFAULT_GAMMA_BRIGHTNESS: u32 = 80;
+const DEFAULT_POSTPONE_ON_DND: bool = false;
+const DEFAULT_DND_POSTPONE: Seconds = Seconds::new(300);
+const DEFAULT_HELPER_TIMEOUT: Seconds = Seconds::new(2);
+const DEFAULT_RESTORE_FOCUS: bool = true;
+const DEFAULT_POPUP_CURSOR: CursorMode = CursorMode::Hidden;
+const DEFAULT_POPUP_ON_TOP: bool = false;
+const DEFAULT_RENDER_SCALE: f32 = 1.0;
+// below it, the text of the overlay gets too blurry to read
+const MIN_RENDER_SCALE: f32 = 0.25;
+const DEFAULT_POPUP_TITLE: &str = "It's break time!";
+const DEFAULT_POPUP_SUBTITLE: &str = "Stand up, stretch and look into the distance.";
+const DEFAULT_ESCALATION: bool = false;
+const DEFAULT_ESCALATION_STEP: Seconds = Seconds::new(300);
+const DEFAULT_ESCALATION_LIMIT: u64 = 3;
+const DEFAULT_DEBT: DebtPolicy = DebtPolicy::Ignore;
+const DEFAULT_NOTIFICATION_URGENCY: Urgency = Urgency::Normal;
+const DEFAULT_NOTIFICATION_APP_NAME: &str = "wlbreaktime";
+const DEFAULT_PRE_BREAK_SUMMARY: &str = "It's break time!";
+const DEFAULT_PRE_BREAK_BODY: &str = "The next break starts in {remaining}.";
+const DEFAULT_BREAK_END_SUMMARY: &str = "Back to work!";
+const DEFAULT_BREAK_END_BODY: &str = "The next break is in {remaining}.";
+
+/*
+ * what to do with the work timer after the system woke up from suspension
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnResume {
+    Reset,
+    Continue,
+    Prompt,
+}
+
+impl OnResume {
+    pub fn name(self) -> &'static str {
+        match self {
+            OnResume::Reset => "reset",
+            OnResume::Continue => "continue",
+            OnResume::Prompt => "prompt",
+        }
+    }
+}
+
+/*
+ * how the time the system was suspended during work counts, unless the work timer is reset anyway
+ * (see OnResume)
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SuspendCountsAs {
+    // at least as long as a break, it starts a fresh work interval
+    Break,
+    // the work timer stands still while suspended
+    Pause,
+    // the work timer runs on, as if there was no suspension
+    Nothing,
+}
+
+impl SuspendCountsAs {
+    pub fn name(self) -> &'static str {
+        match self {
+            SuspendCountsAs::Break => "break",
+            SuspendCountsAs::Pause => "pause",
+            SuspendCountsAs::Nothing => "nothing",
+        }
+    }
+}
+
+/*
+ * the colors of the phases (work, the warning before a break and the break) wherever they're
+ * shown -- besides the default, two palettes that stay apart with every kind of color blindness
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhasePalette {
+    // green, amber and red
+    Default,
+    // Okabe and Ito's: sky blue, yellow and vermilion
+    OkabeIto,
+    // Paul Tol's bright scheme: blue, yellow and rose
+    Tol,
+}
+
+impl PhasePalette {
+    pub fn name(self) -> &'static str {
+        match self {
+            PhasePalette::Default => "default",
+            PhasePalette::OkabeIto => "okabe-ito",
+            PhasePalette::Tol => "tol",
+        }
+    }
+}
+
+/*
+ * the colors of the overlay and the toasts, auto follows the desktop's preference (through the
+ * Settings portal) and stays dark without one
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Theme {
+    Auto,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Auto => "auto",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+}
+
+/*
+ * the cursor shown above the pop-up
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorMode {
+    Hidden,
+    Default,
+}
 
-#[derive(Debug)]
+impl CursorMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CursorMode::Hidden => "hidden",
+            CursorMode::Default => "default",
+        }
+    }
+}
+
+/*
+ * whether the daemon connects to the compositor: auto does if there is a Wayland session (or an X11
+ * one with the x11 feature) and runs headless (sound and notifications only) without one, always
+ * refuses to start without it and never runs headless in a Wayland session too
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PopupMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl PopupMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            PopupMode::Auto => "auto",
+            PopupMode::Always => "always",
+            PopupMode::Never => "never",
+        }
+    }
+}
+
+/*
+ * how the break time that was skipped is made up for: not at all, by lengthening the next breaks
+ * or by an extra break once a whole one is owed
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebtPolicy {
+    Ignore,
+    Extend,
+    ExtraBreak,
+}
+
+impl DebtPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            DebtPolicy::Ignore => "ignore",
+            DebtPolicy::Extend => "extend",
+            DebtPolicy::ExtraBreak => "extra_break",
+        }
+    }
+}
+
+/*
+ * where a skip came from: `wlbreaktime-helper skip` (or anything else sending "skip" to the
+ * socket), tapping or holding Esc on the overlay, or the action of the countdown notification
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipSource {
+    Cli,
+    Overlay,
+    Notification,
+}
+
+impl SkipSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            SkipSource::Cli => "cli",
+            SkipSource::Overlay => "overlay",
+            SkipSource::Notification => "notification",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SkipSource> {
+        match name {
+            "cli" => Some(SkipSource::Cli),
+            "overlay" => Some(SkipSource::Overlay),
+            "notification" => Some(SkipSource::Notification),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * what can be switched on and off while the daemon runs, e.g. `wlbreaktime-helper toggle sound`
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Toggle {
+    Popup,
+    Sound,
+    Notification,
+    Monitors,
+}
+
+impl Toggle {
+    pub const ALL: [Toggle; 4] = [
+        Toggle::Popup,
+        Toggle::Sound,
+        Toggle::Notification,
+        Toggle::Monitors,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Toggle::Popup => "popup",
+            Toggle::Sound => "sound",
+            Toggle::Notification => "notification",
+            Toggle::Monitors => "monitors",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Toggle> {
+        Toggle::ALL.into_iter().find(|toggle| toggle.name() == name)
+    }
+
+    /*
+     * the key of the config files that is switched
+     */
+    pub fn key(self) -> &'static str {
+        match self {
+            Toggle::Popup => "show_popup",
+            Toggle::Sound => "play_sound",
+            Toggle::Notification => "show_notification",
+            Toggle::Monitors => "turn_off_monitors",
+        }
+    }
+
+    pub fn get(self, config: &Config) -> bool {
+        match self {
+            Toggle::Popup => config.show_popup,
+            Toggle::Sound => config.play_sound,
+            Toggle::Notification => config.show_notification,
+            Toggle::Monitors => config.turn_off_monitors,
+        }
+    }
+
+    pub fn set(self, config: &mut Config, on: bool) {
+        match self {
+            Toggle::Popup => config.show_popup = on,
+            Toggle::Sound => config.play_sound = on,
+            Toggle::Notification => config.show_notification = on,
+            Toggle::Monitors => config.turn_off_monitors = on,
+        }
+    }
+}
+
+/*
+ * the urgency of the break notifications, notification daemons may style or sort them by it
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    pub fn name(self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/*
+ * horizontal alignment of the text on the pop-up
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    pub fn name(self) -> &'static str {
+        match self {
+            Align::Left => "left",
+            Align::Center => "center",
+            Align::Right => "right",
+        }
+    }
+}
+
+/*
+ * settings that only apply on certain weekdays, e.g. `Sat,Sun: show_popup=false`
+ */
+#[derive(Debug, Clone)]
+pub struct ScheduleOverride {
+    pub weekdays: Vec<Weekday>,
+    content: String,
+}
+
+impl ScheduleOverride {
+    /*
+     * the settings as they were given, e.g. "show_popup=false"
+     */
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/*
+ * a break at a fixed time of the day, e.g. `fixed_break=12:30 for 45m`
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedBreak {
+    pub start: Time,
+    pub duration: Seconds,
+}
+
+/*
+ * a time window of the day without sounds, e.g. `quiet_hours=22:00-08:00` -- windows that end
+ * before they start span midnight
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub start: Time,
+    pub end: Time,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Config {
-    pub break_interval: u64,
-    pub break_duration: u64,
+    pub break_interval: Seconds,
+    // every work interval is moved by a random offset of at most this many seconds, either way
+    pub break_interval_jitter: Seconds,
+    // the next break is moved if it would start earlier after the last one, e.g. a fixed break
+    // right after a manual one
+    pub min_work_between_breaks: Seconds,
+    // the work timer is paused after this long without input, 0 never pauses it
+    pub auto_pause_after: Seconds,
+    pub break_duration: Seconds,
+    // after the break, the overlay fades out over it while the desktop is restored
+    pub grace_period: Seconds,
     pub show_popup: bool,
+    // only read at startup, show_popup switches the overlay of a connected daemon
+    pub popup: PopupMode,
     pub play_sound: bool,
+    // a soft cue with the notification that announces the break, only if sounds are played
+    pub pre_break_sound: bool,
+    // no sounds are played in it, the overlay and the notifications are unaffected
+    pub quiet_hours: Option<QuietHours>,
+    // name of the output device, None means the default output is used
+    pub audio_device: Option<String>,
+    // a directory with the sounds of the cues, None means the built-in sounds are used
+    pub sound_theme: Option<String>,
     pub show_notification: bool,
+    pub notification_urgency: Urgency,
+    // path to an image or the name of an icon from the icon theme, None means no icon is sent
+    pub notification_icon: Option<String>,
+    // the application name the notifications are sent with
+    pub notification_app_name: String,
+    // the texts of the notifications announcing a break and ending it, with placeholders like
+    // {remaining}, see template.rs
+    pub pre_break_summary: String,
+    pub pre_break_body: String,
+    pub break_end_summary: String,
+    pub break_end_body: String,
+    // only on niri, its workspaces are put back on their outputs after the break
     pub turn_off_monitors: bool,
+    pub on_resume: OnResume,
+    pub suspend_counts_as: SuspendCountsAs,
+    // coming back after at least this long without input is handled like a suspension (see
+    // on_resume), 0 only after suspensions
+    pub resume_after_idle: Seconds,
+    // every consecutively skipped break shortens the next work interval by escalation_step, and
+    // after escalation_limit skips the next break can't be skipped
+    pub escalation: bool,
+    pub escalation_step: Seconds,
+    pub escalation_limit: u64,
+    // the break time skipped today is tracked either way, the policy decides how it's made up for
+    pub debt: DebtPolicy,
+    // only used when not started through the systemd socket
+    pub request_autostart: bool,
+    pub popup_align: Align,
+    // in pixels
+    pub popup_margin: u32,
+    pub popup_title: String,
+    pub popup_subtitle: String,
+    // path to an SVG file, None means the bundled icon is used
+    pub popup_icon: Option<String>,
+    pub popup_cursor: CursorMode,
+    // the overlay on the layer shell's overlay layer, above fullscreen windows such as games
+    pub popup_on_top: bool,
+    // shown as a QR code in a corner of the overlay, e.g. a page with stretches
+    pub popup_qr_url: Option<String>,
+    // the wall-clock time in a top corner of the overlay, in strftime format, None hides it
+    pub popup_clock: Option<String>,
+    // a fontconfig pattern for the text of the overlay and the toasts, None means the built-in font
+    pub font: Option<String>,
+    // the overlay is drawn at this fraction of the output's resolution and scaled up by the
+    // compositor, 1 draws every pixel
+    pub render_scale: f32,
+    pub theme: Theme,
+    pub high_contrast: bool,
+    pub phase_palette: PhasePalette,
+    pub reduce_motion: bool,
+    // sends urgent notifications at every phase change, so screen readers can read them out
+    pub announce_phases: bool,
+    // focuses the window that was focused before the break again afterwards
+    pub restore_focus: bool,
+    // postpones breaks by dnd_postpone while the notification daemon is in do-not-disturb mode
+    pub postpone_on_dnd: bool,
+    pub dnd_postpone: Seconds,
+    // in percent, below it breaks are only announced through a notification while on battery
+    pub battery_saver_below: Option<u32>,
+    // warms and dims the screen during the lead-in and the break
+    pub gamma_shift: bool,
+    // in Kelvin
+    pub gamma_temperature: u32,
+    // in percent
+    pub gamma_brightness: u32,
+    // in strict mode, breaks can't be skipped by interacting with the overlay, nor left through the
+    // compositor's shortcuts
+    pub strict: bool,
+    // strict breaks grab the keyboards and pointers through evdev, for compositors that don't
+    // inhibit their shortcuts -- needs read access to /dev/input
+    pub grab_input: bool,
+    // the countdown of a break that isn't strict starts over with every input, so a break that's
+    // waited out at the keyboard doesn't count as rest
+    pub extend_break_on_activity: bool,
+    // where breaks can be skipped from, strict breaks have a list of their own
+    pub skip_from: Vec<SkipSource>,
+    pub strict_skip_from: Vec<SkipSource>,
+    // taken regardless of the rolling interval, rolling breaks shortly before it are merged into it
+    pub fixed_break: Option<FixedBreak>,
+    // the address this daemon publishes its schedule on as the master, e.g. "0.0.0.0:7878"
+    pub remote_listen: Option<String>,
+    // the master's address, this daemon then follows its breaks and skips
+    pub remote_master: Option<String>,
+    // shared by the master and its peers, remote breaks are disabled without it
+    pub remote_token: Option<String>,
+    // shell commands run at the start and the end of every work interval, e.g. "timew start work"
+    pub time_tracker_start: Option<String>,
+    pub time_tracker_stop: Option<String>,
+    // path to an org file, every work interval is appended to it as a CLOCK line
+    pub time_tracker_org: Option<String>,
+    // how long the helper waits for the daemon's answer, 0 waits forever
+    pub helper_timeout: Seconds,
+    pub schedule_overrides: Vec<ScheduleOverride>,
 }
 
-fn read_configuration(config: &mut Config, content: String) {
-    let re = Regex::new(r"break_interval=(\d+)(s|m)?").unwrap();
-    if let Some(c) = re.captures(&content) {
-        let mut num = c
-            .get(1)
-            .unwrap()
-            .as_str()
-            .parse::<u64>()
-            .expect("Unexpected casting error");
-        if c.get(2).is_some_and(|m| m.as_str() == "m") {
-            num = num * 60;
+/*
+ * the options given on the daemon's command line
+ */
+#[derive(Debug, Clone, Default)]
+pub struct CommandLine {
+    // read instead of the usual config files
+    pub config_file: Option<String>,
+    pub break_interval: Option<Seconds>,
+    pub show_popup: Option<bool>,
+    // started by the session socket of a multi-user machine, as root
+    pub system_session: bool,
+}
+
+impl CommandLine {
+    fn apply(&self, config: &mut Config) {
+        if let Some(break_interval) = self.break_interval {
+            config.break_interval = work_interval_floor(break_interval);
+        }
+        if let Some(show_popup) = self.show_popup {
+            config.show_popup = show_popup;
         }
-        config.break_interval = num;
     }
+}
 
-    let re = Regex::new(r"break_duration=(\d+)(s|m)?").unwrap();
-    if let Some(c) = re.captures(&content) {
-        let mut num = c
-            .get(1)
-            .unwrap()
-            .as_str()
-            .parse::<u64>()
-            .expect("Unexpected casting error");
-        if c.get(2).is_some_and(|m| m.as_str() == "m") {
-            num = num * 60;
+pub fn set_command_line(command_line: CommandLine) {
+    if COMMAND_LINE.set(command_line).is_err() {
+        println!("The command line was already parsed!");
+    }
+}
+
+impl Config {
+    /*
+     * returns the configuration with all overrides for the current weekday applied
+     */
+    pub fn for_today(&self) -> Config {
+        self.for_weekday(Zoned::now().weekday())
+    }
+
+    pub fn for_weekday(&self, weekday: Weekday) -> Config {
+        let mut config = self.clone();
+        SILENT.set(true);
+        for schedule_override in &self.schedule_overrides {
+            if schedule_override.weekdays.contains(&weekday) {
+                read_configuration(&mut config, schedule_override.content.clone());
+            }
         }
-        config.break_duration = num;
+        // the weekday overrides are part of the config files, the environment beats them as well
+        read_environment_overrides(&mut config);
+        SILENT.set(false);
+        // not even the weekday overrides beat the command line
+        if let Some(command_line) = COMMAND_LINE.get() {
+            command_line.apply(&mut config);
+        }
+        config
     }
 
-    let re = Regex::new(r"show_popup=(true|false)").unwrap();
-    if let Some(c) = re.captures(&content) {
-        let value = c.get(1).unwrap().as_str() == "true";
-        config.show_popup = value;
+    /*
+     * returns the seconds until today's fixed break starts, None if there is none or it's already
+     * over
+     */
+    pub fn seconds_until_fixed_break(&self) -> Option<Seconds> {
+        let fixed_break = self.fixed_break?;
+        let now = Zoned::now();
+        let start = now
+            .date()
+            .to_datetime(fixed_break.start)
+            .to_zoned(now.time_zone().clone())
+            .ok()?;
+        let seconds = now.duration_until(&start).as_secs();
+        (seconds >= 0).then_some(Seconds::new(seconds as u64))
+    }
+
+    /*
+     * returns true if it's currently within the quiet hours
+     */
+    pub fn quiet_now(&self) -> bool {
+        self.quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contains(Zoned::now().time()))
+    }
+
+    /*
+     * true if the break may be skipped from the source, strict breaks follow their own list
+     */
+    pub fn skip_allowed(&self, source: SkipSource) -> bool {
+        if self.strict {
+            self.strict_skip_from.contains(&source)
+        } else {
+            self.skip_from.contains(&source)
+        }
+    }
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    match day {
+        "Mon" => Some(Weekday::Monday),
+        "Tue" => Some(Weekday::Tuesday),
+        "Wed" => Some(Weekday::Wednesday),
+        "Thu" => Some(Weekday::Thursday),
+        "Fri" => Some(Weekday::Friday),
+        "Sat" => Some(Weekday::Saturday),
+        "Sun" => Some(Weekday::Sunday),
+        _ => None,
+    }
+}
+
+/*
+ * moves all lines starting with a list of weekdays into the override table and returns the
+ * remaining content
+ */
+fn read_schedule_overrides(config: &mut Config, content: String) -> String {
+    let re = Regex::new(SCHEDULE_OVERRIDE).unwrap();
+
+    let mut remainder = String::new();
+    for line in content.lines() {
+        match re.captures(line) {
+            Some(c) => {
+                let weekdays: Option<Vec<Weekday>> = c
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .split(',')
+                    .map(|day| parse_weekday(day.trim()))
+                    .collect();
+                // the pattern only lets weekdays through, a line that slips by anyway is dropped
+                let Some(weekdays) = weekdays else {
+                    warn(format!(
+                        "The weekdays of '{}' can't be read, the line is ignored.",
+                        line.trim()
+                    ));
+                    continue;
+                };
+                config.schedule_overrides.push(ScheduleOverride {
+                    weekdays,
+                    content: c.get(2).unwrap().as_str().trim().to_string(),
+                });
+            }
+            None => {
+                remainder.push_str(line);
+                remainder.push('\n');
+            }
+        }
+    }
+    remainder
+}
+
+/*
+ * drops the sections whose condition doesn't hold on this machine and returns the remaining
+ * content, so the same file can be shared by several machines (e.g. through a dotfiles repository)
+ */
+fn read_conditional_sections(content: &str, path: &str) -> String {
+    let re = Regex::new(CONDITIONAL_SECTION).unwrap();
+    let hostname = hostname();
+
+    // the keys before the first section apply everywhere
+    let mut applies = true;
+    let mut remainder = String::new();
+    for line in content.lines() {
+        let line = match re.captures(line) {
+            Some(c) => {
+                applies = condition_holds(&c[1], hostname.as_deref(), path);
+                c.get(2).unwrap().as_str()
+            }
+            None => line,
+        };
+        if applies {
+            remainder.push_str(line);
+            remainder.push('\n');
+        }
+    }
+    remainder
+}
+
+/*
+ * host:<name> holds on the machine of that hostname, env:<NAME>=<value> while the variable has the
+ * value and env:<NAME> while it's set at all
+ */
+fn condition_holds(condition: &str, hostname: Option<&str>, path: &str) -> bool {
+    match condition.trim().split_once(':') {
+        Some(("host", host)) => hostname == Some(host.trim()),
+        Some(("env", variable)) => match variable.split_once('=') {
+            Some((name, value)) => env::var_os(name.trim()).is_some_and(|set| set == value),
+            None => env::var_os(variable.trim()).is_some(),
+        },
+        _ => {
+            warn(format!(
+                "The config file '{path}' has the unknown section [{condition}], its keys are ignored."
+            ));
+            false
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    fs::read_to_string(HOSTNAME_PATH)
+        .ok()
+        .map(|hostname| hostname.trim().to_string())
+}
+
+/*
+ * reads a duration in seconds, which may be suffixed with 's' or 'm'
+ */
+fn read_seconds(content: &str, key: &str) -> Option<Seconds> {
+    let re = Regex::new(&format!(r"\b{key}=(\d+)(s|m)?\b")).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    let Ok(num) = c[1].parse::<u64>() else {
+        warn(format!(
+            "The {key} of {} is too large, it's ignored.",
+            &c[1]
+        ));
+        return None;
     };
+    let seconds = if c.get(2).is_some_and(|m| m.as_str() == "m") {
+        Minutes::new(num).to_seconds()
+    } else {
+        Some(Seconds::new(num))
+    };
+    at_most_max_duration(seconds, key, &c[0][key.len() + 1..])
+}
 
-    let re = Regex::new(r"play_sound=(true|false)").unwrap();
-    if let Some(c) = re.captures(&content) {
-        let value = c.get(1).unwrap().as_str() == "true";
-        config.play_sound = value;
+/*
+ * drops durations beyond protocol::MAX_DURATION (or too large for seconds at all), the clocks would
+ * overflow adding them -- like the helper's durations
+ */
+fn at_most_max_duration(seconds: Option<Seconds>, key: &str, value: &str) -> Option<Seconds> {
+    let seconds = seconds.filter(|seconds| *seconds <= protocol::MAX_DURATION);
+    if seconds.is_none() {
+        warn(format!(
+            "The {key} of {value} is longer than {} days, it's ignored.",
+            protocol::MAX_DURATION.as_secs() / (24 * 60 * 60)
+        ));
+    }
+    seconds
+}
+
+/*
+ * raises work intervals below MIN_WORK_INTERVAL to it
+ */
+pub fn work_interval_floor(seconds: Seconds) -> Seconds {
+    if seconds < MIN_WORK_INTERVAL {
+        warn(format!(
+            "The work interval of {} seconds is too short, using {} seconds instead.",
+            seconds.as_secs(),
+            MIN_WORK_INTERVAL.as_secs()
+        ));
+        return MIN_WORK_INTERVAL;
+    }
+    seconds
+}
+
+/*
+ * reads a duration that may be prefixed with '±' (or '+-'), e.g. break_interval_jitter=±5m
+ */
+fn read_jitter(content: &str, key: &str) -> Option<Seconds> {
+    let re = Regex::new(&format!(r"\b{key}=(?:±|\+-)?(\d+)(s|m)?\b")).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    let Ok(num) = c[1].parse::<u64>() else {
+        warn(format!(
+            "The {key} of {} is too large, it's ignored.",
+            &c[1]
+        ));
+        return None;
+    };
+    let seconds = if c.get(2).is_some_and(|m| m.as_str() == "m") {
+        Minutes::new(num).to_seconds()
+    } else {
+        Some(Seconds::new(num))
+    };
+    at_most_max_duration(seconds, key, &c[0][key.len() + 1..])
+}
+
+fn read_number(content: &str, key: &str) -> Option<u64> {
+    let re = Regex::new(&format!(r"\b{key}=(\d+)\b")).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    match c[1].parse::<u64>() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            warn(format!(
+                "The {key} of {} is too large, it's ignored.",
+                &c[1]
+            ));
+            None
+        }
+    }
+}
+
+/*
+ * reads a number like read_number, one that doesn't fit into 32 bits is ignored
+ */
+fn read_u32(content: &str, key: &str) -> Option<u32> {
+    let number = read_number(content, key)?;
+    match u32::try_from(number) {
+        Ok(number) => Some(number),
+        Err(_) => {
+            warn(format!("The {key} of {number} is too large, it's ignored."));
+            None
+        }
+    }
+}
+
+/*
+ * reads a scale between MIN_RENDER_SCALE and 1, e.g. render_scale=0.5 -- others are clamped
+ */
+fn read_scale(content: &str, key: &str) -> Option<f32> {
+    let re = Regex::new(&format!(r"\b{key}=(\d+(?:\.\d+)?)\b")).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    // digits always parse, very long ones as infinity
+    let scale = c[1].parse::<f32>().unwrap_or(f32::INFINITY);
+    let clamped = scale.clamp(MIN_RENDER_SCALE, 1.0);
+    if clamped != scale {
+        warn(format!(
+            "The {key} of {scale} is out of range, using {clamped} instead."
+        ));
+    }
+    Some(clamped)
+}
+
+fn read_bool(content: &str, key: &str) -> Option<bool> {
+    let re = Regex::new(&format!(r"\b{key}=(true|false)\b")).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    Some(&c[1] == "true")
+}
+
+/*
+ * reads a strftime format, e.g. popup_clock="%H:%M" -- an empty format hides the clock, an invalid
+ * one is ignored
+ */
+fn read_clock_format(content: &str, key: &str) -> Option<Option<String>> {
+    let format = read_string(content, key)?;
+    if format.is_empty() {
+        return Some(None);
+    }
+    match jiff::fmt::strtime::format(format.as_str(), &Zoned::now()) {
+        Ok(_) => Some(Some(format)),
+        Err(err) => {
+            warn(format!(
+                "The {key} \"{format}\" is not a valid time format! The error: {err}"
+            ));
+            None
+        }
+    }
+}
+
+/*
+ * reads a quoted string, e.g. popup_title="Time for a break"
+ */
+fn read_string(content: &str, key: &str) -> Option<String> {
+    let re = Regex::new(&format!(r#"\b{key}="([^"]*)""#)).unwrap();
+    let Some(c) = re.captures(content) else {
+        report_unreadable(content, key);
+        return None;
+    };
+    Some(c[1].to_string())
+}
+
+/*
+ * reads a comma separated list of skip sources, e.g. skip_from=cli,notification -- only an
+ * explicitly empty list (skip_from= at the end of the line) forbids skipping altogether, a list
+ * without any known source is ignored
+ */
+fn read_skip_sources(content: &str, key: &str) -> Option<Vec<SkipSource>> {
+    let empty = Regex::new(&format!(r"\b{key}=[ \t]*(?:\n|$)")).unwrap();
+    let re = Regex::new(&format!(r"\b{key}=([a-z,]+)(?:\s|$)")).unwrap();
+    let Some(captures) = re.captures(content) else {
+        if empty.is_match(content) {
+            return Some(Vec::new());
+        }
+        report_unreadable(content, key);
+        return None;
     };
+    let mut sources = Vec::new();
+    for name in captures[1].split(',').filter(|name| !name.is_empty()) {
+        match SkipSource::from_name(name) {
+            Some(source) => sources.push(source),
+            None => warn(format!(
+                "Ignoring the unknown skip source '{name}' of {key}."
+            )),
+        }
+    }
+    if sources.is_empty() {
+        warn(format!(
+            "The {key} of '{}' names no known skip source, it's ignored.",
+            &captures[1]
+        ));
+        return None;
+    }
+    Some(sources)
+}
+
+fn read_fixed_break(content: &str) -> Option<FixedBreak> {
+    let regex = Regex::new(r"\bfixed_break=(\d{1,2}):(\d{2}) for (\d+)(s|m)?\b").unwrap();
+    let Some(captures) = regex.captures(content) else {
+        report_unreadable(content, "fixed_break");
+        return None;
+    };
+    let hour = captures[1].parse::<i8>().ok()?;
+    let minute = captures[2].parse::<i8>().ok()?;
+    let start = match Time::new(hour, minute, 0, 0) {
+        Ok(start) => start,
+        Err(err) => {
+            warn(format!(
+                "Ignoring the fixed break, its time is invalid! The error: {err}"
+            ));
+            return None;
+        }
+    };
+    let Ok(number) = captures[3].parse::<u64>() else {
+        warn("Ignoring the fixed break, its duration is too large.".to_string());
+        return None;
+    };
+    let duration = if captures.get(4).is_some_and(|unit| unit.as_str() == "m") {
+        Minutes::new(number).to_seconds()
+    } else {
+        Some(Seconds::new(number))
+    };
+    let value = captures[0].strip_prefix("fixed_break=").unwrap_or_default();
+    let duration = at_most_max_duration(duration, "fixed_break", value)?;
+    Some(FixedBreak { start, duration })
+}
+
+fn read_quiet_hours(content: &str) -> Option<QuietHours> {
+    let regex = Regex::new(r"\bquiet_hours=(\d{1,2}):(\d{2})-(\d{1,2}):(\d{2})\b").unwrap();
+    let Some(captures) = regex.captures(content) else {
+        report_unreadable(content, "quiet_hours");
+        return None;
+    };
+    let time = |hour: usize| -> Option<Time> {
+        let time = Time::new(
+            captures[hour].parse::<i8>().ok()?,
+            captures[hour + 1].parse::<i8>().ok()?,
+            0,
+            0,
+        );
+        match time {
+            Ok(time) => Some(time),
+            Err(err) => {
+                warn(format!(
+                    "Ignoring the quiet hours, a time is invalid! The error: {err}"
+                ));
+                None
+            }
+        }
+    };
+    Some(QuietHours {
+        start: time(1)?,
+        end: time(3)?,
+    })
+}
+
+fn read_configuration(config: &mut Config, content: String) {
+    if let Some(value) = read_seconds(&content, "work_interval") {
+        config.break_interval = work_interval_floor(value);
+    }
+
+    if let Some(value) = read_jitter(&content, "break_interval_jitter") {
+        config.break_interval_jitter = value;
+    }
 
-    let re = Regex::new(r"show_notification=(true|false)").unwrap();
+    if let Some(value) = read_seconds(&content, "min_work_between_breaks") {
+        config.min_work_between_breaks = value;
+    }
+
+    if let Some(value) = read_seconds(&content, "auto_pause_after") {
+        config.auto_pause_after = value;
+    }
+
+    if let Some(value) = read_seconds(&content, "break_duration") {
+        if value < MIN_BREAK_DURATION {
+            warn(format!(
+                "The break_duration of {} seconds is too short, using {} seconds instead.",
+                value.as_secs(),
+                MIN_BREAK_DURATION.as_secs()
+            ));
+        }
+        config.break_duration = value.max(MIN_BREAK_DURATION);
+    }
+
+    if let Some(value) = read_seconds(&content, "grace_period") {
+        config.grace_period = value;
+    }
+
+    if let Some(value) = read_bool(&content, "show_popup") {
+        config.show_popup = value;
+    }
+
+    let re = Regex::new(r"\bpopup=(auto|always|never)\b").unwrap();
     if let Some(c) = re.captures(&content) {
-        let value = c.get(1).unwrap().as_str() == "true";
+        config.popup = match c.get(1).unwrap().as_str() {
+            "always" => PopupMode::Always,
+            "never" => PopupMode::Never,
+            _ => PopupMode::Auto,
+        };
+    } else {
+        report_unreadable(&content, "popup");
+    }
+
+    if let Some(value) = read_bool(&content, "play_sound") {
+        config.play_sound = value;
+    }
+
+    if let Some(value) = read_bool(&content, "pre_break_sound") {
+        config.pre_break_sound = value;
+    }
+
+    if let Some(value) = read_quiet_hours(&content) {
+        config.quiet_hours = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "audio_device") {
+        config.audio_device = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "sound_theme") {
+        // an empty path keeps the built-in sounds
+        config.sound_theme = Some(value).filter(|path| !path.is_empty());
+    }
+
+    if let Some(value) = read_bool(&content, "show_notification") {
         config.show_notification = value;
-    };
+    }
 
-    let re = Regex::new(r"turn_off_monitors=(true|false)").unwrap();
+    let re = Regex::new(r"\bnotification_urgency=(low|normal|critical)\b").unwrap();
     if let Some(c) = re.captures(&content) {
-        let value = c.get(1).unwrap().as_str() == "true";
+        config.notification_urgency = match c.get(1).unwrap().as_str() {
+            "low" => Urgency::Low,
+            "critical" => Urgency::Critical,
+            _ => Urgency::Normal,
+        };
+    } else {
+        report_unreadable(&content, "notification_urgency");
+    }
+
+    if let Some(value) = read_string(&content, "notification_icon") {
+        config.notification_icon = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "notification_app_name") {
+        config.notification_app_name = value;
+    }
+
+    if let Some(value) = read_string(&content, "pre_break_summary") {
+        config.pre_break_summary = value;
+    }
+
+    if let Some(value) = read_string(&content, "pre_break_body") {
+        config.pre_break_body = value;
+    }
+
+    if let Some(value) = read_string(&content, "break_end_summary") {
+        config.break_end_summary = value;
+    }
+
+    if let Some(value) = read_string(&content, "break_end_body") {
+        config.break_end_body = value;
+    }
+
+    if let Some(value) = read_bool(&content, "turn_off_monitors") {
         config.turn_off_monitors = value;
-    };
+    }
+
+    let re = Regex::new(r"\bon_resume=(reset|continue|prompt)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.on_resume = match c.get(1).unwrap().as_str() {
+            "continue" => OnResume::Continue,
+            "prompt" => OnResume::Prompt,
+            _ => OnResume::Reset,
+        };
+    } else {
+        report_unreadable(&content, "on_resume");
+    }
+
+    let re = Regex::new(r"\bsuspend_counts_as=(break|pause|nothing)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.suspend_counts_as = match c.get(1).unwrap().as_str() {
+            "break" => SuspendCountsAs::Break,
+            "nothing" => SuspendCountsAs::Nothing,
+            _ => SuspendCountsAs::Pause,
+        };
+    } else {
+        report_unreadable(&content, "suspend_counts_as");
+    }
+    if let Some(value) = read_seconds(&content, "resume_after_idle") {
+        config.resume_after_idle = value;
+    }
+
+    if let Some(value) = read_bool(&content, "escalation") {
+        config.escalation = value;
+    }
+
+    if let Some(value) = read_seconds(&content, "escalation_step") {
+        config.escalation_step = value;
+    }
+
+    if let Some(value) = read_number(&content, "escalation_limit") {
+        config.escalation_limit = value;
+    }
+
+    let re = Regex::new(r"\bdebt=(ignore|extend|extra_break)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.debt = match c.get(1).unwrap().as_str() {
+            "extend" => DebtPolicy::Extend,
+            "extra_break" => DebtPolicy::ExtraBreak,
+            _ => DebtPolicy::Ignore,
+        };
+    } else {
+        report_unreadable(&content, "debt");
+    }
+
+    if let Some(value) = read_bool(&content, "request_autostart") {
+        config.request_autostart = value;
+    }
+
+    let re = Regex::new(r"\bpopup_align=(left|center|right)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.popup_align = match c.get(1).unwrap().as_str() {
+            "left" => Align::Left,
+            "right" => Align::Right,
+            _ => Align::Center,
+        };
+    } else {
+        report_unreadable(&content, "popup_align");
+    }
+
+    if let Some(value) = read_u32(&content, "popup_margin") {
+        config.popup_margin = value;
+    }
+
+    if let Some(value) = read_string(&content, "popup_title") {
+        config.popup_title = value;
+    }
+
+    if let Some(value) = read_string(&content, "popup_subtitle") {
+        config.popup_subtitle = value;
+    }
+
+    if let Some(value) = read_string(&content, "popup_icon") {
+        config.popup_icon = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "popup_qr_url") {
+        config.popup_qr_url = Some(value);
+    }
+
+    if let Some(value) = read_clock_format(&content, "popup_clock") {
+        config.popup_clock = value;
+    }
+
+    if let Some(value) = read_string(&content, "font") {
+        config.font = Some(value);
+    }
+
+    if let Some(value) = read_scale(&content, "render_scale") {
+        config.render_scale = value;
+    }
+
+    let re = Regex::new(r"\bpopup_cursor=(hidden|default)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.popup_cursor = match c.get(1).unwrap().as_str() {
+            "default" => CursorMode::Default,
+            _ => CursorMode::Hidden,
+        };
+    } else {
+        report_unreadable(&content, "popup_cursor");
+    }
+
+    if let Some(value) = read_bool(&content, "popup_on_top") {
+        config.popup_on_top = value;
+    }
+
+    if let Some(value) = read_bool(&content, "strict") {
+        config.strict = value;
+    }
+
+    if let Some(value) = read_bool(&content, "grab_input") {
+        config.grab_input = value;
+    }
+
+    if let Some(value) = read_bool(&content, "extend_break_on_activity") {
+        config.extend_break_on_activity = value;
+    }
+
+    if let Some(value) = read_skip_sources(&content, "skip_from") {
+        config.skip_from = value;
+    }
+
+    if let Some(value) = read_skip_sources(&content, "strict_skip_from") {
+        config.strict_skip_from = value;
+    }
+
+    let re = Regex::new(r"\btheme=(auto|light|dark)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.theme = match c.get(1).unwrap().as_str() {
+            "light" => Theme::Light,
+            "dark" => Theme::Dark,
+            _ => Theme::Auto,
+        };
+    } else {
+        report_unreadable(&content, "theme");
+    }
+
+    if let Some(value) = read_bool(&content, "high_contrast") {
+        config.high_contrast = value;
+    }
+
+    let re = Regex::new(r"\bphase_palette=(default|okabe-ito|tol)\b").unwrap();
+    if let Some(c) = re.captures(&content) {
+        config.phase_palette = match c.get(1).unwrap().as_str() {
+            "okabe-ito" => PhasePalette::OkabeIto,
+            "tol" => PhasePalette::Tol,
+            _ => PhasePalette::Default,
+        };
+    } else {
+        report_unreadable(&content, "phase_palette");
+    }
+
+    if let Some(value) = read_bool(&content, "reduce_motion") {
+        config.reduce_motion = value;
+    }
+
+    if let Some(value) = read_bool(&content, "announce_phases") {
+        config.announce_phases = value;
+    }
+
+    if let Some(value) = read_bool(&content, "restore_focus") {
+        config.restore_focus = value;
+    }
+
+    if let Some(value) = read_bool(&content, "postpone_on_dnd") {
+        config.postpone_on_dnd = value;
+    }
+
+    if let Some(value) = read_seconds(&content, "dnd_postpone") {
+        config.dnd_postpone = value;
+    }
+
+    if let Some(value) = read_u32(&content, "battery_saver_below") {
+        // a percentage
+        if value > 100 {
+            warn(format!(
+                "The battery_saver_below of {value} is out of range, using 100 instead."
+            ));
+        }
+        config.battery_saver_below = Some(value.min(100));
+    }
+
+    if let Some(value) = read_bool(&content, "gamma_shift") {
+        config.gamma_shift = value;
+    }
+
+    if let Some(value) = read_u32(&content, "gamma_temperature") {
+        config.gamma_temperature = value;
+    }
+
+    if let Some(value) = read_u32(&content, "gamma_brightness") {
+        config.gamma_brightness = value;
+    }
+
+    if let Some(value) = read_fixed_break(&content) {
+        config.fixed_break = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "remote_listen") {
+        config.remote_listen = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "remote_master") {
+        config.remote_master = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "remote_token") {
+        // an empty token would let in every peer that sends an empty line
+        if value.is_empty() {
+            warn("The remote_token is empty, remote breaks are disabled.".to_string());
+            config.remote_token = None;
+        } else {
+            config.remote_token = Some(value);
+        }
+    }
+
+    if let Some(value) = read_string(&content, "time_tracker_start") {
+        config.time_tracker_start = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "time_tracker_stop") {
+        config.time_tracker_stop = Some(value);
+    }
+
+    if let Some(value) = read_string(&content, "time_tracker_org") {
+        config.time_tracker_org = Some(value);
+    }
+
+    if let Some(value) = read_seconds(&content, "helper_timeout") {
+        config.helper_timeout = value;
+    }
 }
 
-pub fn load_configuration() -> Result<Config, Box<dyn std::error::Error>> {
-    let mut config = Config {
-        break_interval: DEFAULT_BREAK_INTERVAL_SECONDS,
-        break_duration: DEFAULT_BREAK_DURATION_SECONDS,
+/*
+ * the configuration without any config file, e.g. for the snapshot tests of the overlay
+ */
+pub fn defaults() -> Config {
+    Config {
+        break_interval: DEFAULT_BREAK_INTERVAL,
+        break_interval_jitter: DEFAULT_BREAK_INTERVAL_JITTER,
+        min_work_between_breaks: DEFAULT_MIN_WORK_BETWEEN_BREAKS,
+        auto_pause_after: DEFAULT_AUTO_PAUSE_AFTER,
+        break_duration: DEFAULT_BREAK_DURATION,
+        grace_period: DEFAULT_GRACE_PERIOD,
         show_popup: DEFAULT_SHOW_POPUP,
+        popup: DEFAULT_POPUP,
         play_sound: DEFAULT_PLAY_SOUND,
+        pre_break_sound: DEFAULT_PRE_BREAK_SOUND,
+        quiet_hours: None,
+        audio_device: None,
+        sound_theme: None,
         show_notification: DEFAULT_SHOW_NOTIFICATION,
+        notification_urgency: DEFAULT_NOTIFICATION_URGENCY,
+        notification_icon: None,
+        notification_app_name: DEFAULT_NOTIFICATION_APP_NAME.to_string(),
+        pre_break_summary: DEFAULT_PRE_BREAK_SUMMARY.to_string(),
+        pre_break_body: DEFAULT_PRE_BREAK_BODY.to_string(),
+        break_end_summary: DEFAULT_BREAK_END_SUMMARY.to_string(),
+        break_end_body: DEFAULT_BREAK_END_BODY.to_string(),
         turn_off_monitors: DEFAULT_TURN_OFF_MONITORS,
+        on_resume: DEFAULT_ON_RESUME,
+        suspend_counts_as: DEFAULT_SUSPEND_COUNTS_AS,
+        resume_after_idle: DEFAULT_RESUME_AFTER_IDLE,
+        escalation: DEFAULT_ESCALATION,
+        escalation_step: DEFAULT_ESCALATION_STEP,
+        escalation_limit: DEFAULT_ESCALATION_LIMIT,
+        debt: DEFAULT_DEBT,
+        request_autostart: DEFAULT_REQUEST_AUTOSTART,
+        popup_align: DEFAULT_POPUP_ALIGN,
+        popup_margin: DEFAULT_POPUP_MARGIN,
+        popup_title: DEFAULT_POPUP_TITLE.to_string(),
+        popup_subtitle: DEFAULT_POPUP_SUBTITLE.to_string(),
+        popup_icon: None,
+        popup_cursor: DEFAULT_POPUP_CURSOR,
+        popup_on_top: DEFAULT_POPUP_ON_TOP,
+        popup_qr_url: None,
+        popup_clock: None,
+        font: None,
+        render_scale: DEFAULT_RENDER_SCALE,
+        theme: DEFAULT_THEME,
+        high_contrast: DEFAULT_HIGH_CONTRAST,
+        phase_palette: DEFAULT_PHASE_PALETTE,
+        reduce_motion: DEFAULT_REDUCE_MOTION,
+        announce_phases: DEFAULT_ANNOUNCE_PHASES,
+        restore_focus: DEFAULT_RESTORE_FOCUS,
+        postpone_on_dnd: DEFAULT_POSTPONE_ON_DND,
+        dnd_postpone: DEFAULT_DND_POSTPONE,
+        battery_saver_below: None,
+        gamma_shift: DEFAULT_GAMMA_SHIFT,
+        gamma_temperature: DEFAULT_GAMMA_TEMPERATURE,
+        gamma_brightness: DEFAULT_GAMMA_BRIGHTNESS,
+        strict: DEFAULT_STRICT,
+        grab_input: DEFAULT_GRAB_INPUT,
+        extend_break_on_activity: DEFAULT_EXTEND_BREAK_ON_ACTIVITY,
+        skip_from: DEFAULT_SKIP_FROM.to_vec(),
+        strict_skip_from: DEFAULT_STRICT_SKIP_FROM.to_vec(),
+        fixed_break: None,
+        remote_listen: None,
+        remote_master: None,
+        remote_token: None,
+        time_tracker_start: None,
+        time_tracker_stop: None,
+        time_tracker_org: None,
+        helper_timeout: DEFAULT_HELPER_TIMEOUT,
+        schedule_overrides: Vec::new(),
+    }
+}
+
+/*
+ * drops comment lines, so a commented-out key isn't read like a set one
+ */
+fn strip_comments(content: &str) -> String {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+/*
+ * the sources as they're written in the config, e.g. "cli,overlay"
+ */
+pub fn skip_source_names(sources: &[SkipSource]) -> String {
+    sources
+        .iter()
+        .map(|source| source.name())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/*
+ * the config file written by `wlbreaktime-helper init-config`, every key is commented out with its
+ * default value -- it's generated from the defaults, so it can't drift from them
+ */
+// only the helper writes it
+#[allow(dead_code)]
+pub fn default_config_file() -> String {
+    // a new field doesn't compile until it's documented here
+    let Config {
+        break_interval,
+        break_interval_jitter,
+        min_work_between_breaks,
+        auto_pause_after,
+        break_duration,
+        grace_period,
+        show_popup,
+        popup,
+        play_sound,
+        pre_break_sound,
+        quiet_hours: _,
+        audio_device: _,
+        sound_theme: _,
+        show_notification,
+        notification_urgency,
+        notification_icon: _,
+        notification_app_name,
+        pre_break_summary,
+        pre_break_body,
+        break_end_summary,
+        break_end_body,
+        turn_off_monitors,
+        on_resume,
+        suspend_counts_as,
+        resume_after_idle,
+        escalation,
+        escalation_step,
+        escalation_limit,
+        debt,
+        request_autostart,
+        popup_align,
+        popup_margin,
+        popup_title,
+        popup_subtitle,
+        popup_icon: _,
+        popup_qr_url: _,
+        popup_clock: _,
+        font: _,
+        render_scale,
+        popup_cursor,
+        popup_on_top,
+        theme,
+        high_contrast,
+        phase_palette,
+        reduce_motion,
+        announce_phases,
+        restore_focus,
+        postpone_on_dnd,
+        dnd_postpone,
+        battery_saver_below: _,
+        gamma_shift,
+        gamma_temperature,
+        gamma_brightness,
+        strict,
+        grab_input,
+        extend_break_on_activity,
+        skip_from,
+        strict_skip_from,
+        fixed_break: _,
+        remote_listen: _,
+        remote_master: _,
+        remote_token: _,
+        time_tracker_start: _,
+        time_tracker_stop: _,
+        time_tracker_org: _,
+        helper_timeout,
+        schedule_overrides: _,
+    } = defaults();
+
+    format!(
+        r#"# wlbreaktime's configuration
+#
+# every key is commented out with its default value, remove the '#' in front of a key to change it.
+# durations are given in seconds or suffixed with 's' or 'm', e.g. 90s or 30m, strings are quoted.
+config_version={version}
+
+# the time between breaks
+#work_interval={work_interval}
+# moves every break by a random offset of at most this much, so breaks don't always land at the
+# same minute
+#break_interval_jitter=±{break_interval_jitter}
+# the least work time between two breaks, later breaks are moved to keep it (e.g. a fixed break
+# right after a manual one)
+#min_work_between_breaks={min_work_between_breaks}
+# without input for this long, the work timer is paused until there is input again, so it only
+# counts actual work -- 0s never pauses it
+#auto_pause_after={auto_pause_after}
+# how long a break lasts
+#break_duration={break_duration}
+# after the break, the overlay fades out over the grace period, 0s removes it at once
+#grace_period={grace_period}
+
+# what announces a break
+#show_popup={show_popup}
+# whether to connect to the compositor: auto runs without the overlay (sound and notifications only)
+# outside of a Wayland session, e.g. on a TTY or under X11 (unless built with the x11 feature),
+# always refuses to start there and never doesn't connect at all
+#popup={popup}
+#play_sound={play_sound}
+# a soft cue with the notification 10 seconds before the break
+#pre_break_sound={pre_break_sound}
+# no sounds are played during these hours, the overlay and the notifications still are
+#quiet_hours=22:00-08:00
+# the name of the output device, the default output is used without it
+#audio_device="Built-in Audio"
+# a directory with the files pre_break, break_start, break_end and tick in any format that can be
+# played (e.g. break_start.ogg) -- missing ones are the built-in sounds: a chime before the break,
+# the gong when it starts and ends and no tick in its last seconds
+#sound_theme="/path/to/theme"
+#show_notification={show_notification}
+# how break notifications are sent: their urgency (low, normal or critical), an icon (a path to an
+# image or the name of an icon from the icon theme, none is sent without it) and the application name
+#notification_urgency={notification_urgency}
+#notification_icon="alarm-symbolic"
+#notification_app_name="{notification_app_name}"
+# the texts of the notifications announcing a break and ending it: {{remaining}} is the time until
+# the break (or the next one), {{break_duration}} the break's length and {{cycle}} the break's
+# number today, e.g. pre_break_body="Break {{cycle}} of the day, {{break_duration}} long"
+#pre_break_summary="{pre_break_summary}"
+#pre_break_body="{pre_break_body}"
+#break_end_summary="{break_end_summary}"
+#break_end_body="{break_end_body}"
+# only on niri, the workspaces are moved back to their outputs after the break
+#turn_off_monitors={turn_off_monitors}
+
+# what happens to the work timer after a suspension: reset, continue or prompt
+#on_resume={on_resume}
+# the same happens when there is input again after this long without any, 0s only after suspensions
+#resume_after_idle={resume_after_idle}
+# unless it's reset, how the suspension counts for the work timer: break (if it was at least as
+# long as a break, a fresh work interval starts), pause or nothing (it counts as work time)
+#suspend_counts_as={suspend_counts_as}
+# every consecutively skipped break shortens the next work interval by escalation_step, after
+# escalation_limit skips the next break can't be skipped
+#escalation={escalation}
+#escalation_step={escalation_step}
+#escalation_limit={escalation_limit}
+# the break time skipped today is made up for: ignore leaves it at that, extend lengthens the next
+# breaks by it (each by at most its own length), extra_break halves the next work interval once a
+# whole break is owed -- a manual break that's taken pays it back in any case
+#debt={debt}
+# breaks can't be skipped by interacting with the overlay, the compositor's shortcuts (e.g. switching
+# workspaces) are inhibited while it's shown
+#strict={strict}
+# strict breaks also grab the keyboards and pointers, for compositors that keep their shortcuts --
+# the daemon needs read access to /dev/input, e.g. through the input group
+#grab_input={grab_input}
+# breaks that aren't strict start over with every input (after a few seconds without any, the
+# countdown goes on), at most until they took three times as long -- needs ext-idle-notify
+#extend_break_on_activity={extend_break_on_activity}
+# where breaks can be skipped from (cli, overlay, notification), strict breaks have their own list
+#skip_from={skip_from}
+#strict_skip_from={strict_skip_from}
+# only used when not started through the systemd socket
+#request_autostart={request_autostart}
+
+# the overlay: the alignment of its text (left, center or right), its margin in pixels and its texts
+#popup_align={popup_align}
+#popup_margin={popup_margin}
+#popup_title="{popup_title}"
+#popup_subtitle="{popup_subtitle}"
+# path to an SVG file, an empty path disables the icon, the bundled coffee cup is used without it
+#popup_icon="/path/to/icon.svg"
+# the cursor above the overlay: hidden or default
+#popup_cursor={popup_cursor}
+# keeps the overlay above fullscreen windows (e.g. games) on compositors with the layer shell, on
+# the others the monitors are turned off during breaks instead, or a critical notification is sent
+#popup_on_top={popup_on_top}
+# a link shown as a QR code in the bottom corner of the overlay, e.g. to a stretching routine
+#popup_qr_url="https://example.com/stretches"
+# the current time in a top corner of the overlay, in strftime format, e.g. "%I:%M %p" -- an empty
+# format or none hides it
+#popup_clock="%H:%M"
+# a fontconfig pattern for the text of the overlay and the toasts, an empty pattern or none keeps the
+# built-in font -- characters the font lacks (e.g. CJK or emoji) come from fontconfig's fallbacks
+#font="Noto Sans:bold"
+# draws the overlay at this fraction of the output's resolution (at least 0.25) and lets the
+# compositor scale it up, e.g. 0.5 on 4K outputs -- only if the compositor supports wp_viewporter
+#render_scale={render_scale}
+# the colors of the overlay and the toasts: auto (the desktop's preference, dark without one),
+# light or dark -- high_contrast takes precedence on the overlay
+#theme={theme}
+#high_contrast={high_contrast}
+# the colors of work, the warning before a break and the break on the overlay, the toasts and in
+# `wlbreaktime-helper waybar`: default, okabe-ito or tol (both are safe for color blindness)
+#phase_palette={phase_palette}
+#reduce_motion={reduce_motion}
+# sends urgent notifications at every phase change, so screen readers can read them out
+#announce_phases={announce_phases}
+# focuses the window that was focused before the break again afterwards
+#restore_focus={restore_focus}
+
+# postpones breaks by dnd_postpone while the notification daemon is in do-not-disturb mode
+#postpone_on_dnd={postpone_on_dnd}
+#dnd_postpone={dnd_postpone}
+# in percent, below it breaks are only announced through a notification while on battery
+#battery_saver_below=20
+# warms and dims the screen during the lead-in and the break, the temperature in Kelvin and the
+# brightness in percent
+#gamma_shift={gamma_shift}
+#gamma_temperature={gamma_temperature}
+#gamma_brightness={gamma_brightness}
+
+# a break at a fixed time of the day, rolling breaks shortly before it are merged into it
+#fixed_break=12:30 for 45m
+
+# remote breaks: the master publishes its schedule on remote_listen, its peers follow the master at
+# remote_master, both need the same token
+#remote_listen="0.0.0.0:7878"
+#remote_master="192.168.1.2:7878"
+#remote_token="secret"
+
+# shell commands run at the start and the end of every work interval, and an org file every work
+# interval is appended to as a CLOCK line
+#time_tracker_start="timew start work"
+#time_tracker_stop="timew stop"
+#time_tracker_org="/path/to/work.org"
+
+# how long wlbreaktime-helper waits for the daemon's answer, 0s waits forever -- `get` and `status`
+# are sent once more before it gives up
+#helper_timeout={helper_timeout}
+
+# settings that only apply on certain weekdays
+#Sat,Sun: work_interval=60m show_popup=false
+
+# settings that only apply on some machines, up to the next section -- keep them at the end
+#[host:worklaptop]
+#work_interval=25m
+#[env:WAYLAND_DISPLAY=wayland-1]
+#turn_off_monitors=false
+"#,
+        version = migration::CONFIG_VERSION,
+        work_interval = break_interval.suffixed(),
+        break_interval_jitter = break_interval_jitter.suffixed(),
+        min_work_between_breaks = min_work_between_breaks.suffixed(),
+        auto_pause_after = auto_pause_after.suffixed(),
+        break_duration = break_duration.suffixed(),
+        grace_period = grace_period.suffixed(),
+        helper_timeout = helper_timeout.suffixed(),
+        notification_urgency = notification_urgency.name(),
+        on_resume = on_resume.name(),
+        suspend_counts_as = suspend_counts_as.name(),
+        resume_after_idle = resume_after_idle.suffixed(),
+        escalation_step = escalation_step.suffixed(),
+        debt = debt.name(),
+        popup_align = popup_align.name(),
+        popup_cursor = popup_cursor.name(),
+        popup = popup.name(),
+        theme = theme.name(),
+        phase_palette = phase_palette.name(),
+        skip_from = skip_source_names(&skip_from),
+        strict_skip_from = skip_source_names(&strict_skip_from),
+        dnd_postpone = dnd_postpone.suffixed(),
+    )
+}
+
+pub fn load_configuration() -> Result<Config, Box<dyn std::error::Error>> {
+    WARNINGS.lock().unwrap().clear();
+    let mut config = defaults();
+
+    let command_line = COMMAND_LINE.get().cloned().unwrap_or_default();
+    let config_paths = config_paths(command_line.config_file.as_deref())?;
+    read_config_files(
+        &mut config,
+        &config_paths,
+        command_line.config_file.is_some(),
+    )?;
+
+    // the weekday overrides are only applied on their days, but checked right away
+    for schedule_override in &config.schedule_overrides {
+        read_configuration(&mut config.clone(), schedule_override.content.clone());
+    }
+
+    read_environment_overrides(&mut config);
+    command_line.apply(&mut config);
+    Ok(config)
+}
+
+/*
+ * the files to read, the one given on the command line instead of the usual ones -- it comes with
+ * its drop-ins like every other
+ */
+fn config_paths(config_file: Option<&str>) -> Result<Vec<String>, VarError> {
+    match config_file {
+        Some(path) => Ok(std::iter::once(path.to_string())
+            .chain(paths::drop_in_paths(path))
+            .collect()),
+        None => paths::config_paths(),
+    }
+}
+
+/*
+ * reads the files in order, later ones take precedence -- missing ones are skipped, unless they are
+ * required (e.g. given on the command line)
+ */
+fn read_config_files(
+    config: &mut Config,
+    config_paths: &[String],
+    required: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for path in config_paths {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                let migrated = migration::migrate(&content);
+                if migrated.version > migration::CONFIG_VERSION {
+                    warn(format!(
+                        "The config file '{path}' is of a newer version ({}), keys this version doesn't know are ignored.",
+                        migrated.version
+                    ));
+                }
+                if !migrated.applied.is_empty() {
+                    warn(format!(
+                        "The config file '{path}' is of an older version ({}), run `wlbreaktime-helper migrate-config` to update it.",
+                        migrated.applied.join(", ")
+                    ));
+                }
+                let content = strip_comments(&migrated.content);
+                let content = read_conditional_sections(&content, path);
+                let content = read_schedule_overrides(config, content);
+                read_configuration(config, content);
+            }
+            // do nothing, just means that there is nothing configured on this level
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => {}
+            Err(err) => {
+                return Err(format!("The config file '{path}' could not be read: {err}").into());
+            }
+        };
+    }
+    Ok(())
+}
+
+/*
+ * sets the key in the user's config file (or the one given on the command line), e.g.
+ * work_interval=45m, and returns its path -- the first line setting the key is changed, comments,
+ * weekday overrides and conditional sections are left alone. the file is replaced at once, so it's
+ * never read half written, and a symlinked file (e.g. from a dotfiles repository) stays a symlink
+ *
+ * the drop-ins are read after the file, so if one of them sets the key, the last of those is
+ * changed instead -- otherwise it would still win over the new value
+ */
+pub fn persist(key: &str, value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let path = match COMMAND_LINE
+        .get()
+        .and_then(|command_line| command_line.config_file.clone())
+    {
+        Some(path) => path,
+        None => paths::user_config_path()?,
     };
+    persist_into(&path, key, value)
+}
 
-    match fs::read_to_string("/etc/".to_string() + CONFIG_PATH) {
-        Ok(content) => read_configuration(&mut config, content),
-        Err(err) if err.kind() == ErrorKind::NotFound => {}
-        // do nothing, just means that there is nothing configured on system level
-        Err(_) => panic!("Other error!"),
+/*
+ * sets the key in the file like persist, or in the last of its drop-ins that sets it
+ */
+fn persist_into(path: &str, key: &str, value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let path = paths::drop_in_paths(path)
+        .into_iter()
+        .rev()
+        .find(|drop_in| {
+            fs::read_to_string(drop_in)
+                .is_ok_and(|content| sets_key(&migration::migrate(&content).content, key))
+        })
+        .unwrap_or(path.to_string());
+    let path = match fs::canonicalize(&path) {
+        Ok(target) => target.to_string_lossy().into_owned(),
+        Err(_) => path,
     };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("The config file '{path}' could not be read: {err}").into()),
+    };
+    // the file is migrated first, otherwise an older name of the key would still be read
+    let content = migration::migrate(&content).content;
 
-    let config_home = match env::var("XDG_CONFIG_HOME") {
-        Ok(path) => path,
-        Err(err) if err == VarError::NotPresent => {
-            let home = env::var("HOME")?;
-            home + "/.config"
+    let setting = format!("{key}={value}");
+    let re = Regex::new(&format!(r"\b{key}=\S*")).unwrap();
+    let schedule_override = Regex::new(SCHEDULE_OVERRIDE).unwrap();
+    let conditional_section = Regex::new(CONDITIONAL_SECTION).unwrap();
+    let mut persisted = String::with_capacity(content.len() + setting.len() + 1);
+    let mut replaced = false;
+    // the keys of conditional sections only apply on some machines, they're left alone
+    let mut in_section = false;
+    for line in content.lines() {
+        if !in_section && conditional_section.is_match(line) {
+            in_section = true;
+            // a key that isn't set yet goes before the sections, so it applies everywhere
+            if !replaced {
+                persisted.push_str(&setting);
+                persisted.push('\n');
+                replaced = true;
+            }
         }
-        Err(err) => {
-            panic!("Error '{err}' occured while trying to read XDG_CONFIG_HOME!");
+        if !replaced
+            && !in_section
+            && !line.trim_start().starts_with('#')
+            && !schedule_override.is_match(line)
+            && re.is_match(line)
+        {
+            persisted.push_str(&re.replace(line, regex::NoExpand(&setting)));
+            replaced = true;
+        } else {
+            persisted.push_str(line);
         }
-    };
+        persisted.push('\n');
+    }
+    if !replaced {
+        persisted.push_str(&setting);
+        persisted.push('\n');
+    }
 
-    match fs::read_to_string(config_home + "/" + CONFIG_PATH) {
-        Ok(content) => read_configuration(&mut config, content),
-        Err(err) if err.kind() == ErrorKind::NotFound => {}
-        // do nothing, just means that there is nothing configured on user level
-        Err(_) => panic!("Other error!"),
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = format!("{path}.tmp");
+    fs::write(&temporary, persisted)?;
+    fs::rename(&temporary, &path)?;
+    Ok(path)
+}
+
+/*
+ * true if the content sets the key outside of comments, weekday overrides and conditional sections,
+ * like the line persist() changes
+ */
+fn sets_key(content: &str, key: &str) -> bool {
+    let re = Regex::new(&format!(r"\b{key}=\S*")).unwrap();
+    let schedule_override = Regex::new(SCHEDULE_OVERRIDE).unwrap();
+    let conditional_section = Regex::new(CONDITIONAL_SECTION).unwrap();
+    content
+        .lines()
+        .take_while(|line| !conditional_section.is_match(line))
+        .any(|line| {
+            !line.trim_start().starts_with('#')
+                && !schedule_override.is_match(line)
+                && re.is_match(line)
+        })
+}
+
+/*
+ * applies the WLBREAKTIME_* variables on top of the config files -- strings are quoted in the files,
+ * so every value is read both unquoted and quoted and each key only matches the form it expects
+ */
+fn read_environment_overrides(config: &mut Config) {
+    // unlike env::vars, variables that are not valid UTF-8 are skipped instead of panicking
+    let overrides: Vec<(String, String)> = env::vars_os()
+        .filter_map(|(name, value)| {
+            let key = name.to_str()?.strip_prefix(ENV_PREFIX)?.to_lowercase();
+            Some((key, value.to_str()?.trim_matches('"').to_string()))
+        })
+        .collect();
+    if overrides.is_empty() {
+        return;
+    }
+
+    let keys: Vec<&str> = overrides.iter().map(|(key, _)| key.as_str()).collect();
+    if !SILENT.get() && !UNLOGGED.get() {
+        println!("Overriding {} from the environment.", keys.join(", "));
+    }
+    let unquoted: String = overrides
+        .iter()
+        .map(|(key, value)| format!("{key}={value}\n"))
+        .collect();
+    let quoted: String = overrides
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{value}\"\n"))
+        .collect();
+    // without a config_version, the names of every version are understood. every key is only
+    // readable in one of the forms, so only those readable in neither are reported
+    let mut unreadable: Option<HashSet<String>> = None;
+    for content in [unquoted, quoted] {
+        UNREADABLE.set(Some(HashSet::new()));
+        read_configuration(config, migration::migrate(&content).content);
+        let keys = UNREADABLE.take().unwrap_or_default();
+        unreadable = Some(match unreadable {
+            Some(previous) => previous.intersection(&keys).cloned().collect(),
+            None => keys,
+        });
+    }
+    for key in unreadable.unwrap_or_default() {
+        warn(format!(
+            "The value of {ENV_PREFIX}{} can't be read, it's ignored.",
+            key.to_uppercase()
+        ));
+    }
+}
+
+/*
+ * reports something that's wrong with the config, the daemon goes on with what it could read
+ */
+fn warn(warning: String) {
+    if SILENT.get() {
+        return;
+    }
+    if !UNLOGGED.get() {
+        println!("{warning}");
+    }
+    WARNINGS.lock().unwrap().push(warning);
+}
+
+/*
+ * reports a key that is set, but to a value that can't be read -- it keeps its previous value
+ */
+fn report_unreadable(content: &str, key: &str) {
+    let re = Regex::new(&format!(r"\b{key}=(\S*)")).unwrap();
+    let Some(c) = re.captures(content) else {
+        return;
     };
+    let collected = UNREADABLE.with_borrow_mut(|unreadable| {
+        unreadable
+            .as_mut()
+            .map(|unreadable| unreadable.insert(key.to_string()))
+            .is_some()
+    });
+    if !collected {
+        warn(format!(
+            "The value '{}' of {key} can't be read, it's ignored.",
+            &c[1]
+        ));
+    }
+}
 
-    Ok(config)
+/*
+ * loads the config files without reporting anything, e.g. for the helper's own settings -- the
+ * daemon already reports what's wrong with them
+ */
+pub fn load_silently() -> Result<Config, Box<dyn std::error::Error>> {
+    SILENT.set(true);
+    let config = load_configuration();
+    SILENT.set(false);
+    config
+}
+
+/*
+ * loads the config files like the daemon and returns what's wrong with them, for
+ * `wlbreaktime-helper check-config` -- an error if a file can't be read at all
+ */
+// only the helper checks the config
+#[allow(dead_code)]
+pub fn check_configuration() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    load_configuration()?;
+    Ok(WARNINGS.lock().unwrap().clone())
+}
+
+/*
+ * like check_configuration, but the problems are only counted instead of logged again -- for the
+ * daemon's diagnostics, which are asked for repeatedly
+ */
+pub fn count_problems() -> Result<usize, Box<dyn std::error::Error>> {
+    UNLOGGED.set(true);
+    let result = load_configuration();
+    UNLOGGED.set(false);
+    result?;
+    Ok(WARNINGS.lock().unwrap().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // a variable that's set in every environment the tests run in, and one that's never set
+    const SET: &str = "PATH";
+    const UNSET: &str = "WLBREAKTIME_TEST_NEVER_SET";
+
+    // held by the tests that read the WLBREAKTIME_* variables, they change them
+    static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    /*
+     * a fresh directory for the config files of a single test
+     */
+    fn config_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("wlbreaktime-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("config.d")).unwrap();
+        dir
+    }
+
+    fn path(path: PathBuf) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    /*
+     * the config read from the content on top of the defaults, with the variables set while the
+     * environment is read on top of it
+     */
+    fn read_with_environment(content: &str, variables: &[(&str, &str)]) -> Config {
+        let _environment = ENVIRONMENT.lock().unwrap();
+        let (mut config, _) = read(content);
+        // SAFETY: the tests that read the environment hold the lock, nothing else reads it
+        unsafe {
+            for (name, value) in variables {
+                env::set_var(name, value);
+            }
+        }
+        read_environment_overrides(&mut config);
+        unsafe {
+            for (name, _) in variables {
+                env::remove_var(name);
+            }
+        }
+        config
+    }
+
+    /*
+     * the config read from the content on top of the defaults, along with the keys reported as
+     * unreadable
+     */
+    fn read(content: &str) -> (Config, HashSet<String>) {
+        let mut config = defaults();
+        UNREADABLE.set(Some(HashSet::new()));
+        read_configuration(&mut config, content.to_string());
+        (config, UNREADABLE.take().unwrap_or_default())
+    }
+
+    #[test]
+    fn skip_sources_are_read() {
+        let (config, unreadable) = read("skip_from=cli,overlay\nstrict_skip_from=notification\n");
+        assert_eq!(config.skip_from, vec![SkipSource::Cli, SkipSource::Overlay]);
+        assert_eq!(config.strict_skip_from, vec![SkipSource::Notification]);
+        assert!(unreadable.is_empty());
+    }
+
+    #[test]
+    fn only_an_empty_list_forbids_skipping() {
+        let (config, _) = read("skip_from=\n");
+        assert!(config.skip_from.is_empty());
+
+        for content in [
+            "skip_from=CLI\n",
+            "skip_from= cli\n",
+            "skip_from=Notification\n",
+        ] {
+            let (config, unreadable) = read(content);
+            assert_eq!(config.skip_from, defaults().skip_from, "{content}");
+            assert!(unreadable.contains("skip_from"), "{content}");
+        }
+        // a list without a single known source
+        let (config, _) = read("skip_from=mouse\n");
+        assert_eq!(config.skip_from, defaults().skip_from);
+    }
+
+    #[test]
+    fn durations_are_at_most_the_maximum() {
+        let (config, _) = read("break_duration=99999999999999s\nwork_interval=99999999999m\n");
+        assert_eq!(config.break_duration, defaults().break_duration);
+        assert_eq!(config.break_interval, defaults().break_interval);
+
+        let (config, _) = read(&format!(
+            "break_duration={}s\n",
+            protocol::MAX_DURATION.as_secs()
+        ));
+        assert_eq!(config.break_duration, protocol::MAX_DURATION);
+    }
+
+    #[test]
+    fn overlong_fixed_breaks_are_ignored() {
+        let (config, _) = read("fixed_break=12:00 for 99999999999999999m\n");
+        assert!(config.fixed_break.is_none());
+
+        let (config, _) = read("fixed_break=12:00 for 30m\n");
+        assert_eq!(
+            config.fixed_break.map(|fixed_break| fixed_break.duration),
+            Some(Seconds::new(30 * 60))
+        );
+    }
+
+    #[test]
+    fn drop_ins_override_their_file_in_order() {
+        let dir = config_dir("drop-ins");
+        let file = path(dir.join("config"));
+        fs::write(
+            &file,
+            "work_interval=30m\nbreak_duration=60s\nplay_sound=false\n",
+        )
+        .unwrap();
+        fs::write(dir.join("config.d/20-late.conf"), "break_duration=90s\n").unwrap();
+        fs::write(dir.join("config.d/10-early.conf"), "break_duration=70s\n").unwrap();
+        // not a drop-in
+        fs::write(dir.join("config.d/30-ignored.bak"), "play_sound=true\n").unwrap();
+
+        let config_paths = config_paths(Some(&file)).unwrap();
+        assert_eq!(
+            config_paths,
+            vec![
+                file.clone(),
+                path(dir.join("config.d/10-early.conf")),
+                path(dir.join("config.d/20-late.conf")),
+            ]
+        );
+
+        let mut config = defaults();
+        read_config_files(&mut config, &config_paths, true).unwrap();
+        // the last drop-in wins, the keys no drop-in sets keep the file's values
+        assert_eq!(config.break_duration, Seconds::new(90));
+        assert_eq!(config.break_interval, Seconds::new(30 * 60));
+        assert!(!config.play_sound);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_missing_file_is_only_an_error_if_required() {
+        let dir = config_dir("missing");
+        let missing = vec![path(dir.join("config"))];
+        assert!(read_config_files(&mut defaults(), &missing, false).is_ok());
+        assert!(read_config_files(&mut defaults(), &missing, true).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn persist_writes_into_the_last_drop_in_setting_the_key() {
+        let dir = config_dir("persist");
+        let file = path(dir.join("config"));
+        fs::write(&file, "work_interval=30m\n").unwrap();
+        fs::write(dir.join("config.d/10-a.conf"), "work_interval=20m\n").unwrap();
+        fs::write(dir.join("config.d/20-b.conf"), "work_interval=25m\n").unwrap();
+        fs::write(dir.join("config.d/30-c.conf"), "play_sound=false\n").unwrap();
+
+        let written = persist_into(&file, "work_interval", "45m").unwrap();
+        assert_eq!(
+            Path::new(&written),
+            dir.join("config.d/20-b.conf").canonicalize().unwrap()
+        );
+
+        let mut config = defaults();
+        read_config_files(&mut config, &config_paths(Some(&file)).unwrap(), true).unwrap();
+        assert_eq!(config.break_interval, Seconds::new(45 * 60));
+        assert_eq!(
+            fs::read_to_string(dir.join("config.d/10-a.conf")).unwrap(),
+            "work_interval=20m\n"
+        );
+
+        // a key no drop-in sets goes into the file itself
+        let written = persist_into(&file, "break_duration", "90s").unwrap();
+        assert_eq!(
+            Path::new(&written),
+            Path::new(&file).canonicalize().unwrap()
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_section_that_holds_is_kept() {
+        let content = format!("break_duration=60s\n[env:{SET}]\nplay_sound=false\n");
+        let remainder = read_conditional_sections(&content, "config");
+        // the section itself is left as an empty line
+        assert_eq!(remainder, "break_duration=60s\n\nplay_sound=false\n");
+
+        // keys may follow on the same line
+        let content = format!("[env:{SET}] play_sound=false\n");
+        let (config, _) = read(&read_conditional_sections(&content, "config"));
+        assert!(!config.play_sound);
+    }
+
+    #[test]
+    fn a_section_that_does_not_hold_is_dropped_until_the_next_one() {
+        let content = format!(
+            "break_duration=60s\n[env:{UNSET}]\nplay_sound=false\n[host:]\nshow_popup=false\n[env:{SET}]\nwork_interval=30m\n"
+        );
+        let remainder = read_conditional_sections(&content, "config");
+        assert_eq!(remainder, "break_duration=60s\n\nwork_interval=30m\n");
+
+        // an unknown condition never holds
+        let remainder = read_conditional_sections("[weekday:Mon]\nplay_sound=false\n", "config");
+        assert_eq!(remainder, "");
+    }
+
+    #[test]
+    fn weekday_overrides_only_count_in_sections_that_hold() {
+        let _environment = ENVIRONMENT.lock().unwrap();
+        let content = format!(
+            "[env:{SET}]\nSat: play_sound=false\n[env:{UNSET}]\nSat: break_duration=60s\nSun: show_popup=false\n[env:{SET}] Sun: break_duration=90s\n"
+        );
+        let mut config = defaults();
+        let content =
+            read_schedule_overrides(&mut config, read_conditional_sections(&content, "config"));
+        assert_eq!(content.trim(), "");
+        assert_eq!(
+            config
+                .schedule_overrides
+                .iter()
+                .map(|schedule_override| (
+                    schedule_override.weekdays.clone(),
+                    schedule_override.content()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (vec![Weekday::Saturday], "play_sound=false"),
+                (vec![Weekday::Sunday], "break_duration=90s"),
+            ]
+        );
+
+        let saturday = config.for_weekday(Weekday::Saturday);
+        assert!(!saturday.play_sound);
+        assert_eq!(saturday.break_duration, defaults().break_duration);
+        let sunday = config.for_weekday(Weekday::Sunday);
+        assert!(sunday.show_popup);
+        assert_eq!(sunday.break_duration, Seconds::new(90));
+    }
+
+    #[test]
+    fn strings_from_the_environment_may_be_quoted() {
+        let config = read_with_environment("", &[("WLBREAKTIME_POPUP_TITLE", "Time to stretch")]);
+        assert_eq!(config.popup_title, "Time to stretch");
+        let config =
+            read_with_environment("", &[("WLBREAKTIME_POPUP_TITLE", "\"Time to stretch\"")]);
+        assert_eq!(config.popup_title, "Time to stretch");
+    }
+
+    #[test]
+    fn numbers_from_the_environment_are_read_unquoted() {
+        let config = read_with_environment(
+            "",
+            &[
+                ("WLBREAKTIME_WORK_INTERVAL", "45m"),
+                ("WLBREAKTIME_BREAK_DURATION", "120"),
+            ],
+        );
+        assert_eq!(config.break_interval, Seconds::new(45 * 60));
+        assert_eq!(config.break_duration, Seconds::new(120));
+    }
+
+    #[test]
+    fn unreadable_values_from_the_environment_are_reported() {
+        let config = read_with_environment("", &[("WLBREAKTIME_BREAK_DURATION", "soon")]);
+        assert_eq!(config.break_duration, defaults().break_duration);
+        assert!(WARNINGS.lock().unwrap().contains(
+            &"The value of WLBREAKTIME_BREAK_DURATION can't be read, it's ignored.".to_string()
+        ));
+    }
+
+    #[test]
+    fn the_environment_beats_the_config_files() {
+        let config = read_with_environment(
+            "break_duration=60s\npopup_title=\"Break\"\n",
+            &[("WLBREAKTIME_BREAK_DURATION", "90s")],
+        );
+        assert_eq!(config.break_duration, Seconds::new(90));
+        assert_eq!(config.popup_title, "Break");
+    }
+
+    #[test]
+    fn an_empty_remote_token_disables_remote_breaks() {
+        let (config, _) = read("remote_token=\"secret\"\n");
+        assert_eq!(config.remote_token.as_deref(), Some("secret"));
+        // e.g. a drop-in that clears the token of the main file
+        let (mut config, _) = read("remote_token=\"secret\"\n");
+        read_configuration(&mut config, "remote_token=\"\"\n".to_string());
+        assert_eq!(config.remote_token, None);
+    }
 }
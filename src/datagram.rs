@@ -0,0 +1,123 @@
+// reading the sockets' datagrams whatever their size, and sending responses of any length
+//
+// recv() silently cuts off a datagram that is longer than its buffer, so the size of the next one
+// is peeked first (with MSG_TRUNC, recv() returns the real length) -- it's read up to the caller's
+// limit, a longer one is reported as truncated instead of being handled cut off
+//
+// a socket that passes credentials (SO_PASSCRED) gets the sender's user from the kernel with every
+// datagram, it's peeked along with the size
+use std::{
+    io, mem,
+    os::{
+        fd::AsRawFd,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    path::Path,
+    ptr,
+};
+
+use crate::protocol;
+
+pub struct Datagram {
+    pub bytes: Vec<u8>,
+    pub address: SocketAddr,
+    // the datagram's whole length, more than the bytes if it was longer than the limit
+    pub size: usize,
+    // the sender's user, None unless the socket passes credentials
+    pub uid: Option<u32>,
+}
+
+impl Datagram {
+    pub fn truncated(&self) -> bool {
+        self.size > self.bytes.len()
+    }
+}
+
+/*
+ * the next datagram, read up to the limit -- waits like recv_from(), as long as the socket's read
+ * timeout allows
+ */
+pub fn receive(socket: &UnixDatagram, limit: usize) -> io::Result<Datagram> {
+    let (size, uid) = peek(socket)?;
+    let mut bytes = vec![0; size.min(limit)];
+    let (bytes_read, address) = socket.recv_from(&mut bytes)?;
+    bytes.truncate(bytes_read);
+    Ok(Datagram {
+        bytes,
+        address,
+        size,
+        uid,
+    })
+}
+
+/*
+ * has the kernel attach the sender's credentials to every datagram the socket receives, so they
+ * can't be forged like e.g. the owner of the sender's socket file
+ */
+pub fn pass_credentials(socket: &UnixDatagram) -> io::Result<()> {
+    let enabled: libc::c_int = 1;
+    // SAFETY: the option's value is a c_int that outlives the call
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PASSCRED,
+            ptr::from_ref(&enabled).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/*
+ * the size of the next datagram and its sender's user, if credentials are passed
+ */
+fn peek(socket: &UnixDatagram) -> io::Result<(usize, Option<u32>)> {
+    // room for the SCM_CREDENTIALS message, aligned like the control messages have to be
+    let mut control = [0_u64; 8];
+    // SAFETY: an all-zero msghdr is valid, it has no buffers for the data
+    let mut header: libc::msghdr = unsafe { mem::zeroed() };
+    header.msg_control = control.as_mut_ptr().cast();
+    header.msg_controllen = mem::size_of_val(&control) as _;
+    // SAFETY: nothing is written into the empty data buffers, the control messages fit into the
+    // buffer the header points at, and the datagram stays queued with MSG_PEEK
+    let size = unsafe {
+        libc::recvmsg(
+            socket.as_raw_fd(),
+            &mut header,
+            libc::MSG_PEEK | libc::MSG_TRUNC,
+        )
+    };
+    // negative on errors, e.g. when the read timed out or was interrupted
+    let size = usize::try_from(size).map_err(|_| io::Error::last_os_error())?;
+
+    let mut uid = None;
+    // SAFETY: recvmsg() wrote the control messages the header describes, the macros stay in them
+    unsafe {
+        let mut message = libc::CMSG_FIRSTHDR(&header);
+        while !message.is_null() {
+            if (*message).cmsg_level == libc::SOL_SOCKET
+                && (*message).cmsg_type == libc::SCM_CREDENTIALS
+            {
+                let credentials =
+                    ptr::read_unaligned(libc::CMSG_DATA(message).cast::<libc::ucred>());
+                uid = Some(credentials.uid);
+            }
+            message = libc::CMSG_NXTHDR(&header, message);
+        }
+    }
+    Ok((size, uid))
+}
+
+/*
+ * sends the response in as many chunks as it takes, the first one that can't be sent ends it
+ */
+pub fn send_response(socket: &UnixDatagram, id: u32, payload: &str, path: &Path) -> io::Result<()> {
+    for chunk in protocol::response_chunks(id, payload) {
+        socket.send_to(chunk.as_bytes(), path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+// the break debt: the break time that was skipped today and not made up for yet
+//
+// a skipped scheduled break adds what was left of it, a break that's taken pays back what it
+// lasted beyond its own length -- with debt=extend every break is lengthened by the debt, with
+// debt=extra_break the next break comes early once a whole one is owed. like the breaks taken
+// today, the debt starts over every day
+use std::sync::Mutex;
+
+use jiff::{Zoned, civil::Date};
+use wlbreaktime::duration::Seconds;
+
+static DEBT: Mutex<Debt> = Mutex::new(Debt {
+    day: None,
+    owed: Seconds::ZERO,
+});
+
+struct Debt {
+    // the day the debt was run up on
+    day: Option<Date>,
+    owed: Seconds,
+}
+
+impl Debt {
+    fn today(&mut self) -> &mut Seconds {
+        let today = Zoned::now().date();
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.owed = Seconds::ZERO;
+        }
+        &mut self.owed
+    }
+}
+
+/*
+ * the break time skipped today that's not made up for yet
+ */
+pub(crate) fn owed() -> Seconds {
+    *DEBT.lock().unwrap().today()
+}
+
+pub(crate) fn add(skipped: Seconds) {
+    *DEBT.lock().unwrap().today() += skipped;
+}
+
+pub(crate) fn pay(made_up: Seconds) {
+    let mut debt = DEBT.lock().unwrap();
+    let owed = debt.today();
+    *owed = owed.saturating_sub(made_up);
+}
+
+/*
+ * how much longer the break is with debt=extend, at most its own length -- the rest is owed on
+ */
+pub(crate) fn extension(break_duration: Seconds, owed: Seconds) -> Seconds {
+    owed.min(break_duration)
+}
+
+/*
+ * whether the next break is an extra one with debt=extra_break, that is once a whole break is owed
+ */
+pub(crate) fn extra_break_due(break_duration: Seconds, owed: Seconds) -> bool {
+    !break_duration.is_zero() && owed >= break_duration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_is_at_most_the_break() {
+        let break_duration = Seconds::new(300);
+        assert_eq!(extension(break_duration, Seconds::ZERO), Seconds::ZERO);
+        assert_eq!(
+            extension(break_duration, Seconds::new(120)),
+            Seconds::new(120)
+        );
+        assert_eq!(extension(break_duration, Seconds::new(900)), break_duration);
+    }
+
+    #[test]
+    fn extra_break_once_a_whole_break_is_owed() {
+        let break_duration = Seconds::new(300);
+        assert!(!extra_break_due(break_duration, Seconds::new(299)));
+        assert!(extra_break_due(break_duration, Seconds::new(300)));
+        assert!(!extra_break_due(Seconds::ZERO, Seconds::ZERO));
+    }
+
+    #[test]
+    fn paying_never_goes_below_zero() {
+        add(Seconds::new(60));
+        pay(Seconds::new(600));
+        assert_eq!(owed(), Seconds::ZERO);
+    }
+}
@@ -0,0 +1,288 @@
+// the scheduler's internal state as pretty JSON, returned for `wlbreaktime-helper debug dump`
+//
+// meant to be attached to bug reports about missed or double breaks. the schedule is decided in
+// main's loop, so it's recorded here at every phase boundary, like the stats are counted -- the
+// running phase adds its deadlines when the dump is requested
+use std::{sync::Mutex, time::Instant};
+
+use serde_json::{Map, Value, json};
+use wlbreaktime::duration::Seconds;
+
+use crate::{
+    capabilities,
+    config::{Config, skip_source_names},
+    dnd, idle, jitter, resources, stats, timers,
+};
+
+static SCHEDULE: Mutex<Option<Schedule>> = Mutex::new(None);
+
+/*
+ * what main decided for the current phase
+ */
+pub(crate) struct Schedule {
+    // for today, without the changes for the current break (e.g. the battery saver's)
+    pub(crate) config: Config,
+    pub(crate) consecutive_skips: u64,
+    pub(crate) last_break_end: Option<Instant>,
+    // the time until the fixed break, if it's the next break
+    pub(crate) fixed_break: Option<Seconds>,
+    pub(crate) battery_saver: bool,
+    pub(crate) quiet_hours: bool,
+    pub(crate) skip_blocked: Option<String>,
+}
+
+pub(crate) fn record(schedule: Schedule) {
+    *SCHEDULE.lock().unwrap() = Some(schedule);
+}
+
+/*
+ * the whole state, the phase is described by the loop it's running in
+ */
+pub(crate) fn dump(phase: Value) -> String {
+    let capabilities = capabilities::get();
+    let timers: Vec<Value> = timers::pending()
+        .into_iter()
+        .map(|(remaining, message)| json!({ "remaining": remaining.as_secs(), "message": message }))
+        .collect();
+    let schedule = SCHEDULE.lock().unwrap();
+
+    let mut state = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "phase": phase,
+        "capabilities": {
+            "wayland": capabilities.wayland,
+            "x11": capabilities.x11,
+            "audio": capabilities.audio,
+            "notifications": capabilities.notifications,
+            "journald": capabilities.journald,
+        },
+        "inhibitors": {
+            "do_not_disturb": dnd::notifications_inhibited(),
+            "idle_for": idle::since().map(|since| since.elapsed().as_secs()),
+            "battery_saver": schedule.as_ref().is_some_and(|schedule| schedule.battery_saver),
+            "quiet_hours": schedule.as_ref().is_some_and(|schedule| schedule.quiet_hours),
+            "skip_blocked": schedule.as_ref().and_then(|schedule| schedule.skip_blocked.clone()),
+        },
+        "counters": {
+            "jitter": jitter::current(),
+            "stats": name_values(&stats::report()),
+        },
+        "timers": timers,
+        "cache": resources::report(),
+    });
+    // nothing is recorded before the first work interval starts
+    if let Some(schedule) = schedule.as_ref() {
+        state["counters"]["consecutive_skips"] = schedule.consecutive_skips.into();
+        state["counters"]["since_last_break"] = schedule
+            .last_break_end
+            .map(|end| end.elapsed().as_secs())
+            .into();
+        state["counters"]["until_fixed_break"] = schedule.fixed_break.map(Seconds::as_secs).into();
+        state["config"] = config(&schedule.config);
+    }
+
+    serde_json::to_string_pretty(&state).unwrap_or_else(|err| err.to_string())
+}
+
+/*
+ * "name=value" lines as an object, numbers are kept as numbers
+ */
+fn name_values(lines: &str) -> Value {
+    let mut object = Map::new();
+    for (name, value) in lines.lines().filter_map(|line| line.split_once('=')) {
+        let value = value
+            .parse::<u64>()
+            .map_or_else(|_| value.into(), Value::from);
+        object.insert(name.to_string(), value);
+    }
+    Value::Object(object)
+}
+
+/*
+ * the config with durations in seconds, the remote token is left out
+ */
+fn config(config: &Config) -> Value {
+    // a new field doesn't compile until it's part of the dump
+    let Config {
+        break_interval,
+        break_interval_jitter,
+        min_work_between_breaks,
+        auto_pause_after,
+        break_duration,
+        grace_period,
+        show_popup,
+        popup,
+        play_sound,
+        pre_break_sound,
+        quiet_hours,
+        audio_device,
+        sound_theme,
+        show_notification,
+        notification_urgency,
+        notification_icon,
+        notification_app_name,
+        pre_break_summary,
+        pre_break_body,
+        break_end_summary,
+        break_end_body,
+        turn_off_monitors,
+        on_resume,
+        suspend_counts_as,
+        resume_after_idle,
+        escalation,
+        escalation_step,
+        escalation_limit,
+        debt,
+        request_autostart,
+        popup_align,
+        popup_margin,
+        popup_title,
+        popup_subtitle,
+        popup_icon,
+        popup_qr_url,
+        popup_clock,
+        font,
+        render_scale,
+        popup_cursor,
+        popup_on_top,
+        theme,
+        high_contrast,
+        phase_palette,
+        reduce_motion,
+        announce_phases,
+        restore_focus,
+        postpone_on_dnd,
+        dnd_postpone,
+        battery_saver_below,
+        gamma_shift,
+        gamma_temperature,
+        gamma_brightness,
+        strict,
+        grab_input,
+        extend_break_on_activity,
+        skip_from,
+        strict_skip_from,
+        fixed_break,
+        remote_listen,
+        remote_master,
+        remote_token,
+        time_tracker_start,
+        time_tracker_stop,
+        time_tracker_org,
+        helper_timeout,
+        schedule_overrides,
+    } = config;
+
+    let quiet_hours = quiet_hours.map(|quiet_hours| {
+        format!(
+            "{}-{}",
+            quiet_hours.start.strftime("%H:%M"),
+            quiet_hours.end.strftime("%H:%M")
+        )
+    });
+    let fixed_break = fixed_break.map(|fixed_break| {
+        json!({
+            "start": fixed_break.start.strftime("%H:%M").to_string(),
+            "duration": fixed_break.duration.as_secs(),
+        })
+    });
+    // bug reports are shared, the token isn't
+    let remote_token = remote_token.as_ref().map(|_| "<set>");
+    let schedule_overrides: Vec<Value> = schedule_overrides
+        .iter()
+        .map(|schedule_override| {
+            let weekdays: Vec<String> = schedule_override
+                .weekdays
+                .iter()
+                .map(|weekday| format!("{weekday:?}"))
+                .collect();
+            json!({ "weekdays": weekdays, "settings": schedule_override.content() })
+        })
+        .collect();
+
+    // a single json! of every key exceeds the macro's recursion limit
+    let entries = [
+        ("break_interval", json!(break_interval.as_secs())),
+        (
+            "break_interval_jitter",
+            json!(break_interval_jitter.as_secs()),
+        ),
+        (
+            "min_work_between_breaks",
+            json!(min_work_between_breaks.as_secs()),
+        ),
+        ("auto_pause_after", json!(auto_pause_after.as_secs())),
+        ("break_duration", json!(break_duration.as_secs())),
+        ("grace_period", json!(grace_period.as_secs())),
+        ("show_popup", json!(show_popup)),
+        ("popup", json!(popup.name())),
+        ("play_sound", json!(play_sound)),
+        ("pre_break_sound", json!(pre_break_sound)),
+        ("quiet_hours", json!(quiet_hours)),
+        ("audio_device", json!(audio_device)),
+        ("sound_theme", json!(sound_theme)),
+        ("show_notification", json!(show_notification)),
+        ("notification_urgency", json!(notification_urgency.name())),
+        ("notification_icon", json!(notification_icon)),
+        ("notification_app_name", json!(notification_app_name)),
+        ("pre_break_summary", json!(pre_break_summary)),
+        ("pre_break_body", json!(pre_break_body)),
+        ("break_end_summary", json!(break_end_summary)),
+        ("break_end_body", json!(break_end_body)),
+        ("turn_off_monitors", json!(turn_off_monitors)),
+        ("on_resume", json!(on_resume.name())),
+        ("suspend_counts_as", json!(suspend_counts_as.name())),
+        ("resume_after_idle", json!(resume_after_idle.as_secs())),
+        ("escalation", json!(escalation)),
+        ("escalation_step", json!(escalation_step.as_secs())),
+        ("escalation_limit", json!(escalation_limit)),
+        ("debt", json!(debt.name())),
+        ("request_autostart", json!(request_autostart)),
+        ("popup_align", json!(popup_align.name())),
+        ("popup_margin", json!(popup_margin)),
+        ("popup_title", json!(popup_title)),
+        ("popup_subtitle", json!(popup_subtitle)),
+        ("popup_icon", json!(popup_icon)),
+        ("popup_qr_url", json!(popup_qr_url)),
+        ("popup_clock", json!(popup_clock)),
+        ("font", json!(font)),
+        ("render_scale", json!(render_scale)),
+        ("popup_cursor", json!(popup_cursor.name())),
+        ("popup_on_top", json!(popup_on_top)),
+        ("theme", json!(theme.name())),
+        ("high_contrast", json!(high_contrast)),
+        ("phase_palette", json!(phase_palette.name())),
+        ("reduce_motion", json!(reduce_motion)),
+        ("announce_phases", json!(announce_phases)),
+        ("restore_focus", json!(restore_focus)),
+        ("postpone_on_dnd", json!(postpone_on_dnd)),
+        ("dnd_postpone", json!(dnd_postpone.as_secs())),
+        ("battery_saver_below", json!(battery_saver_below)),
+        ("gamma_shift", json!(gamma_shift)),
+        ("gamma_temperature", json!(gamma_temperature)),
+        ("gamma_brightness", json!(gamma_brightness)),
+        ("strict", json!(strict)),
+        ("grab_input", json!(grab_input)),
+        ("extend_break_on_activity", json!(extend_break_on_activity)),
+        ("skip_from", json!(skip_source_names(skip_from))),
+        (
+            "strict_skip_from",
+            json!(skip_source_names(strict_skip_from)),
+        ),
+        ("fixed_break", json!(fixed_break)),
+        ("remote_listen", json!(remote_listen)),
+        ("remote_master", json!(remote_master)),
+        ("remote_token", json!(remote_token)),
+        ("time_tracker_start", json!(time_tracker_start)),
+        ("time_tracker_stop", json!(time_tracker_stop)),
+        ("time_tracker_org", json!(time_tracker_org)),
+        ("helper_timeout", json!(helper_timeout.as_secs())),
+        ("schedule_overrides", json!(schedule_overrides)),
+    ];
+    Value::Object(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
@@ -0,0 +1,112 @@
+// why the breaks start when they do, as JSON for `wlbreaktime-helper debug decisions`
+//
+// the dump shows the scheduler's state, this is how it got there: every decision about the next
+// break is recorded with its reason, e.g. that it was postponed because notifications were
+// inhibited or merged into the fixed break. only the last ones are kept, so the log doesn't grow
+// while the daemon runs for weeks
+use std::{collections::VecDeque, sync::Mutex};
+
+use jiff::Zoned;
+use serde_json::{Value, json};
+
+// enough for a day of breaks with everything that moved them
+const CAPACITY: usize = 200;
+
+static DECISIONS: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Decision {
+    // the work interval until the next break was set
+    Scheduled,
+    // the rolling break was merged into the fixed break
+    Merged,
+    // the break was moved later, e.g. to keep some work time between breaks
+    Moved,
+    Postponed,
+    // the break was lengthened to make up for skipped ones
+    Extended,
+    // the work timer stopped or went on
+    Paused,
+    Resumed,
+    // the work timer started over
+    Reset,
+    Fired,
+    // the announced break didn't start
+    Cancelled,
+}
+
+impl Decision {
+    fn name(self) -> &'static str {
+        match self {
+            Decision::Scheduled => "scheduled",
+            Decision::Merged => "merged",
+            Decision::Moved => "moved",
+            Decision::Postponed => "postponed",
+            Decision::Extended => "extended",
+            Decision::Paused => "paused",
+            Decision::Resumed => "resumed",
+            Decision::Reset => "reset",
+            Decision::Fired => "fired",
+            Decision::Cancelled => "cancelled",
+        }
+    }
+}
+
+struct Entry {
+    at: Zoned,
+    decision: Decision,
+    reason: String,
+}
+
+/*
+ * appends the decision, the oldest one is dropped once the log is full
+ */
+pub(crate) fn record(decision: Decision, reason: impl Into<String>) {
+    let mut decisions = DECISIONS.lock().unwrap();
+    if decisions.len() == CAPACITY {
+        decisions.pop_front();
+    }
+    decisions.push_back(Entry {
+        at: Zoned::now(),
+        decision,
+        reason: reason.into(),
+    });
+}
+
+/*
+ * the decisions from the oldest to the latest, as pretty JSON
+ */
+pub(crate) fn report() -> String {
+    let decisions: Vec<Value> = DECISIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            json!({
+                "at": entry.at.strftime("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+                "decision": entry.decision.name(),
+                "reason": entry.reason,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&decisions).unwrap_or_else(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_decisions() {
+        for seconds in 0..CAPACITY + 5 {
+            record(
+                Decision::Scheduled,
+                format!("next break in {seconds} seconds"),
+            );
+        }
+        let report: Vec<Value> = serde_json::from_str(&report()).unwrap();
+        assert_eq!(report.len(), CAPACITY);
+        assert_eq!(report[0]["reason"], "next break in 5 seconds");
+        assert_eq!(report[CAPACITY - 1]["decision"], "scheduled");
+    }
+}
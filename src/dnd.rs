@@ -0,0 +1,39 @@
+// detection of "do not disturb" modes of notification daemons
+use std::process::Command;
+
+#[cfg(feature = "dbus")]
+use zbus::blocking::{Connection, Proxy};
+
+/*
+ * returns true if the notification daemon currently holds back notifications
+ * the `Inhibited` property of the notification spec is checked first, mako's modes afterwards
+ */
+pub fn notifications_inhibited() -> bool {
+    #[cfg(feature = "dbus")]
+    if let Ok(inhibited) = inhibited_property() {
+        return inhibited;
+    }
+    mako_do_not_disturb()
+}
+
+#[cfg(feature = "dbus")]
+fn inhibited_property() -> Result<bool, zbus::Error> {
+    let connection = Connection::session()?;
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )?;
+    proxy.get_property::<bool>("Inhibited")
+}
+
+fn mako_do_not_disturb() -> bool {
+    match Command::new("makoctl").arg("mode").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|mode| mode.trim() == "do-not-disturb"),
+        // mako is not installed
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,135 @@
+// durations with their unit in the type, so seconds can't be mistaken for minutes
+//
+// the protocol, the config files and the scheduler count in seconds, the helper's arguments in
+// minutes unless they're suffixed (`set 45` is 45 minutes, `set 90s` 90 seconds). a bare u64 is
+// only ever a count, the durations are converted between the units explicitly. there is no
+// Display either, the protocol sends plain seconds where the config files take suffixed ones
+use std::{
+    ops::{Add, AddAssign, Sub},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Seconds(u64);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Minutes(u64);
+
+impl Seconds {
+    pub const ZERO: Seconds = Seconds(0);
+
+    pub const fn new(seconds: u64) -> Seconds {
+        Seconds(seconds)
+    }
+
+    pub const fn as_secs(self) -> u64 {
+        self.0
+    }
+
+    /*
+     * the whole minutes and the seconds left over, e.g. for "12 minutes and 5 seconds"
+     */
+    pub const fn split(self) -> (Minutes, u64) {
+        (Minutes(self.0 / 60), self.0 % 60)
+    }
+
+    pub const fn is_whole_minutes(self) -> bool {
+        self.0.is_multiple_of(60)
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn saturating_sub(self, other: Seconds) -> Seconds {
+        Seconds(self.0.saturating_sub(other.0))
+    }
+
+    pub const fn saturating_mul(self, factor: u64) -> Seconds {
+        Seconds(self.0.saturating_mul(factor))
+    }
+
+    pub const fn checked_mul(self, factor: u64) -> Option<Seconds> {
+        match self.0.checked_mul(factor) {
+            Some(seconds) => Some(Seconds(seconds)),
+            None => None,
+        }
+    }
+
+    pub const fn as_duration(self) -> Duration {
+        Duration::from_secs(self.0)
+    }
+
+    /*
+     * in the format of the config files, in minutes if it's a whole number of them, e.g. "5m" or
+     * "90s"
+     */
+    pub fn suffixed(self) -> String {
+        if self.is_whole_minutes() {
+            format!("{}m", self.0 / 60)
+        } else {
+            format!("{}s", self.0)
+        }
+    }
+}
+
+impl Minutes {
+    pub const fn new(minutes: u64) -> Minutes {
+        Minutes(minutes)
+    }
+
+    pub const fn as_minutes(self) -> u64 {
+        self.0
+    }
+
+    /*
+     * None if the seconds overflow
+     */
+    pub const fn to_seconds(self) -> Option<Seconds> {
+        match self.0.checked_mul(60) {
+            Some(seconds) => Some(Seconds(seconds)),
+            None => None,
+        }
+    }
+}
+
+impl From<Minutes> for Seconds {
+    fn from(minutes: Minutes) -> Seconds {
+        Seconds(minutes.0.saturating_mul(60))
+    }
+}
+
+impl From<Seconds> for Duration {
+    fn from(seconds: Seconds) -> Duration {
+        seconds.as_duration()
+    }
+}
+
+// the fraction of the last second is dropped, like Duration::as_secs
+impl From<Duration> for Seconds {
+    fn from(duration: Duration) -> Seconds {
+        Seconds(duration.as_secs())
+    }
+}
+
+impl Add for Seconds {
+    type Output = Seconds;
+
+    fn add(self, other: Seconds) -> Seconds {
+        Seconds(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Seconds {
+    fn add_assign(&mut self, other: Seconds) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Seconds {
+    type Output = Seconds;
+
+    fn sub(self, other: Seconds) -> Seconds {
+        Seconds(self.0 - other.0)
+    }
+}
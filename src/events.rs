@@ -0,0 +1,104 @@
+// the event stream: clients send "subscribe" (or "unsubscribe") to the events socket from a bound
+// socket and receive every phase change as a datagram afterwards
+//
+// every event is numbered and stamped, e.g. "skip seq=12 at=1760000000", so a subscriber that
+// reconnects (or missed a datagram) notices the gap and asks for the current state with `status`
+// instead. the numbers start over with the daemon
+use std::{
+    io::ErrorKind,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use crate::clock;
+
+// the number of the last published event, 0 before the first one
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/*
+ * the event with the next sequence number and the time it happened in seconds since the epoch,
+ * e.g. "break manual seq=3 at=1760000000" -- the event's own words come first, so readers that
+ * only look at them keep working
+ */
+pub(crate) fn stamp(event: &str) -> String {
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1;
+    format!(
+        "{event} seq={sequence} at={}",
+        clock::timestamp(Instant::now())
+    )
+}
+
+pub(crate) struct EventStream {
+    socket: UnixDatagram,
+    subscribers: Vec<PathBuf>,
+}
+
+impl EventStream {
+    pub(crate) fn new(socket: UnixDatagram) -> Result<EventStream, Box<dyn std::error::Error>> {
+        // subscriptions are only handled when publishing, so reading must never block
+        socket.set_nonblocking(true)?;
+        Ok(EventStream {
+            socket,
+            subscribers: Vec::new(),
+        })
+    }
+
+    pub(crate) fn publish(&mut self, event: &str) {
+        self.handle_subscriptions();
+
+        let socket = &self.socket;
+        self.subscribers.retain(|subscriber| {
+            match socket.send_to(event.as_bytes(), subscriber) {
+                Ok(_) => true,
+                // the subscriber went away without unsubscribing
+                Err(err) => {
+                    println!("[events]: Dropping the subscriber {subscriber:?}: {err}");
+                    false
+                }
+            }
+        });
+    }
+
+    fn handle_subscriptions(&mut self) {
+        let mut buffer = [0; 100];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((bytes_read, address)) => {
+                    let Some(path) = address.as_pathname() else {
+                        println!("[events]: Ignoring a message from an unbound socket.");
+                        continue;
+                    };
+                    match &buffer[..bytes_read] {
+                        b"subscribe" => self.subscribe(path),
+                        b"unsubscribe" => self.subscribers.retain(|subscriber| subscriber != path),
+                        message => println!(
+                            "[events]: Received unknown message '{}'",
+                            String::from_utf8_lossy(message)
+                        ),
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    println!("[events]: Unable to read subscriptions! The error: {err}");
+                    break;
+                }
+            }
+        }
+    }
+
+    /*
+     * the subscription is answered with the number of the last event, so a subscriber that
+     * reconnects knows right away whether it missed any
+     */
+    fn subscribe(&mut self, path: &Path) {
+        if !self.subscribers.iter().any(|subscriber| subscriber == path) {
+            self.subscribers.push(path.to_path_buf());
+        }
+        let answer = format!("subscribed seq={}", SEQUENCE.load(Ordering::Relaxed));
+        if let Err(err) = self.socket.send_to(answer.as_bytes(), path) {
+            println!("[events]: The subscription of {path:?} could not be answered: {err}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// remembering the focused window before a break and focusing it again afterwards
+//
+// the overlay (or turning the monitors off) sometimes leaves the focus on the wrong window, so the
+// activated toplevel is looked up through wlr-foreign-toplevel-management
+use log::{error, info};
+
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle, event_created_child};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::wayland::State;
+
+#[derive(Debug)]
+pub(crate) struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    activated: bool,
+    // state changes are only applied on the done event
+    pending_activated: bool,
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                state.toplevels.push(Toplevel {
+                    handle: toplevel,
+                    activated: false,
+                    pending_activated: false,
+                });
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => {
+                info!("The foreign toplevel manager finished");
+                state.toplevel_manager = None;
+            }
+            _ => {
+                error!("Unconfigured foreign toplevel manager event {event:?}");
+            }
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(index) = state
+            .toplevels
+            .iter()
+            .position(|toplevel| &toplevel.handle == handle)
+        else {
+            return;
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: states } => {
+                // the states are an array of native endian u32 values
+                let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+                state.toplevels[index].pending_activated = states
+                    .chunks_exact(4)
+                    .any(|value| u32::from_ne_bytes(value.try_into().unwrap()) == activated);
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                let toplevel = &mut state.toplevels[index];
+                toplevel.activated = toplevel.pending_activated;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                state.toplevels.remove(index).handle.destroy();
+            }
+            // title, app id and outputs are not needed
+            _ => {}
+        }
+    }
+}
+
+/*
+ * starts tracking the windows and returns the currently focused one
+ */
+pub(crate) fn snapshot_focus(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    qh: &QueueHandle<State>,
+) -> Result<Option<ZwlrForeignToplevelHandleV1>, Box<dyn std::error::Error>> {
+    let Some((registry, name, version)) = &data.toplevel_manager_global else {
+        return Ok(None);
+    };
+    data.toplevel_manager = Some(registry.bind(*name, *version, qh, ()));
+    info!("Bound foreign toplevel manager");
+
+    // all existing toplevels are announced right after binding
+    event_queue.roundtrip(data)?;
+    Ok(data
+        .toplevels
+        .iter()
+        .find(|toplevel| toplevel.activated)
+        .map(|toplevel| toplevel.handle.clone()))
+}
+
+pub(crate) fn restore_focus(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    handle: &ZwlrForeignToplevelHandleV1,
+) -> Result<(), Box<dyn std::error::Error>> {
+    event_queue.roundtrip(data)?;
+
+    let Some(seat) = &data.seat else {
+        return Ok(());
+    };
+    // the window might have been closed during the break
+    if data
+        .toplevels
+        .iter()
+        .any(|toplevel| &toplevel.handle == handle)
+    {
+        handle.activate(seat);
+        event_queue.flush()?;
+        info!("Restored the focus");
+    }
+    Ok(())
+}
+
+/*
+ * the toplevels are only tracked around breaks
+ */
+pub(crate) fn stop_tracking(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(manager) = data.toplevel_manager.take() {
+        manager.stop();
+    }
+    for toplevel in data.toplevels.drain(..) {
+        toplevel.handle.destroy();
+    }
+    event_queue.flush()?;
+    Ok(())
+}
@@ -0,0 +1,435 @@
+// the fonts of the text on the overlay and the toasts: the built-in 8x8 font, or the font
+// configured with `font=` (a fontconfig pattern, e.g. "Noto Sans:bold"), and fontconfig's
+// fallbacks for characters neither of them has, e.g. CJK or emoji in custom messages
+//
+// fonts are looked up through `fc-match`, like the compositors are asked through their own
+// commands, and loaded once. glyphs are rasterized on first use and kept for the following frames,
+// like the icon in resources.rs
+//
+// texts are laid out like a browser would: the bidi algorithm orders the runs of right-to-left
+// (Arabic, Hebrew) and left-to-right text, each run is split where the font changes and every
+// piece is shaped with rustybuzz, so letters join and marks sit where they belong. the built-in
+// font is not shaped, its characters are only put in order
+use std::{collections::BTreeMap, fs, ops::Range, process::Command, sync::Mutex};
+
+use ab_glyph::{Font, FontVec, GlyphId, PxScale, ScaleFont, point};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use unicode_bidi::BidiInfo;
+
+use crate::{config::Config, render};
+
+// the fonts fontconfig suggests for a character are tried in order until one can draw it
+const MAX_FALLBACK_CANDIDATES: usize = 5;
+// the rasterizations are dropped all at once when there are more, e.g. after the text was shrunk
+// through a lot of sizes
+const MAX_CACHED_GLYPHS: usize = 4096;
+// the same goes for the laid out texts, the countdown alone adds one every second
+const MAX_CACHED_LINES: usize = 256;
+
+static FONTS: Mutex<Fonts> = Mutex::new(Fonts {
+    pattern: None,
+    loaded: Vec::new(),
+    primary: None,
+    sources: BTreeMap::new(),
+    glyphs: BTreeMap::new(),
+    lines: BTreeMap::new(),
+    fontconfig_missing: false,
+});
+
+struct LoadedFont {
+    path: String,
+    // the font's index in a collection (.ttc)
+    index: u32,
+    font: FontVec,
+}
+
+/*
+ * where the glyph of a character comes from
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    Builtin,
+    Font(usize),
+}
+
+/*
+ * the coverage of a rasterized glyph, positioned relative to the top of the line and the pen
+ */
+pub(crate) struct Glyph {
+    pub(crate) left: i32,
+    pub(crate) top: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    // between 0 and 255, row by row
+    pub(crate) coverage: Vec<u8>,
+}
+
+/*
+ * a character of a text, either drawn from the built-in font or from its rasterization
+ */
+pub(crate) enum Placed<'a> {
+    Builtin(char),
+    Outline(&'a Glyph),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Builtin(char),
+    Outline { font: usize, id: GlyphId },
+}
+
+/*
+ * an item of a laid out text, in pixels from the start of the line -- y is only moved by marks
+ */
+#[derive(Debug, Clone, Copy)]
+struct Positioned {
+    x: f32,
+    y: f32,
+    item: Item,
+}
+
+/*
+ * a text in visual order, from left to right
+ */
+struct Line {
+    width: f32,
+    items: Vec<Positioned>,
+}
+
+struct Fonts {
+    // the configured pattern, None means the built-in font is the primary one
+    pattern: Option<String>,
+    loaded: Vec<LoadedFont>,
+    primary: Option<usize>,
+    sources: BTreeMap<char, Source>,
+    // keyed by the font, the glyph and the line height
+    glyphs: BTreeMap<(usize, GlyphId, u32), Glyph>,
+    // keyed by the text and the line height
+    lines: BTreeMap<(String, u32), Line>,
+    // so a system without fontconfig isn't asked for every character again
+    fontconfig_missing: bool,
+}
+
+/*
+ * loads the configured font, the built-in font stays the primary one if it can't be found
+ */
+pub(crate) fn set(config: &Config) {
+    let mut fonts = FONTS.lock().unwrap();
+    fonts.pattern = config.font.clone().filter(|pattern| !pattern.is_empty());
+    let Some(pattern) = fonts.pattern.clone() else {
+        return;
+    };
+    match fonts.matches(&pattern).first() {
+        Some((path, index)) => match fonts.load(path, *index) {
+            Some(loaded) => {
+                println!("Using the font '{path}' for '{pattern}'.");
+                fonts.primary = Some(loaded);
+            }
+            None => println!("The font '{path}' could not be loaded, using the built-in font."),
+        },
+        None => println!("No font matches '{pattern}', using the built-in font."),
+    }
+}
+
+/*
+ * the width of the text in pixels, the built-in font advances by the line height
+ */
+pub(crate) fn width(text: &str, line_height: u32) -> u32 {
+    let mut fonts = FONTS.lock().unwrap();
+    fonts.line(text, line_height).width.ceil() as u32
+}
+
+/*
+ * walks the laid out text from left to right with the position of every item, the glyphs from
+ * fonts are rasterized (or taken from the cache) on the way
+ */
+pub(crate) fn shape(text: &str, line_height: u32, mut each: impl FnMut(u32, i32, Placed)) {
+    let mut fonts = FONTS.lock().unwrap();
+    if fonts.glyphs.len() > MAX_CACHED_GLYPHS {
+        fonts.glyphs.clear();
+    }
+    fonts.line(text, line_height);
+    let Fonts {
+        loaded,
+        glyphs,
+        lines,
+        ..
+    } = &mut *fonts;
+    let line = &lines[&(text.to_string(), line_height)];
+    for positioned in &line.items {
+        let (x, y) = (
+            positioned.x.max(0.0).round() as u32,
+            positioned.y.round() as i32,
+        );
+        match positioned.item {
+            Item::Builtin(character) => each(x, y, Placed::Builtin(character)),
+            Item::Outline { font, id } => {
+                let glyph = glyphs
+                    .entry((font, id, line_height))
+                    .or_insert_with(|| rasterize(&loaded[font].font, id, line_height));
+                each(x, y, Placed::Outline(glyph));
+            }
+        }
+    }
+}
+
+impl Fonts {
+    /*
+     * the text laid out, from the cache if it was laid out at this height before
+     */
+    fn line(&mut self, text: &str, line_height: u32) -> &Line {
+        let key = (text.to_string(), line_height);
+        if !self.lines.contains_key(&key) {
+            if self.lines.len() > MAX_CACHED_LINES {
+                self.lines.clear();
+            }
+            let line = self.lay_out(text, line_height);
+            self.lines.insert(key.clone(), line);
+        }
+        &self.lines[&key]
+    }
+
+    fn lay_out(&mut self, text: &str, line_height: u32) -> Line {
+        let bidi = BidiInfo::new(text, None);
+        let mut line = Line {
+            width: 0.0,
+            items: Vec::new(),
+        };
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                let mut pieces = self.pieces(text, run);
+                // right-to-left runs are shaped from right to left, so their pieces come last first
+                if rtl {
+                    pieces.reverse();
+                }
+                for (source, range) in pieces {
+                    match source {
+                        Source::Builtin => {
+                            let characters: Vec<char> = if rtl {
+                                text[range].chars().rev().collect()
+                            } else {
+                                text[range].chars().collect()
+                            };
+                            for character in characters {
+                                line.items.push(Positioned {
+                                    x: line.width,
+                                    y: 0.0,
+                                    item: Item::Builtin(character),
+                                });
+                                line.width += line_height as f32;
+                            }
+                        }
+                        Source::Font(font) => {
+                            self.shape_piece(font, &text[range], rtl, line_height, &mut line)
+                        }
+                    }
+                }
+            }
+        }
+        line
+    }
+
+    /*
+     * the run split where the font changes, as byte ranges of the text
+     */
+    fn pieces(&mut self, text: &str, run: Range<usize>) -> Vec<(Source, Range<usize>)> {
+        let mut pieces: Vec<(Source, Range<usize>)> = Vec::new();
+        for (offset, character) in text[run.clone()].char_indices() {
+            let source = self.source(character);
+            let start = run.start + offset;
+            let end = start + character.len_utf8();
+            match pieces.last_mut() {
+                Some((last, range)) if *last == source => range.end = end,
+                _ => pieces.push((source, start..end)),
+            }
+        }
+        pieces
+    }
+
+    /*
+     * appends the glyphs rustybuzz picked for the piece, they come in visual order -- the font is
+     * scaled so ascent and descent fill the line, like when it's rasterized
+     */
+    fn shape_piece(&self, font: usize, piece: &str, rtl: bool, line_height: u32, line: &mut Line) {
+        let loaded = &self.loaded[font];
+        let Some(face) = Face::from_slice(loaded.font.as_slice(), loaded.index) else {
+            return;
+        };
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(piece);
+        buffer.set_direction(if rtl {
+            Direction::RightToLeft
+        } else {
+            Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        let shaped = rustybuzz::shape(&face, &[], buffer);
+
+        let scale = line_height as f32 / loaded.font.height_unscaled();
+        for (info, position) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+            line.items.push(Positioned {
+                x: line.width + position.x_offset as f32 * scale,
+                // rustybuzz's y axis points up
+                y: -position.y_offset as f32 * scale,
+                item: Item::Outline {
+                    font,
+                    id: GlyphId(info.glyph_id as u16),
+                },
+            });
+            line.width += position.x_advance as f32 * scale;
+        }
+    }
+
+    /*
+     * the configured font if it has the character, then the built-in font (unless another font was
+     * configured, it would look out of place), then the fonts already loaded as fallbacks and
+     * finally the ones fontconfig suggests -- without any of them, the built-in font's '?' is used
+     */
+    fn source(&mut self, character: char) -> Source {
+        if let Some(source) = self.sources.get(&character) {
+            return *source;
+        }
+        let source = match self.primary {
+            Some(primary) if can_draw(&self.loaded[primary].font, character) => {
+                Source::Font(primary)
+            }
+            None if render::builtin_glyph(character).is_some() => Source::Builtin,
+            _ => self.fallback(character).unwrap_or(Source::Builtin),
+        };
+        self.sources.insert(character, source);
+        source
+    }
+
+    fn fallback(&mut self, character: char) -> Option<Source> {
+        if let Some(font) = (0..self.loaded.len()).find(|font| {
+            Some(*font) != self.primary && can_draw(&self.loaded[*font].font, character)
+        }) {
+            return Some(Source::Font(font));
+        }
+
+        // the configured family is preferred among the fonts that have the character
+        let pattern = format!(
+            "{}:charset={:x}",
+            self.pattern.as_deref().unwrap_or(""),
+            character as u32
+        );
+        for (path, index) in self
+            .matches(&pattern)
+            .into_iter()
+            .take(MAX_FALLBACK_CANDIDATES)
+        {
+            if self
+                .loaded
+                .iter()
+                .any(|loaded| loaded.path == path && loaded.index == index)
+            {
+                continue;
+            }
+            let Some(font) = self.load(&path, index) else {
+                continue;
+            };
+            if can_draw(&self.loaded[font].font, character) {
+                println!("Using the font '{path}' for '{character}'.");
+                return Some(Source::Font(font));
+            }
+            // it doesn't help with this character, but one of the next might need it
+        }
+        None
+    }
+
+    /*
+     * the files and collection indexes of the fonts matching the pattern, best match first
+     */
+    fn matches(&mut self, pattern: &str) -> Vec<(String, u32)> {
+        if self.fontconfig_missing {
+            return Vec::new();
+        }
+        let output = Command::new("fc-match")
+            .args(["--sort", "--format=%{file}\t%{index}\n", pattern])
+            .output();
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                println!("'fc-match' failed with {} for '{pattern}'.", output.status);
+                return Vec::new();
+            }
+            Err(err) => {
+                println!(
+                    "Fontconfig is not available, only the built-in font is used! The error: {err}"
+                );
+                self.fontconfig_missing = true;
+                return Vec::new();
+            }
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (path, index) = line.split_once('\t')?;
+                Some((path.to_string(), index.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /*
+     * the position of the loaded font, None if it can't be read or parsed
+     */
+    fn load(&mut self, path: &str, index: u32) -> Option<usize> {
+        let font = fs::read(path)
+            .ok()
+            .and_then(|data| FontVec::try_from_vec_and_index(data, index).ok())?;
+        self.loaded.push(LoadedFont {
+            path: path.to_string(),
+            index,
+            font,
+        });
+        Some(self.loaded.len() - 1)
+    }
+}
+
+/*
+ * whether the font has an outline for the character -- color emoji fonts only have bitmaps
+ */
+fn can_draw(font: &FontVec, character: char) -> bool {
+    let id = font.glyph_id(character);
+    id.0 != 0
+        && (character.is_whitespace()
+            || font
+                .outline_glyph(id.with_scale(PxScale::from(16.0)))
+                .is_some())
+}
+
+/*
+ * the baseline is at the font's ascent below the top of the line, the font is scaled so ascent and
+ * descent fill the line
+ */
+fn rasterize(font: &FontVec, id: GlyphId, line_height: u32) -> Glyph {
+    let scale = PxScale::from(line_height as f32);
+    let scaled = font.as_scaled(scale);
+    let glyph = id.with_scale_and_position(scale, point(0.0, scaled.ascent()));
+    let Some(outlined) = font.outline_glyph(glyph) else {
+        return Glyph {
+            left: 0,
+            top: 0,
+            width: 0,
+            height: 0,
+            coverage: Vec::new(),
+        };
+    };
+    let bounds = outlined.px_bounds();
+    let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+    let mut coverage = vec![0; (width * height) as usize];
+    outlined.draw(|x, y, value| {
+        if x < width && y < height {
+            coverage[(y * width + x) as usize] = (value.clamp(0.0, 1.0) * 255.0) as u8;
+        }
+    });
+    Glyph {
+        left: bounds.min.x as i32,
+        top: bounds.min.y as i32,
+        width,
+        height,
+        coverage,
+    }
+}
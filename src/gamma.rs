@@ -0,0 +1,251 @@
+// warming and dimming the screen through wlr-gamma-control
+//
+// the compositor restores the original gamma tables as soon as a gamma control is destroyed, so
+// nothing needs to be saved
+use log::{error, info};
+use std::{
+    fs,
+    io::{Seek, SeekFrom, Write},
+    os::fd::AsFd,
+};
+
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_wlr::gamma_control::v1::client::{
+    zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+use wlbreaktime::paths;
+
+use crate::wayland::State;
+
+// the visual bell inverts the colors this often, each time for the duration
+#[cfg(feature = "audio")]
+const FLASHES: u32 = 2;
+#[cfg(feature = "audio")]
+const FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(150);
+
+#[derive(Debug)]
+pub(crate) struct GammaControl {
+    control: ZwlrGammaControlV1,
+    size: Option<u32>,
+    // the table that was set, empty until then
+    table: Vec<u8>,
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrGammaControlManagerV1,
+        _: zwlr_gamma_control_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("Gamma control manager event");
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        control: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(index) = state
+            .gamma_controls
+            .iter()
+            .position(|gamma_control| &gamma_control.control == control)
+        else {
+            return;
+        };
+
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => {
+                state.gamma_controls[index].size = Some(size);
+            }
+            zwlr_gamma_control_v1::Event::Failed => {
+                // e.g. because another client already controls the gamma of this output
+                error!("The gamma of an output could not be changed");
+                state.gamma_controls.remove(index).control.destroy();
+            }
+            _ => {
+                error!("Unconfigured gamma control event {event:?}");
+            }
+        }
+    }
+}
+
+/*
+ * approximates the color of a black body with the given temperature, the channels are scaled to
+ * 0.0-1.0 -- based on Tanner Helland's fit of the CIE data
+ */
+fn temperature_to_rgb(kelvin: u32) -> [f64; 3] {
+    let temperature = kelvin.clamp(1000, 6500) as f64 / 100.0;
+    let green = 99.4708025861 * temperature.ln() - 161.1195681661;
+    let blue = if temperature <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (temperature - 10.0).ln() - 305.0447927307
+    };
+    [
+        1.0,
+        (green / 255.0).clamp(0.0, 1.0),
+        (blue / 255.0).clamp(0.0, 1.0),
+    ]
+}
+
+/*
+ * warms and dims all outputs, brightness is given in percent
+ */
+pub(crate) fn start_gamma_shift(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    qh: &QueueHandle<State>,
+    temperature: u32,
+    brightness: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if data.gamma_manager.is_none() {
+        println!("The compositor does not support gamma control, not shifting the colors.");
+        return Ok(());
+    }
+    if !data.gamma_controls.is_empty() {
+        // already shifted, e.g. during the lead-in
+        return Ok(());
+    }
+
+    add_controls(data, qh);
+    // waiting for the sizes of the gamma tables
+    event_queue.roundtrip(data)?;
+
+    let factors = temperature_to_rgb(temperature);
+    let brightness = brightness.min(100) as f64 / 100.0;
+    for gamma_control in &mut data.gamma_controls {
+        let Some(size) = gamma_control.size else {
+            continue;
+        };
+        gamma_control.table = table(size, factors, brightness);
+        set_table(&gamma_control.control, &gamma_control.table)?;
+    }
+
+    event_queue.roundtrip(data)?;
+    info!("Shifted the gamma of {} outputs", data.gamma_controls.len());
+    Ok(())
+}
+
+pub(crate) fn stop_gamma_shift(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for gamma_control in data.gamma_controls.drain(..) {
+        gamma_control.control.destroy();
+    }
+    event_queue.flush()?;
+    Ok(())
+}
+
+/*
+ * the visual bell, e.g. when a sound can't be played: the colors of all outputs are inverted a few
+ * times, a shift that's in place is inverted along and kept afterwards
+ */
+#[cfg(feature = "audio")]
+pub(crate) fn flash(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    qh: &QueueHandle<State>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if data.gamma_manager.is_none() {
+        return Err("the compositor does not support gamma control".into());
+    }
+    // without a shift, the gamma controls are only held for the flashes
+    let unshifted = data.gamma_controls.is_empty();
+    if unshifted {
+        add_controls(data, qh);
+        event_queue.roundtrip(data)?;
+        for gamma_control in &mut data.gamma_controls {
+            if let Some(size) = gamma_control.size {
+                gamma_control.table = table(size, [1.0; 3], 1.0);
+            }
+        }
+    }
+
+    for _ in 0..FLASHES {
+        for inverted in [true, false] {
+            for gamma_control in &data.gamma_controls {
+                if gamma_control.table.is_empty() {
+                    continue;
+                }
+                if inverted {
+                    set_table(&gamma_control.control, &invert(&gamma_control.table))?;
+                } else {
+                    set_table(&gamma_control.control, &gamma_control.table)?;
+                }
+            }
+            event_queue.roundtrip(data)?;
+            std::thread::sleep(FLASH_DURATION);
+        }
+    }
+
+    info!("Flashed {} outputs", data.gamma_controls.len());
+    if unshifted {
+        stop_gamma_shift(event_queue, data)?;
+    }
+    Ok(())
+}
+
+fn add_controls(data: &mut State, qh: &QueueHandle<State>) {
+    let Some(manager) = &data.gamma_manager else {
+        return;
+    };
+    for output in &data.outputs {
+        data.gamma_controls.push(GammaControl {
+            control: manager.get_gamma_control(output, qh, ()),
+            size: None,
+            table: Vec::new(),
+        });
+    }
+}
+
+/*
+ * the table contains all red values, followed by all green and all blue values
+ */
+fn table(size: u32, factors: [f64; 3], brightness: f64) -> Vec<u8> {
+    let mut table = Vec::with_capacity(size as usize * 3 * 2);
+    for factor in factors {
+        for index in 0..size {
+            let linear = index as f64 / (size.max(2) - 1) as f64;
+            let value = (linear * factor * brightness * u16::MAX as f64) as u16;
+            table.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+    table
+}
+
+/*
+ * the values of every channel in reverse, so dark becomes bright
+ */
+#[cfg(feature = "audio")]
+fn invert(table: &[u8]) -> Vec<u8> {
+    table
+        .chunks(table.len() / 3)
+        .flat_map(|channel| channel.chunks(2).rev().flatten().copied())
+        .collect()
+}
+
+fn set_table(control: &ZwlrGammaControlV1, table: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = paths::runtime_dir()? + "/wlbreaktime-gamma";
+    // the compositor reads the table starting at the current offset
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+    file.write_all(table)?;
+    file.seek(SeekFrom::Start(0))?;
+    control.set_gamma(file.as_fd());
+    fs::remove_file(&path)?;
+    Ok(())
+}
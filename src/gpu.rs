@@ -0,0 +1,371 @@
+// the overlay drawn through wgpu instead of wl_shm, only with the gpu feature
+//
+// the layout still draws into the canvas, so both renderers show the same overlay -- the GPU gets
+// the parts that changed as a texture, scales it to the surface (render_scale doesn't need a
+// viewporter then) and presents it in sync with the output. the device is kept between breaks,
+// every overlay gets its own surface
+use std::ptr::NonNull;
+
+use log::info;
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
+use wayland_client::{
+    Proxy, QueueHandle,
+    protocol::{
+        wl_output::Transform,
+        wl_shm::Format,
+        wl_surface::{self},
+    },
+};
+use wgpu::{
+    Adapter, Backends, BindGroup, CompositeAlphaMode, Device, Instance, InstanceDescriptor,
+    PresentMode, Queue, RenderPipeline, Surface, SurfaceConfiguration, SurfaceError,
+    SurfaceTargetUnsafe, Texture, TextureFormat,
+};
+
+use crate::{
+    render::Canvas,
+    wayland::{Renderer, State},
+};
+
+// a triangle covering the whole surface, sampling the canvas
+const SHADER: &str = r"
+struct Varyings {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> Varyings {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return Varyings(vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0), uv);
+}
+
+@group(0) @binding(0) var canvas: texture_2d<f32>;
+@group(0) @binding(1) var canvas_sampler: sampler;
+
+@fragment
+fn fragment(in: Varyings) -> @location(0) vec4<f32> {
+    return textureSample(canvas, canvas_sampler, in.uv);
+}
+";
+
+/*
+ * kept in the Wayland state between breaks, the device is only requested for the first overlay
+ */
+#[derive(Debug, Default)]
+pub(crate) struct Gpu {
+    instance: Option<Instance>,
+    device: Option<(Adapter, Device, Queue)>,
+    // set once the GPU failed, the overlay is drawn through wl_shm from then on
+    pub(crate) unavailable: bool,
+}
+
+/*
+ * the overlay of one break, drawn onto its surface by the GPU
+ */
+#[derive(Debug)]
+pub(crate) struct GpuRenderer {
+    surface: Surface<'static>,
+    config: SurfaceConfiguration,
+    device: Device,
+    queue: Queue,
+    pipeline: RenderPipeline,
+    texture: Texture,
+    bind_group: BindGroup,
+    // the canvas as it was uploaded the last time
+    presented: Vec<u32>,
+}
+
+/*
+ * a renderer for the overlay's surface, None if the GPU can't draw onto it -- the canvas is drawn
+ * at render_size and scaled to surface_size
+ */
+pub(crate) fn renderer(
+    gpu: &mut Gpu,
+    wl_surface: &wl_surface::WlSurface,
+    surface_size: (u32, u32),
+    render_size: (u32, u32),
+) -> Option<GpuRenderer> {
+    match GpuRenderer::new(gpu, wl_surface, surface_size, render_size) {
+        Ok(renderer) => Some(renderer),
+        Err(err) => {
+            println!(
+                "The GPU can't draw the overlay, using shared memory instead! The error: {err}"
+            );
+            gpu.unavailable = true;
+            None
+        }
+    }
+}
+
+impl GpuRenderer {
+    fn new(
+        gpu: &mut Gpu,
+        wl_surface: &wl_surface::WlSurface,
+        surface_size: (u32, u32),
+        render_size: (u32, u32),
+    ) -> Result<GpuRenderer, Box<dyn std::error::Error>> {
+        let backend = wl_surface
+            .backend()
+            .upgrade()
+            .ok_or("the Wayland connection is gone")?;
+        let display = NonNull::new(backend.display_ptr().cast())
+            .ok_or("the Wayland connection has no display")?;
+        let surface_pointer = NonNull::new(wl_surface.id().as_ptr().cast())
+            .ok_or("the overlay's surface was destroyed")?;
+
+        let instance = gpu.instance.get_or_insert_with(|| {
+            Instance::new(&InstanceDescriptor {
+                backends: Backends::VULKAN | Backends::GL,
+                ..Default::default()
+            })
+        });
+        // SAFETY: the connection outlives the renderer, which is dropped before the surface is
+        // destroyed (Renderer::release)
+        let surface = unsafe {
+            instance.create_surface_unsafe(SurfaceTargetUnsafe::RawHandle {
+                raw_display_handle: RawDisplayHandle::Wayland(WaylandDisplayHandle::new(display)),
+                raw_window_handle: RawWindowHandle::Wayland(WaylandWindowHandle::new(
+                    surface_pointer,
+                )),
+            })?
+        };
+
+        if gpu.device.is_none() {
+            let adapter =
+                pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::LowPower,
+                    force_fallback_adapter: false,
+                    compatible_surface: Some(&surface),
+                }))?;
+            let (device, queue) =
+                pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+                    label: Some("overlay"),
+                    ..Default::default()
+                }))?;
+            info!("Drawing the overlay on {:?}", adapter.get_info());
+            gpu.device = Some((adapter, device, queue));
+        }
+        let (adapter, device, queue) = gpu.device.as_ref().unwrap();
+
+        let capabilities = surface.get_capabilities(adapter);
+        let format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|format| {
+                matches!(
+                    format,
+                    TextureFormat::Bgra8Unorm | TextureFormat::Rgba8Unorm
+                )
+            })
+            .or(capabilities.formats.first().copied())
+            .ok_or("the surface supports no formats")?;
+        // the canvas is premultiplied, like wl_shm expects it -- without the alpha channel the
+        // overlay fades to black instead, like with Xrgb8888
+        let alpha_mode = if capabilities
+            .alpha_modes
+            .contains(&CompositeAlphaMode::PreMultiplied)
+        {
+            CompositeAlphaMode::PreMultiplied
+        } else {
+            CompositeAlphaMode::Auto
+        };
+        // without waiting for the output, unless that's all the surface offers
+        let present_mode = if capabilities.present_modes.contains(&PresentMode::Mailbox) {
+            PresentMode::Mailbox
+        } else {
+            PresentMode::Fifo
+        };
+        let config = SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: surface_size.0,
+            height: surface_size.1,
+            present_mode,
+            desired_maximum_frame_latency: 2,
+            alpha_mode,
+            view_formats: Vec::new(),
+        };
+        surface.configure(device, &config);
+
+        // the canvas holds the bytes of sRGB colors, the texture decodes them if the surface
+        // encodes them again
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("canvas"),
+            size: wgpu::Extent3d {
+                width: render_size.0,
+                height: render_size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: if format.is_srgb() {
+                TextureFormat::Bgra8UnormSrgb
+            } else {
+                TextureFormat::Bgra8Unorm
+            },
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("canvas"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("overlay"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("overlay"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vertex"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fragment"),
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("canvas"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        info!("Created the GPU surface, {format:?} in {present_mode:?} with {alpha_mode:?} alpha");
+
+        Ok(GpuRenderer {
+            surface,
+            config,
+            device: device.clone(),
+            queue: queue.clone(),
+            pipeline,
+            texture,
+            bind_group,
+            presented: Vec::new(),
+        })
+    }
+
+    /*
+     * uploads what changed since the last frame into the texture
+     */
+    fn upload(&mut self, canvas: &Canvas) {
+        let Some(damage) = canvas.changed_rect(&self.presented) else {
+            return;
+        };
+        // the texture is BGRA, which is how the canvas' ARGB pixels are laid out in memory
+        let (region, bytes) = canvas.region_bytes(Format::Argb8888, Transform::Normal, damage);
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.x,
+                    y: region.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bytes,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(region.width * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: region.width,
+                height: region.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.presented.clear();
+        self.presented.extend_from_slice(canvas.pixels());
+    }
+}
+
+impl Renderer for GpuRenderer {
+    /*
+     * the compositor rotates the surface, the GPU draws it upright
+     */
+    fn transform(&self) -> Transform {
+        Transform::Normal
+    }
+
+    /*
+     * presenting commits the surface already, committing it again only adds the frame callback
+     */
+    fn present(
+        &mut self,
+        canvas: &Canvas,
+        _: &mut State,
+        _: &QueueHandle<State>,
+        _: &wl_surface::WlSurface,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // e.g. the swapchain went stale, the frame is drawn the next time
+            Err(SurfaceError::Outdated | SurfaceError::Timeout) => {
+                self.surface.configure(&self.device, &self.config);
+                return Ok(false);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        self.upload(canvas);
+
+        let view = frame.texture.create_view(&Default::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("overlay"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        self.queue.submit([encoder.finish()]);
+        frame.present();
+        Ok(true)
+    }
+
+    /*
+     * the GPU surface can't outlive the overlay's surface, the device is kept in the state
+     */
+    fn release(self: Box<Self>, _: &mut State) {}
+}
@@ -0,0 +1,115 @@
+// keeping the input from the compositor during strict breaks, e.g. `grab_input=true`
+//
+// not every compositor lets the overlay inhibit its shortcuts, so strict breaks can grab the
+// keyboards and pointers through evdev (EVIOCGRAB) instead: the compositor gets no input until the
+// grab is released. that needs no root, only read access to /dev/input (e.g. through the input
+// group). the kernel releases the grab as soon as the devices are closed, so a daemon that crashed
+// or was killed by the watchdog doesn't keep the input
+use std::{
+    fs::{self, File},
+    io,
+    os::fd::AsRawFd,
+    path::PathBuf,
+};
+
+// _IOW('E', 0x90, int)
+const EVIOCGRAB: libc::c_ulong = 0x4004_4590;
+const INPUT_DIR: &str = "/dev/input";
+const SYS_INPUT_DIR: &str = "/sys/class/input";
+const UDEV_DATA_DIR: &str = "/run/udev/data";
+// the properties udev tags the devices with, power buttons or lid switches are left alone
+const GRABBED_KINDS: [&str; 5] = [
+    "ID_INPUT_KEYBOARD",
+    "ID_INPUT_MOUSE",
+    "ID_INPUT_TOUCHPAD",
+    "ID_INPUT_TOUCHSCREEN",
+    "ID_INPUT_TABLET",
+];
+
+/*
+ * the grabbed devices, released when it's dropped
+ */
+pub(crate) struct Grab {
+    devices: Vec<File>,
+}
+
+impl Drop for Grab {
+    fn drop(&mut self) {
+        for device in &self.devices {
+            if let Err(err) = set_grab(device, false) {
+                println!("An input device could not be released! The error: {err}");
+            }
+        }
+        println!("Released {} input devices.", self.devices.len());
+    }
+}
+
+/*
+ * true if at least one keyboard or pointer can be opened, i.e. grabbed
+ */
+pub(crate) fn available() -> bool {
+    input_devices().iter().any(|path| File::open(path).is_ok())
+}
+
+/*
+ * grabs every keyboard and pointer that can be opened, an error if there was none
+ */
+pub(crate) fn grab() -> Result<Grab, Box<dyn std::error::Error>> {
+    let mut devices = Vec::new();
+    for path in input_devices() {
+        let grabbed = File::open(&path).and_then(|device| {
+            set_grab(&device, true)?;
+            Ok(device)
+        });
+        match grabbed {
+            Ok(device) => devices.push(device),
+            Err(err) => println!("The input device {path:?} could not be grabbed: {err}"),
+        }
+    }
+
+    if devices.is_empty() {
+        return Err("no input device could be grabbed".into());
+    }
+    println!("Grabbed {} input devices.", devices.len());
+    Ok(Grab { devices })
+}
+
+fn set_grab(device: &File, grab: bool) -> io::Result<()> {
+    // the grab belongs to the open file, closing it releases the grab as well
+    let result =
+        unsafe { libc::ioctl(device.as_raw_fd(), EVIOCGRAB as _, libc::c_int::from(grab)) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/*
+ * the event devices udev marked as keyboards or pointers
+ */
+fn input_devices() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(INPUT_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with("event") && is_keyboard_or_pointer(name))
+        .map(|name| PathBuf::from(INPUT_DIR).join(name))
+        .collect()
+}
+
+fn is_keyboard_or_pointer(name: &str) -> bool {
+    // e.g. "13:64", udev's database is keyed by the device number
+    let Ok(number) = fs::read_to_string(format!("{SYS_INPUT_DIR}/{name}/dev")) else {
+        return false;
+    };
+    let Ok(properties) = fs::read_to_string(format!("{UDEV_DATA_DIR}/c{}", number.trim())) else {
+        return false;
+    };
+    properties.lines().any(|line| {
+        GRABBED_KINDS
+            .iter()
+            .any(|kind| line.strip_prefix("E:") == Some(&format!("{kind}=1")))
+    })
+}
@@ -0,0 +1,239 @@
+// pausing the work timer while there is no input, e.g. `auto_pause_after=5m`
+//
+// the compositor reports idleness through ext-idle-notify on a connection of its own, which is
+// dispatched in the background. the idle state is global, like the stats, and every change wakes
+// the work loop up through the daemon's socket, like the remote breaks do. breaks that are extended
+// on activity watch the input the same way, with a timeout of a few seconds. without the popup
+// feature there is no wayland connection, so the work timer is never paused
+#[cfg(feature = "popup")]
+use std::{
+    fs,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+#[cfg(feature = "popup")]
+use wayland_client::{
+    Connection, Dispatch, QueueHandle,
+    protocol::{
+        wl_registry::{Event, WlRegistry},
+        wl_seat,
+    },
+};
+#[cfg(feature = "popup")]
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::{self, ExtIdleNotifierV1},
+};
+#[cfg(feature = "popup")]
+use wlbreaktime::{paths, protocol};
+
+// the commands the work loop is woken up with, they are not answered
+pub(crate) const IDLE_COMMAND: &str = "idle";
+pub(crate) const ACTIVE_COMMAND: &str = "active";
+// how long there's no input before a break counts it as rest, for `extend_break_on_activity`
+#[cfg(feature = "popup")]
+const REST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// when the last input happened, None while there is input
+static IDLE_SINCE: Mutex<Option<Instant>> = Mutex::new(None);
+// the same with a timeout of a few seconds, None while there is input or if it's not watched
+static RESTING_SINCE: Mutex<Option<Instant>> = Mutex::new(None);
+static INPUT_WATCHED: AtomicBool = AtomicBool::new(false);
+
+// what an idle notification is for
+#[cfg(feature = "popup")]
+enum Watch {
+    // pausing the work timer, after `auto_pause_after`
+    Pause,
+    // resting during breaks
+    Rest,
+}
+
+#[cfg(feature = "popup")]
+struct Idle {
+    notifier: Option<ExtIdleNotifierV1>,
+    seat: Option<wl_seat::WlSeat>,
+    timeout: Option<Duration>,
+    socket: UnixDatagram,
+    daemon_path: PathBuf,
+}
+
+#[cfg(feature = "popup")]
+impl Idle {
+    fn set_idle(&self, idle_since: Option<Instant>) {
+        *IDLE_SINCE.lock().unwrap() = idle_since;
+        let command = if idle_since.is_some() {
+            IDLE_COMMAND
+        } else {
+            ACTIVE_COMMAND
+        };
+        let request = protocol::request(protocol::request_id(), command);
+        if let Err(err) = self.socket.send_to(request.as_bytes(), &self.daemon_path) {
+            println!("[idle]: The daemon could not be woken up! The error: {err}");
+        }
+    }
+}
+
+#[cfg(feature = "popup")]
+impl Dispatch<WlRegistry, ()> for Idle {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let Event::Global {
+            name, interface, ..
+        } = event
+        {
+            match &interface[..] {
+                "ext_idle_notifier_v1" => state.notifier = Some(registry.bind(name, 1, qh, ())),
+                // the first seat is the one the input comes from
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "popup")]
+impl Dispatch<wl_seat::WlSeat, ()> for Idle {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[cfg(feature = "popup")]
+impl Dispatch<ExtIdleNotifierV1, ()> for Idle {
+    fn event(
+        _: &mut Self,
+        _: &ExtIdleNotifierV1,
+        _: ext_idle_notifier_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[cfg(feature = "popup")]
+impl Dispatch<ExtIdleNotificationV1, Watch> for Idle {
+    fn event(
+        state: &mut Self,
+        _: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        watch: &Watch,
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let timeout = match watch {
+            Watch::Pause => state.timeout.unwrap_or_default(),
+            Watch::Rest => REST_TIMEOUT,
+        };
+        // the compositor waited for the timeout, so that's when the last input happened
+        let since = match event {
+            ext_idle_notification_v1::Event::Idled => {
+                let now = Instant::now();
+                Some(now.checked_sub(timeout).unwrap_or(now))
+            }
+            ext_idle_notification_v1::Event::Resumed => None,
+            _ => return,
+        };
+        match watch {
+            Watch::Pause => state.set_idle(since),
+            // the break sees it on its next wake-up, at least once per second with a view
+            Watch::Rest => *RESTING_SINCE.lock().unwrap() = since,
+        }
+    }
+}
+
+/*
+ * when the last input happened, None while there is input or if idleness is not watched
+ */
+pub(crate) fn since() -> Option<Instant> {
+    *IDLE_SINCE.lock().unwrap()
+}
+
+/*
+ * when the last input happened, like since, but after a few seconds without input already --
+ * None while there is input, or if the input is not watched
+ */
+pub(crate) fn resting_since() -> Option<Instant> {
+    *RESTING_SINCE.lock().unwrap()
+}
+
+/*
+ * whether resting_since knows about the input
+ */
+pub(crate) fn input_watched() -> bool {
+    INPUT_WATCHED.load(Ordering::Relaxed)
+}
+
+/*
+ * watches for idleness in the background, the daemon is woken up whenever it starts or ends --
+ * without a timeout, the work timer is never paused and only the rest during breaks is watched
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn watch(
+    timeout: Option<Duration>,
+    daemon_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::connect_to_env()?;
+    let mut event_queue = connection.new_event_queue();
+    let qh = event_queue.handle();
+    let _registry = connection.display().get_registry(&qh, ());
+
+    let socket_path = paths::idle_socket_path()?;
+    // a previous instance might have left its socket behind
+    let _ = fs::remove_file(&socket_path);
+    let mut state = Idle {
+        notifier: None,
+        seat: None,
+        timeout,
+        socket: UnixDatagram::bind(&socket_path)?,
+        daemon_path: daemon_path.to_path_buf(),
+    };
+    event_queue.roundtrip(&mut state)?;
+
+    let (Some(notifier), Some(seat)) = (&state.notifier, &state.seat) else {
+        return Err("the compositor does not support ext-idle-notify".into());
+    };
+    let millis = |timeout: Duration| u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+    let _notification = timeout
+        .map(|timeout| notifier.get_idle_notification(millis(timeout), seat, &qh, Watch::Pause));
+    let _rest = notifier.get_idle_notification(millis(REST_TIMEOUT), seat, &qh, Watch::Rest);
+    INPUT_WATCHED.store(true, Ordering::Relaxed);
+
+    thread::spawn(move || {
+        loop {
+            if let Err(err) = event_queue.blocking_dispatch(&mut state) {
+                println!("[idle]: Lost the connection to the compositor! The error: {err}");
+                // the work timer shouldn't stay paused
+                *IDLE_SINCE.lock().unwrap() = None;
+                INPUT_WATCHED.store(false, Ordering::Relaxed);
+                return;
+            }
+        }
+    });
+    Ok(())
+}
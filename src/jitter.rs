@@ -0,0 +1,55 @@
+// random offsets of the work intervals, e.g. `break_interval_jitter=±5m`
+//
+// breaks then don't land at the same minute every time, e.g. right at the start of a recurring
+// meeting. the generator is seeded once at startup and the seed is logged, so a day's schedule can
+// be reproduced -- like the stats, the current offset is global, so `status` can report it
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use fastrand::Rng;
+use wlbreaktime::duration::Seconds;
+
+static JITTER: Mutex<Jitter> = Mutex::new(Jitter {
+    rng: None,
+    current: 0,
+});
+
+struct Jitter {
+    rng: Option<Rng>,
+    // the offset of the current work interval in seconds
+    current: i64,
+}
+
+/*
+ * seeds the generator from the current time
+ */
+pub(crate) fn seed() {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64);
+    println!("Seeded the break interval jitter with {seed}.");
+    JITTER.lock().unwrap().rng = Some(Rng::with_seed(seed));
+}
+
+/*
+ * the interval moved by a random offset of at most the jitter in either direction -- the offset
+ * is limited to half of the interval, so breaks never follow each other right away
+ */
+pub(crate) fn apply(interval: Seconds, jitter: Seconds) -> Seconds {
+    let mut state = JITTER.lock().unwrap();
+    let jitter = jitter.as_secs().min(interval.as_secs() / 2) as i64;
+    state.current = match state.rng.as_mut() {
+        Some(rng) if jitter > 0 => rng.i64(-jitter..=jitter),
+        _ => 0,
+    };
+    Seconds::new(interval.as_secs().saturating_add_signed(state.current))
+}
+
+/*
+ * the offset of the current work interval in seconds, e.g. -90 if the break comes earlier
+ */
+pub(crate) fn current() -> i64 {
+    JITTER.lock().unwrap().current
+}
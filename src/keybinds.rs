@@ -0,0 +1,182 @@
+// the recommended keybindings, so breaks can be taken and skipped without a terminal
+//
+// `wlbreaktime-helper install-keybinds` writes them for the running compositor, the daemon lists
+// them for `wlbreaktime-helper bindinfo`. every bind is Super+Alt with a letter, which compositors
+// rarely bind by default
+use std::{env, env::VarError};
+
+use crate::paths;
+
+pub struct Bind {
+    // a letter, the modifiers are the same for every bind
+    pub key: char,
+    pub args: &'static [&'static str],
+    pub description: &'static str,
+}
+
+pub const BINDS: [Bind; 5] = [
+    Bind {
+        key: 'B',
+        args: &["break"],
+        description: "take a break now",
+    },
+    Bind {
+        key: 'S',
+        args: &["skip"],
+        description: "skip the current break",
+    },
+    Bind {
+        key: 'P',
+        args: &["set", "5"],
+        description: "postpone the next break to 5 minutes from now",
+    },
+    Bind {
+        key: 'R',
+        args: &["reset"],
+        description: "start the work interval over",
+    },
+    Bind {
+        key: 'F',
+        args: &["focus", "60"],
+        description: "no breaks for the next hour",
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compositor {
+    Niri,
+    Sway,
+    Hyprland,
+}
+
+impl Compositor {
+    /*
+     * the running compositor, from the variables it sets for its clients
+     */
+    pub fn detect() -> Option<Compositor> {
+        if env::var_os("NIRI_SOCKET").is_some() {
+            Some(Compositor::Niri)
+        } else if env::var_os("SWAYSOCK").is_some() {
+            Some(Compositor::Sway)
+        } else if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Compositor::Hyprland)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compositor::Niri => "niri",
+            Compositor::Sway => "sway",
+            Compositor::Hyprland => "Hyprland",
+        }
+    }
+
+    /*
+     * where the binds are written to, next to the compositor's own config
+     */
+    pub fn snippet_path(self) -> Result<String, VarError> {
+        Ok(match self {
+            Compositor::Niri => paths::app_config_dir("niri")? + "/wlbreaktime.kdl",
+            Compositor::Sway => paths::app_config_dir("sway")? + "/wlbreaktime",
+            Compositor::Hyprland => paths::app_config_dir("hypr")? + "/wlbreaktime.conf",
+        })
+    }
+
+    /*
+     * the line that includes the snippet in the compositor's config
+     */
+    pub fn include_line(self, snippet_path: &str) -> String {
+        match self {
+            Compositor::Niri => format!("include \"{snippet_path}\""),
+            Compositor::Sway => format!("include {snippet_path}"),
+            Compositor::Hyprland => format!("source = {snippet_path}"),
+        }
+    }
+
+    /*
+     * every bind in the syntax of the compositor's config
+     */
+    pub fn snippet(self, helper_path: &str) -> String {
+        let header = "wlbreaktime's keybindings, written by `wlbreaktime-helper install-keybinds`";
+        let binds = BINDS.iter().map(|bind| match self {
+            Compositor::Niri => format!(
+                "    // {}\n    {}",
+                bind.description,
+                self.bind_line(bind, helper_path)
+            ),
+            Compositor::Sway | Compositor::Hyprland => format!(
+                "# {}\n{}",
+                bind.description,
+                self.bind_line(bind, helper_path)
+            ),
+        });
+        let binds = binds.collect::<Vec<_>>().join("\n");
+        match self {
+            Compositor::Niri => format!("// {header}\nbinds {{\n{binds}\n}}\n"),
+            Compositor::Sway | Compositor::Hyprland => format!("# {header}\n{binds}\n"),
+        }
+    }
+
+    fn bind_line(self, bind: &Bind, helper_path: &str) -> String {
+        let command = format!("{helper_path} {}", bind.args.join(" "));
+        match self {
+            Compositor::Niri => {
+                let args: Vec<String> = bind.args.iter().map(|arg| format!("\"{arg}\"")).collect();
+                format!(
+                    "Mod+Alt+{} {{ spawn \"{helper_path}\" {}; }}",
+                    bind.key,
+                    args.join(" ")
+                )
+            }
+            Compositor::Sway => format!(
+                "bindsym Mod4+Mod1+{} exec {command}",
+                bind.key.to_ascii_lowercase()
+            ),
+            Compositor::Hyprland => format!("bind = SUPER ALT, {}, exec, {command}", bind.key),
+        }
+    }
+
+    /*
+     * the command that registers the bind right away, None for niri, which only reads binds from
+     * its config
+     */
+    pub fn register_command(self, bind: &Bind, helper_path: &str) -> Option<Vec<String>> {
+        match self {
+            Compositor::Niri => None,
+            Compositor::Sway => Some(vec![
+                "swaymsg".to_string(),
+                self.bind_line(bind, helper_path),
+            ]),
+            Compositor::Hyprland => {
+                let line = self.bind_line(bind, helper_path);
+                let value = line.strip_prefix("bind = ").unwrap_or(&line).to_string();
+                Some(vec![
+                    "hyprctl".to_string(),
+                    "keyword".to_string(),
+                    "bind".to_string(),
+                    value,
+                ])
+            }
+        }
+    }
+}
+
+/*
+ * the binds as "<keys>=<command> -- <description>" lines, answered to `bindinfo`
+ */
+pub fn list(helper_path: &str) -> String {
+    BINDS
+        .iter()
+        .map(|bind| {
+            format!(
+                "Super+Alt+{}={helper_path} {} -- {}",
+                bind.key,
+                bind.args.join(" "),
+                bind.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,451 @@
+use std::sync::Arc;
+
+use jiff::{Zoned, fmt::strtime};
+use qrcodegen::{QrCode, QrCodeEcc};
+use resvg::{tiny_skia::Pixmap, usvg::Tree};
+
+use crate::{
+    config::{Align, Config, SkipSource, Theme},
+    palette::{self, Phase},
+    render::{Canvas, GLYPH_SIZE},
+    resources,
+};
+
+/*
+ * the colors used on the overlay
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Palette {
+    pub(crate) background: u32,
+    pub(crate) text: u32,
+    pub(crate) hint: u32,
+}
+
+const DEFAULT_PALETTE: Palette = Palette {
+    background: 0xFF333333,
+    text: 0xFFEEEEEE,
+    hint: 0xFF999999,
+};
+
+// the hint still has a contrast ratio of 5.7:1 on the light background
+const LIGHT_PALETTE: Palette = Palette {
+    background: 0xFFF2F2F2,
+    text: 0xFF1A1A1A,
+    hint: 0xFF5F5F5F,
+};
+
+// pure black and white (contrast ratio 21:1) and a yellow hint (19.6:1) exceed WCAG AAA
+const HIGH_CONTRAST_PALETTE: Palette = Palette {
+    background: 0xFF000000,
+    text: 0xFFFFFFFF,
+    hint: 0xFFFFFF00,
+};
+
+// QR codes are read best as black on white, whatever the palette
+const QR_DARK: u32 = 0xFF000000;
+const QR_LIGHT: u32 = 0xFFFFFFFF;
+
+const TITLE_SCALE: u32 = 6;
+const COUNTDOWN_SCALE: u32 = 16;
+const SUBTITLE_SCALE: u32 = 3;
+const FOOTER_SCALE: u32 = 2;
+const STATS_SCALE: u32 = 2;
+const CLOCK_SCALE: u32 = 3;
+// the strip in the break's color along the top edge, in pixels
+const PHASE_STRIP_HEIGHT: u32 = 8;
+// the light margin around a QR code, in modules -- scanners need it to find the code
+const QR_QUIET_ZONE: u32 = 4;
+// smaller modules are hard to scan from a phone held at arm's length, in pixels
+const QR_MIN_MODULE_SIZE: u32 = 3;
+
+pub(crate) struct TextBlock {
+    pub(crate) text: String,
+    pub(crate) scale: u32,
+    pub(crate) color: u32,
+}
+
+impl TextBlock {
+    fn new(text: &str, scale: u32, color: u32) -> TextBlock {
+        TextBlock {
+            text: text.to_string(),
+            scale,
+            color,
+        }
+    }
+
+    /*
+     * shrinks the text until it fits into the available width
+     */
+    fn fitted_scale(&self, available_width: u32) -> u32 {
+        let mut scale = self.scale;
+        while scale > 1 && Canvas::text_width(&self.text, scale) > available_width {
+            scale -= 1;
+        }
+        scale
+    }
+}
+
+/*
+ * the colors of the theme, high contrast takes precedence -- they change along with the desktop's
+ * preference for the auto theme
+ */
+pub(crate) fn palette(theme: Theme, high_contrast: bool) -> Palette {
+    if high_contrast {
+        HIGH_CONTRAST_PALETTE
+    } else if palette::light(theme) {
+        LIGHT_PALETTE
+    } else {
+        DEFAULT_PALETTE
+    }
+}
+
+/*
+ * how the break can be skipped from the overlay or the helper, None if it can't be skipped from
+ * either of them
+ */
+fn footer_hint(config: &Config) -> Option<&'static str> {
+    match (
+        config.skip_allowed(SkipSource::Overlay),
+        config.skip_allowed(SkipSource::Cli),
+    ) {
+        (true, true) => Some("Hold Esc, tap or run 'wlbreaktime-helper skip' to skip this break"),
+        (true, false) => Some("Hold Esc or tap to skip this break"),
+        (false, true) => Some("Run 'wlbreaktime-helper skip' to skip this break"),
+        (false, false) => None,
+    }
+}
+
+/*
+ * the configured link as a QR code, None without a link or if it's too long for one
+ */
+fn qr_code(config: &Config) -> Option<QrCode> {
+    let url = config
+        .popup_qr_url
+        .as_deref()
+        .filter(|url| !url.is_empty())?;
+    match QrCode::encode_text(url, QrCodeEcc::Medium) {
+        Ok(qr) => Some(qr),
+        Err(err) => {
+            println!("The popup_qr_url can't be shown as a QR code! The error: {err}");
+            None
+        }
+    }
+}
+
+/*
+ * the content of the break overlay: the title at the top, the countdown, the subtitle and the work
+ * stats in the middle and the footer hint at the bottom
+ */
+pub(crate) struct Layout {
+    pub(crate) title: TextBlock,
+    pub(crate) countdown: TextBlock,
+    pub(crate) subtitle: TextBlock,
+    // how long the last work stretch was and how many breaks were taken today
+    pub(crate) stats: Option<TextBlock>,
+    pub(crate) footer: Option<TextBlock>,
+    // the wall-clock time, formatted with clock_format whenever it's set
+    pub(crate) clock: Option<TextBlock>,
+    clock_format: Option<String>,
+    pub(crate) icon: Option<Arc<Tree>>,
+    // the bundled icon is drawn in the text's color, so it follows the theme
+    tint_icon: bool,
+    // the configured link, encoded once per break
+    pub(crate) qr: Option<QrCode>,
+    // how far Esc has been held to skip, between 0 and 1
+    pub(crate) hold_progress: f32,
+    // the rasterized icon is only looked up again when its size changes
+    icon_cache: Option<(u32, Arc<Pixmap>)>,
+    pub(crate) palette: Palette,
+    theme: Theme,
+    high_contrast: bool,
+    // the break's color of the phase palette
+    pub(crate) phase_color: u32,
+    // disables the fade-in of the overlay
+    pub(crate) reduce_motion: bool,
+    pub(crate) align: Align,
+    pub(crate) margin: u32,
+    pub(crate) spacing: u32,
+}
+
+impl Layout {
+    pub(crate) fn new(config: &Config, skippable: bool) -> Layout {
+        let palette = palette(config.theme, config.high_contrast);
+
+        Layout {
+            title: TextBlock::new(&config.popup_title, TITLE_SCALE, palette.text),
+            // the text is replaced on every render
+            countdown: TextBlock::new("00:00", COUNTDOWN_SCALE, palette.text),
+            subtitle: TextBlock::new(&config.popup_subtitle, SUBTITLE_SCALE, palette.text),
+            stats: None,
+            footer: skippable
+                .then(|| footer_hint(config))
+                .flatten()
+                .map(|hint| TextBlock::new(hint, FOOTER_SCALE, palette.hint)),
+            clock: None,
+            clock_format: config.popup_clock.clone(),
+            icon: resources::icon(&config.popup_icon),
+            tint_icon: config.popup_icon.is_none(),
+            qr: qr_code(config),
+            hold_progress: 0.0,
+            icon_cache: None,
+            palette,
+            theme: config.theme,
+            high_contrast: config.high_contrast,
+            phase_color: palette::color(config.phase_palette, Phase::Break),
+            reduce_motion: config.reduce_motion,
+            align: config.popup_align,
+            margin: config.popup_margin,
+            spacing: GLYPH_SIZE * SUBTITLE_SCALE,
+        }
+    }
+
+    /*
+     * takes over the colors if the desktop switched between light and dark since the last frame
+     */
+    fn follow_theme(&mut self) {
+        let palette = palette(self.theme, self.high_contrast);
+        if palette == self.palette {
+            return;
+        }
+        self.palette = palette;
+        for block in [&mut self.title, &mut self.countdown, &mut self.subtitle] {
+            block.color = palette.text;
+        }
+        for block in [&mut self.stats, &mut self.footer, &mut self.clock]
+            .into_iter()
+            .flatten()
+        {
+            block.color = palette.hint;
+        }
+    }
+
+    pub(crate) fn render(&mut self, canvas: &mut Canvas, remaining_seconds: u64) {
+        self.countdown.text = format!(
+            "{:02}:{:02}",
+            remaining_seconds / 60,
+            remaining_seconds % 60
+        );
+
+        self.follow_theme();
+        canvas.fill(self.palette.background);
+        canvas.fill_rect(0, 0, canvas.width, PHASE_STRIP_HEIGHT, self.phase_color);
+
+        let available_width = canvas.width.saturating_sub(2 * self.margin);
+        // the title leaves room for the clock next to it, on both sides if it's centered
+        let clock_width = self.draw_clock(canvas, available_width);
+        let clock_room = match (clock_width, self.align) {
+            (0, _) => 0,
+            (width, Align::Center) => 2 * (width + self.spacing),
+            (width, Align::Left | Align::Right) => width + self.spacing,
+        };
+        let title_scale = self
+            .title
+            .fitted_scale(available_width.saturating_sub(clock_room));
+        let mut countdown_scale = self.countdown.fitted_scale(available_width);
+        let subtitle_scale = self.subtitle.fitted_scale(available_width);
+
+        let title_height = GLYPH_SIZE * title_scale;
+        self.draw_block(canvas, &self.title, title_scale, self.margin);
+
+        let mut footer_height = 0;
+        if let Some(footer) = &self.footer {
+            let footer_scale = footer.fitted_scale(available_width);
+            footer_height = GLYPH_SIZE * footer_scale;
+            let y = canvas.height.saturating_sub(self.margin + footer_height);
+            self.draw_block(canvas, footer, footer_scale, y);
+
+            // the hold progress fills a bar in the spacing right above the hint, so nothing moves
+            if self.hold_progress > 0.0 {
+                let bar_height = self.spacing / 3;
+                let bar_y = y.saturating_sub(self.spacing / 2 + bar_height / 2);
+                let bar_width = (available_width as f32 * self.hold_progress.min(1.0)) as u32;
+                canvas.fill_rect(self.margin, bar_y, bar_width, bar_height, self.palette.hint);
+            }
+        }
+
+        // the icon, the countdown and the subtitle are centered in the space between title and
+        // footer
+        let top = self.margin + title_height + self.spacing;
+        let bottom = canvas
+            .height
+            .saturating_sub(self.margin + footer_height + self.spacing);
+        let available_height = bottom.saturating_sub(top);
+        let subtitle_height = GLYPH_SIZE * subtitle_scale;
+        let stats_scale = self
+            .stats
+            .as_ref()
+            .map_or(0, |stats| stats.fitted_scale(available_width));
+        let stats_height = match stats_scale {
+            0 => 0,
+            scale => self.spacing + GLYPH_SIZE * scale,
+        };
+        let spacing = self.spacing;
+        let middle_height = |icon_size: u32, countdown_scale: u32| {
+            let icon_height = if icon_size > 0 {
+                icon_size + spacing
+            } else {
+                0
+            };
+            icon_height + GLYPH_SIZE * countdown_scale + spacing + subtitle_height + stats_height
+        };
+
+        // scaling the icon with the output, but never letting it take more than a third of the
+        // available height
+        let mut icon_size = match self.icon {
+            Some(_) => (canvas.width.min(canvas.height) / 5).min(available_height / 3),
+            None => 0,
+        };
+        while countdown_scale > 1 && middle_height(icon_size, countdown_scale) > available_height {
+            countdown_scale -= 1;
+        }
+        if middle_height(icon_size, countdown_scale) > available_height {
+            icon_size = 0;
+        }
+
+        let countdown_height = GLYPH_SIZE * countdown_scale;
+        let mut y =
+            top + available_height.saturating_sub(middle_height(icon_size, countdown_scale)) / 2;
+
+        if icon_size > 0 {
+            let x = match self.align {
+                Align::Left => self.margin,
+                Align::Center => canvas.width.saturating_sub(icon_size) / 2,
+                Align::Right => canvas.width.saturating_sub(self.margin + icon_size),
+            };
+            let (tint, color) = (self.tint_icon, self.palette.text);
+            if let Some(pixmap) = self.rasterize_icon(icon_size) {
+                let (width, height) = (pixmap.width(), pixmap.height());
+                if tint {
+                    canvas.draw_mask(x, y, width, height, pixmap.data(), color);
+                } else {
+                    canvas.draw_rgba(x, y, width, height, pixmap.data());
+                }
+            }
+            y += icon_size + self.spacing;
+        }
+
+        self.draw_block(canvas, &self.countdown, countdown_scale, y);
+        self.draw_block(
+            canvas,
+            &self.subtitle,
+            subtitle_scale,
+            y + countdown_height + self.spacing,
+        );
+        if let Some(stats) = &self.stats {
+            let y = y + countdown_height + self.spacing + subtitle_height + self.spacing;
+            self.draw_block(canvas, stats, stats_scale, y);
+        }
+
+        self.draw_qr_code(canvas, bottom);
+    }
+
+    /*
+     * dark modules on a light square in the bottom corner opposite of the text's alignment, right
+     * above the footer -- it's left out if the output is too small to scan it
+     */
+    fn draw_qr_code(&self, canvas: &mut Canvas, bottom: u32) {
+        let Some(qr) = &self.qr else {
+            return;
+        };
+        let modules = qr.size() as u32 + 2 * QR_QUIET_ZONE;
+        let module_size = canvas.width.min(canvas.height) / 6 / modules;
+        if module_size < QR_MIN_MODULE_SIZE {
+            return;
+        }
+        let size = modules * module_size;
+        let x = match self.align {
+            Align::Right => self.margin,
+            Align::Left | Align::Center => canvas.width.saturating_sub(self.margin + size),
+        };
+        let y = bottom.saturating_sub(size);
+
+        canvas.fill_rect(x, y, size, size, QR_LIGHT);
+        for row in 0..qr.size() {
+            for column in 0..qr.size() {
+                if qr.get_module(column, row) {
+                    canvas.fill_rect(
+                        x + (QR_QUIET_ZONE + column as u32) * module_size,
+                        y + (QR_QUIET_ZONE + row as u32) * module_size,
+                        module_size,
+                        module_size,
+                        QR_DARK,
+                    );
+                }
+            }
+        }
+    }
+
+    /*
+     * the clock in the top corner opposite of the text's alignment, returns its width -- 0 without
+     * a clock or if there is no room for it next to the title
+     */
+    fn draw_clock(&self, canvas: &mut Canvas, available_width: u32) -> u32 {
+        let Some(clock) = &self.clock else {
+            return 0;
+        };
+        let width = Canvas::text_width(&clock.text, clock.scale);
+        if width > available_width / 3 {
+            return 0;
+        }
+        let x = match self.align {
+            Align::Right => self.margin,
+            Align::Left | Align::Center => canvas.width.saturating_sub(self.margin + width),
+        };
+        canvas.draw_text(x, self.margin, clock.scale, clock.color, &clock.text);
+        width
+    }
+
+    /*
+     * formats the time for the clock, it's drawn with the next frame
+     */
+    pub(crate) fn set_clock(&mut self, now: &Zoned) {
+        let Some(format) = &self.clock_format else {
+            return;
+        };
+        // the format was checked when the config was read
+        let text = strtime::format(format.as_str(), now).unwrap_or_default();
+        match &mut self.clock {
+            Some(clock) => clock.text = text,
+            None => self.clock = Some(TextBlock::new(&text, CLOCK_SCALE, self.palette.hint)),
+        }
+    }
+
+    /*
+     * shows the last work stretch and today's breaks below the subtitle
+     */
+    pub(crate) fn set_stats(&mut self, worked: u64, taken_today: u64) {
+        // whole minutes are enough to read at a glance
+        let worked = match worked / 60 {
+            0 => "less than a minute".to_string(),
+            1 => "1 minute".to_string(),
+            minutes => format!("{minutes} minutes"),
+        };
+        let text = format!("You worked for {worked}. Breaks taken today: {taken_today}");
+        self.stats = Some(TextBlock::new(&text, STATS_SCALE, self.palette.hint));
+    }
+
+    /*
+     * the icon rasterized at the given size, earlier breaks share theirs through the resource cache
+     */
+    fn rasterize_icon(&mut self, size: u32) -> Option<&Pixmap> {
+        let icon = self.icon.as_ref()?;
+        if self
+            .icon_cache
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != size)
+        {
+            self.icon_cache = Some((size, resources::rasterized_icon(icon, size)?));
+        }
+        self.icon_cache.as_ref().map(|(_, pixmap)| pixmap.as_ref())
+    }
+
+    fn draw_block(&self, canvas: &mut Canvas, block: &TextBlock, scale: u32, y: u32) {
+        let width = Canvas::text_width(&block.text, scale);
+        let x = match self.align {
+            Align::Left => self.margin,
+            Align::Center => canvas.width.saturating_sub(width) / 2,
+            Align::Right => canvas.width.saturating_sub(self.margin + width),
+        };
+        canvas.draw_text(x, y, scale, block.color, &block.text);
+    }
+}
@@ -0,0 +1,8 @@
+// code shared between the daemon and the helper
+pub mod datagram;
+pub mod duration;
+pub mod keybinds;
+pub mod migration;
+pub mod paths;
+pub mod protocol;
+pub mod units;
@@ -0,0 +1,129 @@
+// the user's graphical sessions, as logind sees them -- whatever display manager (or TTY) started
+// them
+//
+// once the last graphical session ended (e.g. after logging out to a TTY), the schedule stops
+// instead of failing against a compositor that is gone, and once a new one began, the daemon
+// restarts so it connects to the new compositor. a daemon started without a graphical session
+// (e.g. headless on a TTY) stays as it is until one ended
+use std::{
+    fs,
+    os::unix::{fs::MetadataExt, net::UnixDatagram},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use wlbreaktime::{paths, protocol};
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedObjectPath,
+};
+
+// the command the daemon is woken up with whenever a graphical session ended or began, it's not
+// answered
+pub(crate) const SESSION_COMMAND: &str = "session";
+
+const LOGIND_DESTINATION: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+// the session types of logind that have a display server
+const GRAPHICAL_TYPES: [&str; 3] = ["wayland", "x11", "mir"];
+
+// set while the user has no graphical session left
+static ENDED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn ended() -> bool {
+    ENDED.load(Ordering::Relaxed)
+}
+
+/*
+ * watches the sessions in the background, the daemon is woken up whenever the last graphical one
+ * ended or a new one began after that
+ */
+pub(crate) fn watch(daemon_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        LOGIND_DESTINATION,
+        LOGIND_PATH,
+        MANAGER_INTERFACE,
+    )?;
+    // subscribed before the sessions are listed, so no change is missed in between
+    let signals = manager.receive_all_signals()?;
+    let mut graphical = graphical_session(&connection, &manager)?;
+
+    let socket_path = paths::logind_socket_path()?;
+    // a previous instance might have left its socket behind
+    let _ = fs::remove_file(&socket_path);
+    let socket = UnixDatagram::bind(&socket_path)?;
+    let daemon_path = daemon_path.to_path_buf();
+
+    thread::spawn(move || {
+        for signal in signals {
+            let header = signal.header();
+            let member = header.member().map(|member| member.as_str());
+            if !matches!(member, Some("SessionNew" | "SessionRemoved")) {
+                continue;
+            }
+            let now_graphical = match graphical_session(&connection, &manager) {
+                Ok(now_graphical) => now_graphical,
+                Err(err) => {
+                    println!("[logind]: The sessions could not be listed! The error: {err}");
+                    continue;
+                }
+            };
+            let changed = now_graphical != graphical;
+            graphical = now_graphical;
+            // a daemon started without a graphical session is kept as it is when one begins
+            if !changed || (now_graphical && !ended()) {
+                continue;
+            }
+            ENDED.store(!now_graphical, Ordering::Relaxed);
+            println!(
+                "[logind]: {}",
+                if now_graphical {
+                    "A graphical session began."
+                } else {
+                    "The last graphical session ended."
+                }
+            );
+            wake(&socket, &daemon_path);
+        }
+        println!("[logind]: Lost the connection to logind, sessions are no longer watched.");
+    });
+    Ok(())
+}
+
+fn wake(socket: &UnixDatagram, daemon_path: &Path) {
+    let request = protocol::request(protocol::request_id(), SESSION_COMMAND);
+    if let Err(err) = socket.send_to(request.as_bytes(), daemon_path) {
+        println!("[logind]: The daemon could not be woken up! The error: {err}");
+    }
+}
+
+/*
+ * whether the user has a session with a display server that isn't closing, e.g. one whose
+ * processes are still shut down after the logout
+ */
+fn graphical_session(
+    connection: &Connection,
+    manager: &Proxy,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // the id, the uid, the user name, the seat and the object of every session
+    let sessions: Vec<(String, u32, String, String, OwnedObjectPath)> =
+        manager.call("ListSessions", &())?;
+    let uid = fs::metadata("/proc/self")?.uid();
+    for (_, session_uid, _, _, path) in sessions {
+        if session_uid != uid {
+            continue;
+        }
+        let session = Proxy::new(connection, LOGIND_DESTINATION, path, SESSION_INTERFACE)?;
+        let kind: String = session.get_property("Type")?;
+        let state: String = session.get_property("State")?;
+        if GRAPHICAL_TYPES.contains(&kind.as_str()) && state != "closing" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
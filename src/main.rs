@@ -1,270 +1,2159 @@
 // TODO posting errors to journald at an incredibly fast rate: "an error occurred on output stream: A backend-specific error has occurred: ALSA function
 // 'snd_pcm_poll_descriptors_revents' failed with error 'Unknown errno (-5)'"
-use core::str;
-use libsystemd::{
-    activation::{self, FileDescriptor, IsType},
-    daemon::{self, NotifyState},
-};
+use libsystemd::daemon::{self, NotifyState};
 use std::{
-    io::{Cursor, ErrorKind},
-    os::{
-        fd::{FromRawFd, IntoRawFd},
-        unix::net::UnixDatagram,
-    },
-    process::Command,
-    sync::Arc,
+    env,
+    io::ErrorKind,
+    os::unix::net::UnixDatagram,
+    path::Path,
     time::{Duration, Instant},
 };
-// show pop-up
-use wayland_client::{Connection, EventQueue};
-// play a sound
-use rodio::{Decoder, OutputStream, OutputStreamHandle, source::Source};
 // show notifications
-use notify_rust::Notification;
+#[cfg(feature = "notifications")]
+use std::fs;
 
+// show pop-up
+#[cfg(feature = "popup")]
 mod wayland;
-use wayland::{State, check_for_globals, show_popup};
+#[cfg(feature = "popup")]
+use wayland::{discard_prepared_frame, hide_popup, prepare_popup, show_popup};
+#[cfg(feature = "popup")]
+mod supervisor;
 
-use crate::wayland::wait_until_work;
+use wlbreaktime::{duration::Seconds, keybinds, paths, protocol};
 
+mod announce;
+mod capabilities;
+mod cli;
+mod clock;
+mod commands;
+mod compositor;
+// play a sound
+#[cfg(feature = "audio")]
+mod audio;
+mod batch;
+mod battery;
 mod config;
+mod debt;
+mod debug;
+mod decisions;
+mod dnd;
+mod events;
+#[cfg(feature = "popup")]
+mod focus;
+#[cfg(feature = "popup")]
+mod fonts;
+#[cfg(feature = "popup")]
+mod gamma;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod grab;
+mod idle;
+mod jitter;
+#[cfg(feature = "popup")]
+mod layout;
+#[cfg(feature = "dbus")]
+mod logind;
+mod notify;
+mod palette;
+#[cfg(feature = "dbus")]
+mod portal;
+mod remote;
+#[cfg(feature = "popup")]
+mod render;
+mod resources;
+mod session;
+mod shutdown;
+mod skip;
+mod sockets;
+mod stats;
+mod template;
+mod timers;
+#[cfg(feature = "popup")]
+mod toast;
+mod toggles;
+mod tracking;
+mod watchdog;
+#[cfg(feature = "x11")]
+mod x11;
+use commands::State;
+use config::{Config, DebtPolicy, OnResume, SkipSource, SuspendCountsAs};
+use decisions::Decision;
+#[cfg(feature = "popup")]
+use layout::Layout;
+use palette::Phase;
+use shutdown::Shutdown;
+use stats::BreakReason;
 
+// how long the resume prompt waits for the daemon's response
+#[cfg(feature = "notifications")]
 const NORMAL_READ_TIMEOUT: u64 = 3;
+const MINIMUM_ESCALATED_INTERVAL: Seconds = Seconds::new(300);
+// how long sending a response may block, in seconds
+const RESPONSE_WRITE_TIMEOUT: u64 = 1;
+// how often the wall clock is compared to the monotonic clock during work time
+const CLOCK_CHECK_INTERVAL: u64 = 60;
+// rolling breaks that would start this shortly before the fixed break are merged into it
+const FIXED_BREAK_MERGE_WINDOW: Seconds = Seconds::new(900);
+// between the notification announcing a break and the break itself
+const LEAD_IN: Duration = Duration::from_secs(10);
+// a break that's extended on activity takes at most this many times its duration
+const MAX_EXTENDED_BREAK: u64 = 3;
+// how long before a break the overlay's toast warns about it, in seconds
+#[cfg(feature = "popup")]
+const BREAK_WARNING: u64 = palette::WARNING_SECONDS;
+
+/*
+ * asks the user whether the work timer should be reset after waking up from suspension
+ * the answer is delivered through the daemon's own socket, just like a helper would do it
+ */
+#[cfg(feature = "notifications")]
+fn prompt_for_reset(socket: &UnixDatagram, reason: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !capabilities::get().notifications {
+        return Err("no notification daemon is running".into());
+    }
+
+    let daemon_path = socket
+        .local_addr()?
+        .as_pathname()
+        .map(Path::to_path_buf)
+        .ok_or("The systemd socket is not bound to a path!")?;
+
+    let handle = notify::notification()
+        .summary("Welcome back!")
+        .body(&format!(
+            "{reason} Do you want to start a fresh work interval?"
+        ))
+        .action("reset", "Start fresh")
+        .action("continue", "Continue")
+        .show()?;
+
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "reset"
+                && let Err(err) = send_request(&daemon_path, "reset")
+            {
+                println!("The work timer could not be reset! The error: {err}");
+            }
+        });
+    });
+
+    Ok(())
+}
+
+#[cfg(not(feature = "notifications"))]
+fn prompt_for_reset(
+    _socket: &UnixDatagram,
+    _reason: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("built without the notifications feature".into())
+}
+
+/*
+ * sends the command from the prompt socket, on behalf of a notification's action
+ */
+#[cfg(feature = "notifications")]
+pub(crate) fn send_request(
+    daemon_path: &Path,
+    command: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let prompt_path = paths::prompt_socket_path()?;
+    // a previous prompt might have left its socket behind
+    let _ = fs::remove_file(&prompt_path);
+
+    let prompt_socket = UnixDatagram::bind(&prompt_path)?;
+    prompt_socket.set_read_timeout(Some(Duration::from_secs(NORMAL_READ_TIMEOUT)))?;
+    let request = protocol::request(protocol::request_id(), command);
+    prompt_socket.send_to(request.as_bytes(), daemon_path)?;
+
+    // e.g. the new remaining time of a reset, unless the phase changed in the meantime
+    match wlbreaktime::datagram::receive(&prompt_socket, protocol::CHUNK_SIZE) {
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    fs::remove_file(&prompt_path)?;
+    Ok(())
+}
+
+/*
+ * the work time since the start, without the time paused since paused_since
+ */
+fn worked(start: Instant, paused_since: Option<Instant>) -> Duration {
+    paused_since
+        .unwrap_or_else(Instant::now)
+        .saturating_duration_since(start)
+}
+
+/*
+ * how the work time ended
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WorkEnd {
+    Elapsed,
+    // a break was requested through the helper, optionally with its own duration
+    Skipped {
+        break_duration: Option<Seconds>,
+    },
+    // a focus session ended, the break starts right away
+    FocusEnded,
+    // the wall clock jumped, so the schedule needs to be re-evaluated with the remaining seconds
+    ClockJumped {
+        remaining: Seconds,
+    },
+    // the daemon was asked to quit or restart
+    Shutdown {
+        shutdown: Shutdown,
+        remaining: Seconds,
+    },
+    // a command during the lead-in moved the announced break, the next work time runs it
+    Postponed,
+    // the announced break was skipped during the lead-in
+    BreakSkipped,
+}
 
 /*
- * returns true if work time was skipped
+ * the work time until the break, or the lead-in if there is a skip policy for the announced break
+ * -- the lead-in runs the same commands, however it ends on a skip or on anything that moves the
+ * break, and it isn't paused without input
  */
 fn wait_until_break(
     socket: &mut UnixDatagram,
-    break_interval: u64,
-) -> Result<bool, Box<dyn std::error::Error>> {
+    break_interval: Seconds,
+    config: &Config,
+    lead_in: Option<&skip::Policy>,
+) -> Result<WorkEnd, Box<dyn std::error::Error>> {
     //waiting until it's break time
-    println!("Work time!");
+    let state = if lead_in.is_some() {
+        println!(
+            "Lead-in, the break starts in {} seconds!",
+            break_interval.as_secs()
+        );
+        State::LeadIn
+    } else {
+        println!("Work time!");
+        State::Work
+    };
     let mut breaktime = false;
     let mut now = Instant::now();
-    let mut skipped = false;
+    let mut work_end = WorkEnd::Elapsed;
 
     // to enable changing the remaining time, the break duration needs to be mutable
-    let mut work_duration_seconds = break_interval;
+    let mut work_duration = break_interval;
+    // while a focus session is active, the timer can't be changed and the break starts when the
+    // session ends
+    let mut focus_session = false;
+    let mut wall_clock = clock::WallClock::new();
+    let mut suspend_clock = clock::SuspendClock::new();
+    // set while the work timer is paused because there is no input
+    let mut paused_since: Option<Instant> = None;
+    // the toast is shown once per interval, setting or resetting the timer starts a new one
+    #[cfg(feature = "popup")]
+    let warn = warn_with_toast(config);
+    #[cfg(feature = "popup")]
+    let mut warned = false;
 
     while !breaktime {
+        watchdog::beat();
+        timers::fire_due();
+
+        // without a graphical session nobody takes the breaks, the daemon restarts in the next one
+        #[cfg(feature = "dbus")]
+        if logind::ended() {
+            let remaining = work_duration.saturating_sub(worked(now, paused_since).into());
+            println!("The graphical session ended, stopped the schedule until the next one.");
+            decisions::record(Decision::Paused, "the graphical session ended");
+            let shutdown = wait_for_session(socket)?;
+            return Ok(WorkEnd::Shutdown {
+                shutdown,
+                remaining,
+            });
+        }
+
+        // the work timer stands still without input, focus sessions run on regardless
+        match (
+            idle::since().filter(|_| !focus_session && lead_in.is_none()),
+            paused_since,
+        ) {
+            (Some(idle_since), None) => {
+                paused_since = Some(idle_since.max(now));
+                println!("There is no input, paused the work timer.");
+                decisions::record(Decision::Paused, "there is no input");
+            }
+            (None, Some(paused)) => {
+                let away = paused.elapsed();
+                let worked = worked(now, paused_since);
+                now = Instant::now().checked_sub(worked).unwrap_or(now);
+                paused_since = None;
+                println!("There is input again, resumed the work timer.");
+                decisions::record(Decision::Resumed, "there is input again");
+
+                // coming back after a long absence is handled like waking up from suspension
+                let resume_after = config.resume_after_idle;
+                if !focus_session && !resume_after.is_zero() && away >= resume_after.as_duration() {
+                    let away_for = format!(" of {} seconds", away.as_secs());
+                    match config.on_resume {
+                        OnResume::Reset => {
+                            work_duration = break_interval;
+                            now = Instant::now();
+                            #[cfg(feature = "popup")]
+                            {
+                                warned = false;
+                            }
+                            println!(
+                                "Reset timer after an absence{away_for}. Next break is in {} seconds!",
+                                work_duration.as_secs()
+                            );
+                            decisions::record(Decision::Reset, format!("an absence{away_for}"));
+                        }
+                        OnResume::Prompt => {
+                            println!("Back after an absence{away_for}, asking whether to reset.");
+                            if let Err(err) = prompt_for_reset(socket, "You were away for a while.")
+                            {
+                                println!("Unable to show the resume prompt! The error: {err}");
+                            }
+                        }
+                        OnResume::Continue => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
         // setting read timeout every time, because for every break it's set to a different value
         // and on interrupts it needs to be adjusted
-        let seconds_until_break = work_duration_seconds
-            .checked_sub(now.elapsed().as_secs())
-            .unwrap_or(1);
+        let seconds_until_break = work_duration
+            .saturating_sub(worked(now, paused_since).into())
+            .as_secs()
+            .max(1);
 
-        socket.set_read_timeout(Some(Duration::from_secs(seconds_until_break)))?;
+        // the lead-in's notification announces the last seconds itself
+        #[cfg(feature = "popup")]
+        let warn_now = warn && !warned && !focus_session && paused_since.is_none();
+        #[cfg(feature = "popup")]
+        if warn_now && (LEAD_IN.as_secs() + 1..=BREAK_WARNING).contains(&seconds_until_break) {
+            warned = true;
+            toast::show(
+                "Break soon!",
+                &format!("The next break starts in {seconds_until_break} seconds."),
+                Phase::Warn,
+            );
+        }
+        #[cfg(feature = "popup")]
+        let seconds_until_wake_up = if warn_now && seconds_until_break > BREAK_WARNING {
+            seconds_until_break - BREAK_WARNING
+        } else {
+            seconds_until_break
+        };
+        #[cfg(not(feature = "popup"))]
+        let seconds_until_wake_up = seconds_until_break;
+
+        socket.set_read_timeout(Some(timers::cap(Duration::from_secs(
+            seconds_until_wake_up.min(CLOCK_CHECK_INTERVAL),
+        ))))?;
 
-        let mut buffer = [0; 300];
-        let result = socket.recv_from(&mut buffer);
+        let result = batch::receive(socket, "work");
+        let interrupted = matches!(&result, Err(err) if err.kind() == ErrorKind::Interrupted);
         match result {
-            Ok((bytes_read, return_address)) => {
-                assert!(bytes_read > 0);
-                // not every command needs a response, however it simplifies things if
-                // unbound sockets are not accepted
-                let path = return_address
-                    .as_pathname()
-                    .expect("Unable to respond, because the message came from an unbound socket!");
-                // trimming the last byte, because it's one of the zeros written by us
-                let string_read = str::from_utf8(&buffer[..bytes_read])?;
-                match string_read {
+            Ok(None) => continue,
+            Ok(Some(request)) => {
+                let (path, command) = (request.path.as_path(), &*request.command);
+                if let Some(reason) = commands::refusal(state, command) {
+                    commands::refuse(socket, &request, state, reason);
+                    continue;
+                }
+                match command {
+                    command if lead_in.is_some() && commands::moves_break(command) => {
+                        println!("[lead-in]: Postponed the announced break for '{command}'.");
+                        decisions::record(
+                            Decision::Cancelled,
+                            format!("'{command}' during the lead-in"),
+                        );
+                        batch::requeue(request);
+                        breaktime = true;
+                        work_end = WorkEnd::Postponed;
+                    }
+                    // only reached during the lead-in, otherwise there is no break to skip
+                    "skip" | skip::NOTIFICATION_COMMAND => {
+                        let source = skip::source(command, path).unwrap_or(SkipSource::Cli);
+                        match lead_in.and_then(|skip| skip.refusal(source)) {
+                            None => {
+                                batch::acknowledge(socket, &request, "ok");
+                                println!(
+                                    "The announced break was skipped through the {}!",
+                                    source.name()
+                                );
+                                decisions::record(
+                                    Decision::Cancelled,
+                                    format!(
+                                        "skipped through the {} during the lead-in",
+                                        source.name()
+                                    ),
+                                );
+                                breaktime = true;
+                                work_end = WorkEnd::BreakSkipped;
+                            }
+                            Some(reason) => {
+                                batch::acknowledge(socket, &request, "refused");
+                                println!("[lead-in]: Refused to skip the break: {reason}");
+                                notify::show(
+                                    "This break can't be skipped!",
+                                    &reason,
+                                    Phase::Break,
+                                )?;
+                            }
+                        }
+                    }
                     "break" => {
+                        batch::acknowledge(socket, &request, "ok");
                         println!("Skipped to break!");
                         breaktime = true;
-                        skipped = true;
+                        work_end = WorkEnd::Skipped {
+                            break_duration: None,
+                        };
                     }
-                    "set" => {
-                        socket.set_read_timeout(Some(Duration::from_secs(NORMAL_READ_TIMEOUT)))?;
-                        buffer = [0; 300];
-                        let result = socket.recv_from(&mut buffer);
-                        match result {
-                            Ok((bytes_read, _)) => {
-                                let string_read = str::from_utf8(&buffer[..bytes_read])?;
-                                let minutes = string_read.parse::<u64>().unwrap();
-                                work_duration_seconds = minutes * 60;
-                                now = Instant::now();
+                    command if command.starts_with("break ") => {
+                        // an ad-hoc break with its own duration, in minutes unless it has a unit
+                        match protocol::parse_duration(&command["break ".len()..]) {
+                            Some(seconds) => {
+                                batch::acknowledge(socket, &request, "ok");
+                                println!("Skipped to a break of {} seconds!", seconds.as_secs());
+                                breaktime = true;
+                                work_end = WorkEnd::Skipped {
+                                    break_duration: Some(seconds),
+                                };
+                            }
+                            None => {
+                                batch::acknowledge(socket, &request, "invalid duration");
+                                println!("[work]: Invalid break duration in '{command}'")
+                            }
+                        }
+                    }
+                    command if command.starts_with("focus ") => {
+                        match protocol::parse_duration(&command["focus ".len()..]) {
+                            Some(seconds) => {
+                                batch::acknowledge(socket, &request, "ok");
+                                work_duration = Seconds::from(worked(now, paused_since)) + seconds;
+                                focus_session = true;
                                 println!(
-                                    "Set timer, next break in {work_duration_seconds} seconds!"
+                                    "Started a focus session, next break in {} seconds!",
+                                    seconds.as_secs()
+                                );
+                                decisions::record(
+                                    Decision::Scheduled,
+                                    format!("a focus session of {} seconds", seconds.as_secs()),
                                 );
                             }
-                            Err(err) if err.kind() == ErrorKind::WouldBlock => println!(
-                                "While trying to read the second argument (minutes), a timeout happened and no time could be set! Probably the helper crashed."
-                            ),
-                            Err(err) => {
-                                let kind = err.kind();
-                                panic!(
-                                    "[work]: Unexpected error '{err}' with ErrorKind {kind} while trying to read second argument (minutes)!"
+                            None => {
+                                batch::acknowledge(socket, &request, "invalid duration");
+                                println!("[work]: Invalid focus duration in '{command}'")
+                            }
+                        }
+                    }
+                    command if command.starts_with("timer ") => {
+                        start_timer(socket, &request, &command["timer ".len()..], "work");
+                    }
+                    // always answered, like the timers
+                    command if command.starts_with("toggle ") => {
+                        let state = toggles::command(&command["toggle ".len()..]);
+                        batch::respond(socket, &request, &state);
+                    }
+                    command if command.starts_with("set ") => {
+                        let (persist, argument) = protocol::split_persist(&command["set ".len()..]);
+                        let result = match protocol::parse_duration(argument) {
+                            Some(_) if focus_session => {
+                                println!(
+                                    "[work]: Ignored setting the timer during a focus session."
                                 );
+                                "ignored".to_string()
                             }
+                            Some(seconds) => {
+                                work_duration = config::work_interval_floor(seconds);
+                                now = Instant::now();
+                                #[cfg(feature = "popup")]
+                                {
+                                    warned = false;
+                                }
+                                println!(
+                                    "Set timer, next break in {} seconds!",
+                                    work_duration.as_secs()
+                                );
+                                decisions::record(
+                                    Decision::Scheduled,
+                                    format!("set to {} seconds", work_duration.as_secs()),
+                                );
+                                if persist {
+                                    persist_work_interval(work_duration)
+                                } else {
+                                    "ok".to_string()
+                                }
+                            }
+                            None => {
+                                println!("[work]: Invalid duration in '{command}'");
+                                "invalid duration".to_string()
+                            }
+                        };
+                        // a persisted interval is always answered, so the helper can report
+                        // whether it was written
+                        if persist {
+                            batch::respond(socket, &request, &result);
+                        } else {
+                            batch::acknowledge(socket, &request, &result);
                         }
                     }
+                    "reset" if focus_session => {
+                        let remainder = work_duration
+                            .saturating_sub(worked(now, paused_since).into())
+                            .as_secs();
+                        batch::respond(socket, &request, &remainder.to_string());
+                        println!("[work]: Ignored resetting the timer during a focus session.");
+                    }
                     "reset" => {
-                        work_duration_seconds = break_interval;
+                        work_duration = break_interval;
                         now = Instant::now();
-                        socket.send_to(work_duration_seconds.to_string().as_bytes(), path)?;
-                        println!("Reset timer, next break in {work_duration_seconds} seconds!");
+                        #[cfg(feature = "popup")]
+                        {
+                            warned = false;
+                        }
+                        let seconds = work_duration.as_secs();
+                        batch::respond(socket, &request, &seconds.to_string());
+                        println!("Reset timer, next break in {seconds} seconds!");
+                        decisions::record(
+                            Decision::Reset,
+                            format!("reset, the next break is in {seconds} seconds"),
+                        );
                     }
                     "get" => {
-                        let remainder = work_duration_seconds
-                            .checked_sub(now.elapsed().as_secs())
-                            .unwrap_or(0);
-
-                        socket.send_to(remainder.to_string().as_bytes(), path)?;
-                        // TODO implement some way (here and in wayland.rs) for the helper to know
-                        // when it's break time and when it's work time, e.g. not just sending the
-                        // seconds but also a 0/1 signal
+                        let remainder = work_duration
+                            .saturating_sub(worked(now, paused_since).into())
+                            .as_secs();
+                        // followed by when the break starts, for widgets showing the time of day, and
+                        // what the seconds count down
+                        let at = clock::timestamp_in(Duration::from_secs(remainder));
+                        let phase = if focus_session { "focus" } else { "work" };
+                        batch::respond(socket, &request, &format!("{remainder} {at} {phase}"));
+                    }
+                    "status" => {
+                        let remainder = work_duration
+                            .saturating_sub(worked(now, paused_since).into())
+                            .as_secs();
+                        let active = capabilities::get().active();
+                        let at = clock::timestamp_in(Duration::from_secs(remainder));
+                        let toggled = toggles::status();
+                        let owed = debt::owed().as_secs();
+                        // work time is followed by the offset the jitter moved the break by, every
+                        // phase by when it's over -- while paused, as if work resumed now -- the
+                        // toggles and the break debt
+                        let status = if focus_session {
+                            format!("focus {remainder} {active} {at} {toggled} {owed}")
+                        } else if paused_since.is_some() {
+                            format!("paused {remainder} {active} {at} {toggled} {owed}")
+                        } else {
+                            format!(
+                                "work {remainder} {active} {:+} {at} {toggled} {owed}",
+                                jitter::current()
+                            )
+                        };
+                        batch::respond(socket, &request, &status);
+                    }
+                    "waybar" => {
+                        let remainder = work_duration
+                            .saturating_sub(worked(now, paused_since).into())
+                            .as_secs();
+                        // focus sessions and pauses don't lead up to a break
+                        let status = if focus_session {
+                            palette::waybar(Phase::Work, "focus", remainder)
+                        } else if paused_since.is_some() {
+                            palette::waybar(Phase::Work, "paused", remainder)
+                        } else {
+                            palette::waybar(Phase::before_break(remainder), "work", remainder)
+                        };
+                        batch::respond(socket, &request, &status);
+                    }
+                    "hello" => {
+                        batch::respond(socket, &request, "hello");
+                    }
+                    // the idle state and the sessions are checked on every wake-up anyway
+                    command if wake_up(command) => {}
+                    "diag" => {
+                        batch::respond(socket, &request, &diagnostics());
+                    }
+                    "debug decisions" => {
+                        batch::respond(socket, &request, &decisions::report());
+                    }
+                    "debug dump" => {
+                        let remainder = work_duration
+                            .saturating_sub(worked(now, paused_since).into())
+                            .as_secs();
+                        let name = if focus_session {
+                            "focus"
+                        } else if paused_since.is_some() {
+                            "paused"
+                        } else {
+                            "work"
+                        };
+                        let phase = serde_json::json!({
+                            "name": name,
+                            "remaining": remainder,
+                            "ends_at": clock::timestamp_in(Duration::from_secs(remainder)),
+                            "work_duration": work_duration.as_secs(),
+                            "worked": worked(now, paused_since).as_secs(),
+                            "paused_for": paused_since.map(|since| since.elapsed().as_secs()),
+                        });
+                        batch::respond(socket, &request, &debug::dump(phase));
+                    }
+                    "stats" => {
+                        batch::respond(socket, &request, &stats::report());
+                    }
+                    "bindinfo" => {
+                        batch::respond(socket, &request, &bind_info());
+                    }
+                    "quit" | "restart" if !shutdown::authorized(request.uid) => {
+                        println!("[work]: Refused '{command}' from another user at {path:?}.");
+                        batch::respond(socket, &request, "denied");
+                    }
+                    "quit" | "restart" => {
+                        batch::respond(socket, &request, "ok");
+                        let shutdown = Shutdown::from_command(command).unwrap();
+                        let remaining =
+                            work_duration.saturating_sub(worked(now, paused_since).into());
+                        return Ok(WorkEnd::Shutdown {
+                            shutdown,
+                            remaining,
+                        });
+                    }
+                    _ => {
+                        batch::acknowledge(socket, &request, "unknown command");
+                        println!("[work]: Received unknown command '{command}'");
                     }
-                    &_ => panic!("found match, but non-optional capture group is missing!"),
                 }
             }
             Err(err) if err.kind() == ErrorKind::WouldBlock => {} // do nothing on timeout
-            Err(err) if err.kind() == ErrorKind::Interrupted => {
-                // interrupt happens when system wakes up from suspension -> treat like reset
-                work_duration_seconds = break_interval;
+            // interrupt happens when system wakes up from suspension, handled below
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => {
+                let kind = err.kind();
+                panic!("[work]: Unexpected error '{err}' with ErrorKind {kind} reading!");
+            }
+        }
+
+        // the clocks tell how long the system slept, the interrupted read only that it did
+        let slept = suspend_clock.check();
+        if interrupted || slept.is_some() {
+            let slept_for = slept.map_or_else(String::new, |slept| {
+                format!(" of {:.1} seconds", slept.as_secs_f64())
+            });
+            let reset = match config.on_resume {
+                _ if focus_session => {
+                    println!(
+                        "System suspension{slept_for} was detected, continuing the focus session."
+                    );
+                    false
+                }
+                OnResume::Reset => true,
+                OnResume::Continue | OnResume::Prompt => {
+                    if config.on_resume == OnResume::Prompt {
+                        println!(
+                            "System suspension{slept_for} was detected, asking whether to reset."
+                        );
+                        if let Err(err) = prompt_for_reset(socket, "The system was suspended.") {
+                            println!("Unable to show the resume prompt! The error: {err}");
+                        }
+                    }
+                    match (config.suspend_counts_as, slept) {
+                        (SuspendCountsAs::Break, Some(slept))
+                            if slept >= config.break_duration.as_duration() =>
+                        {
+                            true
+                        }
+                        // while paused because there was no input, the suspension is no work either
+                        (SuspendCountsAs::Nothing, Some(slept)) if paused_since.is_none() => {
+                            now = now.checked_sub(slept).unwrap_or(now);
+                            println!(
+                                "System suspension{slept_for} was detected, it counts as work time."
+                            );
+                            decisions::record(
+                                Decision::Moved,
+                                format!("the suspension{slept_for} counts as work time"),
+                            );
+                            false
+                        }
+                        _ => {
+                            println!(
+                                "System suspension{slept_for} was detected, continuing the work timer."
+                            );
+                            false
+                        }
+                    }
+                }
+            };
+            if reset {
+                work_duration = break_interval;
                 now = Instant::now();
+                #[cfg(feature = "popup")]
+                {
+                    warned = false;
+                }
                 println!(
-                    "Reset timer because system suspension was detected. Next break is in {work_duration_seconds} seconds!"
+                    "Reset timer because system suspension{slept_for} was detected. Next break is in {} seconds!",
+                    work_duration.as_secs()
                 );
+                decisions::record(
+                    Decision::Reset,
+                    format!("the system was suspended{slept_for}"),
+                );
+            }
+        }
+
+        if Seconds::from(worked(now, paused_since)) >= work_duration {
+            if focus_session {
+                println!("Focus session is over!");
+                work_end = WorkEnd::FocusEnded;
+            } else {
+                println!("Work time is over!");
+            }
+            breaktime = true;
+        } else if !focus_session && let Some(jump) = wall_clock.check() {
+            let remaining = work_duration.saturating_sub(worked(now, paused_since).into());
+            println!("The clock jumped by {jump:.0} seconds, re-evaluating the schedule.");
+            decisions::record(
+                Decision::Moved,
+                format!("the wall clock jumped by {jump:.0} seconds"),
+            );
+            return Ok(WorkEnd::ClockJumped { remaining });
+        }
+    }
+
+    Ok(work_end)
+}
+
+/*
+ * true if the overlay's toasts announce the break a minute ahead
+ */
+fn warn_with_toast(config: &Config) -> bool {
+    config.show_popup && config.show_notification && capabilities::get().wayland
+}
+
+/*
+ * what is shown during a break, e.g. the overlay
+ */
+pub(crate) trait BreakView {
+    /*
+     * shows the remaining time, returns true if the break should be skipped, e.g. because the
+     * overlay was tapped
+     */
+    fn update(&mut self, remaining: Seconds) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /*
+     * returns true as soon as the socket is readable, false if the view has something to update
+     * (e.g. a frame callback or input arrived) or the timeout elapsed
+     */
+    fn wait(
+        &mut self,
+        socket: &UnixDatagram,
+        timeout: Duration,
+    ) -> Result<bool, Box<dyn std::error::Error>>;
+}
+
+/*
+ * returns true if the break was skipped
+ * the skip policy decides whether a skip is allowed, depending on where it came from
+ * the view is updated whenever it woke up the break, but at least once per second
+ */
+fn wait_until_work(
+    socket: &mut UnixDatagram,
+    break_duration: Seconds,
+    extend_on_activity: bool,
+    skip: &skip::Policy,
+    mut view: Option<&mut dyn BreakView>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // waiting until the break is over
+    println!("Break time!");
+    let mut breaktime = true;
+    let mut skipped = false;
+    let now = Instant::now();
+    // the last second the tick was played for
+    #[cfg(feature = "audio")]
+    let mut ticked = break_duration;
+    // setting read timeout every time, because outside of every break it's set to a different value
+    socket.set_read_timeout(Some(timers::cap(break_duration.as_duration())))?;
+
+    while breaktime {
+        watchdog::beat();
+        timers::fire_due();
+
+        // with a view, the socket is only read once it's readable, so the view is never blocked
+        if let Some(view) = view.as_mut() {
+            let elapsed = now.elapsed();
+            let remaining = break_remaining(now, break_duration, extend_on_activity);
+            if remaining.is_zero() {
+                println!("Break is over!");
+                break;
+            }
+            #[cfg(feature = "audio")]
+            if remaining != ticked {
+                ticked = remaining;
+                audio::tick(remaining.as_secs());
+            }
+            if view.update(remaining)? {
+                println!("Break was skipped from the overlay!");
+                skipped = true;
+                break;
+            }
+
+            // the countdown changes with every full second
+            let until_next_second = Duration::from_secs(1)
+                .saturating_sub(Duration::from_nanos(elapsed.subsec_nanos().into()));
+            if !batch::pending() && !view.wait(socket, until_next_second)? {
+                continue;
+            }
+        }
+
+        let result = batch::receive(socket, "break");
+        match result {
+            Ok(None) => continue,
+            Ok(Some(request)) => {
+                let (path, command) = (request.path.as_path(), &*request.command);
+
+                let remainder = break_remaining(now, break_duration, extend_on_activity);
+                let at = clock::timestamp_in(remainder.as_duration());
+                let remainder = remainder.as_secs();
+
+                let skip_source = skip::source(command, path);
+                if let Some(source) = skip_source
+                    && skip.allows(source)
+                {
+                    batch::acknowledge(socket, &request, "ok");
+                    println!("Break was skipped through the {}!", source.name());
+                    breaktime = false;
+                    skipped = true;
+                } else {
+                    if command == "get" {
+                        batch::respond(socket, &request, &format!("{remainder} {at} break"));
+                    } else if command == "status" {
+                        let active = capabilities::get().active();
+                        let reason = stats::current_reason().name();
+                        batch::respond(
+                            socket,
+                            &request,
+                            &format!(
+                                "break {remainder} {active} {reason} {at} {} {}",
+                                toggles::status(),
+                                debt::owed().as_secs()
+                            ),
+                        );
+                    } else if command == "waybar" {
+                        let status = palette::waybar(Phase::Break, "break", remainder);
+                        batch::respond(socket, &request, &status);
+                    } else if command == "hello" {
+                        batch::respond(socket, &request, "hello");
+                    } else if command == "diag" {
+                        batch::respond(socket, &request, &diagnostics());
+                    } else if command == "debug dump" {
+                        let phase = serde_json::json!({
+                            "name": "break",
+                            "remaining": remainder,
+                            "ends_at": at,
+                            "break_duration": break_duration.as_secs(),
+                            "reason": stats::current_reason().name(),
+                            "overlay": view.is_some(),
+                        });
+                        batch::respond(socket, &request, &debug::dump(phase));
+                    } else if command == "debug decisions" {
+                        batch::respond(socket, &request, &decisions::report());
+                    } else if command == "stats" {
+                        batch::respond(socket, &request, &stats::report());
+                    } else if command == "bindinfo" {
+                        batch::respond(socket, &request, &bind_info());
+                    } else if let Some(shutdown) = Shutdown::from_command(command) {
+                        if shutdown::authorized(request.uid) {
+                            // the break is ended, so it's cleaned up before the daemon stops
+                            batch::respond(socket, &request, "ok");
+                            shutdown::request(shutdown);
+                            breaktime = false;
+                            continue;
+                        }
+                        println!("[break]: Refused '{command}' from another user at {path:?}.");
+                        batch::respond(socket, &request, "denied");
+                    } else if wake_up(command) {
+                        // only the work timer is paused, a session that ended stops the schedule
+                        // once the break is over
+                    } else if let Some(argument) = command.strip_prefix("timer ") {
+                        start_timer(socket, &request, argument, "break");
+                    } else if let Some(argument) = command.strip_prefix("toggle ") {
+                        batch::respond(socket, &request, &toggles::command(argument));
+                    } else if let Some(reason) = commands::refusal(State::Break, command) {
+                        commands::refuse(socket, &request, State::Break, reason);
+                    } else if let Some(source) = skip_source {
+                        let reason = skip.refusal(source).unwrap_or_default();
+                        batch::acknowledge(socket, &request, "refused");
+                        println!("[break]: Refused to skip the break: {reason}");
+                        notify::show("This break can't be skipped!", &reason, Phase::Break)?;
+                    } else {
+                        batch::acknowledge(socket, &request, "unknown command");
+                        println!("[break]: Received unknown command '{command}'");
+                    }
+
+                    if remainder > 0 {
+                        socket
+                            .set_read_timeout(Some(timers::cap(Duration::from_secs(remainder))))?;
+                        breaktime = true;
+                    } else {
+                        println!("Break is over!");
+                        breaktime = false;
+                    }
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                let remainder = break_remaining(now, break_duration, extend_on_activity);
+                if !remainder.is_zero() {
+                    // the read times out for every timer that is due, when input extended the break
+                    // and to feed the watchdog
+                    if !timers::fire_due() && !extend_on_activity && watchdog::interval().is_none()
+                    {
+                        println!(
+                            "[break]: Read was interrupted after {} seconds.",
+                            now.elapsed().as_secs()
+                        );
+                    }
+                    socket.set_read_timeout(Some(timers::cap(remainder.as_duration())))?;
+                } else {
+                    println!("Break is over!");
+                    breaktime = false;
+                }
             }
             Err(err) => {
                 let kind = err.kind();
-                panic!("[work]: Unexpected error '{err}' with ErrorKind {kind} reading!");
+                panic!("[break]: Unexpected error '{err}' with ErrorKind {kind} reading!");
             }
         }
+    }
 
-        if now.elapsed().as_secs() >= work_duration_seconds {
-            println!("Work time is over!");
-            breaktime = true;
+    let extended = Seconds::from(now.elapsed()).saturating_sub(break_duration);
+    if !skipped && !extended.is_zero() {
+        println!(
+            "The break was extended by {} seconds of input.",
+            extended.as_secs()
+        );
+    }
+    Ok(skipped)
+}
+
+/*
+ * the commands the daemon's own watchers wake it up with, they are not answered
+ */
+fn wake_up(command: &str) -> bool {
+    #[cfg(feature = "dbus")]
+    if command == logind::SESSION_COMMAND {
+        return true;
+    }
+    command == idle::IDLE_COMMAND || command == idle::ACTIVE_COMMAND
+}
+
+/*
+ * waits until a graphical session began again, every command is answered meanwhile -- the daemon
+ * is restarted in the new session, unless it's asked to quit or restart before
+ */
+#[cfg(feature = "dbus")]
+fn wait_for_session(socket: &UnixDatagram) -> Result<Shutdown, Box<dyn std::error::Error>> {
+    // woken up only to keep the watchdog fed
+    socket.set_read_timeout(watchdog::interval())?;
+    loop {
+        watchdog::beat();
+        let request = match batch::receive(socket, "no session") {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(err) if matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) => {
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let command = &*request.command;
+        if command == logind::SESSION_COMMAND && !logind::ended() {
+            println!("A graphical session began, restarting the schedule in it.");
+            return Ok(Shutdown::Restart);
+        }
+        if wake_up(command) {
+            continue;
+        }
+        match Shutdown::from_command(command) {
+            Some(shutdown) if shutdown::authorized(request.uid) => {
+                batch::respond(socket, &request, "ok");
+                return Ok(shutdown);
+            }
+            _ => batch::respond(socket, &request, "no graphical session"),
         }
     }
+}
 
-    Ok(skipped)
+/*
+ * the seconds until the break that started at start is over -- a break that's extended on
+ * activity only counts the time without input, at most until it took MAX_EXTENDED_BREAK times as
+ * long
+ */
+fn break_remaining(start: Instant, break_duration: Seconds, extend_on_activity: bool) -> Seconds {
+    let elapsed = start.elapsed().into();
+    if !extend_on_activity || !idle::input_watched() {
+        return break_duration.saturating_sub(elapsed);
+    }
+    let rested =
+        idle::resting_since().map_or(Seconds::ZERO, |since| since.max(start).elapsed().into());
+    break_duration.saturating_sub(rested).min(
+        break_duration
+            .saturating_mul(MAX_EXTENDED_BREAK)
+            .saturating_sub(elapsed),
+    )
 }
 
-fn play_sound(
-    stream_handle: &OutputStreamHandle,
-    sound_data: &Arc<[u8]>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // https://stackoverflow.com/questions/78742705/how-to-play-sound-from-memory-using-rodio
-    let source = Decoder::new(Cursor::new(Arc::clone(&sound_data))).unwrap();
+/*
+ * writes the interval of `set --persist` into the config file, returns the helper's answer
+ */
+fn persist_work_interval(seconds: Seconds) -> String {
+    let value = seconds.suffixed();
+    match config::persist("work_interval", &value) {
+        Ok(path) => {
+            println!("Wrote work_interval={value} to '{path}'.");
+            "ok".to_string()
+        }
+        Err(err) => {
+            println!("The work interval could not be written to the config file! The error: {err}");
+            format!("not written to the config file: {err}")
+        }
+    }
+}
 
-    // Play the sound directly on the device
-    stream_handle.play_raw(source.convert_samples())?;
-    Ok(())
+/*
+ * starts a timer from "<duration> [message]", e.g. "20m Tea is ready" -- unlike the other
+ * commands that change the schedule, it's always answered, so the helper can report typos
+ */
+fn start_timer(socket: &UnixDatagram, request: &batch::Request, argument: &str, phase: &str) {
+    let (duration, message) = argument.split_once(' ').unwrap_or((argument, ""));
+    match cli::parse_duration(duration) {
+        Ok(seconds) => {
+            batch::respond(socket, request, "ok");
+            timers::add(seconds, message.trim());
+        }
+        Err(err) => {
+            batch::respond(socket, request, "invalid duration");
+            println!("[{phase}]: Invalid timer in 'timer {argument}': {err}");
+        }
+    }
+}
+
+/*
+ * the daemon's health as "name=value" lines, requested by `wlbreaktime-helper doctor`
+ */
+fn diagnostics() -> String {
+    let capabilities = capabilities::get();
+    let yes_no = |available: bool| if available { "yes" } else { "no" };
+    // the files are read again, so changes since the start are checked as well -- the problems
+    // are counted like `wlbreaktime-helper check-config` lists them
+    let config = match config::count_problems() {
+        Ok(0) => "ok".to_string(),
+        Ok(problems) => format!("{problems} problem(s), see `wlbreaktime-helper check-config`"),
+        Err(err) => err.to_string(),
+    };
+    let grab_input = config::load_silently().is_ok_and(|config| config.grab_input);
+    // only of interest if it's configured
+    let input_grab = if grab_input {
+        yes_no(capabilities.input_grab)
+    } else {
+        "off"
+    };
+
+    format!(
+        "wayland={}\nx11={}\naudio={}\nnotifications={}\njournald={}\ninput_grab={input_grab}\ncache={}\nconfig={config}",
+        yes_no(capabilities.wayland),
+        yes_no(capabilities.x11),
+        yes_no(capabilities.audio),
+        yes_no(capabilities.notifications),
+        yes_no(capabilities.journald),
+        resources::report(),
+    )
+}
+
+/*
+ * the recommended keybindings, they run the helper next to the daemon
+ */
+fn bind_info() -> String {
+    let helper_path = env::current_exe()
+        .ok()
+        .map(|daemon_path| daemon_path.with_file_name("wlbreaktime-helper"))
+        .filter(|helper_path| helper_path.exists());
+    match helper_path
+        .as_ref()
+        .and_then(|helper_path| helper_path.to_str())
+    {
+        Some(helper_path) => keybinds::list(helper_path),
+        // the binds still work if it's on the PATH
+        None => keybinds::list("wlbreaktime-helper"),
+    }
+}
+
+/*
+ * phase changes go to the local subscribers and, as the schedule master, to the remote peers
+ */
+fn publish(
+    event_stream: &mut Option<events::EventStream>,
+    master: &mut Option<remote::Master>,
+    event: &str,
+) {
+    // the peers of a schedule master read the same numbers as the subscribers
+    let event = events::stamp(event);
+    if let Some(event_stream) = event_stream.as_mut() {
+        event_stream.publish(&event);
+    }
+    if let Some(master) = master.as_mut() {
+        master.publish(&event);
+    }
+}
+
+/*
+ * every consecutively skipped break shortens the work interval, but never below
+ * MINIMUM_ESCALATED_INTERVAL
+ */
+fn escalated_interval(config: &Config, consecutive_skips: u64) -> Seconds {
+    if !config.escalation {
+        return config.break_interval;
+    }
+
+    config
+        .break_interval
+        .saturating_sub(config.escalation_step.saturating_mul(consecutive_skips))
+        .max(MINIMUM_ESCALATED_INTERVAL.min(config.break_interval))
+}
+
+/*
+ * the interval until an extra break with debt=extra_break, half of the usual one
+ */
+fn extra_break_interval(config: &Config, consecutive_skips: u64) -> Seconds {
+    let interval = escalated_interval(config, consecutive_skips);
+    Seconds::new(interval.as_secs() / 2).max(MINIMUM_ESCALATED_INTERVAL.min(interval))
+}
+
+/*
+ * whether there is a compositor to connect to, the same variables wayland-client reads
+ */
+#[cfg(feature = "popup")]
+fn wayland_session() -> bool {
+    env::var_os("WAYLAND_SOCKET").is_some() || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/*
+ * whether the overlay can be put above fullscreen windows, without Wayland there is no overlay
+ * to cover
+ */
+#[cfg(feature = "popup")]
+fn layer_shell(wayland: &Option<supervisor::Wayland>) -> bool {
+    wayland
+        .as_ref()
+        .is_none_or(|(_, data)| data.layer_shell.is_some())
+}
+
+/*
+ * plays the cue, if it can't be played (no audio device, or it failed) the screen flashes instead
+ * -- a visual bell, so the cue isn't lost
+ */
+#[cfg(feature = "audio")]
+fn play_cue(cue: audio::Cue, #[cfg(feature = "popup")] wayland: &mut Option<supervisor::Wayland>) {
+    let played = if capabilities::get().audio {
+        audio::play(cue)
+    } else {
+        Err("no audio device is available".into())
+    };
+    let Err(err) = played else {
+        return;
+    };
+    println!(
+        "The {cue:?} sound could not be played, flashing the screen instead! The error: {err}"
+    );
+    #[cfg(feature = "popup")]
+    match supervisor::run(wayland, "visual bell", |event_queue, data| {
+        let qh = event_queue.handle();
+        gamma::flash(event_queue, data, &qh)
+    }) {
+        Some(Ok(())) => {}
+        Some(Err(err)) => println!("The screen could not be flashed! The error: {err}"),
+        None => println!("There is no Wayland connection, the screen can't be flashed."),
+    }
+}
+
+/*
+ * how the work interval came about, for the decision log
+ */
+fn scheduled_reason(
+    config: &Config,
+    consecutive_skips: u64,
+    remaining: Seconds,
+    restored: bool,
+    extra_break: bool,
+) -> String {
+    let remaining = remaining.as_secs();
+    if restored {
+        return format!("continuing the saved schedule, {remaining} seconds are left");
+    }
+    let mut reason = format!("a work interval of {remaining} seconds");
+    if escalated_interval(config, consecutive_skips) < config.break_interval {
+        reason.push_str(&format!(
+            ", shortened after {consecutive_skips} skipped breaks"
+        ));
+    }
+    if extra_break {
+        reason.push_str(&format!(
+            ", halved for an extra break, {} seconds of breaks were skipped today",
+            debt::owed().as_secs()
+        ));
+    }
+    let jitter = jitter::current();
+    if jitter != 0 {
+        reason.push_str(&format!(", moved by {jitter:+} seconds of jitter"));
+    }
+    reason
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if !daemon::booted() {
-        println!("Not running systemd, early exit.");
+    // before anything else, so --help doesn't bind the sockets
+    let command_line = cli::parse();
+    // the user's environment and privileges before anything else, nothing may run as root
+    if command_line.system_session && !session::attach()? {
         return Ok(());
-    };
+    }
+    let config_file_given = command_line.config_file.is_some();
+    config::set_command_line(command_line);
 
     // systemd setup -- receive file descriptor (socket handle)
-    let mut descriptors = activation::receive_descriptors(true)?;
-    assert!(
-        descriptors.len() == 1,
-        "Systemd passed more than one file descriptor (socket). Configuration must be wrong!"
-    );
+    // without socket activation (e.g. when autostarted through the Background portal) the socket
+    // is bound by the daemon itself
+    let activated = daemon::booted() && env::var("LISTEN_FDS").is_ok();
+    let sockets = if activated {
+        sockets::from_systemd()?
+    } else {
+        println!("Not started through the systemd socket, binding the sockets directly.");
+        sockets::bind()?
+    };
+    let mut socket = sockets.control;
+    // the credentials tell whether `quit` and `restart` come from the daemon's own user
+    wlbreaktime::datagram::pass_credentials(&socket)?;
+    // a long response is sent in chunks, a helper that stops reading them must not stall the daemon
+    socket.set_write_timeout(Some(Duration::from_secs(RESPONSE_WRITE_TIMEOUT)))?;
+    let mut event_stream = match sockets.events {
+        Some(events) => Some(events::EventStream::new(events)?),
+        None => None,
+    };
+    let _metrics = sockets.metrics;
 
-    let fd = descriptors.pop().unwrap();
-    assert!(
-        fd.is_unix(),
-        "The systemd socket was configured incorrectly!"
-    );
+    let base_config = config::load_configuration()?;
+    if !config_file_given
+        && !paths::config_paths()?
+            .iter()
+            .any(|path| Path::new(path).exists())
+    {
+        println!(
+            "No config file found, the defaults are used. Run `wlbreaktime-helper init-config` to write one with every option explained."
+        );
+    }
+
+    // remote peers are only accepted with a token
+    let mut master = match (&base_config.remote_token, &base_config.remote_listen) {
+        (Some(token), Some(address)) => match remote::Master::listen(address, token) {
+            Ok(master) => Some(master),
+            Err(err) => {
+                println!("The schedule could not be published! The error: {err}");
+                None
+            }
+        },
+        (None, Some(_)) => {
+            println!("No remote_token is configured, the schedule is not published.");
+            None
+        }
+        _ => None,
+    };
+    match (&base_config.remote_token, &base_config.remote_master) {
+        (Some(token), Some(address)) => match socket.local_addr()?.as_pathname() {
+            Some(daemon_path) => {
+                remote::follow(address.clone(), token.clone(), daemon_path.to_path_buf())
+            }
+            None => {
+                println!("The control socket is not bound to a path, the master can't be followed.")
+            }
+        },
+        (None, Some(_)) => println!("No remote_token is configured, the master is not followed."),
+        _ => {}
+    }
+
+    #[cfg(feature = "popup")]
+    if !base_config.auto_pause_after.is_zero() || base_config.extend_break_on_activity {
+        match socket.local_addr()?.as_pathname() {
+            Some(daemon_path) => {
+                let timeout = Some(base_config.auto_pause_after)
+                    .filter(|seconds| !seconds.is_zero())
+                    .map(Seconds::as_duration);
+                if let Err(err) = idle::watch(timeout, daemon_path) {
+                    println!(
+                        "Idleness is not watched, the work timer is not paused and breaks are not extended! The error: {err}"
+                    );
+                }
+            }
+            None => println!("The control socket is not bound to a path, idleness is not watched."),
+        }
+    }
 
-    let mut socket = unsafe { UnixDatagram::from_raw_fd(FileDescriptor::into_raw_fd(fd)) };
+    #[cfg(feature = "dbus")]
+    match socket.local_addr()?.as_pathname() {
+        Some(daemon_path) => {
+            if let Err(err) = logind::watch(daemon_path) {
+                println!(
+                    "The sessions are not watched, the schedule goes on after logging out! The error: {err}"
+                );
+            }
+        }
+        None => {
+            println!("The control socket is not bound to a path, the sessions are not watched.")
+        }
+    }
 
-    let config = config::load_configuration()?;
+    #[cfg(feature = "dbus")]
+    if !activated && base_config.request_autostart {
+        portal::request_autostart();
+    }
+
+    // features that were compiled out are reported once, if they are configured
+    if cfg!(not(feature = "dbus")) && !activated && base_config.request_autostart {
+        println!("Built without the dbus feature, autostart can't be requested.");
+    }
+    if cfg!(not(feature = "audio")) && base_config.play_sound {
+        println!("Built without the audio feature, no sounds are played.");
+    }
+    if cfg!(not(feature = "popup")) && base_config.show_popup {
+        println!("Built without the popup feature, breaks are not shown on screen.");
+    }
+    if cfg!(not(feature = "popup")) && base_config.popup == config::PopupMode::Always {
+        return Err("popup=always, however the daemon was built without the popup feature".into());
+    }
+    if cfg!(not(feature = "popup")) && !base_config.auto_pause_after.is_zero() {
+        println!("Built without the popup feature, the work timer is not paused without input.");
+    }
+    if cfg!(not(feature = "popup")) && base_config.extend_break_on_activity {
+        println!("Built without the popup feature, breaks are not extended on input.");
+    }
+    if cfg!(not(feature = "notifications")) && base_config.show_notification {
+        println!("Built without the notifications feature, notifications are only logged.");
+    }
+
+    // every component that is not available gets disabled with a single log line
+    let mut capabilities = capabilities::Capabilities {
+        journald: libsystemd::logging::connected_to_journal(),
+        ..Default::default()
+    };
 
     // audio setup
-    // get output stream handle to default physical sound device
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    // load sound into memory and create a pointer to it
-    let bytes = include_bytes!("../resources/rebana_l_gong.wav");
-    let sound_data: Arc<[u8]> = Arc::from(bytes.clone());
-
-    // wayland set-up
-    let connection = Connection::connect_to_env().unwrap();
-    let display = connection.display();
-    let mut event_queue: EventQueue<State> = connection.new_event_queue();
-    let qh = event_queue.handle();
-    let _registry = display.get_registry(&qh, ());
-
-    let mut data = State {
-        wl_shm: None,
-        surface_size: None,
-        accepted_formats: Vec::new(),
-        compositor: None,
-        base: None,
+    // get output stream handle to the configured or the default physical sound device
+    #[cfg(feature = "audio")]
+    {
+        audio::set_theme(&base_config.sound_theme);
+        let mut audio_output = audio::AudioOutput::new(base_config.audio_device.clone());
+        capabilities.audio = audio_output.available();
+        if capabilities.audio {
+            audio::set_output(audio_output);
+        } else {
+            println!("No audio device is available, sounds are disabled.");
+        }
+    }
+
+    // wayland set-up, without a session (e.g. on a TTY or under X11) breaks are announced by the
+    // sound and the notifications only
+    #[cfg(feature = "popup")]
+    let mut wayland = match base_config.popup {
+        config::PopupMode::Never => None,
+        config::PopupMode::Auto if !wayland_session() => None,
+        _ => match wayland::connect(base_config.popup_cursor) {
+            Ok(wayland) => {
+                capabilities.wayland = true;
+                Some(wayland)
+            }
+            Err(err) => {
+                println!("Wayland is not available! The error: {err}");
+                None
+            }
+        },
     };
+    // under X11 the overlay is a window of its own, it's only used without Wayland
+    #[cfg(feature = "x11")]
+    let x11 =
+        if wayland.is_none() && base_config.popup != config::PopupMode::Never && x11::session() {
+            match x11::connect() {
+                Ok(x11) => {
+                    println!("The overlay is shown through X11.");
+                    capabilities.x11 = true;
+                    Some(x11)
+                }
+                Err(err) => {
+                    println!("X11 is not available! The error: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+    #[cfg(feature = "popup")]
+    if !capabilities.wayland && !capabilities.x11 {
+        if base_config.popup == config::PopupMode::Always {
+            return Err("popup=always, however the overlay can't be shown".into());
+        }
+        println!(
+            "The overlay is disabled, breaks are announced by the sound and the notifications."
+        );
+    }
+
+    capabilities.input_grab = grab::available();
+    if base_config.grab_input && !capabilities.input_grab {
+        println!(
+            "No input device can be grabbed, strict breaks rely on the compositor. Is the user in the input group?"
+        );
+    }
 
-    // waiting on compositor to advertise globals
-    event_queue.blocking_dispatch(&mut data).unwrap();
+    capabilities.notifications = notify::server_available();
+    if cfg!(feature = "notifications") && !capabilities.notifications {
+        println!("No notification daemon is running, notifications are only logged.");
+    }
+
+    notify::set_style(&base_config);
+    palette::set(&base_config);
+    #[cfg(all(feature = "dbus", feature = "popup"))]
+    if base_config.theme == config::Theme::Auto {
+        portal::watch_color_scheme();
+    }
+    #[cfg(feature = "popup")]
+    fonts::set(&base_config);
+    timers::configure(&base_config);
+    toggles::configure(&base_config);
+    for (key, text) in [
+        ("pre_break_summary", &base_config.pre_break_summary),
+        ("pre_break_body", &base_config.pre_break_body),
+        ("break_end_summary", &base_config.break_end_summary),
+        ("break_end_body", &base_config.break_end_body),
+    ] {
+        template::check(key, text);
+    }
+    jitter::seed();
+    // the sound and the icon are decoded once instead of for every break
+    resources::preload(&base_config);
 
-    // make sure all necessary globals have been bound
-    check_for_globals(&data)?;
+    println!("Active capabilities: {}", capabilities.active());
+    capabilities::set(capabilities);
 
     // breaktime is ready -> notify systemd
-    let sent = daemon::notify(true, &[NotifyState::Ready]).expect("notify failed");
-    assert!(
-        sent,
-        "The systemd service seems to have been configured incorrectly (not Type=notify)!"
-    );
+    // the environment is kept, the watchdog needs NOTIFY_SOCKET for as long as the daemon runs
+    if activated {
+        let sent = daemon::notify(false, &[NotifyState::Ready]).expect("notify failed");
+        assert!(
+            sent,
+            "The systemd service seems to have been configured incorrectly (not Type=notify)!"
+        );
+        watchdog::start();
+    }
+
+    let mut time_tracker = tracking::from_config(&base_config);
+    let mut compositor = compositor::detect();
+    if compositor.is_none() && base_config.turn_off_monitors {
+        println!("The compositor is not supported, the monitors are not turned off during breaks.");
+    }
+    #[cfg(feature = "popup")]
+    if base_config.popup_on_top && !layer_shell(&wayland) {
+        println!(
+            "The compositor has no layer shell, fullscreen windows may cover the overlay. {}",
+            if compositor.is_some() {
+                "The monitors are turned off during breaks instead."
+            } else {
+                "Breaks are announced by a critical notification as well."
+            }
+        );
+    }
+
+    // a daemon that was restarted (or quit) through the helper continues its schedule
+    let restored = shutdown::load_state();
+    if let Some(state) = restored {
+        println!(
+            "Continuing the saved schedule, next break in {} seconds.",
+            state.remaining.as_secs()
+        );
+    }
+    let mut restored_remaining = restored.map(|state| state.remaining);
+
+    // number of breaks that were skipped in a row, used for the strictness escalation
+    let mut consecutive_skips = restored.map_or(0, |state| state.consecutive_skips);
+    // when the last break ended, so the next one keeps some work time in between
+    let mut last_break_end: Option<Instant> = None;
 
     loop {
-        let skipped = wait_until_break(&mut socket, config.break_interval)?;
+        if let Some(time_tracker) = time_tracker.as_mut()
+            && let Err(err) = time_tracker.start_work()
+        {
+            println!("The work interval could not be tracked! The error: {err}");
+        }
+
+        // weekday overrides are consulted at every phase boundary
+        let today = base_config.for_today();
+        // once a whole break is owed, the next one comes after half the interval
+        let extra_break = today.debt == DebtPolicy::ExtraBreak
+            && debt::extra_break_due(today.break_duration, debt::owed());
+        let break_interval = jitter::apply(
+            if extra_break {
+                extra_break_interval(&today, consecutive_skips)
+            } else {
+                escalated_interval(&today, consecutive_skips)
+            },
+            today.break_interval_jitter,
+        );
+        let work_start = Instant::now();
+        let restored = restored_remaining.take();
+        let mut remaining =
+            restored.map_or(break_interval, |remaining| remaining.min(break_interval));
+        decisions::record(
+            Decision::Scheduled,
+            scheduled_reason(
+                &today,
+                consecutive_skips,
+                remaining,
+                restored.is_some(),
+                extra_break,
+            ),
+        );
+        let (mut work_end, fixed_break) = loop {
+            // everything bound to the wall clock is re-evaluated after it jumped
+            let config = toggles::apply(base_config.for_today());
+            let fixed_break = config
+                .seconds_until_fixed_break()
+                .filter(|seconds| *seconds < remaining + FIXED_BREAK_MERGE_WINDOW);
+            if let Some(seconds) = fixed_break {
+                println!(
+                    "The next break is the fixed break in {} seconds.",
+                    seconds.as_secs()
+                );
+                decisions::record(
+                    Decision::Merged,
+                    format!(
+                        "the fixed break in {} seconds is less than {} seconds after the rolling one in {} seconds",
+                        seconds.as_secs(),
+                        FIXED_BREAK_MERGE_WINDOW.as_secs(),
+                        remaining.as_secs()
+                    ),
+                );
+            }
+
+            // e.g. a manual break shortly before the fixed break doesn't get another one right after
+            let earliest = last_break_end.map_or(Seconds::ZERO, |end| {
+                config
+                    .min_work_between_breaks
+                    .saturating_sub(end.elapsed().into())
+            });
+            let mut interval = fixed_break.unwrap_or(remaining);
+            if interval < earliest {
+                println!(
+                    "Moving the next break to {} seconds from now, so there are at least {} seconds of work between breaks.",
+                    earliest.as_secs(),
+                    config.min_work_between_breaks.as_secs()
+                );
+                decisions::record(
+                    Decision::Moved,
+                    format!(
+                        "to {} seconds from now, for {} seconds of work since the last break",
+                        earliest.as_secs(),
+                        config.min_work_between_breaks.as_secs()
+                    ),
+                );
+                interval = earliest;
+            }
+            debug::record(debug::Schedule {
+                config: config.clone(),
+                consecutive_skips,
+                last_break_end,
+                fixed_break,
+                battery_saver: false,
+                quiet_hours: false,
+                skip_blocked: None,
+            });
+            match wait_until_break(&mut socket, interval, &config, None)? {
+                WorkEnd::ClockJumped { remaining: left } => {
+                    remaining = match fixed_break {
+                        // the rolling interval is kept on the monotonic clock
+                        Some(_) => break_interval.saturating_sub(work_start.elapsed().into()),
+                        None => left,
+                    };
+                }
+                work_end => break (work_end, fixed_break),
+            }
+        };
+        let config = toggles::apply(base_config.for_today());
+
+        // manually started breaks are never postponed
+        while work_end == WorkEnd::Elapsed
+            && config.postpone_on_dnd
+            && dnd::notifications_inhibited()
+        {
+            println!(
+                "Notifications are inhibited, postponing the break by {} seconds.",
+                config.dnd_postpone.as_secs()
+            );
+            publish(&mut event_stream, &mut master, "postpone");
+            decisions::record(
+                Decision::Postponed,
+                format!(
+                    "by {} seconds, notifications are inhibited",
+                    config.dnd_postpone.as_secs()
+                ),
+            );
+            let mut postpone = config.dnd_postpone;
+            work_end = loop {
+                match wait_until_break(&mut socket, postpone, &config, None)? {
+                    WorkEnd::ClockJumped { remaining } => postpone = remaining,
+                    work_end => break work_end,
+                }
+            };
+        }
 
-        if !skipped && config.show_notification {
-            Notification::new()
-                .summary("It's break time!")
-                .body("The next break starts in 10 seconds.")
-                .show()?;
-            std::thread::sleep(Duration::from_secs(10));
+        if let WorkEnd::Shutdown {
+            shutdown,
+            remaining,
+        } = work_end
+        {
+            if let Some(time_tracker) = time_tracker.as_mut()
+                && let Err(err) = time_tracker.stop_work()
+            {
+                println!("The work interval could not be tracked! The error: {err}");
+            }
+            return shutdown::execute(
+                shutdown,
+                shutdown::State {
+                    remaining,
+                    consecutive_skips,
+                },
+            );
         }
 
-        if config.play_sound {
-            play_sound(&stream_handle, &sound_data)?;
+        // on low battery, the break is only announced through a notification
+        let battery_saver = config.battery_saver_below.is_some_and(|threshold| {
+            battery::discharging_percentage()
+                .is_some_and(|percentage| percentage < threshold as f64)
+        });
+        if battery_saver {
+            println!("The battery is low, the break is only announced through a notification.");
+        }
+        // checked separately from the schedule, the break itself happens either way
+        let quiet_hours = config.play_sound && config.quiet_now();
+        if quiet_hours {
+            println!("It's within the quiet hours, the break is announced without sounds.");
         }
 
-        if config.turn_off_monitors {
-            let status = Command::new("niri")
-                .arg("msg")
-                .arg("action")
-                .arg("power-off-monitors")
-                .status();
+        // the colors are shifted during the lead-in and the break, unless the monitors are off anyway
+        #[cfg(feature = "popup")]
+        let shift_gamma = config.gamma_shift && !config.turn_off_monitors && !battery_saver;
+        #[cfg(feature = "popup")]
+        if shift_gamma {
+            supervisor::run(&mut wayland, "gamma shift", |event_queue, data| {
+                let qh = event_queue.handle();
+                gamma::start_gamma_shift(
+                    event_queue,
+                    data,
+                    &qh,
+                    config.gamma_temperature,
+                    config.gamma_brightness,
+                )
+            })
+            .transpose()?;
+        }
 
-            if let Err(err) = status {
-                println!("Monitors could not be turned off! The error: {err}");
+        // the lead-in ends at a fixed time, whatever is prepared for the break in the meantime
+        let lead_in_end = (work_end == WorkEnd::Elapsed && config.show_notification)
+            .then(|| Instant::now() + LEAD_IN);
+        // the break's number today, counting the taken ones
+        let cycle = stats::taken_today() + 1;
+        if lead_in_end.is_some() {
+            let (summary, body) = template::notification(
+                &config.pre_break_summary,
+                &config.pre_break_body,
+                LEAD_IN.into(),
+                config.break_duration,
+                cycle,
+            );
+            notify::show(&summary, &body, Phase::Warn)?;
+            #[cfg(feature = "audio")]
+            if config.play_sound && config.pre_break_sound && !battery_saver && !quiet_hours {
+                play_cue(
+                    audio::Cue::PreBreak,
+                    #[cfg(feature = "popup")]
+                    &mut wayland,
+                );
             }
         }
 
-        if config.show_popup {
-            show_popup(
-                &mut event_queue,
-                &mut data,
-                &qh,
-                &mut socket,
-                config.break_duration,
+        let mut config = toggles::apply(base_config.for_today());
+        // ad-hoc breaks don't change the configured cycle
+        if let WorkEnd::Skipped {
+            break_duration: Some(break_duration),
+        } = work_end
+        {
+            config.break_duration = break_duration;
+        } else if work_end == WorkEnd::Elapsed
+            && fixed_break.is_some()
+            && let Some(fixed_break) = config.fixed_break
+        {
+            config.break_duration = fixed_break.duration;
+        }
+        // scheduled breaks make up for the skipped ones, manual ones count as made up entirely
+        let manual = matches!(work_end, WorkEnd::Skipped { .. });
+        let extension = if config.debt == DebtPolicy::Extend && !manual {
+            debt::extension(config.break_duration, debt::owed())
+        } else {
+            Seconds::ZERO
+        };
+        if !extension.is_zero() {
+            config.break_duration += extension;
+            println!(
+                "The break is extended by {} seconds of skipped breaks.",
+                extension.as_secs()
+            );
+            decisions::record(
+                Decision::Extended,
+                format!(
+                    "by {} seconds to make up for skipped breaks",
+                    extension.as_secs()
+                ),
+            );
+        }
+        // the extra break itself is made up time as well
+        let made_up = manual || (extra_break && fixed_break.is_none());
+
+        if battery_saver {
+            config.show_popup = false;
+            config.play_sound = false;
+        }
+        if quiet_hours {
+            config.play_sound = false;
+        }
+        // a fullscreen window (e.g. a game) may cover the overlay without the layer shell
+        #[cfg(feature = "popup")]
+        if config.popup_on_top
+            && config.show_popup
+            && !config.turn_off_monitors
+            && !layer_shell(&wayland)
+        {
+            if compositor.is_some() {
+                config.turn_off_monitors = true;
+            } else {
+                notify::show_urgent(
+                    "It's break time!",
+                    &format!(
+                        "Take a break of {}.",
+                        announce::spoken_duration(config.break_duration)
+                    ),
+                    Phase::Break,
+                )?;
+            }
+        }
+
+        let skip_blocked = (config.escalation && consecutive_skips >= config.escalation_limit)
+            .then(|| {
+                format!(
+                    "You skipped the last {consecutive_skips} breaks, so this one has to be taken."
+                )
+            });
+
+        debug::record(debug::Schedule {
+            config: base_config.for_today(),
+            consecutive_skips,
+            last_break_end,
+            fixed_break,
+            battery_saver,
+            quiet_hours,
+            skip_blocked: skip_blocked.clone(),
+        });
+
+        // the overlay's first frame is uploaded during the lead-in, so it shows up right away
+        #[cfg(feature = "popup")]
+        let mut layout = Layout::new(&config, skip_blocked.is_none());
+        #[cfg(feature = "popup")]
+        layout.set_stats(work_start.elapsed().as_secs(), stats::taken_today());
+        #[cfg(feature = "popup")]
+        if lead_in_end.is_some()
+            && config.show_popup
+            && let Some(Err(err)) = supervisor::run(
+                &mut wayland,
+                "overlay's preparation",
+                |event_queue, data| {
+                    let qh = event_queue.handle();
+                    data.render_scale = config.render_scale;
+                    prepare_popup(event_queue, data, &qh, &mut layout, config.break_duration)
+                },
+            )
+        {
+            println!("The overlay could not be prepared! The error: {err}");
+        }
+        let skip = skip::Policy::new(&config, skip_blocked);
+
+        // the commands are still run during the lead-in, skipping or moving the break ends it
+        let lead_in = match lead_in_end {
+            Some(lead_in_end) => {
+                let seconds = lead_in_end.saturating_duration_since(Instant::now());
+                wait_until_break(&mut socket, seconds.into(), &config, Some(&skip))?
+            }
+            None => WorkEnd::Elapsed,
+        };
+        if matches!(
+            lead_in,
+            WorkEnd::Postponed | WorkEnd::BreakSkipped | WorkEnd::Shutdown { .. }
+        ) {
+            #[cfg(feature = "popup")]
+            {
+                if shift_gamma {
+                    supervisor::run(&mut wayland, "gamma restoration", gamma::stop_gamma_shift)
+                        .transpose()?;
+                }
+                if let Some((_, data)) = wayland.as_mut() {
+                    discard_prepared_frame(data);
+                }
+            }
+            if let Some(time_tracker) = time_tracker.as_mut()
+                && let Err(err) = time_tracker.stop_work()
+            {
+                println!("The work interval could not be tracked! The error: {err}");
+            }
+        }
+        match lead_in {
+            WorkEnd::Shutdown {
+                shutdown,
+                remaining,
+            } => {
+                return shutdown::execute(
+                    shutdown,
+                    shutdown::State {
+                        remaining,
+                        consecutive_skips,
+                    },
+                );
+            }
+            WorkEnd::BreakSkipped => {
+                stats::break_started(BreakReason::Scheduled);
+                stats::break_skipped();
+                consecutive_skips += 1;
+                if !made_up {
+                    debt::add(config.break_duration.saturating_sub(extension));
+                }
+                publish(&mut event_stream, &mut master, "skip");
+                publish(&mut event_stream, &mut master, "work");
+                continue;
+            }
+            WorkEnd::Postponed => {
+                publish(&mut event_stream, &mut master, "postpone");
+                continue;
+            }
+            // e.g. `break`, which starts it right away
+            _ => {}
+        }
+        decisions::record(
+            Decision::Fired,
+            match work_end {
+                WorkEnd::Skipped {
+                    break_duration: None,
+                } => "requested with `break`".to_string(),
+                WorkEnd::Skipped {
+                    break_duration: Some(seconds),
+                } => format!(
+                    "requested with `break`, lasting {} seconds",
+                    seconds.as_secs()
+                ),
+                WorkEnd::FocusEnded => "the focus session ended".to_string(),
+                _ if fixed_break.is_some() => "the fixed break is due".to_string(),
+                _ => "the work interval elapsed".to_string(),
+            },
+        );
+
+        if battery_saver {
+            notify::show(
+                "It's break time!",
+                &format!(
+                    "The battery is low, so there is no overlay. Take a break of {}.",
+                    announce::spoken_duration(config.break_duration)
+                ),
+                Phase::Break,
             )?;
+        }
+        if let Some(reason) = &skip.blocked
+            && config.show_notification
+        {
+            notify::show("Skipping is blocked for this break!", reason, Phase::Break)?;
+        }
+
+        #[cfg(feature = "popup")]
+        let focused = if config.restore_focus {
+            supervisor::run(&mut wayland, "focus snapshot", |event_queue, data| {
+                let qh = event_queue.handle();
+                focus::snapshot_focus(event_queue, data, &qh)
+            })
+            .transpose()?
+            .flatten()
         } else {
-            wait_until_work(&mut socket, config.break_duration)?;
+            None
+        };
+
+        #[cfg(feature = "audio")]
+        {
+            audio::set_ticking(config.play_sound);
+            if config.play_sound {
+                play_cue(
+                    audio::Cue::BreakStart,
+                    #[cfg(feature = "popup")]
+                    &mut wayland,
+                );
+            }
         }
 
-        if config.turn_off_monitors {
-            let status = Command::new("niri")
-                .arg("msg")
-                .arg("action")
-                .arg("power-on-monitors")
-                .status();
+        // the outputs might disconnect, so the workspaces are put back where they were afterwards
+        if config.turn_off_monitors
+            && let Some(compositor) = compositor.as_mut()
+        {
+            if let Err(err) = compositor.save_workspaces() {
+                println!("The workspaces could not be saved! The error: {err}");
+            }
+            if let Err(err) = compositor.power_off_monitors() {
+                println!("Monitors could not be turned off! The error: {err}");
+            }
+        }
+
+        // breaks requested through the helper are manual, everything else follows the schedule
+        let reason = match work_end {
+            WorkEnd::Skipped { .. } => BreakReason::Manual,
+            _ => BreakReason::Scheduled,
+        };
+        stats::break_started(reason);
+        publish(
+            &mut event_stream,
+            &mut master,
+            &format!("break {}", reason.name()),
+        );
 
-            if let Err(err) = status {
+        if let Some(time_tracker) = time_tracker.as_mut()
+            && let Err(err) = time_tracker.stop_work()
+        {
+            println!("The work interval could not be tracked! The error: {err}");
+        }
+
+        if config.announce_phases {
+            announce::announce(&format!(
+                "Break started. It lasts {}.",
+                announce::spoken_duration(config.break_duration)
+            ));
+        }
+
+        // for compositors that don't inhibit their shortcuts, released right after the break
+        let input_grab = (config.strict && config.grab_input && capabilities::get().input_grab)
+            .then(|| match grab::grab() {
+                Ok(input_grab) => Some(input_grab),
+                Err(err) => {
+                    println!("The input devices could not be grabbed! The error: {err}");
+                    None
+                }
+            })
+            .flatten();
+        // strict breaks hold the input anyway
+        let extend_on_activity = config.extend_break_on_activity && !config.strict;
+
+        let break_start = Instant::now();
+        #[cfg(feature = "popup")]
+        let break_skipped = {
+            let shown = if config.show_popup {
+                supervisor::run(&mut wayland, "break", |event_queue, data| {
+                    let qh = event_queue.handle();
+                    data.cursor_mode = config.popup_cursor;
+                    data.tap_to_skip = skip.allows(SkipSource::Overlay);
+                    data.inhibit_shortcuts = config.strict;
+                    data.popup_on_top = config.popup_on_top;
+                    data.extend_on_activity = extend_on_activity;
+                    data.render_scale = config.render_scale;
+                    show_popup(
+                        event_queue,
+                        data,
+                        &qh,
+                        &mut socket,
+                        config.break_duration,
+                        &skip,
+                        &mut layout,
+                    )
+                })
+                .transpose()?
+            } else {
+                None
+            };
+            #[cfg(feature = "x11")]
+            let shown = match &x11 {
+                Some(x11) if shown.is_none() && config.show_popup => Some(x11::show_popup(
+                    x11,
+                    &mut socket,
+                    config.break_duration,
+                    skip.allows(SkipSource::Overlay),
+                    &skip,
+                    &mut layout,
+                )?),
+                _ => shown,
+            };
+            match shown {
+                Some(break_skipped) => break_skipped,
+                // without the overlay (or after it crashed), the rest of the break is counted down
+                // in a notification -- closed when it's dropped, right after the break
+                None => {
+                    let mut countdown =
+                        notify::countdown(&config, &socket, skip.allows(SkipSource::Notification));
+                    wait_until_work(
+                        &mut socket,
+                        config
+                            .break_duration
+                            .saturating_sub(break_start.elapsed().into()),
+                        extend_on_activity,
+                        &skip,
+                        countdown
+                            .as_mut()
+                            .map(|countdown| countdown.as_mut() as &mut dyn BreakView),
+                    )?
+                }
+            }
+        };
+
+        #[cfg(not(feature = "popup"))]
+        let break_skipped = {
+            let mut countdown =
+                notify::countdown(&config, &socket, skip.allows(SkipSource::Notification));
+            wait_until_work(
+                &mut socket,
+                config.break_duration,
+                extend_on_activity,
+                &skip,
+                countdown
+                    .as_mut()
+                    .map(|countdown| countdown.as_mut() as &mut dyn BreakView),
+            )?
+        };
+
+        drop(input_grab);
+
+        // the quiet hours might have started during the break, or the sound was toggled off
+        #[cfg(feature = "audio")]
+        if config.play_sound && toggles::apply(config.clone()).play_sound && !config.quiet_now() {
+            play_cue(
+                audio::Cue::BreakEnd,
+                #[cfg(feature = "popup")]
+                &mut wayland,
+            );
+        }
+
+        // the overlay is still shown, so the monitors wake up and the gamma is restored behind it
+        // on low battery, the monitors are left off until there is input
+        if config.turn_off_monitors
+            && !battery_saver
+            && let Some(compositor) = compositor.as_mut()
+        {
+            // waking the monitors up can switch the audio device (e.g. to a monitor's speakers), so
+            // the chime is played out before
+            #[cfg(feature = "audio")]
+            audio::wait_until_played(audio::PLAYED_TIMEOUT);
+            if let Err(err) = compositor.power_on_monitors() {
                 println!("Monitors could not be turned on! The error: {err}");
             }
+            if let Err(err) = compositor.restore_workspaces() {
+                println!("The workspaces could not be restored! The error: {err}");
+            }
+        }
+
+        #[cfg(feature = "popup")]
+        if shift_gamma {
+            supervisor::run(&mut wayland, "gamma restoration", gamma::stop_gamma_shift)
+                .transpose()?;
         }
 
-        if config.play_sound {
-            play_sound(&stream_handle, &sound_data)?;
+        if break_skipped {
+            stats::break_skipped();
+            consecutive_skips += 1;
+        } else {
+            // a full break was taken
+            stats::break_taken();
+            consecutive_skips = 0;
+        }
+        // the own part of a scheduled break that was skipped is owed, anything beyond it pays back
+        let lasted = Seconds::from(break_start.elapsed());
+        if made_up {
+            debt::pay(lasted);
+        } else {
+            let own = config.break_duration.saturating_sub(extension);
+            debt::add(own.saturating_sub(lasted));
+            debt::pay(lasted.saturating_sub(own));
+        }
+
+        if break_skipped {
+            publish(&mut event_stream, &mut master, "skip");
+        }
+        publish(&mut event_stream, &mut master, "work");
+
+        let next_interval = escalated_interval(&base_config.for_today(), consecutive_skips);
+        if config.announce_phases {
+            announce::announce(&format!(
+                "{} Back to work, the next break is in {}.",
+                if break_skipped {
+                    "Break was skipped."
+                } else {
+                    "Break is over."
+                },
+                announce::spoken_duration(next_interval)
+            ));
+        } else if config.show_notification && break_skipped && warn_with_toast(&config) {
+            // a transient message, it doesn't need the notification daemon
+            #[cfg(feature = "popup")]
+            toast::show(
+                "Break skipped!",
+                &format!(
+                    "The next break is in {}.",
+                    announce::spoken_duration(next_interval)
+                ),
+                Phase::Work,
+            );
+        } else if config.show_notification {
+            let (summary, body) = template::notification(
+                &config.break_end_summary,
+                &config.break_end_body,
+                next_interval,
+                config.break_duration,
+                cycle,
+            );
+            notify::show(&summary, &body, Phase::Work)?;
+        }
+
+        // the windows below only get the focus back once the overlay is gone
+        #[cfg(feature = "popup")]
+        {
+            let grace_period = if config.reduce_motion {
+                Duration::ZERO
+            } else {
+                config.grace_period.as_duration()
+            };
+            supervisor::run(&mut wayland, "end of the break", |event_queue, data| {
+                let qh = event_queue.handle();
+                hide_popup(event_queue, data, &qh, grace_period)?;
+                if let Some(handle) = &focused {
+                    focus::restore_focus(event_queue, data, handle)?;
+                }
+                focus::stop_tracking(event_queue, data)
+            })
+            .transpose()?;
+        }
+
+        last_break_end = Some(Instant::now());
+
+        // a shutdown requested during the break waits until everything above was restored
+        if let Some(shutdown) = shutdown::requested() {
+            let state = shutdown::State {
+                remaining: escalated_interval(&base_config.for_today(), consecutive_skips),
+                consecutive_skips,
+            };
+            return shutdown::execute(shutdown, state);
         }
     }
 }
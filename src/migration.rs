@@ -0,0 +1,87 @@
+// the schema of the config files and how files of older versions are upgraded to it
+//
+// files without `config_version` are of the first version. renamed keys (or keys that changed
+// their unit) are migrated whenever a file is loaded, `wlbreaktime-helper migrate-config` writes
+// the migrated file next to the original
+use regex::Regex;
+
+pub const CONFIG_VERSION: u64 = 2;
+const FIRST_VERSION: u64 = 1;
+
+/*
+ * a change of the schema, it upgrades files to the given version
+ */
+struct Migration {
+    version: u64,
+    description: &'static str,
+    apply: fn(&str) -> String,
+}
+
+const MIGRATIONS: [Migration; 1] = [Migration {
+    version: 2,
+    description: "break_interval was renamed to work_interval",
+    apply: rename_break_interval,
+}];
+
+pub struct Migrated {
+    pub content: String,
+    // the version the file was written for
+    pub version: u64,
+    // the descriptions of the migrations that were applied, empty if the file is up to date
+    pub applied: Vec<&'static str>,
+}
+
+/*
+ * upgrades the content of a config file to the current schema, files of newer versions are
+ * returned as they are
+ */
+pub fn migrate(content: &str) -> Migrated {
+    let version = version(content);
+    let mut migrated = content.to_string();
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > version)
+    {
+        migrated = (migration.apply)(&migrated);
+        applied.push(migration.description);
+    }
+
+    if !applied.is_empty() {
+        migrated = set_version(&migrated);
+    }
+    Migrated {
+        content: migrated,
+        version,
+        applied,
+    }
+}
+
+fn version(content: &str) -> u64 {
+    let re = Regex::new(r"(?m)^\s*config_version=(\d+)").unwrap();
+    re.captures(content)
+        .and_then(|c| c.get(1).unwrap().as_str().parse().ok())
+        .unwrap_or(FIRST_VERSION)
+}
+
+fn set_version(content: &str) -> String {
+    let re = Regex::new(r"(?m)^(\s*)config_version=\d+").unwrap();
+    if re.is_match(content) {
+        re.replace(content, format!("${{1}}config_version={CONFIG_VERSION}"))
+            .into_owned()
+    } else {
+        format!("config_version={CONFIG_VERSION}\n{content}")
+    }
+}
+
+/*
+ * renames every occurrence of the key, including those in weekday overrides
+ */
+fn rename_key(content: &str, old: &str, new: &str) -> String {
+    let re = Regex::new(&format!(r"\b{old}=")).unwrap();
+    re.replace_all(content, format!("{new}=")).into_owned()
+}
+
+fn rename_break_interval(content: &str) -> String {
+    rename_key(content, "break_interval", "work_interval")
+}
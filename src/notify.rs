@@ -0,0 +1,344 @@
+// messages go through a chain that is walked at runtime: a desktop notification, if that fails
+// (e.g. no notification daemon is running) a toast drawn by the daemon itself, and without the
+// popup feature or a compositor that can show it, the log -- with the terminal's bell if the
+// daemon runs in one, e.g. on a TTY
+//
+// every notification carries the configured application name and icon, the break notifications
+// also a fixed category, so notification daemons can be set up to style them (e.g. in mako's
+// `[category=x-wlbreaktime.break]`)
+use std::{
+    io::{self, IsTerminal},
+    os::unix::net::UnixDatagram,
+};
+#[cfg(feature = "notifications")]
+use std::{path::Path, sync::OnceLock, time::Duration};
+
+#[cfg(feature = "notifications")]
+use notify_rust::{Hint, Notification, NotificationHandle, Timeout};
+#[cfg(feature = "notifications")]
+use rustix::{
+    event::{PollFd, PollFlags, Timespec, poll},
+    io::Errno,
+};
+#[cfg(feature = "notifications")]
+use wlbreaktime::duration::Seconds;
+
+use crate::BreakView;
+use crate::config::Config;
+#[cfg(feature = "notifications")]
+use crate::config::Urgency;
+use crate::palette::Phase;
+
+#[cfg(feature = "notifications")]
+const CATEGORY: &str = "x-wlbreaktime.break";
+#[cfg(feature = "notifications")]
+const TIMER_CATEGORY: &str = "x-wlbreaktime.timer";
+
+// set once at startup, like the capabilities
+#[cfg(feature = "notifications")]
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+#[cfg(feature = "notifications")]
+#[derive(Debug, Clone)]
+struct Style {
+    urgency: Urgency,
+    icon: Option<String>,
+    app_name: String,
+}
+
+#[cfg_attr(not(feature = "notifications"), allow(unused_variables))]
+pub(crate) fn set_style(config: &Config) {
+    #[cfg(feature = "notifications")]
+    if STYLE
+        .set(Style {
+            urgency: config.notification_urgency,
+            icon: config.notification_icon.clone(),
+            app_name: config.notification_app_name.clone(),
+        })
+        .is_err()
+    {
+        println!("The notification style was already set!");
+    }
+}
+
+/*
+ * a notification with the configured application name and icon, summary and body are up to the
+ * caller
+ */
+#[cfg(feature = "notifications")]
+pub(crate) fn notification() -> Notification {
+    let mut notification = Notification::new();
+    if let Some(style) = STYLE.get() {
+        notification.appname(&style.app_name);
+        if let Some(icon) = &style.icon {
+            notification.icon(icon);
+        }
+    }
+    notification
+}
+
+#[cfg(feature = "notifications")]
+fn urgency() -> notify_rust::Urgency {
+    match STYLE.get().map(|style| style.urgency) {
+        Some(Urgency::Low) => notify_rust::Urgency::Low,
+        Some(Urgency::Critical) => notify_rust::Urgency::Critical,
+        Some(Urgency::Normal) | None => notify_rust::Urgency::Normal,
+    }
+}
+
+/*
+ * the phase the message is about colors its toast
+ */
+pub(crate) fn show(
+    summary: &str,
+    body: &str,
+    phase: Phase,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "notifications")]
+    return show_with(summary, body, phase, urgency());
+    #[cfg(not(feature = "notifications"))]
+    {
+        fall_back(summary, body, phase);
+        Ok(())
+    }
+}
+
+/*
+ * like show, however critical whatever urgency is configured, so it's shown above fullscreen
+ * windows as well -- for breaks the overlay can't be shown on top of everything
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn show_urgent(
+    summary: &str,
+    body: &str,
+    phase: Phase,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "notifications")]
+    return show_with(summary, body, phase, notify_rust::Urgency::Critical);
+    #[cfg(not(feature = "notifications"))]
+    {
+        fall_back(summary, body, phase);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "notifications")]
+fn show_with(
+    summary: &str,
+    body: &str,
+    phase: Phase,
+    urgency: notify_rust::Urgency,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // the notification daemon might have been started or stopped since the daemon started
+    match notification()
+        .summary(summary)
+        .body(body)
+        .urgency(urgency)
+        .hint(Hint::Category(CATEGORY.to_string()))
+        .show()
+    {
+        Ok(_) => return Ok(()),
+        Err(err) => println!("The notification could not be shown! The error: {err}"),
+    }
+
+    fall_back(summary, body, phase);
+    Ok(())
+}
+
+/*
+ * the rest of the chain after the desktop notifications
+ */
+#[cfg_attr(not(feature = "popup"), allow(unused_variables))]
+fn fall_back(summary: &str, body: &str, phase: Phase) {
+    #[cfg(feature = "popup")]
+    if crate::capabilities::get().wayland {
+        crate::toast::show(summary, body, phase);
+        return;
+    }
+
+    let bell = if io::stdout().is_terminal() {
+        "\x07"
+    } else {
+        ""
+    };
+    println!("{bell}{summary} {body}");
+}
+
+/*
+ * the notification of a timer that is up, it's no break notification, so it has its own category
+ */
+pub(crate) fn show_timer(message: &str) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "notifications")]
+    match notification()
+        .summary("Time is up!")
+        .body(message)
+        .urgency(urgency())
+        .hint(Hint::Category(TIMER_CATEGORY.to_string()))
+        .show()
+    {
+        Ok(_) => return Ok(()),
+        Err(err) => println!("The notification could not be shown! The error: {err}"),
+    }
+
+    fall_back("Time is up!", message, Phase::Work);
+    Ok(())
+}
+
+/*
+ * returns true if a notification daemon is running
+ */
+pub(crate) fn server_available() -> bool {
+    #[cfg(feature = "notifications")]
+    return notify_rust::get_server_information().is_ok();
+    #[cfg(not(feature = "notifications"))]
+    false
+}
+
+/*
+ * without the overlay, a resident notification counts the break down -- it's replaced every second
+ * and closed as soon as the break ends, however it ended
+ */
+#[cfg(feature = "notifications")]
+struct Countdown {
+    handle: Option<NotificationHandle>,
+    // the remaining time the notification currently shows
+    shown: Seconds,
+    // the notification has a "Skip" action
+    skippable: bool,
+}
+
+#[cfg(feature = "notifications")]
+impl BreakView for Countdown {
+    fn update(&mut self, remaining: Seconds) -> Result<bool, Box<dyn std::error::Error>> {
+        if remaining == self.shown {
+            return Ok(false);
+        }
+        if let Some(handle) = &self.handle {
+            // replaces the shown notification instead of adding another one
+            match countdown_notification(remaining, self.skippable)
+                .id(handle.id())
+                .show()
+            {
+                Ok(handle) => self.handle = Some(handle),
+                Err(err) => println!("The break countdown could not be updated! The error: {err}"),
+            }
+            self.shown = remaining;
+        }
+        Ok(false)
+    }
+
+    fn wait(
+        &mut self,
+        socket: &UnixDatagram,
+        timeout: Duration,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut fds = [PollFd::new(socket, PollFlags::IN)];
+        match poll(&mut fds, Some(&Timespec::try_from(timeout)?)) {
+            Ok(_) => Ok(fds[0].revents().contains(PollFlags::IN)),
+            // e.g. the system woke up from suspension
+            Err(Errno::INTR) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(feature = "notifications")]
+impl Drop for Countdown {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.close();
+        }
+    }
+}
+
+/*
+ * the countdown shown during a break without the overlay, None if there is no notification daemon
+ * or notifications are disabled -- if it's skippable, its action sends the skip to the daemon
+ */
+#[cfg_attr(not(feature = "notifications"), allow(unused_variables))]
+pub(crate) fn countdown(
+    config: &Config,
+    socket: &UnixDatagram,
+    skippable: bool,
+) -> Option<Box<dyn BreakView>> {
+    #[cfg(feature = "notifications")]
+    if config.show_notification && crate::capabilities::get().notifications {
+        match countdown_notification(config.break_duration, skippable).show() {
+            Ok(handle) => {
+                // the first handle waits for the action, the countdown replaces the notification
+                // through another one
+                let id = handle.id();
+                let handle = if skippable {
+                    listen_for_skip(socket, handle);
+                    countdown_notification(config.break_duration, skippable)
+                        .id(id)
+                        .show()
+                        .ok()
+                } else {
+                    Some(handle)
+                };
+                return Some(Box::new(Countdown {
+                    handle,
+                    shown: config.break_duration,
+                    skippable,
+                }));
+            }
+            Err(err) => println!("The break countdown could not be shown! The error: {err}"),
+        }
+    }
+    None
+}
+
+/*
+ * the countdown with the remaining break time as "m:ss", e.g. "Back to work in 1:05."
+ */
+#[cfg(feature = "notifications")]
+fn countdown_notification(remaining: Seconds, skippable: bool) -> Notification {
+    let (minutes, seconds) = remaining.split();
+    let mut notification = notification();
+    if skippable {
+        notification.action("skip", "Skip");
+    }
+    notification
+        .summary("Break in progress")
+        .body(&format!(
+            "Back to work in {}:{seconds:02}.",
+            minutes.as_minutes()
+        ))
+        .urgency(urgency())
+        .hint(Hint::Category(CATEGORY.to_string()))
+        .hint(Hint::Resident(true))
+        // the updates would chime every second otherwise
+        .hint(Hint::SuppressSound(true))
+        .timeout(Timeout::Never);
+    notification
+}
+
+/*
+ * waits for the countdown's "Skip" action in the background, until the countdown is closed
+ */
+#[cfg(feature = "notifications")]
+fn listen_for_skip(socket: &UnixDatagram, handle: NotificationHandle) {
+    let daemon_path = match socket.local_addr() {
+        Ok(address) => address.as_pathname().map(Path::to_path_buf),
+        Err(err) => {
+            println!("The countdown can't be skipped, the socket has no address! The error: {err}");
+            return;
+        }
+    };
+    let Some(daemon_path) = daemon_path else {
+        println!("The countdown can't be skipped, the socket is not bound to a path!");
+        return;
+    };
+
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "skip"
+                && let Err(err) =
+                    crate::send_request(&daemon_path, crate::skip::NOTIFICATION_COMMAND)
+            {
+                println!("The break could not be skipped! The error: {err}");
+            }
+        });
+    });
+}
@@ -0,0 +1,151 @@
+// the colors of the phases, in one place for every surface that shows them: the strip along the
+// top of the overlay, the stripe of the toasts and the status for waybar (`wlbreaktime-helper
+// waybar`), whose classes are the phases' names
+//
+// the palette is picked through `phase_palette`, the overlay's own colors (background, text) follow
+// `theme` and `high_contrast` instead -- the desktop's preferred color scheme for `theme=auto` is
+// kept here as well, the Settings portal updates it whenever it changes
+use std::sync::OnceLock;
+#[cfg(feature = "popup")]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use serde_json::json;
+
+#[cfg(feature = "popup")]
+use crate::config::Theme;
+use crate::config::{Config, PhasePalette};
+
+// the last minute before a break counts as the warning, it's also when the toast warns about it
+pub(crate) const WARNING_SECONDS: u64 = 60;
+
+// set once at startup, like the notification style
+static PALETTE: OnceLock<PhasePalette> = OnceLock::new();
+#[cfg(feature = "popup")]
+static THEME: OnceLock<Theme> = OnceLock::new();
+// the portal's org.freedesktop.appearance color-scheme: 0 is no preference, 1 dark and 2 light
+#[cfg(feature = "popup")]
+static COLOR_SCHEME: AtomicU32 = AtomicU32::new(0);
+#[cfg(feature = "popup")]
+const PREFERS_LIGHT: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Phase {
+    Work,
+    // shortly before a break
+    Warn,
+    Break,
+}
+
+impl Phase {
+    /*
+     * work time, or the warning if the break starts within a minute
+     */
+    pub(crate) fn before_break(remaining_seconds: u64) -> Phase {
+        if remaining_seconds <= WARNING_SECONDS {
+            Phase::Warn
+        } else {
+            Phase::Work
+        }
+    }
+
+    /*
+     * the CSS class of the phase in waybar
+     */
+    pub(crate) fn class(self) -> &'static str {
+        match self {
+            Phase::Work => "work",
+            Phase::Warn => "warn",
+            Phase::Break => "break",
+        }
+    }
+}
+
+pub(crate) fn set(config: &Config) {
+    if PALETTE.set(config.phase_palette).is_err() {
+        println!("The phase palette was already set!");
+    }
+    #[cfg(feature = "popup")]
+    let _ = THEME.set(config.theme);
+}
+
+/*
+ * the theme set at startup, e.g. for the toasts
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn theme() -> Theme {
+    *THEME.get().unwrap_or(&Theme::Auto)
+}
+
+#[cfg(all(feature = "popup", feature = "dbus"))]
+pub(crate) fn set_color_scheme(color_scheme: u32) {
+    COLOR_SCHEME.store(color_scheme, Ordering::Relaxed);
+}
+
+/*
+ * whether the theme is light, for auto only if the desktop prefers it
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn light(theme: Theme) -> bool {
+    match theme {
+        Theme::Auto => COLOR_SCHEME.load(Ordering::Relaxed) == PREFERS_LIGHT,
+        Theme::Light => true,
+        Theme::Dark => false,
+    }
+}
+
+/*
+ * the color of the phase in the palette set at startup
+ */
+pub(crate) fn current(phase: Phase) -> u32 {
+    color(*PALETTE.get().unwrap_or(&PhasePalette::Default), phase)
+}
+
+/*
+ * ARGB, like the canvas expects it
+ */
+pub(crate) fn color(palette: PhasePalette, phase: Phase) -> u32 {
+    match (palette, phase) {
+        (PhasePalette::Default, Phase::Work) => 0xFF4CAF50,
+        (PhasePalette::Default, Phase::Warn) => 0xFFFFC107,
+        (PhasePalette::Default, Phase::Break) => 0xFFF44336,
+        (PhasePalette::OkabeIto, Phase::Work) => 0xFF56B4E9,
+        (PhasePalette::OkabeIto, Phase::Warn) => 0xFFF0E442,
+        (PhasePalette::OkabeIto, Phase::Break) => 0xFFD55E00,
+        (PhasePalette::Tol, Phase::Work) => 0xFF4477AA,
+        (PhasePalette::Tol, Phase::Warn) => 0xFFCCBB44,
+        (PhasePalette::Tol, Phase::Break) => 0xFFEE6677,
+    }
+}
+
+/*
+ * e.g. "#4caf50", for Pango markup and CSS
+ */
+pub(crate) fn css(color: u32) -> String {
+    format!("#{:06x}", color & 0xFFFFFF)
+}
+
+/*
+ * the status as a waybar custom module expects it (`"return-type": "json"`): the remaining time in
+ * the phase's color, the phase as the class and the daemon's state (work, focus, paused or break)
+ * as the alt text
+ */
+pub(crate) fn waybar(phase: Phase, state: &str, remaining_seconds: u64) -> String {
+    let remaining = format!(
+        "{:02}:{:02}",
+        remaining_seconds / 60,
+        remaining_seconds % 60
+    );
+    let tooltip = match state {
+        "break" => format!("Break, {remaining} left"),
+        "focus" => format!("Focus session, {remaining} left"),
+        "paused" => format!("Paused, {remaining} of work left"),
+        _ => format!("Next break in {remaining}"),
+    };
+    json!({
+        "text": format!("<span color=\"{}\">{remaining}</span>", css(current(phase))),
+        "alt": state,
+        "tooltip": tooltip,
+        "class": phase.class(),
+    })
+    .to_string()
+}
@@ -0,0 +1,219 @@
+// all filesystem paths used by the daemon and the helper
+//
+// when running inside a Flatpak sandbox, most of the host's directories are not visible (or not
+// shared with the host), so the paths need to follow Flatpak's conventions
+use std::{
+    env::{self, VarError},
+    fs,
+    path::Path,
+};
+
+pub const APP_ID: &str = "io.github.schwafe.wlbreaktime";
+
+const CONFIG_PATH: &str = "wlbreaktime/config";
+// the drop-ins of a config file are in the directory of its name with this suffix
+const DROP_IN_DIR_SUFFIX: &str = ".d";
+const DROP_IN_EXTENSION: &str = "conf";
+pub const SOCKET_NAME: &str = "wlbreaktime.socket";
+pub const EVENTS_SOCKET_NAME: &str = "wlbreaktime-events.socket";
+const SYSTEMD_USER_PATH: &str = "systemd/user";
+// where `wlbreaktime-helper install-service --system` writes the units of multi-user machines
+pub const SYSTEMD_SYSTEM_DIR: &str = "/etc/systemd/system";
+// the system socket `wlbreaktime-helper session` asks for a daemon of its session on
+pub const SESSION_SOCKET_PATH: &str = "/run/wlbreaktime-session.socket";
+// the runtime directory of a user that is not known through XDG_RUNTIME_DIR, followed by the uid
+pub const USER_RUNTIME_DIR_PREFIX: &str = "/run/user/";
+// followed by the helper's process id, so helpers running at the same time get their own socket
+const HELPER_SOCKET_PREFIX: &str = "wlbreaktime-helper-";
+const PROMPT_SOCKET_NAME: &str = "wlbreaktime-prompt.socket";
+const REMOTE_SOCKET_NAME: &str = "wlbreaktime-remote.socket";
+const IDLE_SOCKET_NAME: &str = "wlbreaktime-idle.socket";
+const LOGIND_SOCKET_NAME: &str = "wlbreaktime-logind.socket";
+const STATE_FILE_NAME: &str = "wlbreaktime.state";
+
+pub fn is_flatpak() -> bool {
+    Path::new("/.flatpak-info").exists()
+}
+
+/*
+ * inside the sandbox only $XDG_RUNTIME_DIR/app/$FLATPAK_ID is shared with the host
+ */
+pub fn runtime_dir() -> Result<String, VarError> {
+    let runtime_dir = env::var("XDG_RUNTIME_DIR")?;
+    if is_flatpak() {
+        let app_id = env::var("FLATPAK_ID").unwrap_or(APP_ID.to_string());
+        Ok(runtime_dir + "/app/" + &app_id)
+    } else {
+        Ok(runtime_dir)
+    }
+}
+
+/*
+ * the path the daemon binds its socket to, if it was not passed by systemd
+ */
+pub fn daemon_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + SOCKET_NAME)
+}
+
+pub fn events_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + EVENTS_SOCKET_NAME)
+}
+
+/*
+ * the daemon's socket as seen by clients -- if it does not exist on the host, the daemon might be
+ * running inside a Flatpak sandbox, so its shared runtime directory is used as a fallback
+ */
+pub fn socket_path() -> Result<String, VarError> {
+    let path = daemon_socket_path()?;
+    if is_flatpak() || Path::new(&path).exists() {
+        return Ok(path);
+    }
+
+    let sandboxed_path = env::var("XDG_RUNTIME_DIR")? + "/app/" + APP_ID + "/" + SOCKET_NAME;
+    if Path::new(&sandboxed_path).exists() {
+        Ok(sandboxed_path)
+    } else {
+        Ok(path)
+    }
+}
+
+/*
+ * the helper's socket needs to be next to the daemon's socket, otherwise a sandboxed daemon could
+ * not respond -- every helper process binds its own, e.g. "wlbreaktime-helper-4242.socket"
+ */
+pub fn helper_socket_path() -> Result<String, VarError> {
+    let socket_path = socket_path()?;
+    let dir = socket_path.strip_suffix(SOCKET_NAME).unwrap_or("/");
+    Ok(format!(
+        "{dir}{HELPER_SOCKET_PREFIX}{}.socket",
+        std::process::id()
+    ))
+}
+
+pub fn prompt_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + PROMPT_SOCKET_NAME)
+}
+
+/*
+ * the socket a following daemon uses to pass the master's breaks on to itself
+ */
+pub fn remote_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + REMOTE_SOCKET_NAME)
+}
+
+/*
+ * the socket the idle watcher wakes the daemon up from
+ */
+pub fn idle_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + IDLE_SOCKET_NAME)
+}
+
+/*
+ * the socket the session watcher wakes the daemon up from
+ */
+pub fn logind_socket_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + LOGIND_SOCKET_NAME)
+}
+
+/*
+ * the schedule saved by `quit` and `restart`
+ */
+pub fn state_file_path() -> Result<String, VarError> {
+    Ok(runtime_dir()? + "/" + STATE_FILE_NAME)
+}
+
+pub fn pool_file_path(width: i32, height: i32, format: &str) -> Result<String, VarError> {
+    Ok(runtime_dir()?
+        + "/wlbreaktime-pool-"
+        + &width.to_string()
+        + "-"
+        + &height.to_string()
+        + format)
+}
+
+/*
+ * all configuration files in the order they are read, so later files take precedence, each one
+ * followed by its drop-ins. inside the sandbox, the host's files are used if the sandbox was given
+ * access to them
+ */
+pub fn config_paths() -> Result<Vec<String>, VarError> {
+    let mut paths = vec!["/etc/".to_string() + CONFIG_PATH];
+
+    let home = env::var("HOME");
+    if is_flatpak() {
+        paths.push("/run/host/etc/".to_string() + CONFIG_PATH);
+        // $HOME is not remapped by Flatpak, only XDG_CONFIG_HOME is
+        if let Ok(home) = &home {
+            paths.push(home.clone() + "/.config/" + CONFIG_PATH);
+        }
+    }
+
+    let user_path = user_config_path()?;
+    if !paths.contains(&user_path) {
+        paths.push(user_path);
+    }
+
+    // every file is followed by its drop-ins
+    Ok(paths
+        .into_iter()
+        .flat_map(|path| {
+            let drop_ins = drop_in_paths(&path);
+            std::iter::once(path).chain(drop_ins)
+        })
+        .collect())
+}
+
+/*
+ * the drop-ins of a config file, the *.conf files in the directory next to it with .d appended
+ * (e.g. config.d), in lexical order -- so they can be layered like systemd's, e.g. 10-package.conf
+ * before 50-host.conf
+ */
+pub fn drop_in_paths(path: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(format!("{path}{DROP_IN_DIR_SUFFIX}")) else {
+        return Vec::new();
+    };
+    let mut drop_ins: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == DROP_IN_EXTENSION)
+                && !path.is_dir()
+        })
+        .filter_map(|path| path.to_str().map(str::to_string))
+        .collect();
+    drop_ins.sort();
+    drop_ins
+}
+
+/*
+ * the user's own configuration file, read last apart from its drop-ins -- `wlbreaktime-helper
+ * init-config` writes it
+ */
+pub fn user_config_path() -> Result<String, VarError> {
+    Ok(config_home()? + "/" + CONFIG_PATH)
+}
+
+/*
+ * where `wlbreaktime-helper install-service` writes the systemd units
+ */
+pub fn systemd_user_dir() -> Result<String, VarError> {
+    Ok(config_home()? + "/" + SYSTEMD_USER_PATH)
+}
+
+/*
+ * the config directory of another application, e.g. the compositor's
+ */
+pub fn app_config_dir(app: &str) -> Result<String, VarError> {
+    Ok(config_home()? + "/" + app)
+}
+
+fn config_home() -> Result<String, VarError> {
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(path) => Ok(path),
+        Err(VarError::NotPresent) => Ok(env::var("HOME")? + "/.config"),
+        Err(err) => {
+            panic!("Error '{err}' occured while trying to read XDG_CONFIG_HOME!");
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, env, process, thread};
+
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::{OwnedValue, Value},
+};
+
+#[cfg(feature = "popup")]
+use crate::palette;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const BACKGROUND_INTERFACE: &str = "org.freedesktop.portal.Background";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+#[cfg(feature = "popup")]
+const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+#[cfg(feature = "popup")]
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+#[cfg(feature = "popup")]
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+/*
+ * asks the Background portal to start the daemon together with the session
+ * meant for setups that don't use the systemd units (e.g. Flatpak or non-systemd systems)
+ *
+ * the portal might ask the user first and only responds once they answered, so the request is
+ * made in a thread of its own instead of holding up the first work interval
+ */
+pub fn request_autostart() {
+    let requester = thread::Builder::new()
+        .name("autostart".to_string())
+        .spawn(|| {
+            if let Err(err) = ask_for_autostart() {
+                println!("Autostart could not be requested! The error: {err}");
+            }
+        });
+    if let Err(err) = requester {
+        println!("Autostart could not be requested! The error: {err}");
+    }
+}
+
+fn ask_for_autostart() -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::session()?;
+
+    // the request handle is predictable, so the response signal can be subscribed to before
+    // sending the request -- otherwise the response might be missed
+    let sender = connection
+        .unique_name()
+        .ok_or("The D-Bus connection has no unique name!")?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let token = format!("wlbreaktime_{}", process::id());
+    let request_path = format!("{PORTAL_PATH}/request/{sender}/{token}");
+
+    let request = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        request_path.as_str(),
+        REQUEST_INTERFACE,
+    )?;
+    let mut responses = request.receive_signal("Response")?;
+
+    let command = env::current_exe()?.to_string_lossy().into_owned();
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", Value::from(token.as_str()));
+    options.insert("reason", Value::from("Remind you to take breaks"));
+    options.insert("autostart", Value::from(true));
+    options.insert("commandline", Value::from(vec![command]));
+
+    let background = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        BACKGROUND_INTERFACE,
+    )?;
+    background.call_method("RequestBackground", &("", options))?;
+
+    let response = responses
+        .next()
+        .ok_or("The portal did not respond to the autostart request!")?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = response.body().deserialize()?;
+
+    // 0 -> success, 1 -> cancelled by the user, 2 -> other error
+    match code {
+        0 => {
+            let autostart = results
+                .get("autostart")
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or(false);
+            if autostart {
+                println!("Autostart was enabled through the Background portal.");
+            } else {
+                println!("The Background portal did not allow autostarting.");
+            }
+            Ok(())
+        }
+        1 => Err("The autostart request was denied!".into()),
+        _ => Err(format!("The autostart request failed with code {code}!").into()),
+    }
+}
+
+/*
+ * follows the desktop's preferred color scheme for `theme=auto` in a thread of its own -- the
+ * overlay and the toasts pick it up with their next frame
+ */
+#[cfg(feature = "popup")]
+pub fn watch_color_scheme() {
+    let watcher = thread::Builder::new()
+        .name("color-scheme".to_string())
+        .spawn(|| {
+            if let Err(err) = follow_color_scheme() {
+                println!(
+                    "The desktop's color scheme can't be followed, staying dark! The error: {err}"
+                );
+            }
+        });
+    if let Err(err) = watcher {
+        println!("The color scheme watcher could not be started! The error: {err}");
+    }
+}
+
+#[cfg(feature = "popup")]
+fn follow_color_scheme() -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::session()?;
+    let settings = Proxy::new(
+        &connection,
+        PORTAL_DESTINATION,
+        PORTAL_PATH,
+        SETTINGS_INTERFACE,
+    )?;
+    // subscribed first, so a change right after reading isn't missed
+    let changes = settings.receive_signal("SettingChanged")?;
+
+    // ReadOne is only offered since version 2 of the interface
+    let value: OwnedValue = settings
+        .call("ReadOne", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+        .or_else(|_| settings.call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY)))?;
+    let scheme = color_scheme(&value).ok_or("The portal's color scheme is not a number!")?;
+    println!("The desktop's color scheme is {}.", scheme_name(scheme));
+    palette::set_color_scheme(scheme);
+
+    for change in changes {
+        let (namespace, key, value): (String, String, OwnedValue) = change.body().deserialize()?;
+        if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+            continue;
+        }
+        if let Some(scheme) = color_scheme(&value) {
+            println!(
+                "The desktop's color scheme changed to {}.",
+                scheme_name(scheme)
+            );
+            palette::set_color_scheme(scheme);
+        }
+    }
+    Err("The portal closed the connection!".into())
+}
+
+#[cfg(feature = "popup")]
+fn color_scheme(value: &Value) -> Option<u32> {
+    match value {
+        Value::U32(scheme) => Some(*scheme),
+        // the deprecated Read wraps the value in another variant
+        Value::Value(value) => color_scheme(value),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "popup")]
+fn scheme_name(scheme: u32) -> &'static str {
+    match scheme {
+        1 => "dark",
+        2 => "light",
+        _ => "without a preference",
+    }
+}
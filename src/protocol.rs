@@ -0,0 +1,169 @@
+// the messages exchanged between the helper and the daemon
+//
+// every request is a single datagram "<id> <command> [argument]", responses are sent as
+// "<id> <payload>" -- the id lets the requester match the response to its request. a response that
+// doesn't fit into a chunk is sent in several datagrams, every one but the last is marked with a
+// '+' after the id, e.g. "<id>+ <part>". commands that change the schedule are only answered if
+// they're batched, or if the request has a '!' after its id, e.g. "<id>! skip"
+use std::process;
+
+use crate::duration::{Minutes, Seconds};
+
+// longer requests are refused as a whole instead of being run cut off
+pub const MAX_REQUEST_SIZE: usize = 4096;
+// well below the socket's buffer, which would hold only a few datagrams of the debug dump
+pub const CHUNK_SIZE: usize = 16 * 1024;
+const MORE_CHUNKS: char = '+';
+const ACKNOWLEDGE: char = '!';
+
+/*
+ * a new id for the current process' request
+ */
+pub fn request_id() -> u32 {
+    process::id()
+}
+
+pub fn request(id: u32, command: &str) -> String {
+    format!("{id} {command}")
+}
+
+/*
+ * a request whose commands are answered even if they don't respond on their own, with "ok" or why
+ * they were refused -- the helper waits for it, so scripts learn whether e.g. a skip went through
+ */
+pub fn acknowledged_request(id: u32, command: &str) -> String {
+    format!("{id}{ACKNOWLEDGE} {command}")
+}
+
+/*
+ * whether the message is a request that asks for an acknowledgment
+ */
+pub fn wants_acknowledgment(bytes: &[u8]) -> bool {
+    bytes
+        .split(|byte| *byte == b' ')
+        .next()
+        .is_some_and(|id| id.ends_with(&[ACKNOWLEDGE as u8]))
+}
+
+pub fn response(id: u32, payload: &str) -> String {
+    format!("{id} {payload}")
+}
+
+/*
+ * the datagrams of the response, a single one unless the payload is longer than a chunk
+ */
+pub fn response_chunks(id: u32, payload: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = payload;
+    while rest.len() > CHUNK_SIZE {
+        // the cut must not split a character
+        let mut end = CHUNK_SIZE;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        chunks.push(format!("{id}{MORE_CHUNKS} {}", &rest[..end]));
+        rest = &rest[end..];
+    }
+    chunks.push(response(id, rest));
+    chunks
+}
+
+/*
+ * splits a chunk of a response into its id, whether more chunks follow, and its part of the payload
+ */
+pub fn split_chunk(message: &str) -> Option<(u32, bool, &str)> {
+    let (id, rest) = message.split_once(' ')?;
+    let (id, more) = match id.strip_suffix(MORE_CHUNKS) {
+        Some(id) => (id, true),
+        None => (id, false),
+    };
+    Some((id.parse().ok()?, more, rest))
+}
+
+/*
+ * splits a message into its id and the rest, None if there is no valid id
+ */
+pub fn split_id(message: &str) -> Option<(u32, &str)> {
+    let (id, rest) = message.split_once(' ')?;
+    let id = id.strip_suffix(ACKNOWLEDGE).unwrap_or(id);
+    Some((id.parse().ok()?, rest))
+}
+
+// `set --persist 45` writes the work interval into the user's config file as well
+pub const PERSIST_FLAG: &str = "--persist";
+
+/*
+ * the argument of `set` without the persist flag, and whether it was given
+ */
+pub fn split_persist(argument: &str) -> (bool, &str) {
+    match argument
+        .strip_prefix(PERSIST_FLAG)
+        .and_then(|rest| rest.strip_prefix(' '))
+    {
+        Some(rest) => (true, rest),
+        None => (false, argument),
+    }
+}
+
+// longer durations are refused, the clocks would overflow adding them (e.g. for `timer`)
+pub const MAX_DURATION: Seconds = Seconds::new(30 * 24 * 60 * 60);
+
+/*
+ * the argument of `set`, `break` and `focus`: whole minutes or suffixed with 's' or 'm', e.g. "45",
+ * "90s" or "5m"
+ */
+pub fn parse_duration(argument: &str) -> Option<Seconds> {
+    match argument.strip_suffix('s') {
+        Some(seconds) => parse_number(seconds).map(Seconds::new),
+        None => parse_number(argument.strip_suffix('m').unwrap_or(argument))
+            .and_then(|minutes| Minutes::new(minutes).to_seconds()),
+    }
+    .filter(|seconds| *seconds <= MAX_DURATION)
+}
+
+/*
+ * a duration in seconds or suffixed with 's' or 'm', e.g. for `timer` or the daemon's command line
+ */
+pub fn parse_seconds(argument: &str) -> Option<Seconds> {
+    match argument.strip_suffix('m') {
+        Some(minutes) => {
+            parse_number(minutes).and_then(|minutes| Minutes::new(minutes).to_seconds())
+        }
+        None => parse_number(argument.strip_suffix('s').unwrap_or(argument)).map(Seconds::new),
+    }
+    .filter(|seconds| *seconds <= MAX_DURATION)
+}
+
+fn parse_number(number: &str) -> Option<u64> {
+    // a sign is no duration, even though parse() accepts a leading '+'
+    if !number.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    number.parse::<u64>().ok()
+}
+
+/*
+ * the id and the commands of a received message, or why it can't be handled -- no message, however
+ * malformed, may panic the daemon
+ */
+pub fn parse_request(bytes: &[u8]) -> Result<(u32, Vec<&str>), String> {
+    if bytes.is_empty() {
+        return Err("an empty message".to_string());
+    }
+    let Ok(message) = std::str::from_utf8(bytes) else {
+        return Err("a message that is not valid UTF-8".to_string());
+    };
+    let Some((id, command)) = split_id(message) else {
+        return Err(format!("the message '{message}' without a request id"));
+    };
+    Ok((id, split_batch(command)))
+}
+
+/*
+ * several commands can be sent in a single request, e.g. "skip && set 45"
+ */
+pub const BATCH_SEPARATOR: &str = " && ";
+
+pub fn split_batch(command: &str) -> Vec<&str> {
+    command.split(BATCH_SEPARATOR).map(str::trim).collect()
+}
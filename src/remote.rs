@@ -0,0 +1,162 @@
+// synchronized breaks across machines
+//
+// one daemon is the schedule master and publishes its events over TCP, peers connect to it and
+// follow its breaks and skips by sending the matching commands to their own daemon
+//
+// the token is sent in plain text, so outside of trusted networks the port should only be reached
+// through an SSH tunnel (`ssh -L 7878:localhost:7878 master`)
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use wlbreaktime::{paths, protocol};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+/*
+ * the master's side, peers are accepted and authenticated on a thread of their own and join the
+ * others whenever an event is published
+ */
+pub(crate) struct Master {
+    accepted: Receiver<TcpStream>,
+    peers: Vec<TcpStream>,
+}
+
+impl Master {
+    pub(crate) fn listen(address: &str, token: &str) -> Result<Master, Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind(address)?;
+        println!("Publishing the schedule to peers on {address}.");
+        let (sender, accepted) = mpsc::channel();
+        let token = token.to_string();
+        // a peer that is slow to send its token must not hold up the schedule
+        thread::spawn(move || accept_peers(&listener, &token, &sender));
+        Ok(Master {
+            accepted,
+            peers: Vec::new(),
+        })
+    }
+
+    pub(crate) fn publish(&mut self, event: &str) {
+        self.peers.extend(self.accepted.try_iter());
+
+        let line = format!("{event}\n");
+        self.peers
+            .retain_mut(|peer| match peer.write_all(line.as_bytes()) {
+                Ok(_) => true,
+                Err(err) => {
+                    println!("[remote]: Dropping a peer: {err}");
+                    false
+                }
+            });
+    }
+}
+
+fn accept_peers(listener: &TcpListener, token: &str, sender: &Sender<TcpStream>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("[remote]: Unable to accept a peer! The error: {err}");
+                continue;
+            }
+        };
+        let address = stream
+            .peer_addr()
+            .map_or("?".to_string(), |address| address.to_string());
+        match authenticate(stream, token) {
+            Ok(stream) => {
+                println!("[remote]: The peer {address} connected.");
+                // the daemon is gone
+                if sender.send(stream).is_err() {
+                    return;
+                }
+            }
+            Err(err) => println!("[remote]: Rejected the peer {address}: {err}"),
+        }
+    }
+}
+
+/*
+ * the first line a peer sends is its token, it's read no further than the token's length
+ */
+fn authenticate(stream: TcpStream, token: &str) -> Result<TcpStream, Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    // a stalled peer must not hold up the schedule
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut line = Vec::new();
+    // the token and its line ending
+    let limit = token.len() as u64 + 2;
+    BufReader::new((&stream).take(limit)).read_until(b'\n', &mut line)?;
+    let sent = line.strip_suffix(b"\n").unwrap_or(&line);
+    let sent = sent.strip_suffix(b"\r").unwrap_or(sent);
+    if !same_token(sent, token.as_bytes()) {
+        return Err("wrong token".into());
+    }
+    Ok(stream)
+}
+
+/*
+ * compares every byte whatever the first difference, so the time taken doesn't tell how much of a
+ * guessed token was right
+ */
+fn same_token(sent: &[u8], token: &[u8]) -> bool {
+    sent.len() == token.len()
+        && sent
+            .iter()
+            .zip(token)
+            .fold(0, |difference, (sent, token)| difference | (sent ^ token))
+            == 0
+}
+
+/*
+ * connects to the master in the background and forwards its breaks and skips to this daemon
+ */
+pub(crate) fn follow(address: String, token: String, daemon_path: PathBuf) {
+    thread::spawn(move || {
+        loop {
+            if let Err(err) = follow_master(&address, &token, &daemon_path) {
+                println!("[remote]: Lost the connection to the master {address}: {err}");
+            }
+            thread::sleep(RECONNECT_INTERVAL);
+        }
+    });
+}
+
+fn follow_master(
+    address: &str,
+    token: &str,
+    daemon_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.write_all(format!("{token}\n").as_bytes())?;
+    println!("[remote]: Following the master {address}.");
+
+    let socket_path = paths::remote_socket_path()?;
+    // a previous instance might have left its socket behind
+    let _ = fs::remove_file(&socket_path);
+    let socket = UnixDatagram::bind(&socket_path)?;
+
+    for event in BufReader::new(stream).lines() {
+        // breaks end on their own, so only their start and skips are followed
+        let event = event?;
+        // the master's break reason is dropped, here the break counts as manual
+        let command = match event.split(' ').next().unwrap_or_default() {
+            "break" => "break",
+            "skip" => "skip",
+            _ => continue,
+        };
+        let request = protocol::request(protocol::request_id(), command);
+        socket.send_to(request.as_bytes(), daemon_path)?;
+    }
+
+    fs::remove_file(&socket_path)?;
+    Err("the master closed the connection".into())
+}
@@ -0,0 +1,380 @@
+use font8x8::{BASIC_FONTS, LATIN_FONTS, UnicodeFonts};
+use wayland_client::protocol::{wl_output::Transform, wl_shm::Format};
+
+use crate::fonts::{self, Placed};
+
+// every glyph of the built-in font is 8x8 pixels and gets scaled by an integer factor, the text of
+// other fonts fills lines of the same height
+pub(crate) const GLYPH_SIZE: u32 = 8;
+// the edge length of the squares that transformed regions are copied in
+const TILE_SIZE: usize = 64;
+
+/*
+ * a rectangle on the canvas or in the buffer, in pixels
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Rect {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl Rect {
+    pub(crate) fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+/*
+ * an in-memory ARGB image that is copied into the shm pool once it's done
+ */
+#[derive(Debug, Clone)]
+pub(crate) struct Canvas {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pixels: Vec<u32>,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: u32, height: u32, color: u32) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![color; (width * height) as usize],
+        }
+    }
+
+    pub(crate) fn fill(&mut self, color: u32) {
+        self.pixels.fill(color);
+    }
+
+    pub(crate) fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: u32) {
+        for row in y..(y + height).min(self.height) {
+            let start = (row * self.width + x.min(self.width)) as usize;
+            let end = (row * self.width + (x + width).min(self.width)) as usize;
+            self.pixels[start..end].fill(color);
+        }
+    }
+
+    pub(crate) fn text_width(text: &str, scale: u32) -> u32 {
+        fonts::width(text, GLYPH_SIZE * scale)
+    }
+
+    pub(crate) fn draw_text(&mut self, x: u32, y: u32, scale: u32, color: u32, text: &str) {
+        fonts::shape(
+            text,
+            GLYPH_SIZE * scale,
+            |pen, offset, placed| match placed {
+                Placed::Builtin(character) => {
+                    let glyph = builtin_glyph(character).unwrap_or(BASIC_FONTS.get('?').unwrap());
+                    for (row, bits) in glyph.iter().enumerate() {
+                        for column in 0..GLYPH_SIZE {
+                            // the lowest bit is the leftmost pixel
+                            if bits & (1 << column) != 0 {
+                                self.fill_rect(
+                                    x + pen + column * scale,
+                                    y + row as u32 * scale,
+                                    scale,
+                                    scale,
+                                    color,
+                                );
+                            }
+                        }
+                    }
+                }
+                Placed::Outline(glyph) => {
+                    let left = (x + pen) as i32 + glyph.left;
+                    let top = y as i32 + offset + glyph.top;
+                    for row in 0..glyph.height {
+                        for column in 0..glyph.width {
+                            let coverage = glyph.coverage[(row * glyph.width + column) as usize];
+                            self.blend(left + column as i32, top + row as i32, color, coverage);
+                        }
+                    }
+                }
+            },
+        );
+    }
+
+    /*
+     * mixes the color into the pixel by the coverage, pixels outside of the canvas are skipped
+     */
+    fn blend(&mut self, x: i32, y: i32, color: u32, coverage: u8) {
+        if coverage == 0 || x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        let target = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        let coverage = coverage as u32;
+        let mix = |shift: u32| {
+            let source = (color >> shift) & 0xFF;
+            let destination = (*target >> shift) & 0xFF;
+            ((source * coverage + destination * (255 - coverage)) / 255) << shift
+        };
+        *target = 0xFF000000 | mix(16) | mix(8) | mix(0);
+    }
+
+    /*
+     * darkens every pixel, a factor of 0 results in black and 1 keeps the colors
+     */
+    pub(crate) fn dim(&mut self, factor: f32) {
+        let factor = (factor.clamp(0.0, 1.0) * 255.0) as u32;
+        for pixel in &mut self.pixels {
+            let scale = |shift: u32| (((*pixel >> shift) & 0xFF) * factor / 255) << shift;
+            *pixel = 0xFF000000 | scale(16) | scale(8) | scale(0);
+        }
+    }
+
+    /*
+     * makes every pixel translucent, an opacity of 0 results in a fully transparent canvas -- the
+     * colors are premultiplied, like wl_shm expects them
+     */
+    pub(crate) fn fade(&mut self, opacity: f32) {
+        let factor = (opacity.clamp(0.0, 1.0) * 255.0) as u32;
+        for pixel in &mut self.pixels {
+            let scale = |shift: u32| (((*pixel >> shift) & 0xFF) * factor / 255) << shift;
+            *pixel = scale(24) | scale(16) | scale(8) | scale(0);
+        }
+    }
+
+    /*
+     * composites premultiplied RGBA pixels (as produced by tiny-skia) onto the canvas
+     */
+    pub(crate) fn draw_rgba(&mut self, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        for row in 0..height.min(self.height.saturating_sub(y)) {
+            for column in 0..width.min(self.width.saturating_sub(x)) {
+                let source = ((row * width + column) * 4) as usize;
+                let [red, green, blue, alpha] = [
+                    data[source] as u32,
+                    data[source + 1] as u32,
+                    data[source + 2] as u32,
+                    data[source + 3] as u32,
+                ];
+                if alpha == 0 {
+                    continue;
+                }
+
+                let target = &mut self.pixels[((y + row) * self.width + x + column) as usize];
+                let blend = |source: u32, shift: u32| {
+                    let destination = (*target >> shift) & 0xFF;
+                    (source + destination * (255 - alpha) / 255).min(255) << shift
+                };
+                *target = 0xFF000000 | blend(red, 16) | blend(green, 8) | blend(blue, 0);
+            }
+        }
+    }
+
+    /*
+     * draws RGBA pixels in a single color, their alpha channel is the coverage -- e.g. for a
+     * monochrome icon in the text's color
+     */
+    pub(crate) fn draw_mask(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        color: u32,
+    ) {
+        for row in 0..height {
+            for column in 0..width {
+                let alpha = data[((row * width + column) * 4 + 3) as usize];
+                self.blend((x + column) as i32, (y + row) as i32, color, alpha);
+            }
+        }
+    }
+
+    pub(crate) fn full_rect(&self) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub(crate) fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /*
+     * the smallest rectangle containing every pixel that differs from the previous frame, None if
+     * nothing changed -- without a previous frame of the same size, everything changed
+     */
+    pub(crate) fn changed_rect(&self, previous: &[u32]) -> Option<Rect> {
+        if previous.len() != self.pixels.len() {
+            return Some(self.full_rect());
+        }
+
+        let width = self.width as usize;
+        let mut changed: Option<Rect> = None;
+        for (y, (row, previous_row)) in self
+            .pixels
+            .chunks(width)
+            .zip(previous.chunks(width))
+            .enumerate()
+        {
+            if row == previous_row {
+                continue;
+            }
+            let differs = |(pixel, previous): (&u32, &u32)| pixel != previous;
+            let left = row.iter().zip(previous_row).position(differs).unwrap_or(0);
+            let right = row.iter().zip(previous_row).rposition(differs).unwrap_or(0);
+            let row_rect = Rect {
+                x: left as u32,
+                y: y as u32,
+                width: (right - left + 1) as u32,
+                height: 1,
+            };
+            changed = Some(changed.map_or(row_rect, |changed| changed.union(row_rect)));
+        }
+        changed
+    }
+
+    /*
+     * returns the rectangle's pixels in the memory layout of the given format, transformed like the
+     * output the buffer is shown on, along with where they belong in the buffer (the buffer's width
+     * and height are swapped for rotations by 90 and 270 degrees)
+     */
+    pub(crate) fn region_bytes(
+        &self,
+        format: Format,
+        transform: Transform,
+        rect: Rect,
+    ) -> (Rect, Vec<u8>) {
+        let (width, height) = (rect.width as usize, rect.height as usize);
+        let convert = |pixel: u32| match format {
+            // swapping red and blue
+            Format::Xbgr8888 | Format::Abgr8888 => {
+                (pixel & 0xFF00FF00) | ((pixel & 0xFF) << 16) | ((pixel >> 16) & 0xFF)
+            }
+            _ => pixel,
+        };
+
+        let mut bytes = Vec::with_capacity(width * height * 4);
+        let row_start = |y: usize| (rect.y as usize + y) * self.width as usize + rect.x as usize;
+        if transform == Transform::Normal {
+            // the rows are copied as they are, which is what most outputs need
+            for y in 0..height {
+                let row = &self.pixels[row_start(y)..row_start(y) + width];
+                bytes.extend(row.iter().flat_map(|pixel| convert(*pixel).to_le_bytes()));
+            }
+        } else {
+            let (region_width, region_height) = if swaps_axes(transform) {
+                (height, width)
+            } else {
+                (width, height)
+            };
+            bytes.resize(width * height * 4, 0);
+            // going through the region in tiles, so reading the canvas doesn't jump through
+            // memory for every pixel
+            for tile_y in (0..region_height).step_by(TILE_SIZE) {
+                for tile_x in (0..region_width).step_by(TILE_SIZE) {
+                    for region_y in tile_y..(tile_y + TILE_SIZE).min(region_height) {
+                        for region_x in tile_x..(tile_x + TILE_SIZE).min(region_width) {
+                            let (x, y) = source_point(transform, region_x, region_y, width, height);
+                            let offset = (region_y * region_width + region_x) * 4;
+                            bytes[offset..offset + 4].copy_from_slice(
+                                &convert(self.pixels[row_start(y) + x]).to_le_bytes(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        (self.buffer_rect(transform, rect), bytes)
+    }
+
+    /*
+     * where a rectangle of the canvas ends up in the transformed buffer
+     */
+    pub(crate) fn buffer_rect(&self, transform: Transform, rect: Rect) -> Rect {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (x0, y0) = transform_point(transform, rect.x as usize, rect.y as usize, width, height);
+        let (x1, y1) = transform_point(
+            transform,
+            (rect.x + rect.width - 1) as usize,
+            (rect.y + rect.height - 1) as usize,
+            width,
+            height,
+        );
+        Rect {
+            x: x0.min(x1) as u32,
+            y: y0.min(y1) as u32,
+            width: x0.abs_diff(x1) as u32 + 1,
+            height: y0.abs_diff(y1) as u32 + 1,
+        }
+    }
+}
+
+/*
+ * maps a pixel of an image with the given size into its transformed layout -- flipped transforms
+ * mirror the image around its vertical axis first, the rotations are counter-clockwise
+ */
+fn transform_point(
+    transform: Transform,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> (usize, usize) {
+    let flipped = matches!(
+        transform,
+        Transform::Flipped | Transform::Flipped90 | Transform::Flipped180 | Transform::Flipped270
+    );
+    let x = if flipped { width - 1 - x } else { x };
+    match transform {
+        Transform::_90 | Transform::Flipped90 => (y, width - 1 - x),
+        Transform::_180 | Transform::Flipped180 => (width - 1 - x, height - 1 - y),
+        Transform::_270 | Transform::Flipped270 => (height - 1 - y, x),
+        _ => (x, y),
+    }
+}
+
+/*
+ * the inverse of transform_point, which pixel of the image ends up at the given position of its
+ * transformed layout
+ */
+fn source_point(
+    transform: Transform,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> (usize, usize) {
+    let (x, y) = match transform {
+        Transform::_90 | Transform::Flipped90 => (width - 1 - y, x),
+        Transform::_180 | Transform::Flipped180 => (width - 1 - x, height - 1 - y),
+        Transform::_270 | Transform::Flipped270 => (y, height - 1 - x),
+        _ => (x, y),
+    };
+    let flipped = matches!(
+        transform,
+        Transform::Flipped | Transform::Flipped90 | Transform::Flipped180 | Transform::Flipped270
+    );
+    (if flipped { width - 1 - x } else { x }, y)
+}
+
+pub(crate) fn swaps_axes(transform: Transform) -> bool {
+    matches!(
+        transform,
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270
+    )
+}
+
+/*
+ * the glyph of the built-in font, None for characters it doesn't have
+ */
+pub(crate) fn builtin_glyph(character: char) -> Option<[u8; 8]> {
+    BASIC_FONTS
+        .get(character)
+        .or_else(|| LATIN_FONTS.get(character))
+}
@@ -0,0 +1,321 @@
+// assets that are decoded once and shared by all breaks: the sounds and the overlay's icon
+//
+// like the capabilities, the cache is global, so the audio output and the layout of every break
+// can use it -- `diag` reports its size and how often it saved decoding an asset again
+use std::sync::Mutex;
+#[cfg(any(feature = "audio", feature = "popup"))]
+use std::{fs, sync::Arc};
+#[cfg(feature = "audio")]
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "popup")]
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{Options, Tree},
+};
+#[cfg(feature = "audio")]
+use rodio::{Decoder, Source};
+
+use crate::config::Config;
+
+#[cfg(feature = "audio")]
+const SOUND: &[u8] = include_bytes!("../resources/rebana_l_gong.wav");
+// the files of a sound theme, e.g. break_start.ogg
+#[cfg(feature = "audio")]
+pub(crate) const THEME_SOUNDS: [&str; 4] = ["pre_break", "break_start", "break_end", "tick"];
+#[cfg(feature = "popup")]
+const DEFAULT_ICON: &[u8] = include_bytes!("../resources/coffee.svg");
+
+static CACHE: Mutex<ResourceCache> = Mutex::new(ResourceCache {
+    #[cfg(feature = "audio")]
+    sounds: Vec::new(),
+    #[cfg(feature = "popup")]
+    icons: Vec::new(),
+    hits: 0,
+    misses: 0,
+});
+
+struct ResourceCache {
+    // keyed by the file, the bundled gong has none
+    #[cfg(feature = "audio")]
+    sounds: Vec<(Option<PathBuf>, Sound)>,
+    #[cfg(feature = "popup")]
+    icons: Vec<Icon>,
+    // how often an asset was taken from the cache and how often it had to be decoded
+    hits: u64,
+    misses: u64,
+}
+
+impl ResourceCache {
+    /*
+     * the number of entries and their decoded size in bytes -- parsed icons are counted without a
+     * size, they are small compared to their rasterizations
+     */
+    #[cfg_attr(not(all(feature = "audio", feature = "popup")), allow(unused_mut))]
+    fn size(&self) -> (usize, usize) {
+        let (mut entries, mut bytes) = (0, 0);
+        #[cfg(feature = "audio")]
+        for (_, sound) in &self.sounds {
+            entries += 1;
+            bytes += sound.samples.len() * size_of::<f32>();
+        }
+        #[cfg(feature = "popup")]
+        for icon in &self.icons {
+            entries += 1;
+            if let Some((_, pixmap)) = &icon.rasterized {
+                entries += 1;
+                bytes += pixmap.data().len();
+            }
+        }
+        (entries, bytes)
+    }
+}
+
+/*
+ * an icon and its last rasterization, keyed by the configured path -- the bundled icon has none
+ */
+#[cfg(feature = "popup")]
+struct Icon {
+    path: Option<String>,
+    tree: Arc<Tree>,
+    rasterized: Option<(u32, Arc<Pixmap>)>,
+}
+
+/*
+ * decodes what the configuration needs at startup, so the first break doesn't have to
+ */
+#[cfg_attr(
+    not(any(feature = "audio", feature = "popup")),
+    allow(unused_variables)
+)]
+pub(crate) fn preload(config: &Config) {
+    #[cfg(feature = "audio")]
+    if config.play_sound {
+        let theme = config.sound_theme.as_deref().map(Path::new);
+        if let Some(theme) = theme
+            && let Err(err) = fs::read_dir(theme)
+        {
+            println!(
+                "The sound theme '{}' could not be read, the built-in sounds are played! The error: {err}",
+                theme.display()
+            );
+        }
+        let files = theme
+            .into_iter()
+            .flat_map(|theme| THEME_SOUNDS.map(|name| theme_file(theme, name)))
+            .flatten();
+        for path in files.map(Some).chain([None]) {
+            if let Err(err) = sound(path.as_deref()) {
+                let name = path.as_deref().unwrap_or(Path::new("gong")).display();
+                println!("The sound '{name}' could not be decoded! The error: {err}");
+            }
+        }
+    }
+    #[cfg(feature = "popup")]
+    if config.show_popup {
+        icon(&config.popup_icon);
+    }
+}
+
+/*
+ * the cache as "entries, size, hits, misses", reported by `diag`
+ */
+pub(crate) fn report() -> String {
+    let cache = CACHE.lock().unwrap();
+    let (entries, bytes) = cache.size();
+    format!(
+        "{entries} entries, {} KiB, {} hits, {} misses",
+        bytes / 1024,
+        cache.hits,
+        cache.misses
+    )
+}
+
+/*
+ * a decoded sound, every playback gets its own position in the shared samples
+ */
+#[cfg(feature = "audio")]
+#[derive(Clone)]
+pub(crate) struct Sound {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+    position: usize,
+}
+
+#[cfg(feature = "audio")]
+impl Iterator for Sound {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Source for Sound {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f64(
+            self.samples.len() as f64 / self.channels as f64 / self.sample_rate as f64,
+        ))
+    }
+}
+
+/*
+ * the sound in the file, without one the bundled gong -- sounds that can't be decoded are not
+ * cached, so they are tried again the next time
+ */
+#[cfg(feature = "audio")]
+pub(crate) fn sound(path: Option<&Path>) -> Result<Sound, Box<dyn std::error::Error>> {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((_, sound)) = cache
+        .sounds
+        .iter()
+        .find(|(cached, _)| cached.as_deref() == path)
+    {
+        let sound = sound.clone();
+        cache.hits += 1;
+        return Ok(sound);
+    }
+
+    let sound = match path {
+        Some(path) => decode(Decoder::new(BufReader::new(File::open(path)?))?),
+        None => decode(Decoder::new(Cursor::new(SOUND))?),
+    };
+    cache.misses += 1;
+    cache
+        .sounds
+        .push((path.map(Path::to_path_buf), sound.clone()));
+    Ok(sound)
+}
+
+#[cfg(feature = "audio")]
+fn decode<R: Read + Seek + Send + Sync + 'static>(decoder: Decoder<R>) -> Sound {
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    Sound {
+        samples: decoder.convert_samples::<f32>().collect(),
+        channels,
+        sample_rate,
+        position: 0,
+    }
+}
+
+/*
+ * the file of the theme with the name, whatever its extension -- None if the theme has none or it
+ * can't be read, which is reported at startup
+ */
+#[cfg(feature = "audio")]
+pub(crate) fn theme_file(theme: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(theme)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.file_stem().is_some_and(|stem| stem == name))
+}
+
+/*
+ * an empty path disables the icon, without a path the bundled coffee cup is used -- icons that
+ * can't be loaded are not cached, so they are tried again for the next break
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn icon(path: &Option<String>) -> Option<Arc<Tree>> {
+    if path.as_deref() == Some("") {
+        return None;
+    }
+
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(icon) = cache.icons.iter().find(|icon| icon.path == *path) {
+        let tree = Arc::clone(&icon.tree);
+        cache.hits += 1;
+        return Some(tree);
+    }
+
+    let tree = Arc::new(load_icon(path)?);
+    cache.misses += 1;
+    cache.icons.push(Icon {
+        path: path.clone(),
+        tree: Arc::clone(&tree),
+        rasterized: None,
+    });
+    Some(tree)
+}
+
+/*
+ * the icon rendered into a square pixmap of the given size, keeping its aspect ratio
+ */
+#[cfg(feature = "popup")]
+pub(crate) fn rasterized_icon(tree: &Arc<Tree>, size: u32) -> Option<Arc<Pixmap>> {
+    let mut cache = CACHE.lock().unwrap();
+    let cache = &mut *cache;
+    let icon = cache
+        .icons
+        .iter_mut()
+        .find(|icon| Arc::ptr_eq(&icon.tree, tree));
+    if let Some(icon) = &icon
+        && let Some((cached, pixmap)) = &icon.rasterized
+        && *cached == size
+    {
+        cache.hits += 1;
+        return Some(Arc::clone(pixmap));
+    }
+
+    let mut pixmap = Pixmap::new(size, size)?;
+    let icon_size = tree.size();
+    let scale = size as f32 / icon_size.width().max(icon_size.height());
+    let offset_x = (size as f32 - icon_size.width() * scale) / 2.0;
+    let offset_y = (size as f32 - icon_size.height() * scale) / 2.0;
+    resvg::render(
+        tree,
+        Transform::from_scale(scale, scale).post_translate(offset_x, offset_y),
+        &mut pixmap.as_mut(),
+    );
+
+    let pixmap = Arc::new(pixmap);
+    cache.misses += 1;
+    if let Some(icon) = icon {
+        icon.rasterized = Some((size, Arc::clone(&pixmap)));
+    }
+    Some(pixmap)
+}
+
+#[cfg(feature = "popup")]
+fn load_icon(path: &Option<String>) -> Option<Tree> {
+    let data = match path {
+        Some(path) => match fs::read(path) {
+            Ok(data) => data,
+            Err(err) => {
+                println!("The icon '{path}' could not be read! The error: {err}");
+                return None;
+            }
+        },
+        None => DEFAULT_ICON.to_vec(),
+    };
+
+    match Tree::from_data(&data, &Options::default()) {
+        Ok(tree) => Some(tree),
+        Err(err) => {
+            println!("The icon could not be parsed! The error: {err}");
+            None
+        }
+    }
+}
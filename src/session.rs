@@ -0,0 +1,273 @@
+// the daemon's half of the system mode on multi-user machines, e.g. in a computer lab
+//
+// `wlbreaktime-helper session` connects to the system socket, which starts one instance of the
+// session template as root (Accept=yes). the user is read from the peer credentials of that
+// connection, their runtime directory follows from the uid and the Wayland display from the
+// helper's environment. the daemon takes these over and drops its privileges before anything else
+// is set up -- from then on it's the user's own daemon, binding its sockets in their runtime
+// directory like without systemd
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs::{self, File},
+    io::{self, Read, Write},
+    mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, IntoRawFd},
+        unix::{
+            ffi::OsStrExt,
+            fs::{FileTypeExt, MetadataExt},
+            net::{UnixDatagram, UnixStream},
+        },
+    },
+    path::Path,
+    process, ptr, thread,
+    time::Duration,
+};
+
+use libsystemd::activation;
+use wlbreaktime::paths::{self, USER_RUNTIME_DIR_PREFIX};
+
+// passed on from the helper's environment, besides the Wayland display: the compositor's IPC and
+// the session bus are needed for the keybinds, the notifications and the portal
+const PASSED_VARIABLES: [&str; 7] = [
+    "DBUS_SESSION_BUS_ADDRESS",
+    "XDG_CONFIG_HOME",
+    "XDG_CURRENT_DESKTOP",
+    "XDG_SESSION_TYPE",
+    "NIRI_SOCKET",
+    "SWAYSOCK",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+];
+// the config's overrides through the environment are passed on as well
+const CONFIG_VARIABLE_PREFIX: &str = "WLBREAKTIME_";
+// logind removes the runtime directory once the user's last session ended
+const LOGOUT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// who connected to the session socket
+struct Peer {
+    uid: libc::uid_t,
+    pid: libc::pid_t,
+}
+
+struct User {
+    name: CString,
+    gid: libc::gid_t,
+    home: String,
+}
+
+/*
+ * takes over the session of the user that connected to the session socket, false if their daemon
+ * is running already -- must be called before any other thread is started, it changes the
+ * environment
+ */
+pub(crate) fn attach() -> Result<bool, Box<dyn std::error::Error>> {
+    let mut descriptors = activation::receive_descriptors(true)?;
+    let connection = match (descriptors.pop(), descriptors.is_empty()) {
+        (Some(fd), true) => unsafe { UnixStream::from_raw_fd(fd.into_raw_fd()) },
+        _ => {
+            return Err(
+                "Expected a single connection from systemd, is the session socket configured with Accept=yes?"
+                    .into(),
+            );
+        }
+    };
+    let result = take_over(&connection);
+    let reply = match &result {
+        Ok(Some(display)) => format!("Started the daemon on '{display}'."),
+        Ok(None) => "The daemon of this session is running already.".to_string(),
+        Err(err) => format!("The daemon could not be started: {err}"),
+    };
+    println!("{reply}");
+    // the helper might have given up waiting already
+    let _ = (&connection).write_all((reply + "\n").as_bytes());
+    Ok(result?.is_some())
+}
+
+/*
+ * the Wayland display the daemon was started on, None if the session has its daemon already
+ */
+fn take_over(connection: &UnixStream) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let peer = peer_credentials(connection)?;
+    if peer.uid == 0 {
+        return Err("sessions of root are not supported".into());
+    }
+    let user = user(peer.uid)?;
+    let runtime_dir = format!("{USER_RUNTIME_DIR_PREFIX}{}", peer.uid);
+    if !Path::new(&runtime_dir).is_dir() {
+        return Err(format!("the runtime directory '{runtime_dir}' does not exist").into());
+    }
+    // read while still root, the helper might have exited already and its entry is gone then
+    let environment = peer_environment(&peer);
+    let display = match environment.iter().find(|(key, _)| key == "WAYLAND_DISPLAY") {
+        Some((_, display)) => display.clone(),
+        None => wayland_display(&runtime_dir)
+            .ok_or("no Wayland display was found in the session's runtime directory")?,
+    };
+
+    drop_privileges(peer.uid, &user)?;
+
+    // SAFETY: the daemon has not started any other thread yet
+    unsafe {
+        env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+        env::set_var("WAYLAND_DISPLAY", &display);
+        env::set_var("HOME", &user.home);
+        env::set_var("USER", user.name.to_string_lossy().as_ref());
+        env::set_var("LOGNAME", user.name.to_string_lossy().as_ref());
+        env::set_var(
+            "DBUS_SESSION_BUS_ADDRESS",
+            format!("unix:path={runtime_dir}/bus"),
+        );
+        for (key, value) in &environment {
+            if PASSED_VARIABLES.contains(&key.as_str()) || key.starts_with(CONFIG_VARIABLE_PREFIX) {
+                env::set_var(key, value);
+            }
+        }
+    }
+
+    // a stale socket file refuses the connection, a running daemon accepts it
+    let running = UnixDatagram::unbound()?
+        .connect(paths::daemon_socket_path()?)
+        .is_ok();
+    if running {
+        return Ok(None);
+    }
+
+    println!(
+        "Serving the session of '{}' on '{display}'.",
+        user.name.to_string_lossy()
+    );
+    thread::Builder::new()
+        .name("session".to_string())
+        .spawn(move || {
+            while Path::new(&runtime_dir).exists() {
+                thread::sleep(LOGOUT_CHECK_INTERVAL);
+            }
+            println!("The user's last session ended, quitting.");
+            process::exit(0);
+        })?;
+    Ok(Some(display))
+}
+
+fn peer_credentials(connection: &UnixStream) -> io::Result<Peer> {
+    let mut credentials: libc::ucred = unsafe { mem::zeroed() };
+    let mut length = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            connection.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&raw mut credentials).cast(),
+            &mut length,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Peer {
+        uid: credentials.uid,
+        pid: credentials.pid,
+    })
+}
+
+fn user(uid: libc::uid_t) -> io::Result<User> {
+    let mut passwd: libc::passwd = unsafe { mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut result = ptr::null_mut();
+    let err = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if result.is_null() {
+        return Err(if err == 0 {
+            io::Error::other(format!("there is no user with the id {uid}"))
+        } else {
+            io::Error::from_raw_os_error(err)
+        });
+    }
+    let (name, home) = unsafe {
+        (
+            CStr::from_ptr(passwd.pw_name).to_owned(),
+            CStr::from_ptr(passwd.pw_dir).to_string_lossy().into_owned(),
+        )
+    };
+    Ok(User {
+        name,
+        gid: passwd.pw_gid,
+        home,
+    })
+}
+
+/*
+ * the groups first, they can't be changed anymore once the uid is the user's
+ */
+fn drop_privileges(uid: libc::uid_t, user: &User) -> io::Result<()> {
+    let check = |result: libc::c_int| {
+        if result == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    };
+    check(unsafe { libc::initgroups(user.name.as_ptr(), user.gid) })?;
+    check(unsafe { libc::setgid(user.gid) })?;
+    check(unsafe { libc::setuid(uid) })
+}
+
+/*
+ * the environment of the connecting process, variables that are not valid UTF-8 are skipped
+ *
+ * it's read as root, so the process has to belong to the peer -- the helper might have exited and
+ * its pid been taken by another user's process. the check and the read use the same open file,
+ * which stays bound to the process it was opened for
+ */
+fn peer_environment(peer: &Peer) -> Vec<(String, String)> {
+    let Ok(mut file) = File::open(format!("/proc/{}/environ", peer.pid)) else {
+        return Vec::new();
+    };
+    match file.metadata() {
+        Ok(metadata) if metadata.uid() == peer.uid => {}
+        _ => {
+            println!(
+                "The process {} does not belong to the user {}, its environment is not used.",
+                peer.pid, peer.uid
+            );
+            return Vec::new();
+        }
+    }
+    let mut environ = Vec::new();
+    if file.read_to_end(&mut environ).is_err() {
+        return Vec::new();
+    }
+    environ
+        .split(|byte| *byte == 0)
+        .filter_map(|variable| {
+            let (key, value) = std::str::from_utf8(variable).ok()?.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/*
+ * the first Wayland socket in the runtime directory, e.g. "wayland-1"
+ */
+pub(crate) fn wayland_display(runtime_dir: &str) -> Option<String> {
+    let mut displays: Vec<String> = fs::read_dir(runtime_dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_socket()))
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.as_bytes()
+                .starts_with(b"wayland-")
+                .then(|| name.to_string_lossy().into_owned())
+        })
+        .collect();
+    displays.sort();
+    displays.into_iter().next()
+}
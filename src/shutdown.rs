@@ -0,0 +1,172 @@
+// the `quit` and `restart` commands, for running outside of systemd
+//
+// both save the schedule to the runtime directory first, so a restarted daemon continues where the
+// previous one stopped, and are only accepted from the daemon's own user
+use std::{
+    env, fs, io,
+    os::{
+        fd::RawFd,
+        unix::{fs::MetadataExt, process::CommandExt},
+    },
+    path::Path,
+    process::{self, Command},
+    sync::Mutex,
+};
+
+use wlbreaktime::{duration::Seconds, paths};
+
+use crate::session;
+
+// the first descriptor systemd passes, SD_LISTEN_FDS_START
+const LISTEN_FDS_START: RawFd = 3;
+
+// set by a command during a break, the daemon stops once the break is cleaned up
+static REQUESTED: Mutex<Option<Shutdown>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Shutdown {
+    Quit,
+    Restart,
+}
+
+impl Shutdown {
+    pub(crate) fn from_command(command: &str) -> Option<Shutdown> {
+        match command {
+            "quit" => Some(Shutdown::Quit),
+            "restart" => Some(Shutdown::Restart),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * what is carried over to the next start
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct State {
+    // until the next break
+    pub(crate) remaining: Seconds,
+    pub(crate) consecutive_skips: u64,
+}
+
+pub(crate) fn request(shutdown: Shutdown) {
+    *REQUESTED.lock().unwrap() = Some(shutdown);
+}
+
+pub(crate) fn requested() -> Option<Shutdown> {
+    *REQUESTED.lock().unwrap()
+}
+
+/*
+ * the sender's user, passed along by the kernel with the datagram, has to match the user the daemon
+ * runs as -- without credentials the command is refused
+ */
+pub(crate) fn authorized(uid: Option<u32>) -> bool {
+    match (uid, fs::metadata("/proc/self")) {
+        (Some(uid), Ok(daemon)) => uid == daemon.uid(),
+        _ => false,
+    }
+}
+
+/*
+ * saves the state and either returns, so the daemon can exit, or replaces the daemon with a new
+ * instance of itself
+ */
+pub(crate) fn execute(shutdown: Shutdown, state: State) -> Result<(), Box<dyn std::error::Error>> {
+    let path = paths::state_file_path()?;
+    fs::write(
+        &path,
+        format!(
+            "remaining={}\nconsecutive_skips={}\n",
+            state.remaining.as_secs(),
+            state.consecutive_skips
+        ),
+    )?;
+
+    // e.g. the chime at the end of a break would be cut off by exiting
+    #[cfg(feature = "audio")]
+    crate::audio::wait_until_played(crate::audio::PLAYED_TIMEOUT);
+
+    match shutdown {
+        Shutdown::Quit => {
+            println!("Quitting, the state was saved to {path}.");
+            Ok(())
+        }
+        Shutdown::Restart => {
+            println!("Restarting, the state was saved to {path}.");
+            let mut command = Command::new(env::current_exe()?);
+            command.args(env::args_os().skip(1));
+            // started by systemd, the new instance takes over its sockets instead of binding paths
+            // that belong to the socket units -- it keeps the pid, so it's still the service
+            if let Some(count) = passed_sockets() {
+                for fd in LISTEN_FDS_START..LISTEN_FDS_START + count {
+                    keep_across_exec(fd)?;
+                }
+                command.env("LISTEN_PID", process::id().to_string());
+            }
+            // e.g. the compositor of a new session
+            if let Some(display) = moved_wayland_display() {
+                println!("The Wayland display moved, restarting on {display}.");
+                command.env("WAYLAND_DISPLAY", display);
+            }
+            let err = command.exec();
+            Err(format!("The daemon could not be restarted! The error: {err}").into())
+        }
+    }
+}
+
+/*
+ * the number of sockets systemd passed to the daemon, None if it wasn't socket activated
+ */
+fn passed_sockets() -> Option<RawFd> {
+    env::var("LISTEN_FDS").ok()?.parse().ok()
+}
+
+/*
+ * clears FD_CLOEXEC, so the descriptor stays open in the instance the daemon is replaced with
+ */
+fn keep_across_exec(fd: RawFd) -> io::Result<()> {
+    // SAFETY: only the descriptor's flags are read and changed, it's not closed
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/*
+ * the first Wayland socket in the runtime directory if the daemon's one is gone, None while it's
+ * still there
+ */
+fn moved_wayland_display() -> Option<String> {
+    let runtime_dir = paths::runtime_dir().ok()?;
+    if let Some(display) = env::var_os("WAYLAND_DISPLAY")
+        && Path::new(&runtime_dir).join(display).exists()
+    {
+        return None;
+    }
+    session::wayland_display(&runtime_dir)
+}
+
+/*
+ * the state saved by the previous instance, the file is removed so it's only used once
+ */
+pub(crate) fn load_state() -> Option<State> {
+    let path = paths::state_file_path().ok()?;
+    let content = fs::read_to_string(&path).ok()?;
+    if let Err(err) = fs::remove_file(&path) {
+        println!("The saved state could not be removed! The error: {err}");
+    }
+
+    let value = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))?
+            .parse::<u64>()
+            .ok()
+    };
+    Some(State {
+        remaining: Seconds::new(value("remaining")?),
+        consecutive_skips: value("consecutive_skips")?,
+    })
+}
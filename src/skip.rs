@@ -0,0 +1,72 @@
+// whether a break may be skipped, decided for every source a skip can come from
+//
+// the escalation blocks every source, otherwise the config lists the allowed sources for normal and
+// for strict breaks -- e.g. strict breaks that can still be skipped from the countdown notification,
+// but not through `wlbreaktime-helper skip`
+use std::path::Path;
+
+use wlbreaktime::paths;
+
+use crate::config::{Config, SkipSource};
+
+pub(crate) const NOTIFICATION_COMMAND: &str = "skip notification";
+
+pub(crate) struct Policy {
+    // why the break can't be skipped at all, e.g. after too many skips in a row
+    pub(crate) blocked: Option<String>,
+    config: Config,
+}
+
+impl Policy {
+    pub(crate) fn new(config: &Config, blocked: Option<String>) -> Policy {
+        Policy {
+            blocked,
+            config: config.clone(),
+        }
+    }
+
+    pub(crate) fn allows(&self, source: SkipSource) -> bool {
+        self.refusal(source).is_none()
+    }
+
+    /*
+     * why a skip from the source is refused, None if it's allowed
+     */
+    pub(crate) fn refusal(&self, source: SkipSource) -> Option<String> {
+        if let Some(blocked) = &self.blocked {
+            return Some(blocked.clone());
+        }
+        if self.config.skip_allowed(source) {
+            return None;
+        }
+        let breaks = if self.config.strict {
+            "strict breaks"
+        } else {
+            "breaks"
+        };
+        Some(format!(
+            "Skipping through the {} is not allowed for {breaks}.",
+            source.name()
+        ))
+    }
+}
+
+/*
+ * the source of a skip command, None if it's no skip -- the notification's action sends its skip
+ * from the prompt socket, from anywhere else it counts as a skip through the helper
+ */
+pub(crate) fn source(command: &str, path: &Path) -> Option<SkipSource> {
+    match command {
+        "skip" => Some(SkipSource::Cli),
+        NOTIFICATION_COMMAND => {
+            let from_prompt =
+                paths::prompt_socket_path().is_ok_and(|prompt| path == Path::new(&prompt));
+            Some(if from_prompt {
+                SkipSource::Notification
+            } else {
+                SkipSource::Cli
+            })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,99 @@
+// the sockets the daemon listens on, either passed by systemd or bound by the daemon itself
+//
+// systemd names every passed socket through FileDescriptorName= (LISTEN_FDNAMES), which decides
+// the subsystem it's used for
+use std::{
+    fs,
+    io::ErrorKind,
+    os::{
+        fd::{FromRawFd, IntoRawFd, OwnedFd},
+        unix::net::UnixDatagram,
+    },
+};
+
+use libsystemd::activation::{self, FileDescriptor, IsType};
+use wlbreaktime::{
+    paths,
+    units::{self, EVENTS_NAME},
+};
+
+// without FileDescriptorName=, systemd uses the name of the socket unit
+const CONTROL_NAMES: [&str; 2] = [units::CONTROL_SOCKET_NAME, units::SOCKET_UNIT];
+const METRICS_NAME: &str = "metrics";
+
+pub(crate) struct Sockets {
+    // commands of the helper
+    pub(crate) control: UnixDatagram,
+    // phase changes are published to subscribers
+    pub(crate) events: Option<UnixDatagram>,
+    // reserved for metrics, kept open so systemd does not consider the socket unused
+    pub(crate) metrics: Option<OwnedFd>,
+}
+
+/*
+ * takes the sockets passed by systemd, a single unnamed socket is used for the commands -- the
+ * LISTEN_* variables are kept, a restart hands the sockets on to the next instance (see shutdown)
+ */
+pub(crate) fn from_systemd() -> Result<Sockets, Box<dyn std::error::Error>> {
+    let mut descriptors = activation::receive_descriptors_with_names(false)?;
+    // LISTEN_FDNAMES was not set by older systemd versions
+    if descriptors.is_empty() {
+        descriptors = activation::receive_descriptors(false)?
+            .into_iter()
+            .map(|fd| (fd, CONTROL_NAMES[0].to_string()))
+            .collect();
+    }
+
+    let mut control = None;
+    let mut events = None;
+    let mut metrics = None;
+    for (fd, name) in descriptors {
+        match name.as_str() {
+            name if CONTROL_NAMES.contains(&name) => control = Some(datagram_socket(fd, name)?),
+            EVENTS_NAME => events = Some(datagram_socket(fd, EVENTS_NAME)?),
+            METRICS_NAME => {
+                println!("Metrics are not supported yet, the metrics socket stays unused.");
+                metrics = Some(unsafe { OwnedFd::from_raw_fd(fd.into_raw_fd()) });
+            }
+            name => println!("Ignoring the unknown socket '{name}' passed by systemd."),
+        }
+    }
+
+    Ok(Sockets {
+        control: control.ok_or("Systemd did not pass a control socket!")?,
+        events,
+        metrics,
+    })
+}
+
+fn datagram_socket(
+    fd: FileDescriptor,
+    name: &str,
+) -> Result<UnixDatagram, Box<dyn std::error::Error>> {
+    if !fd.is_unix() {
+        return Err(format!("The systemd socket '{name}' was configured incorrectly!").into());
+    }
+    Ok(unsafe { UnixDatagram::from_raw_fd(fd.into_raw_fd()) })
+}
+
+/*
+ * binds the sockets to their paths in the runtime directory
+ */
+pub(crate) fn bind() -> Result<Sockets, Box<dyn std::error::Error>> {
+    Ok(Sockets {
+        control: bind_path(&paths::daemon_socket_path()?)?,
+        events: Some(bind_path(&paths::events_socket_path()?)?),
+        metrics: None,
+    })
+}
+
+fn bind_path(path: &str) -> Result<UnixDatagram, Box<dyn std::error::Error>> {
+    match UnixDatagram::bind(path) {
+        Err(err) if err.kind() == ErrorKind::AddrInUse => {
+            // a previous instance probably crashed and did not unlink its socket
+            fs::remove_file(path)?;
+            Ok(UnixDatagram::bind(path)?)
+        }
+        result => Ok(result?),
+    }
+}
@@ -0,0 +1,108 @@
+// break statistics since the daemon started, kept apart by the reason of the break
+//
+// like the capabilities, they are global, so the break's command handling can report them
+use std::sync::Mutex;
+
+use jiff::{Zoned, civil::Date};
+
+use crate::debt;
+
+static STATS: Mutex<Stats> = Mutex::new(Stats {
+    current: BreakReason::Scheduled,
+    scheduled: 0,
+    scheduled_skipped: 0,
+    manual: 0,
+    manual_skipped: 0,
+    today: None,
+    taken_today: 0,
+});
+
+/*
+ * why a break started -- manual breaks were requested through the helper, all others were
+ * started by the schedule
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BreakReason {
+    Scheduled,
+    Manual,
+}
+
+impl BreakReason {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            BreakReason::Scheduled => "scheduled",
+            BreakReason::Manual => "manual",
+        }
+    }
+}
+
+struct Stats {
+    // the reason of the last break that started
+    current: BreakReason,
+    scheduled: u64,
+    scheduled_skipped: u64,
+    manual: u64,
+    manual_skipped: u64,
+    // the day the breaks were taken on, the count starts over on the next day
+    today: Option<Date>,
+    taken_today: u64,
+}
+
+pub(crate) fn break_started(reason: BreakReason) {
+    let mut stats = STATS.lock().unwrap();
+    stats.current = reason;
+    match reason {
+        BreakReason::Scheduled => stats.scheduled += 1,
+        BreakReason::Manual => stats.manual += 1,
+    }
+}
+
+pub(crate) fn break_skipped() {
+    let mut stats = STATS.lock().unwrap();
+    match stats.current {
+        BreakReason::Scheduled => stats.scheduled_skipped += 1,
+        BreakReason::Manual => stats.manual_skipped += 1,
+    }
+}
+
+/*
+ * counts a break that lasted until its end
+ */
+pub(crate) fn break_taken() {
+    let mut stats = STATS.lock().unwrap();
+    let today = Zoned::now().date();
+    if stats.today != Some(today) {
+        stats.today = Some(today);
+        stats.taken_today = 0;
+    }
+    stats.taken_today += 1;
+}
+
+/*
+ * the breaks taken until their end today, shown on the overlay
+ */
+pub(crate) fn taken_today() -> u64 {
+    let stats = STATS.lock().unwrap();
+    if stats.today == Some(Zoned::now().date()) {
+        stats.taken_today
+    } else {
+        0
+    }
+}
+
+pub(crate) fn current_reason() -> BreakReason {
+    STATS.lock().unwrap().current
+}
+
+/*
+ * the statistics as "name=value" lines, requested by `wlbreaktime-helper stats`
+ */
+pub(crate) fn report() -> String {
+    let taken_today = taken_today();
+    let debt = debt::owed().as_secs();
+    let stats = STATS.lock().unwrap();
+    format!(
+        "scheduled={}\nscheduled_skipped={}\nmanual={}\nmanual_skipped={}\ntaken_today={taken_today}\ndebt={debt}",
+        stats.scheduled, stats.scheduled_skipped, stats.manual, stats.manual_skipped
+    )
+}
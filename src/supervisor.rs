@@ -0,0 +1,47 @@
+// the wayland code runs on a thread of its own, supervised by the daemon's main thread
+//
+// a protocol error or a bug in the overlay (e.g. a compositor that doesn't advertise any buffer
+// formats) panics on that thread only: the connection is dropped, which also removes the overlay,
+// and the daemon carries on without wayland -- a break that was shown is counted down in a
+// notification instead, so the schedule survives
+use std::thread;
+
+use wayland_client::EventQueue;
+
+use crate::{capabilities, wayland::State};
+
+pub(crate) type Wayland = (EventQueue<State>, State);
+
+/*
+ * runs the task on the wayland thread and waits for it, None if there is no connection or if the
+ * task panicked, which drops the connection
+ */
+pub(crate) fn run<T: Send>(
+    wayland: &mut Option<Wayland>,
+    name: &str,
+    task: impl FnOnce(&mut EventQueue<State>, &mut State) -> Result<T, Box<dyn std::error::Error>>
+    + Send,
+) -> Option<Result<T, Box<dyn std::error::Error>>> {
+    let (event_queue, data) = wayland.as_mut()?;
+    // the errors are sent back as their messages, boxed errors can't leave the thread
+    let result = thread::scope(|scope| {
+        thread::Builder::new()
+            .name("wayland".to_string())
+            .spawn_scoped(scope, || {
+                task(event_queue, data).map_err(|err| err.to_string())
+            })
+            .map(|handle| handle.join())
+    });
+
+    match result {
+        Ok(Ok(result)) => Some(result.map_err(Into::into)),
+        Ok(Err(_)) => {
+            // the panic itself was already logged by the thread
+            println!("The wayland thread crashed during the {name}, the overlay is disabled!");
+            *wayland = None;
+            capabilities::lose_wayland();
+            None
+        }
+        Err(err) => Some(Err(err.into())),
+    }
+}
@@ -0,0 +1,116 @@
+// the texts of the notifications, e.g. pre_break_body="The next break starts in {remaining}."
+//
+// a placeholder is a name in braces, it's replaced by its value in a single pass, so values are
+// never expanded again -- unknown ones are left as they are and reported at startup, so a typo
+// shows up in the notification instead of vanishing
+use wlbreaktime::duration::Seconds;
+
+use crate::announce;
+
+const REMAINING: &str = "remaining";
+const BREAK_DURATION: &str = "break_duration";
+const CYCLE: &str = "cycle";
+const PLACEHOLDERS: [&str; 3] = [REMAINING, BREAK_DURATION, CYCLE];
+
+/*
+ * the summary and the body of a notification -- the remaining time is until the break or, once
+ * it's over, until the next one, the cycle is the break's number today
+ */
+pub(crate) fn notification(
+    summary: &str,
+    body: &str,
+    remaining: Seconds,
+    break_duration: Seconds,
+    cycle: u64,
+) -> (String, String) {
+    let remaining = announce::spoken_duration(remaining);
+    let break_duration = announce::spoken_duration(break_duration);
+    let cycle = cycle.to_string();
+    let values = [
+        (REMAINING, remaining.as_str()),
+        (BREAK_DURATION, break_duration.as_str()),
+        (CYCLE, cycle.as_str()),
+    ];
+    (render(summary, &values), render(body, &values))
+}
+
+/*
+ * the template with every placeholder of the values replaced
+ */
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &after[..end])
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &after[end + 1..];
+            }
+            // kept as it is, the text after the brace might still hold placeholders
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/*
+ * reports the placeholders of the template that are never replaced
+ */
+pub(crate) fn check(key: &str, template: &str) {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            return;
+        };
+        let name = &after[..end];
+        if !PLACEHOLDERS.contains(&name) {
+            println!(
+                "The {key} has the unknown placeholder {{{name}}}, known are {{{}}}.",
+                PLACEHOLDERS.join("}, {")
+            );
+        }
+        rest = &after[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_every_placeholder() {
+        let rendered = render(
+            "Break {cycle} starts in {remaining}, {remaining} to go",
+            &[(REMAINING, "10 seconds"), (CYCLE, "3")],
+        );
+        assert_eq!(rendered, "Break 3 starts in 10 seconds, 10 seconds to go");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        let rendered = render("{remainig} {remaining} {", &[(REMAINING, "5 minutes")]);
+        assert_eq!(rendered, "{remainig} 5 minutes {");
+    }
+
+    #[test]
+    fn doesnt_expand_values() {
+        let rendered = render(
+            "{remaining}{cycle}",
+            &[(REMAINING, "{cycle}"), (CYCLE, "1")],
+        );
+        assert_eq!(rendered, "{cycle}1");
+    }
+}
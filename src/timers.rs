@@ -0,0 +1,128 @@
+// one-shot timers independent of the break cycle, e.g. `wlbreaktime-helper timer 20m "Tea"`
+//
+// the daemon already owns the notifications and the sound, so it keeps the timers as well -- the
+// work and the break loop wake up for the next one and fire the timers that are due. like the
+// stats, the timers are global, so both loops see the same ones
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use wlbreaktime::duration::Seconds;
+
+#[cfg(feature = "audio")]
+use crate::audio;
+use crate::{
+    announce,
+    config::{Config, QuietHours},
+    notify, watchdog,
+};
+
+static TIMERS: Mutex<Vec<Timer>> = Mutex::new(Vec::new());
+// set once at startup, the weekday overrides don't apply to timers
+static SOUND: OnceLock<Sound> = OnceLock::new();
+
+struct Timer {
+    due: Instant,
+    duration: Seconds,
+    // empty if none was given
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+struct Sound {
+    play_sound: bool,
+    quiet_hours: Option<QuietHours>,
+}
+
+pub(crate) fn configure(config: &Config) {
+    let sound = Sound {
+        play_sound: config.play_sound,
+        quiet_hours: config.quiet_hours,
+    };
+    if SOUND.set(sound).is_err() {
+        println!("The sound of the timers was already configured!");
+    }
+}
+
+pub(crate) fn add(duration: Seconds, message: &str) {
+    println!("Started a timer of {} seconds.", duration.as_secs());
+    TIMERS.lock().unwrap().push(Timer {
+        due: Instant::now() + duration.as_duration(),
+        duration,
+        message: message.to_string(),
+    });
+}
+
+/*
+ * the seconds left and the message of every timer that's not due yet
+ */
+pub(crate) fn pending() -> Vec<(Seconds, String)> {
+    let timers = TIMERS.lock().unwrap();
+    timers
+        .iter()
+        .map(|timer| {
+            let remaining = timer.due.saturating_duration_since(Instant::now());
+            (remaining.into(), timer.message.clone())
+        })
+        .collect()
+}
+
+/*
+ * the timeout shortened to when the next timer is due, so reading the socket doesn't delay it --
+ * and to the watchdog's interval, so the main loop checks in often enough
+ */
+pub(crate) fn cap(timeout: Duration) -> Duration {
+    let timeout = watchdog::interval().map_or(timeout, |interval| timeout.min(interval));
+    let timers = TIMERS.lock().unwrap();
+    let until_next = timers
+        .iter()
+        .map(|timer| timer.due.saturating_duration_since(Instant::now()))
+        .min();
+    // a timeout of zero is not allowed for sockets
+    match until_next {
+        Some(until_next) => timeout.min(until_next).max(Duration::from_millis(1)),
+        None => timeout,
+    }
+}
+
+/*
+ * shows a notification and plays a chime for every timer that is due, returns true if there was any
+ */
+pub(crate) fn fire_due() -> bool {
+    let due: Vec<Timer> = {
+        let mut timers = TIMERS.lock().unwrap();
+        let now = Instant::now();
+        let (due, pending) = timers.drain(..).partition(|timer| timer.due <= now);
+        *timers = pending;
+        due
+    };
+
+    for timer in &due {
+        let message = if timer.message.is_empty() {
+            format!(
+                "The timer of {} is up.",
+                announce::spoken_duration(timer.duration)
+            )
+        } else {
+            timer.message.clone()
+        };
+        if let Err(err) = notify::show_timer(&message) {
+            println!("The timer could not be shown! The error: {err}");
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    if !due.is_empty()
+        && let Some(sound) = SOUND.get()
+        && sound.play_sound
+        && !sound
+            .quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contains(jiff::Zoned::now().time()))
+        && let Err(err) = audio::play(audio::Cue::Timer)
+    {
+        println!("The timer's chime could not be played! The error: {err}");
+    }
+    !due.is_empty()
+}
@@ -0,0 +1,254 @@
+// a small toast in the top right corner, drawn by the daemon itself and dismissed after a few
+// seconds. it's used for transient messages (e.g. the warning a minute before a break or a skipped
+// break) and as the fallback for notifications when no notification daemon is running
+//
+// every toast gets a wayland connection and a thread of its own, like the idle watcher, so it
+// neither holds up the schedule nor gets in the way of the overlay. input passes through it
+use std::{
+    fs,
+    io::Write,
+    os::fd::AsFd,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    protocol::{
+        wl_buffer, wl_compositor, wl_output, wl_region,
+        wl_registry::{Event, WlRegistry},
+        wl_shm::{self, Format},
+        wl_shm_pool, wl_surface,
+    },
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, Anchor, ZwlrLayerSurfaceV1},
+};
+use wlbreaktime::paths;
+
+use crate::{
+    layout,
+    palette::{self, Phase},
+    render::{Canvas, GLYPH_SIZE},
+};
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const SUMMARY_SCALE: u32 = 3;
+const BODY_SCALE: u32 = 2;
+// in pixels
+const PADDING: u32 = 16;
+// the stripe in the phase's color along the left edge
+const STRIPE_WIDTH: u32 = 6;
+const MARGIN: i32 = 16;
+const MAX_TEXT_WIDTH: u32 = 960;
+
+// every toast needs a pool file of its own
+static TOASTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct Toast {
+    compositor: Option<wl_compositor::WlCompositor>,
+    shm: Option<wl_shm::WlShm>,
+    layer_shell: Option<ZwlrLayerShellV1>,
+    configured: bool,
+    closed: bool,
+}
+
+impl Dispatch<WlRegistry, ()> for Toast {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let Event::Global {
+            name, interface, ..
+        } = event
+        {
+            match &interface[..] {
+                "wl_compositor" => state.compositor = Some(registry.bind(name, 4, qh, ())),
+                "wl_shm" => state.shm = Some(registry.bind(name, 1, qh, ())),
+                "zwlr_layer_shell_v1" => state.layer_shell = Some(registry.bind(name, 1, qh, ())),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for Toast {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer_surface.ack_configure(serial);
+                state.configured = true;
+            }
+            // e.g. the output it was shown on was disconnected
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+// the other objects don't send anything the toast needs
+delegate_noop!(Toast: ignore wl_compositor::WlCompositor);
+delegate_noop!(Toast: ignore wl_shm::WlShm);
+delegate_noop!(Toast: ignore wl_shm_pool::WlShmPool);
+delegate_noop!(Toast: ignore wl_buffer::WlBuffer);
+delegate_noop!(Toast: ignore wl_surface::WlSurface);
+delegate_noop!(Toast: ignore wl_region::WlRegion);
+delegate_noop!(Toast: ignore ZwlrLayerShellV1);
+
+/*
+ * shows the message in a toast in the background, marked with the phase it's about -- it's logged
+ * if that fails
+ */
+pub(crate) fn show(summary: &str, body: &str, phase: Phase) {
+    let (summary, body) = (summary.to_string(), body.to_string());
+    let color = palette::current(phase);
+    let spawned = thread::Builder::new()
+        .name("toast".to_string())
+        .spawn(move || {
+            if let Err(err) = run(&summary, &body, color) {
+                println!("The toast could not be shown! The error: {err}");
+                println!("{summary} {body}");
+            }
+        });
+    if let Err(err) = spawned {
+        println!("The toast could not be started! The error: {err}");
+    }
+}
+
+fn run(summary: &str, body: &str, color: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::connect_to_env()?;
+    let mut event_queue = connection.new_event_queue();
+    let qh = event_queue.handle();
+    let _registry = connection.display().get_registry(&qh, ());
+    let mut toast = Toast::default();
+    event_queue.roundtrip(&mut toast)?;
+    let (Some(compositor), Some(shm), Some(layer_shell)) = (
+        toast.compositor.clone(),
+        toast.shm.clone(),
+        toast.layer_shell.clone(),
+    ) else {
+        return Err("the compositor does not support wlr-layer-shell".into());
+    };
+
+    let canvas = draw(summary, body, color);
+    let (width, height) = (canvas.width, canvas.height);
+
+    let surface = compositor.create_surface(&qh, ());
+    let layer_surface = layer_shell.get_layer_surface(
+        &surface,
+        None::<&wl_output::WlOutput>,
+        Layer::Overlay,
+        "wlbreaktime-toast".to_string(),
+        &qh,
+        (),
+    );
+    layer_surface.set_size(width, height);
+    layer_surface.set_anchor(Anchor::Top | Anchor::Right);
+    layer_surface.set_margin(MARGIN, MARGIN, 0, 0);
+    // an empty input region, clicks and taps reach the windows below
+    let region = compositor.create_region(&qh, ());
+    surface.set_input_region(Some(&region));
+    region.destroy();
+    surface.commit();
+    while !toast.configured && !toast.closed {
+        event_queue.blocking_dispatch(&mut toast)?;
+    }
+
+    // the pool's file is unlinked right away, the compositor maps it through the descriptor
+    let (_, bytes) = canvas.region_bytes(
+        Format::Argb8888,
+        wl_output::Transform::Normal,
+        canvas.full_rect(),
+    );
+    let index = TOASTS.fetch_add(1, Ordering::Relaxed);
+    let path = paths::pool_file_path(width as i32, height as i32, &format!("toast{index}"))?;
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    file.write_all(&bytes)?;
+
+    let pool = shm.create_pool(file.as_fd(), bytes.len() as i32, &qh, ());
+    let buffer = pool.create_buffer(
+        0,
+        width as i32,
+        height as i32,
+        width as i32 * 4,
+        Format::Argb8888,
+        &qh,
+        (),
+    );
+    surface.attach(Some(&buffer), 0, 0);
+    surface.damage_buffer(0, 0, width as i32, height as i32);
+    surface.commit();
+
+    let shown = Instant::now();
+    while !toast.closed && shown.elapsed() < TOAST_DURATION {
+        event_queue.roundtrip(&mut toast)?;
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    layer_surface.destroy();
+    surface.destroy();
+    buffer.destroy();
+    pool.destroy();
+    connection.flush()?;
+    Ok(())
+}
+
+/*
+ * the summary above the body, both shrunk until they fit, next to the stripe
+ */
+fn draw(summary: &str, body: &str, color: u32) -> Canvas {
+    let fitted = |text: &str, mut scale: u32| {
+        while scale > 1 && Canvas::text_width(text, scale) > MAX_TEXT_WIDTH {
+            scale -= 1;
+        }
+        scale
+    };
+    let (summary_scale, body_scale) = (fitted(summary, SUMMARY_SCALE), fitted(body, BODY_SCALE));
+    let line_height = |scale: u32| scale * GLYPH_SIZE;
+
+    let text_width = Canvas::text_width(summary, summary_scale)
+        .max(Canvas::text_width(body, body_scale))
+        .min(MAX_TEXT_WIDTH);
+    let width = STRIPE_WIDTH + text_width + 2 * PADDING;
+    let height = line_height(summary_scale) + line_height(body_scale) + 3 * PADDING;
+
+    let colors = layout::palette(palette::theme(), false);
+    let mut canvas = Canvas::new(width, height, colors.background);
+    canvas.fill_rect(0, 0, STRIPE_WIDTH, height, color);
+    canvas.draw_text(
+        STRIPE_WIDTH + PADDING,
+        PADDING,
+        summary_scale,
+        colors.text,
+        summary,
+    );
+    canvas.draw_text(
+        STRIPE_WIDTH + PADDING,
+        2 * PADDING + line_height(summary_scale),
+        body_scale,
+        colors.hint,
+        body,
+    );
+    canvas
+}
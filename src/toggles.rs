@@ -0,0 +1,91 @@
+// the popup, the sound, the notifications and turning off the monitors can be switched at runtime,
+// e.g. `wlbreaktime-helper toggle sound` for a call -- without touching the config files
+//
+// a toggle flips what today's config says and lasts until the daemon stops, unless it's persisted.
+// like the timers, the toggles are global, so both loops see the same ones
+use std::sync::{Mutex, OnceLock};
+
+use wlbreaktime::protocol;
+
+use crate::config::{self, Config, Toggle};
+
+// the switched values, a toggle that's back at the configured value is removed
+static TOGGLED: Mutex<Vec<(Toggle, bool)>> = Mutex::new(Vec::new());
+// set once at startup, the weekday overrides are applied to it whenever a toggle is flipped
+static BASE: OnceLock<Config> = OnceLock::new();
+
+pub(crate) fn configure(config: &Config) {
+    if BASE.set(config.clone()).is_err() {
+        println!("The toggles were already configured!");
+    }
+}
+
+/*
+ * today's config with the toggles applied, they take precedence over everything else
+ */
+pub(crate) fn apply(mut config: Config) -> Config {
+    for (toggle, on) in TOGGLED.lock().unwrap().iter() {
+        toggle.set(&mut config, *on);
+    }
+    config
+}
+
+/*
+ * flips the toggle and returns whether it's on now
+ */
+fn flip(toggle: Toggle) -> bool {
+    let configured = BASE.get().is_some_and(|base| toggle.get(&base.for_today()));
+    let mut toggled = TOGGLED.lock().unwrap();
+    let current = toggled
+        .iter()
+        .find(|(switched, _)| *switched == toggle)
+        .map_or(configured, |(_, on)| *on);
+    let on = !current;
+    toggled.retain(|(switched, _)| *switched != toggle);
+    if on != configured {
+        toggled.push((toggle, on));
+    }
+    on
+}
+
+/*
+ * the toggles that differ from the config, e.g. "sound=off,popup=on" -- "none" without any
+ */
+pub(crate) fn status() -> String {
+    let toggled = TOGGLED.lock().unwrap();
+    if toggled.is_empty() {
+        return "none".to_string();
+    }
+    toggled
+        .iter()
+        .map(|(toggle, on)| format!("{}={}", toggle.name(), if *on { "on" } else { "off" }))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/*
+ * handles "[--persist] <toggle>", the answer is the new state -- a persisted toggle is written to
+ * the config file as well, so it's the configured value after a restart
+ */
+pub(crate) fn command(argument: &str) -> String {
+    let (persist, name) = protocol::split_persist(argument);
+    let Some(toggle) = Toggle::from_name(name) else {
+        return "unknown toggle".to_string();
+    };
+    let on = flip(toggle);
+    let state = if on { "on" } else { "off" };
+    println!("Toggled {} {state}.", toggle.name());
+    if !persist {
+        return state.to_string();
+    }
+    match config::persist(toggle.key(), &on.to_string()) {
+        Ok(path) => {
+            println!("Wrote {}={on} to '{path}'.", toggle.key());
+            format!("{state} persisted")
+        }
+        Err(err) => {
+            println!("The toggle could not be written to the config file! The error: {err}");
+            format!("{state} not written to the config file: {err}")
+        }
+    }
+}
@@ -0,0 +1,102 @@
+// time tracking: every work interval is logged to a time tracker, so the break cycle doubles as
+// pomodoro tracking
+//
+// the command backend works with any CLI tracker, e.g. `timew start work` and `timew stop`, the
+// org backend appends CLOCK lines to an org file
+use std::{fs::OpenOptions, io::Write, process::Command};
+
+use jiff::Zoned;
+
+use crate::config::Config;
+
+pub(crate) trait TimeTracker {
+    fn start_work(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    fn stop_work(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/*
+ * runs a shell command at the start and at the end of every work interval
+ */
+pub(crate) struct CommandTracker {
+    start: String,
+    stop: String,
+}
+
+impl TimeTracker for CommandTracker {
+    fn start_work(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        run(&self.start)
+    }
+
+    fn stop_work(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        run(&self.stop)
+    }
+}
+
+fn run(command: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("sh").arg("-c").arg(command).status()?;
+    if !status.success() {
+        return Err(format!("'{command}' failed with {status}").into());
+    }
+    Ok(())
+}
+
+/*
+ * appends a CLOCK line for every work interval to an org file
+ */
+pub(crate) struct OrgTracker {
+    path: String,
+    started: Option<Zoned>,
+}
+
+impl TimeTracker for OrgTracker {
+    fn start_work(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.started = Some(Zoned::now());
+        Ok(())
+    }
+
+    fn stop_work(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(started) = self.started.take() else {
+            return Ok(());
+        };
+        let stopped = Zoned::now();
+        let minutes = started.duration_until(&stopped).as_secs() / 60;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(
+            file,
+            "CLOCK: [{}]--[{}] => {:2}:{:02}",
+            started.strftime("%Y-%m-%d %a %H:%M"),
+            stopped.strftime("%Y-%m-%d %a %H:%M"),
+            minutes / 60,
+            minutes % 60
+        )?;
+        Ok(())
+    }
+}
+
+/*
+ * the configured tracker, None if time tracking is disabled
+ */
+pub(crate) fn from_config(config: &Config) -> Option<Box<dyn TimeTracker>> {
+    if let Some(path) = &config.time_tracker_org {
+        return Some(Box::new(OrgTracker {
+            path: path.clone(),
+            started: None,
+        }));
+    }
+
+    match (&config.time_tracker_start, &config.time_tracker_stop) {
+        (Some(start), Some(stop)) => Some(Box::new(CommandTracker {
+            start: start.clone(),
+            stop: stop.clone(),
+        })),
+        (None, None) => None,
+        _ => {
+            println!("Time tracking needs both time_tracker_start and time_tracker_stop.");
+            None
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// the systemd user units of the daemon, written by `wlbreaktime-helper install-service`
+//
+// they are generated here instead of being copied from resources/, so the socket paths, the names
+// of the passed sockets and the watchdog always match what the daemon expects
+//
+// multi-user machines can install the system units instead (`install-service --system`): a single
+// system socket starts one daemon per login session, see the daemon's session.rs
+use crate::paths::{EVENTS_SOCKET_NAME, SESSION_SOCKET_PATH, SOCKET_NAME};
+
+pub const SERVICE_UNIT: &str = "wlbreaktime.service";
+pub const SOCKET_UNIT: &str = "wlbreaktime.socket";
+pub const EVENTS_SOCKET_UNIT: &str = "wlbreaktime-events.socket";
+pub const SESSION_SOCKET_UNIT: &str = "wlbreaktime-session.socket";
+// a template, every connection to the session socket starts its own instance
+pub const SESSION_SERVICE_UNIT: &str = "wlbreaktime-session@.service";
+
+// the daemon's option to take over the session of the user that connected, without the dashes
+pub const SYSTEM_SESSION_FLAG: &str = "system-session";
+
+// the names the sockets are passed with (FileDescriptorName=), they decide what they're used for
+pub const CONTROL_SOCKET_NAME: &str = "control";
+pub const EVENTS_NAME: &str = "events";
+
+// the daemon feeds the watchdog at half of it
+pub const WATCHDOG_SECONDS: u64 = 30;
+
+const DESCRIPTION: &str = "wlbreaktime -- An application that reminds you to take regular breaks";
+
+/*
+ * every unit's file name and content, the service starts the daemon at the given path
+ */
+pub fn all(daemon_path: &str) -> Vec<(&'static str, String)> {
+    vec![
+        (SERVICE_UNIT, service(daemon_path)),
+        (SOCKET_UNIT, control_socket()),
+        (EVENTS_SOCKET_UNIT, events_socket()),
+    ]
+}
+
+/*
+ * the system units of multi-user machines, the instances start the daemon at the given path
+ */
+pub fn system(daemon_path: &str) -> Vec<(&'static str, String)> {
+    vec![
+        (SESSION_SOCKET_UNIT, session_socket()),
+        (SESSION_SERVICE_UNIT, session_service(daemon_path)),
+    ]
+}
+
+fn exec_start(daemon_path: &str) -> String {
+    // paths with spaces need to be quoted
+    if daemon_path.contains(char::is_whitespace) {
+        format!("\"{daemon_path}\"")
+    } else {
+        daemon_path.to_string()
+    }
+}
+
+fn service(daemon_path: &str) -> String {
+    let exec_start = exec_start(daemon_path);
+    format!(
+        "[Unit]
+Description={DESCRIPTION}
+Requires={SOCKET_UNIT}
+Wants={EVENTS_SOCKET_UNIT}
+PartOf=graphical-session.target
+After=graphical-session.target
+Requisite=graphical-session.target
+
+[Service]
+Type=notify
+Sockets={SOCKET_UNIT} {EVENTS_SOCKET_UNIT}
+ExecStart={exec_start}
+Restart=on-failure
+WatchdogSec={WATCHDOG_SECONDS}
+
+[Install]
+WantedBy=graphical-session.target
+"
+    )
+}
+
+fn control_socket() -> String {
+    format!(
+        "[Unit]
+Description=socket for {DESCRIPTION}
+Requires={SERVICE_UNIT}
+
+[Socket]
+ListenDatagram=%t/{SOCKET_NAME}
+FileDescriptorName={CONTROL_SOCKET_NAME}
+Service={SERVICE_UNIT}
+
+[Install]
+WantedBy=sockets.target
+"
+    )
+}
+
+fn events_socket() -> String {
+    format!(
+        "[Unit]
+Description=event stream socket for {DESCRIPTION}
+
+[Socket]
+ListenDatagram=%t/{EVENTS_SOCKET_NAME}
+FileDescriptorName={EVENTS_NAME}
+Service={SERVICE_UNIT}
+
+[Install]
+WantedBy=sockets.target
+"
+    )
+}
+
+fn session_socket() -> String {
+    format!(
+        "[Unit]
+Description=session socket for {DESCRIPTION}
+
+[Socket]
+ListenStream={SESSION_SOCKET_PATH}
+Accept=yes
+SocketMode=0666
+
+[Install]
+WantedBy=sockets.target
+"
+    )
+}
+
+fn session_service(daemon_path: &str) -> String {
+    let exec_start = exec_start(daemon_path);
+    // started as root, the daemon switches to the connecting user on its own -- User= can't know
+    // who that will be
+    format!(
+        "[Unit]
+Description=session instance of {DESCRIPTION}
+CollectMode=inactive-or-failed
+
+[Service]
+ExecStart={exec_start} --{SYSTEM_SESSION_FLAG}
+"
+    )
+}
@@ -0,0 +1,67 @@
+// systemd's watchdog: with WatchdogSec= in the service (like in the units of
+// `wlbreaktime-helper install-service`), the daemon is restarted unless it checks in regularly
+//
+// the watchdog is fed from its own thread at half of the interval systemd asks for, but only while
+// the main loop beats -- it wakes up at least once per interval for that, so a main loop that hangs
+// e.g. on a compositor call starves the watchdog and systemd restarts the daemon
+use std::{
+    sync::{Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use libsystemd::daemon::{self, NotifyState};
+
+static INTERVAL: OnceLock<Duration> = OnceLock::new();
+static HEARTBEAT: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub(crate) fn start() {
+    let Some(timeout) = daemon::watchdog_enabled(false) else {
+        return;
+    };
+    let interval = (timeout / 2).max(Duration::from_secs(1));
+    if INTERVAL.set(interval).is_err() {
+        println!("The systemd watchdog was already started!");
+        return;
+    }
+    beat();
+    println!("Feeding the systemd watchdog every {interval:?}.");
+    thread::spawn(move || {
+        let mut starved = false;
+        loop {
+            let since_beat = HEARTBEAT
+                .lock()
+                .unwrap()
+                .map_or(Duration::MAX, |heartbeat| heartbeat.elapsed());
+            if since_beat < timeout {
+                starved = false;
+                if let Err(err) = daemon::notify(false, &[NotifyState::Watchdog]) {
+                    println!("The systemd watchdog could not be fed! The error: {err}");
+                }
+            } else if !starved {
+                starved = true;
+                println!(
+                    "The main loop did not check in for {}s, the systemd watchdog is no longer fed!",
+                    since_beat.as_secs()
+                );
+            }
+            thread::sleep(interval);
+        }
+    });
+}
+
+/*
+ * the main loop checks in, called every time it wakes up
+ */
+pub(crate) fn beat() {
+    if INTERVAL.get().is_some() {
+        *HEARTBEAT.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+/*
+ * the interval the main loop has to wake up in to keep the watchdog fed, None without a watchdog
+ */
+pub(crate) fn interval() -> Option<Duration> {
+    INTERVAL.get().copied()
+}
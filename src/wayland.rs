@@ -1,26 +1,69 @@
 use core::str;
+use jiff::Zoned;
 use log::{error, info};
+use rustix::{
+    event::{PollFd, PollFlags, Timespec, poll},
+    io::Errno,
+};
 use std::{
-    env,
     fs::{self, File},
-    io::{BufWriter, ErrorKind, Write},
-    os::{fd::AsFd, unix::net::UnixDatagram},
+    os::{
+        fd::AsFd,
+        unix::{fs::FileExt, net::UnixDatagram},
+    },
     time::{Duration, Instant},
 };
+use wlbreaktime::{duration::Seconds, paths};
+
+#[cfg(feature = "gpu")]
+use crate::gpu::{self, Gpu};
+use crate::{
+    BreakView,
+    config::CursorMode,
+    focus::Toplevel,
+    gamma::GammaControl,
+    layout::Layout,
+    render::{self, Canvas, Rect},
+    skip, wait_until_work,
+};
 
 use wayland_client::{
     Connection, Dispatch, EventQueue, QueueHandle, WEnum,
     protocol::{
-        wl_buffer, wl_compositor, wl_output,
+        wl_buffer, wl_callback, wl_compositor, wl_keyboard, wl_output, wl_pointer, wl_region,
         wl_registry::{Event, WlRegistry},
+        wl_seat,
         wl_shm::{self, Format},
         wl_shm_pool,
         wl_surface::{self},
+        wl_touch,
+    },
+};
+use wayland_protocols::{
+    wp::cursor_shape::v1::client::{
+        wp_cursor_shape_device_v1::{self, Shape, WpCursorShapeDeviceV1},
+        wp_cursor_shape_manager_v1::{self, WpCursorShapeManagerV1},
+    },
+    wp::keyboard_shortcuts_inhibit::zv1::client::{
+        zwp_keyboard_shortcuts_inhibit_manager_v1::{self, ZwpKeyboardShortcutsInhibitManagerV1},
+        zwp_keyboard_shortcuts_inhibitor_v1::{self, ZwpKeyboardShortcutsInhibitorV1},
+    },
+    wp::viewporter::client::{
+        wp_viewport::{self, WpViewport},
+        wp_viewporter::{self, WpViewporter},
+    },
+    xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base},
+};
+use wayland_protocols_wlr::{
+    foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+    gamma_control::v1::client::zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1,
+    layer_shell::v1::client::{
+        zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+        zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
     },
 };
-use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct SurfaceSize {
     width: i32,
     height: i32,
@@ -33,12 +76,207 @@ pub(crate) struct State {
     pub(crate) accepted_formats: Vec<WEnum<Format>>,
     pub(crate) compositor: Option<wl_compositor::WlCompositor>,
     pub(crate) base: Option<xdg_wm_base::XdgWmBase>,
+    // only used with popup_on_top, the overlay is a fullscreen window otherwise
+    pub(crate) layer_shell: Option<ZwlrLayerShellV1>,
+    pub(crate) seat: Option<wl_seat::WlSeat>,
+    pub(crate) pointer: Option<wl_pointer::WlPointer>,
+    pub(crate) cursor_shape_manager: Option<WpCursorShapeManagerV1>,
+    pub(crate) cursor_shape_device: Option<WpCursorShapeDeviceV1>,
+    pub(crate) cursor_mode: CursorMode,
+    pub(crate) touch: Option<wl_touch::WlTouch>,
+    pub(crate) keyboard: Option<wl_keyboard::WlKeyboard>,
+    pub(crate) shortcuts_inhibit_manager: Option<ZwpKeyboardShortcutsInhibitManagerV1>,
+    pub(crate) viewporter: Option<WpViewporter>,
+    // set for every break, below 1 the overlay is drawn smaller and scaled up by the compositor
+    pub(crate) render_scale: f32,
+    // set for every break, tapping and holding Esc only skip if it's allowed
+    pub(crate) tap_to_skip: bool,
+    // set for every break, in strict mode the compositor's shortcuts can't leave the overlay
+    pub(crate) inhibit_shortcuts: bool,
+    // set for every break, the overlay is put above fullscreen windows if the compositor can
+    pub(crate) popup_on_top: bool,
+    // set for every break, the countdown starts over with every input
+    pub(crate) extend_on_activity: bool,
+    pub(crate) skip_requested: bool,
+    pub(crate) escape_held_since: Option<Instant>,
+    // set once the compositor is ready for the next frame of the overlay
+    pub(crate) frame_ready: bool,
+    // the overlay's buffers that the compositor did not release yet
+    pub(crate) buffers_busy: [bool; MAX_BUFFERS],
+    // kept between breaks, so the pool is only recreated when the overlay's size changes
+    overlay_buffers: Option<Buffers>,
+    #[cfg(feature = "gpu")]
+    gpu: Gpu,
+    // uploaded during the lead-in, shown as soon as the overlay is configured
+    prepared_frame: Option<Canvas>,
+    // the overlay of the break that just ended, until it faded out
+    shown_overlay: Option<ShownOverlay>,
+    pub(crate) outputs: Vec<wl_output::WlOutput>,
+    pub(crate) output_transforms: Vec<(wl_output::WlOutput, wl_output::Transform)>,
+    // the output the overlay was shown on the last time
+    pub(crate) popup_output: Option<wl_output::WlOutput>,
+    pub(crate) gamma_manager: Option<ZwlrGammaControlManagerV1>,
+    pub(crate) gamma_controls: Vec<GammaControl>,
+    pub(crate) toplevel_manager_global: Option<(WlRegistry, u32, u32)>,
+    pub(crate) toplevel_manager: Option<ZwlrForeignToplevelManagerV1>,
+    pub(crate) toplevels: Vec<Toplevel>,
+}
+
+/*
+ * the surfaces of an overlay that is still shown and its last frame
+ */
+#[derive(Debug)]
+struct ShownOverlay {
+    wl_surface: wl_surface::WlSurface,
+    viewport: Option<WpViewport>,
+    role: Role,
+    renderer: Box<dyn Renderer>,
+    canvas: Canvas,
+}
+
+/*
+ * what the overlay's surface is to the compositor: a fullscreen window, or a surface on the layer
+ * shell's overlay layer, which stays above fullscreen windows
+ */
+#[derive(Debug)]
+enum Role {
+    Toplevel(xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel),
+    Layer(ZwlrLayerSurfaceV1),
+}
+
+impl Role {
+    fn new(data: &State, qh: &QueueHandle<State>, wl_surface: &wl_surface::WlSurface) -> Role {
+        if data.popup_on_top
+            && let Some(layer_shell) = &data.layer_shell
+        {
+            let layer_surface = layer_shell.get_layer_surface(
+                wl_surface,
+                None,
+                zwlr_layer_shell_v1::Layer::Overlay,
+                paths::APP_ID.to_string(),
+                qh,
+                (),
+            );
+            // the compositor picks the size, all of the output without leaving room for panels
+            layer_surface.set_size(0, 0);
+            layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+            layer_surface.set_exclusive_zone(-1);
+            layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+            info!("Created a layer surface on the overlay layer");
+            return Role::Layer(layer_surface);
+        }
+
+        let xdg_surface = data
+            .base
+            .as_ref()
+            .unwrap()
+            .get_xdg_surface(wl_surface, qh, ());
+        let xdg_top = xdg_surface.get_toplevel(qh, ());
+        xdg_top.set_title("Break time".to_string());
+        xdg_top.set_app_id(paths::APP_ID.to_string());
+        xdg_top.set_fullscreen(None);
+        info!("Created xdg_top and xdg_surface");
+        Role::Toplevel(xdg_surface, xdg_top)
+    }
+
+    fn destroy(self) {
+        match self {
+            Role::Toplevel(xdg_surface, xdg_top) => {
+                xdg_top.destroy();
+                xdg_surface.destroy();
+            }
+            Role::Layer(layer_surface) => layer_surface.destroy(),
+        }
+    }
+}
+
+/*
+ * draws the overlay's canvas onto its surface -- through wl_shm buffers, or through the GPU with
+ * the gpu feature, the layout is the same for both
+ */
+pub(crate) trait Renderer: std::fmt::Debug + Send {
+    /*
+     * how the canvas is laid out in the buffers, set as the surface's buffer transform
+     */
+    fn transform(&self) -> wl_output::Transform;
+
+    /*
+     * shows the canvas with the changed parts damaged, the caller commits the surface -- false if
+     * it can't be shown yet
+     */
+    fn present(
+        &mut self,
+        canvas: &Canvas,
+        data: &mut State,
+        qh: &QueueHandle<State>,
+        wl_surface: &wl_surface::WlSurface,
+    ) -> Result<bool, Box<dyn std::error::Error>>;
+
+    /*
+     * once the overlay faded out, right before its surface is destroyed
+     */
+    fn release(self: Box<Self>, data: &mut State);
+}
+
+impl State {
+    /*
+     * the transform of the output the overlay is shown on -- as long as the overlay did not enter
+     * an output yet, all outputs have to agree on it
+     */
+    fn popup_transform(&self) -> wl_output::Transform {
+        let transform_of = |output: &wl_output::WlOutput| {
+            self.output_transforms
+                .iter()
+                .find(|(known, _)| known == output)
+                .map(|(_, transform)| *transform)
+        };
+        if let Some(transform) = self.popup_output.as_ref().and_then(transform_of) {
+            return transform;
+        }
+
+        match self.output_transforms.first() {
+            Some((_, first))
+                if self
+                    .output_transforms
+                    .iter()
+                    .all(|(_, transform)| transform == first) =>
+            {
+                *first
+            }
+            _ => wl_output::Transform::Normal,
+        }
+    }
+
+    /*
+     * the size the overlay is drawn at, smaller than the surface if it's scaled up by the GPU or
+     * the compositor -- without either, it's always drawn at the surface's size
+     */
+    fn render_size(&self, surface_size: SurfaceSize) -> SurfaceSize {
+        if self.render_scale >= 1.0 || (self.viewporter.is_none() && !self.uses_gpu()) {
+            return surface_size;
+        }
+        let scale = |length: i32| ((length as f32 * self.render_scale).round() as i32).max(1);
+        SurfaceSize {
+            width: scale(surface_size.width),
+            height: scale(surface_size.height),
+        }
+    }
+
+    /*
+     * whether the next overlay is drawn by the GPU
+     */
+    fn uses_gpu(&self) -> bool {
+        #[cfg(feature = "gpu")]
+        return !self.gpu.unavailable;
+        #[cfg(not(feature = "gpu"))]
+        false
+    }
 }
 
 impl Dispatch<wl_output::WlOutput, ()> for State {
     fn event(
-        _state: &mut Self,
-        _output: &wl_output::WlOutput,
+        state: &mut Self,
+        output: &wl_output::WlOutput,
         event: wl_output::Event,
         _: &(),
         _: &Connection,
@@ -59,6 +297,10 @@ impl Dispatch<wl_output::WlOutput, ()> for State {
                 "Output geometry: x: {}, y: {}, physical_width: {}, physical_height: {}, subpixel: {:?}, make: {}, model: {}, transform: {:?}",
                 x, y, physical_width, physical_height, subpixel, make, model, transform
             );
+            if let WEnum::Value(transform) = transform {
+                state.output_transforms.retain(|(known, _)| known != output);
+                state.output_transforms.push((output.clone(), transform));
+            }
         }
     }
 }
@@ -84,14 +326,53 @@ impl Dispatch<WlRegistry, ()> for State {
             // info!("[{}] {} (v{})", name, interface, version);
             match &interface[..] {
                 "wl_compositor" => {
-                    data.compositor =
-                        Some(registry.bind::<wl_compositor::WlCompositor, _, _>(name, 1, qh, ()));
+                    // version 2 brings buffer transforms, version 4 damaging in buffer coordinates
+                    data.compositor = Some(registry.bind::<wl_compositor::WlCompositor, _, _>(
+                        name,
+                        version.min(4),
+                        qh,
+                        (),
+                    ));
                     info!("Bound compositor");
                 }
                 "wl_shm" => {
                     data.wl_shm = Some(registry.bind(name, version, qh, ()));
                     info!("Bound WlShm");
                 }
+                "wl_output" => {
+                    data.outputs
+                        .push(registry.bind(name, version.min(4), qh, ()));
+                    info!("Bound output");
+                }
+                "zwlr_gamma_control_manager_v1" => {
+                    data.gamma_manager = Some(registry.bind(name, 1, qh, ()));
+                    info!("Bound gamma control manager");
+                }
+                "zwlr_foreign_toplevel_manager_v1" => {
+                    // only bound around breaks, otherwise the title changes of all windows would
+                    // pile up while nobody dispatches them
+                    data.toplevel_manager_global = Some((registry.clone(), name, version.min(3)));
+                }
+                "wl_seat" => {
+                    data.seat = Some(registry.bind(name, version.min(7), qh, ()));
+                    info!("Bound seat");
+                }
+                "wp_cursor_shape_manager_v1" => {
+                    data.cursor_shape_manager = Some(registry.bind(name, 1, qh, ()));
+                    info!("Bound cursor shape manager");
+                }
+                "zwp_keyboard_shortcuts_inhibit_manager_v1" => {
+                    data.shortcuts_inhibit_manager = Some(registry.bind(name, 1, qh, ()));
+                    info!("Bound keyboard shortcuts inhibit manager");
+                }
+                "wp_viewporter" => {
+                    data.viewporter = Some(registry.bind(name, 1, qh, ()));
+                    info!("Bound viewporter");
+                }
+                "zwlr_layer_shell_v1" => {
+                    data.layer_shell = Some(registry.bind(name, version.min(4), qh, ()));
+                    info!("Bound layer shell");
+                }
                 "xdg_wm_base" => {
                     data.base =
                         Some(registry.bind::<xdg_wm_base::XdgWmBase, _, _>(name, 1, qh, ()));
@@ -103,16 +384,228 @@ impl Dispatch<WlRegistry, ()> for State {
     }
 }
 
-impl Dispatch<wl_buffer::WlBuffer, ()> for State {
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn event(
+        state: &mut Self,
+        seat: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            if capabilities.contains(wl_seat::Capability::Pointer) && state.pointer.is_none() {
+                let pointer = seat.get_pointer(qh, ());
+                if let Some(manager) = &state.cursor_shape_manager {
+                    state.cursor_shape_device = Some(manager.get_pointer(&pointer, qh, ()));
+                }
+                state.pointer = Some(pointer);
+                info!("Got pointer");
+            }
+
+            if capabilities.contains(wl_seat::Capability::Touch) && state.touch.is_none() {
+                state.touch = Some(seat.get_touch(qh, ()));
+                info!("Got touch");
+            }
+
+            if capabilities.contains(wl_seat::Capability::Keyboard) && state.keyboard.is_none() {
+                state.keyboard = Some(seat.get_keyboard(qh, ()));
+                info!("Got keyboard");
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, ()> for State {
+    fn event(
+        state: &mut Self,
+        pointer: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the overlay is the only surface, so every enter event is about it
+        if let wl_pointer::Event::Enter { serial, .. } = event {
+            match state.cursor_mode {
+                CursorMode::Hidden => pointer.set_cursor(serial, None, 0, 0),
+                CursorMode::Default => match &state.cursor_shape_device {
+                    Some(device) => device.set_shape(serial, Shape::Default),
+                    None => info!("No cursor shape manager, keeping the current cursor"),
+                },
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_touch::WlTouch, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &wl_touch::WlTouch,
+        event: wl_touch::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the countdown is redrawn after every event anyway, so only taps need handling
+        if let wl_touch::Event::Down { .. } = event {
+            if state.tap_to_skip {
+                info!("Overlay was tapped, skipping the break");
+                state.skip_requested = true;
+            } else {
+                info!("Overlay was tapped, but this break can't be skipped by tapping");
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the keys are evdev codes, so Esc can be recognized without a keymap
+        match event {
+            wl_keyboard::Event::Key {
+                key: KEY_ESC,
+                state: WEnum::Value(key_state),
+                ..
+            } if state.tap_to_skip => match key_state {
+                wl_keyboard::KeyState::Pressed => {
+                    state.escape_held_since.get_or_insert_with(Instant::now);
+                }
+                _ => state.escape_held_since = None,
+            },
+            wl_keyboard::Event::Leave { .. } => state.escape_held_since = None,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpCursorShapeManagerV1, ()> for State {
     fn event(
         _: &mut Self,
+        _: &WpCursorShapeManagerV1,
+        _: wp_cursor_shape_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("Cursor shape manager event");
+    }
+}
+
+impl Dispatch<WpCursorShapeDeviceV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WpCursorShapeDeviceV1,
+        _: wp_cursor_shape_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("Cursor shape device event");
+    }
+}
+
+impl Dispatch<ZwpKeyboardShortcutsInhibitManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwpKeyboardShortcutsInhibitManagerV1,
+        _: zwp_keyboard_shortcuts_inhibit_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("Keyboard shortcuts inhibit manager event");
+    }
+}
+
+impl Dispatch<ZwpKeyboardShortcutsInhibitorV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwpKeyboardShortcutsInhibitorV1,
+        event: zwp_keyboard_shortcuts_inhibitor_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the compositor decides when the inhibitor is active, e.g. only while the overlay is focused
+        match event {
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Active => {
+                info!("The compositor's shortcuts are inhibited")
+            }
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Inactive => {
+                info!("The compositor's shortcuts are no longer inhibited")
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WpViewporter, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WpViewporter,
+        event: wp_viewporter::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("wp_viewporter event {event:?}");
+    }
+}
+
+impl Dispatch<WpViewport, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &WpViewport,
+        event: wp_viewport::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("wp_viewport event {event:?}");
+    }
+}
+
+impl Dispatch<wl_buffer::WlBuffer, usize> for State {
+    fn event(
+        state: &mut Self,
         _: &wl_buffer::WlBuffer,
         event: wl_buffer::Event,
+        index: &usize,
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            state.buffers_busy[*index] = false;
+        } else {
+            info!("Buffer event {event:?}");
+        }
+    }
+}
+
+impl Dispatch<wl_callback::WlCallback, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &wl_callback::WlCallback,
+        event: wl_callback::Event,
         _: &(),
         _: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        info!("Buffer event {event:?}");
+        // frame callbacks are the only callbacks that are requested
+        if let wl_callback::Event::Done { .. } = event {
+            state.frame_ready = true;
+        }
     }
 }
 
@@ -163,9 +656,22 @@ impl Dispatch<wl_shm_pool::WlShmPool, ()> for State {
     }
 }
 
-impl Dispatch<wl_surface::WlSurface, ()> for State {
+impl Dispatch<wl_region::WlRegion, ()> for State {
     fn event(
         _: &mut Self,
+        _: &wl_region::WlRegion,
+        event: wl_region::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("wl_region event {event:?}");
+    }
+}
+
+impl Dispatch<wl_surface::WlSurface, ()> for State {
+    fn event(
+        state: &mut Self,
         _: &wl_surface::WlSurface,
         event: wl_surface::Event,
         _: &(),
@@ -173,6 +679,10 @@ impl Dispatch<wl_surface::WlSurface, ()> for State {
         _qh: &QueueHandle<Self>,
     ) {
         info!("wl_surface event {event:?}");
+        // the overlay is the only surface
+        if let wl_surface::Event::Enter { output } = event {
+            state.popup_output = Some(output);
+        }
     }
 }
 
@@ -247,218 +757,825 @@ impl Dispatch<xdg_toplevel::XdgToplevel, ()> for State {
     }
 }
 
-pub fn wait_until_work(
-    socket: &mut UnixDatagram,
-    break_duration: u64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // waiting until the break is over
-    println!("Break time!");
-    let mut breaktime = true;
-    let now = Instant::now();
-    // setting read timeout every time, because outside of every break it's set to a different value
-    socket.set_read_timeout(Some(Duration::from_secs(break_duration)))?;
-
-    while breaktime {
-        let mut buffer = [0; 300];
-        let result = socket.recv_from(&mut buffer);
-        match result {
-            Ok((bytes_read, return_address)) => {
-                assert!(bytes_read > 0);
-                // trimming the last byte, because it's one of the zeros written by us
-                let string_read = str::from_utf8(&buffer[..bytes_read])?;
-
-                let path = return_address
-                    .as_pathname()
-                    .expect("Unable to respond, because the message came from an unbound socket!");
-
-                let remainder = break_duration
-                    .checked_sub(now.elapsed().as_secs())
-                    .unwrap_or(0);
-
-                if string_read == "skip" {
-                    println!("Break was skipped!");
-                    breaktime = false;
-                } else {
-                    if string_read == "get" {
-                        socket.send_to(remainder.to_string().as_bytes(), path)?;
-                    } else {
-                        println!("[break]: Received unknown argument '{string_read}'");
-                    }
-
-                    if remainder > 0 {
-                        socket.set_read_timeout(Some(Duration::from_secs(remainder)))?;
-                        breaktime = true;
-                    } else {
-                        println!("Break is over!");
-                        breaktime = false;
-                    }
-                }
-            }
-            Err(err) if err.kind() == ErrorKind::WouldBlock => {
-                let elapsed = now.elapsed().as_secs();
-                if elapsed < break_duration {
-                    println!("[break]: Read was interrupted after {elapsed} seconds.");
-                    socket.set_read_timeout(Some(Duration::from_secs(break_duration - elapsed)))?;
-                    breaktime = true;
-                } else {
-                    println!("Break is over!");
-                    breaktime = false;
-                }
+impl Dispatch<ZwlrLayerShellV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrLayerShellV1,
+        event: zwlr_layer_shell_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        info!("Layer shell event {event:?}");
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                layer_surface.ack_configure(serial);
+                state.surface_size = Some(SurfaceSize {
+                    width: width as i32,
+                    height: height as i32,
+                });
+                info!("Layer surface configure event to width {width} and height {height}");
             }
-            Err(err) => {
-                let kind = err.kind();
-                panic!("[break]: Unexpected error '{err}' with ErrorKind {kind} reading!");
+            // e.g. because its output was unplugged, the break goes on without it
+            zwlr_layer_surface_v1::Event::Closed => {
+                info!("The compositor closed the overlay's layer surface")
             }
+            _ => info!("Unconfigured layer surface event {event:?}"),
         }
     }
-
-    Ok(())
 }
 
+// evdev code of the Esc key
+const KEY_ESC: u32 = 1;
+const HOLD_TO_SKIP: Duration = Duration::from_secs(3);
+const FADE_STEPS: u32 = 10;
+const FADE_FRAME_DURATION: Duration = Duration::from_millis(30);
+// the overlay starts with two buffers, a third one is only added while the compositor holds both
+const INITIAL_BUFFERS: usize = 2;
+const MAX_BUFFERS: usize = 3;
+
 pub(crate) fn show_popup(
     event_queue: &mut EventQueue<State>,
     data: &mut State,
     qh: &QueueHandle<State>,
     socket: &mut UnixDatagram,
-    break_duration: u64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let wl_surface = data.compositor.as_ref().unwrap().create_surface(&qh, ());
+    break_duration: Seconds,
+    skip: &skip::Policy,
+    layout: &mut Layout,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    let extend_on_activity = data.extend_on_activity;
+    let wl_surface = data.compositor.as_ref().unwrap().create_surface(qh, ());
+    let role = Role::new(data, qh, &wl_surface);
 
-    let xdg_surface = data
-        .base
-        .as_ref()
-        .unwrap()
-        .get_xdg_surface(&wl_surface, &qh, ());
+    // the buffers have an alpha channel for the fade-out, during the break the overlay is opaque
+    set_region(
+        data,
+        qh,
+        &wl_surface,
+        wl_surface::WlSurface::set_opaque_region,
+        true,
+    );
 
-    let xdg_top = xdg_surface.get_toplevel(&qh, ());
-    xdg_top.set_title("Title".to_string());
-    xdg_top.set_app_id("Breaktimer ID".to_string());
-    xdg_top.set_fullscreen(None);
+    let shortcuts_inhibitor = inhibit_shortcuts(data, qh, &wl_surface);
 
     // performing initial commit
     wl_surface.commit();
     // waiting on compositor to react and then acking the configure event
     event_queue.blocking_dispatch(data)?;
 
-    // TODO: creating a pool only needs to be done once, so long as the surface size does not
-    // change -> don't destroy the pool, but instead keep the reference and reuse it
-    let surface_size = data.surface_size.as_ref().unwrap_or(&SurfaceSize {
+    let surface_size = data.surface_size.unwrap_or(SurfaceSize {
         height: 1080,
         width: 1920,
     });
     // FIXME: sometimes the surface size is missing
     // .expect("Surface size was not provided!");
-    let format = choose_format(&data.accepted_formats);
-    let stride = surface_size.width * 4; // always choosing a format of 32 bits
-
-    // TODO: using a file seems inefficient. Can I get a file descriptor of RAM storage?
-    let runtime_dir = env::var("XDG_RUNTIME_DIR")?;
-    let filename = runtime_dir
-        + "/wlbreaktime-pool-"
-        + &surface_size.width.to_string()
-        + "-"
-        + &surface_size.height.to_string()
-        // + "-Xrgb8888"; // TODO: how to get format.to_string()?
-        + &format!("{format:?}"); // HACK: depending on the Debug trait does not sound good
-    //
-    // TODO: * 2 because of double-buffering necessary?
-    let pool_size = surface_size.height * stride * 2;
-
-    draw_checker_board(&filename, surface_size, &format)?;
-    let file = fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(&filename)
-        .unwrap();
-
-    let pool = data
-        .wl_shm
-        .as_ref()
-        .unwrap()
-        .create_pool(file.as_fd(), pool_size, &qh, ());
-
-    let buffer = pool.create_buffer(
-        0,
-        surface_size.width,
-        surface_size.height,
-        stride,
-        format,
-        &qh,
-        (),
-    );
-    info!("Created pool, buffer, xdg_top, xdg_surface and wl_surface!");
+    let (mut renderer, render_size, viewport) =
+        create_renderer(data, qh, &wl_surface, surface_size)?;
+    info!("Created the overlay's surface!");
+
+    // fading the overlay in, unless motion should be reduced
+    let fade_steps = if layout.reduce_motion { 1 } else { FADE_STEPS };
+    let mut canvas = match data.prepared_frame.take() {
+        Some(canvas)
+            if canvas.width == render_size.width as u32
+                && canvas.height == render_size.height as u32 =>
+        {
+            canvas
+        }
+        _ => first_frame(layout, render_size, break_duration),
+    };
 
-    wl_surface.attach(Some(&buffer), 0, 0);
+    let transform = renderer.transform();
+    if transform != wl_output::Transform::Normal {
+        info!("The output is transformed by {transform:?}, transforming the buffer as well");
+    }
+    wl_surface.set_buffer_transform(transform);
+    renderer.present(&canvas, data, qh, &wl_surface)?;
+    // a prepared frame is already uploaded, however the new surface has no content yet
+    wl_surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
     wl_surface.commit();
+    info!(
+        "Committed the overlay's first frame {:?} after the break started",
+        started.elapsed()
+    );
 
     event_queue.blocking_dispatch(data).unwrap();
 
-    wait_until_work(socket, break_duration)?;
+    for step in 2..=fade_steps {
+        std::thread::sleep(FADE_FRAME_DURATION);
+        layout.render(&mut canvas, break_duration.as_secs());
+        canvas.dim(step as f32 / fade_steps as f32);
+        if renderer.present(&canvas, data, qh, &wl_surface)? {
+            wl_surface.commit();
+        }
+        event_queue.roundtrip(data)?;
+    }
+
+    data.escape_held_since = None;
+    data.frame_ready = true;
+    let mut overlay = Overlay {
+        event_queue,
+        data,
+        qh,
+        wl_surface: &wl_surface,
+        renderer: renderer.as_mut(),
+        canvas,
+        layout,
+        rendered: break_duration,
+    };
+    let skipped = wait_until_work(
+        socket,
+        break_duration,
+        extend_on_activity,
+        skip,
+        Some(&mut overlay),
+    )?;
+    // the shortcuts work again during the grace period
+    if let Some(shortcuts_inhibitor) = shortcuts_inhibitor {
+        shortcuts_inhibitor.destroy();
+    }
+
+    // the overlay stays until hide_popup, so the desktop only shows up once it's ready
+    let canvas = overlay.canvas;
+    data.shown_overlay = Some(ShownOverlay {
+        wl_surface,
+        viewport,
+        role,
+        renderer,
+        canvas,
+    });
+    Ok(skipped)
+}
+
+/*
+ * fades the overlay of the last break out over the grace period and destroys it, input already
+ * reaches the windows below while it fades -- without a grace period it's destroyed at once
+ */
+pub(crate) fn hide_popup(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    qh: &QueueHandle<State>,
+    grace_period: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(mut shown) = data.shown_overlay.take() else {
+        return Ok(());
+    };
+    let wl_surface = &shown.wl_surface;
+
+    if !grace_period.is_zero() {
+        set_region(
+            data,
+            qh,
+            wl_surface,
+            wl_surface::WlSurface::set_input_region,
+            false,
+        );
+        wl_surface.set_opaque_region(None);
+        data.tap_to_skip = false;
 
-    pool.destroy(); // "A buffer will keep a reference to the pool it was created from so it is valid to destroy the pool immediately after creating a buffer from it."
-    buffer.destroy();
-    xdg_top.destroy();
-    xdg_surface.destroy();
-    wl_surface.destroy();
-    info!("Destroyed pool, buffer, xdg_top, xdg_surface and wl_surface!");
+        let started = Instant::now();
+        let mut canvas = shown.canvas.clone();
+        while started.elapsed() < grace_period {
+            canvas.clone_from(&shown.canvas);
+            canvas.fade(1.0 - started.elapsed().as_secs_f32() / grace_period.as_secs_f32());
+            if shown.renderer.present(&canvas, data, qh, wl_surface)? {
+                wl_surface.commit();
+            }
+            event_queue.roundtrip(data)?;
+            std::thread::sleep(FADE_FRAME_DURATION);
+        }
+    }
+    // neither taps nor Esc during the grace period count for the next break
+    data.skip_requested = false;
+    data.escape_held_since = None;
+
+    shown.renderer.release(data);
+    if let Some(viewport) = shown.viewport {
+        viewport.destroy();
+    }
+    shown.role.destroy();
+    shown.wl_surface.destroy();
+    info!("Destroyed the overlay's surface!");
 
     event_queue.flush()?;
     Ok(())
 }
 
-fn choose_format(formats: &Vec<WEnum<Format>>) -> Format {
-    if formats.contains(&WEnum::Value(Format::Xrgb8888)) {
-        return Format::Xrgb8888;
-    } else if formats.contains(&WEnum::Value(Format::Argb8888)) {
-        return Format::Argb8888;
-    } else {
-        error!("Neither Xrgb8888 nor Argb8888 are supported");
-        return Format::Xbgr8888;
+// the renderer, the size the overlay is drawn at and the viewport that scales it up
+type OverlayRenderer = (Box<dyn Renderer>, SurfaceSize, Option<WpViewport>);
+
+/*
+ * the GPU renderer with the gpu feature, unless the GPU can't draw onto the surface, otherwise the
+ * wl_shm buffers -- along with the size the overlay is drawn at and the viewport scaling it up
+ */
+fn create_renderer(
+    data: &mut State,
+    qh: &QueueHandle<State>,
+    wl_surface: &wl_surface::WlSurface,
+    surface_size: SurfaceSize,
+) -> Result<OverlayRenderer, Box<dyn std::error::Error>> {
+    #[cfg(feature = "gpu")]
+    if data.uses_gpu() {
+        let render_size = data.render_size(surface_size);
+        let size = |size: SurfaceSize| (size.width as u32, size.height as u32);
+        if let Some(renderer) = gpu::renderer(
+            &mut data.gpu,
+            wl_surface,
+            size(surface_size),
+            size(render_size),
+        ) {
+            return Ok((Box::new(renderer), render_size, None));
+        }
+    }
+
+    let format = choose_format(&data.accepted_formats);
+    // the buffer is laid out like the output, so rotated outputs don't need to rotate it again
+    let transform = data.popup_transform();
+    let render_size = data.render_size(surface_size);
+    let buffers = take_buffers(data, qh, render_size, format, transform)?;
+    let viewport = scale_up(data, qh, wl_surface, surface_size, render_size);
+    Ok((Box::new(buffers), render_size, viewport))
+}
+
+/*
+ * lets the compositor scale the overlay up to the surface if it's drawn smaller, e.g. at half the
+ * resolution of a 4K output
+ */
+fn scale_up(
+    data: &State,
+    qh: &QueueHandle<State>,
+    wl_surface: &wl_surface::WlSurface,
+    surface_size: SurfaceSize,
+    render_size: SurfaceSize,
+) -> Option<WpViewport> {
+    if (render_size.width, render_size.height) == (surface_size.width, surface_size.height) {
+        return None;
+    }
+    let viewport = data.viewporter.as_ref()?.get_viewport(wl_surface, qh, ());
+    viewport.set_destination(surface_size.width, surface_size.height);
+    info!(
+        "Drawing the overlay at {}x{}, scaled up to {}x{}",
+        render_size.width, render_size.height, surface_size.width, surface_size.height
+    );
+    Some(viewport)
+}
+
+/*
+ * in strict mode, keeps the compositor's shortcuts (e.g. switching workspaces) from leaving the
+ * overlay -- compositors may still reserve some of them or ask the user first
+ */
+fn inhibit_shortcuts(
+    data: &State,
+    qh: &QueueHandle<State>,
+    wl_surface: &wl_surface::WlSurface,
+) -> Option<ZwpKeyboardShortcutsInhibitorV1> {
+    if !data.inhibit_shortcuts {
+        return None;
+    }
+    match (&data.shortcuts_inhibit_manager, &data.seat) {
+        (Some(manager), Some(seat)) => Some(manager.inhibit_shortcuts(wl_surface, seat, qh, ())),
+        (None, _) => {
+            info!("No keyboard shortcuts inhibit manager, the compositor's shortcuts keep working");
+            None
+        }
+        (_, None) => None,
     }
 }
 
-fn draw_checker_board(
-    filename: &str,
-    surface_size: &SurfaceSize,
-    _format: &Format, // TODO: use format to determine what's written
+/*
+ * sets a region of the surface to all of it or to nothing
+ */
+fn set_region(
+    data: &State,
+    qh: &QueueHandle<State>,
+    wl_surface: &wl_surface::WlSurface,
+    set: fn(&wl_surface::WlSurface, Option<&wl_region::WlRegion>),
+    everything: bool,
+) {
+    let region = data.compositor.as_ref().unwrap().create_region(qh, ());
+    if everything {
+        region.add(0, 0, i32::MAX, i32::MAX);
+    }
+    set(wl_surface, Some(&region));
+    region.destroy();
+}
+
+/*
+ * renders and uploads the overlay's first frame ahead of the break, e.g. during the lead-in --
+ * the overlay's size is only known after the first break, so there is nothing to prepare before
+ */
+pub(crate) fn prepare_popup(
+    event_queue: &mut EventQueue<State>,
+    data: &mut State,
+    qh: &QueueHandle<State>,
+    layout: &mut Layout,
+    break_duration: Seconds,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let result = File::create_new(filename);
-    match result {
-        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
-            // do nothing, because the file has already been generated
-            Ok(())
-        }
-        Ok(file) => {
-            let mut buf = BufWriter::new(file);
-            let mut index = 0;
-            while index < surface_size.height * surface_size.width {
-                if index % 2 == 0 {
-                    buf.write(b"FF666666")?;
-                } else {
-                    buf.write(b"FFEEEEEE")?;
+    let started = Instant::now();
+    // the compositor releases the buffers of the last overlay along with its surface
+    event_queue.roundtrip(data)?;
+    let Some(surface_size) = data.surface_size else {
+        info!("The overlay's size is not known yet, so its first frame can't be prepared");
+        return Ok(());
+    };
+
+    let render_size = data.render_size(surface_size);
+    let canvas = first_frame(layout, render_size, break_duration);
+    // the GPU surface only exists along with the overlay, so the frame is uploaded once it's shown
+    if !data.uses_gpu() {
+        let format = choose_format(&data.accepted_formats);
+        let transform = data.popup_transform();
+        let mut buffers = take_buffers(data, qh, render_size, format, transform)?;
+        if let Some(index) = buffers.released(&data.buffers_busy, qh)? {
+            buffers.upload(&canvas, index)?;
+        }
+        data.overlay_buffers = Some(buffers);
+    }
+    data.prepared_frame = Some(canvas);
+    info!(
+        "Prepared the overlay's first frame in {:?}",
+        started.elapsed()
+    );
+    Ok(())
+}
+
+/*
+ * drops the first frame prepared for a break that didn't start, e.g. because it was skipped
+ * during the lead-in -- the buffers are kept, the next preparation reuses them
+ */
+pub(crate) fn discard_prepared_frame(data: &mut State) {
+    data.prepared_frame = None;
+}
+
+/*
+ * the buffers of the last break if they still fit the overlay and one of them was released or
+ * another one can be added, otherwise new ones
+ */
+fn take_buffers(
+    data: &mut State,
+    qh: &QueueHandle<State>,
+    surface_size: SurfaceSize,
+    format: Format,
+    transform: wl_output::Transform,
+) -> Result<Buffers, Box<dyn std::error::Error>> {
+    match data.overlay_buffers.take() {
+        Some(buffers)
+            if buffers.fits(surface_size, format, transform)
+                && !buffers.exhausted(&data.buffers_busy) =>
+        {
+            Ok(buffers)
+        }
+        previous => {
+            if let Some(previous) = previous {
+                previous.destroy();
+            }
+            data.buffers_busy = [false; MAX_BUFFERS];
+            let buffers = Buffers::new(data, qh, surface_size, format, transform)?;
+            info!("Created pool and buffers!");
+            Ok(buffers)
+        }
+    }
+}
+
+/*
+ * the first step of the fade-in, or the whole overlay if motion should be reduced
+ */
+fn first_frame(layout: &mut Layout, surface_size: SurfaceSize, break_duration: Seconds) -> Canvas {
+    let mut canvas = Canvas::new(
+        surface_size.width as u32,
+        surface_size.height as u32,
+        0xFF000000,
+    );
+    let fade_steps = if layout.reduce_motion { 1 } else { FADE_STEPS };
+    layout.set_clock(&Zoned::now());
+    layout.render(&mut canvas, break_duration.as_secs());
+    canvas.dim(1.0 / fade_steps as f32);
+    canvas
+}
+
+/*
+ * the overlay during the break, it's redrawn whenever the compositor is ready for the next frame
+ * and the countdown or the hold progress changed
+ */
+struct Overlay<'a> {
+    event_queue: &'a mut EventQueue<State>,
+    data: &'a mut State,
+    qh: &'a QueueHandle<State>,
+    wl_surface: &'a wl_surface::WlSurface,
+    renderer: &'a mut dyn Renderer,
+    canvas: Canvas,
+    layout: &'a mut Layout,
+    rendered: Seconds,
+}
+
+impl BreakView for Overlay<'_> {
+    fn update(&mut self, remaining: Seconds) -> Result<bool, Box<dyn std::error::Error>> {
+        let hold_progress = self.data.escape_held_since.map_or(0.0, |since| {
+            since.elapsed().as_secs_f32() / HOLD_TO_SKIP.as_secs_f32()
+        });
+        if hold_progress >= 1.0 {
+            info!("Esc was held, skipping the break");
+            self.data.escape_held_since = None;
+            return Ok(true);
+        }
+
+        // the countdown changes every second, the hold progress on every frame
+        let changed =
+            remaining != self.rendered || hold_progress > 0.0 || self.layout.hold_progress > 0.0;
+        if changed && self.data.frame_ready {
+            self.layout.hold_progress = hold_progress;
+            self.layout.set_clock(&Zoned::now());
+            self.layout.render(&mut self.canvas, remaining.as_secs());
+            // without a released buffer, the frame is drawn once the compositor releases one
+            if self
+                .renderer
+                .present(&self.canvas, self.data, self.qh, self.wl_surface)?
+            {
+                self.rendered = remaining;
+                self.wl_surface.frame(self.qh, ());
+                self.data.frame_ready = false;
+                self.wl_surface.commit();
+            }
+        }
+        Ok(std::mem::take(&mut self.data.skip_requested))
+    }
+
+    fn wait(
+        &mut self,
+        socket: &UnixDatagram,
+        timeout: Duration,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        // events that were read along with earlier ones have to be dispatched before reading again
+        let guard = loop {
+            match self.event_queue.prepare_read() {
+                Some(guard) => break guard,
+                None => {
+                    self.event_queue.dispatch_pending(self.data)?;
                 }
-                index += 1;
             }
+        };
+        self.event_queue.flush()?;
+
+        let (socket_readable, events_readable) = {
+            let connection = guard.connection_fd();
+            let mut fds = [
+                PollFd::new(socket, PollFlags::IN),
+                PollFd::new(&connection, PollFlags::IN),
+            ];
+            match poll(&mut fds, Some(&Timespec::try_from(timeout)?)) {
+                Ok(_) => (
+                    fds[0].revents().contains(PollFlags::IN),
+                    !fds[1].revents().is_empty(),
+                ),
+                // e.g. the system woke up from suspension
+                Err(Errno::INTR) => (false, false),
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        // otherwise the guard cancels the read when it's dropped
+        if events_readable {
+            guard.read()?;
+            self.event_queue.dispatch_pending(self.data)?;
+        }
+        Ok(socket_readable)
+    }
+}
+
+/*
+ * two buffers in one pool, every frame is drawn into a buffer the compositor released and only the
+ * parts that changed since that buffer was last drawn into are uploaded -- while the compositor
+ * holds both (e.g. it keeps the last one until the next is shown), the pool grows by a third one
+ * instead of drawing into a buffer that might be on screen
+ */
+#[derive(Debug)]
+struct Buffers {
+    file: File,
+    pool: wl_shm_pool::WlShmPool,
+    buffers: Vec<wl_buffer::WlBuffer>,
+    // in bytes
+    buffer_size: usize,
+    stride: usize,
+    format: Format,
+    transform: wl_output::Transform,
+    // the size of the canvas the buffers were created for, the surface's unless it's scaled up
+    surface_size: (i32, i32),
+    // what changed since each buffer was last drawn into, None if it's up to date
+    stale: Vec<Option<Rect>>,
+    // the canvas as it was presented the last time
+    presented: Vec<u32>,
+}
+
+impl Buffers {
+    fn new(
+        data: &State,
+        qh: &QueueHandle<State>,
+        surface_size: SurfaceSize,
+        format: Format,
+        transform: wl_output::Transform,
+    ) -> Result<Buffers, Box<dyn std::error::Error>> {
+        let (width, height) = if render::swaps_axes(transform) {
+            (surface_size.height, surface_size.width)
+        } else {
+            (surface_size.width, surface_size.height)
+        };
+
+        // TODO: using a file seems inefficient. Can I get a file descriptor of RAM storage?
+        // HACK: depending on the Debug trait does not sound good
+        let filename = paths::pool_file_path(
+            surface_size.width,
+            surface_size.height,
+            &format!("{format:?}"),
+        )?;
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&filename)?;
+
+        let stride = width * 4; // always choosing a format of 32 bits
+        let buffer_size = height * stride;
+        let pool_size = INITIAL_BUFFERS as i32 * buffer_size;
+        file.set_len(pool_size as u64)?;
+
+        let pool = data
+            .wl_shm
+            .as_ref()
+            .unwrap()
+            .create_pool(file.as_fd(), pool_size, qh, ());
+        let buffers = (0..INITIAL_BUFFERS)
+            .map(|index| {
+                pool.create_buffer(
+                    index as i32 * buffer_size,
+                    width,
+                    height,
+                    stride,
+                    format,
+                    qh,
+                    index,
+                )
+            })
+            .collect();
+
+        Ok(Buffers {
+            file,
+            pool,
+            buffers,
+            buffer_size: buffer_size as usize,
+            stride: stride as usize,
+            format,
+            transform,
+            surface_size: (surface_size.width, surface_size.height),
+            stale: vec![None; INITIAL_BUFFERS],
+            presented: Vec::new(),
+        })
+    }
+
+    fn fits(
+        &self,
+        surface_size: SurfaceSize,
+        format: Format,
+        transform: wl_output::Transform,
+    ) -> bool {
+        self.surface_size == (surface_size.width, surface_size.height)
+            && self.format == format
+            && self.transform == transform
+    }
+
+    /*
+     * whether the compositor holds every buffer and there can't be another one
+     */
+    fn exhausted(&self, busy: &[bool; MAX_BUFFERS]) -> bool {
+        self.buffers.len() == MAX_BUFFERS && busy.iter().all(|busy| *busy)
+    }
+
+    /*
+     * the first buffer the compositor is not using, or a new one if it holds all of them -- None
+     * once there are as many buffers as there may be
+     */
+    fn released(
+        &mut self,
+        busy: &[bool; MAX_BUFFERS],
+        qh: &QueueHandle<State>,
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        if let Some(index) = (0..self.buffers.len()).find(|index| !busy[*index]) {
+            return Ok(Some(index));
+        }
+        let index = self.buffers.len();
+        if index == MAX_BUFFERS {
+            return Ok(None);
+        }
+
+        // pools can only grow, the buffers created from it keep their offsets
+        let pool_size = (index + 1) * self.buffer_size;
+        self.file.set_len(pool_size as u64)?;
+        self.pool.resize(pool_size as i32);
+        let width = self.stride / 4;
+        let height = self.buffer_size / self.stride;
+        self.buffers.push(self.pool.create_buffer(
+            (index * self.buffer_size) as i32,
+            width as i32,
+            height as i32,
+            self.stride as i32,
+            self.format,
+            qh,
+            index,
+        ));
+        // nothing was drawn into the new buffer yet
+        let (width, height) = self.surface_size;
+        self.stale.push(Some(Rect {
+            x: 0,
+            y: 0,
+            width: width as u32,
+            height: height as u32,
+        }));
+        info!("The compositor holds all {index} buffers, added another one");
+        Ok(Some(index))
+    }
+
+    /*
+     * uploads the canvas into a released buffer, returns what changed since the last upload
+     */
+    fn upload(
+        &mut self,
+        canvas: &Canvas,
+        index: usize,
+    ) -> Result<Option<Rect>, Box<dyn std::error::Error>> {
+        let damage = canvas.changed_rect(&self.presented);
+        if let Some(damage) = damage {
+            for stale in &mut self.stale {
+                *stale = Some(stale.map_or(damage, |stale| stale.union(damage)));
+            }
+        }
 
-            // TODO: empty part for double-buffering?
-            index = 0;
-            while index < surface_size.height * surface_size.width {
-                buf.write(b"00000000")?;
-                index += 1;
+        if let Some(stale) = self.stale[index].take() {
+            let (region, bytes) = canvas.region_bytes(self.format, self.transform, stale);
+            let row_length = region.width as usize * 4;
+            // full rows are contiguous in the pool, e.g. for the first frame
+            if row_length == self.stride {
+                let offset = index * self.buffer_size + region.y as usize * self.stride;
+                self.file.write_all_at(&bytes, offset as u64)?;
+            } else {
+                for (row, pixels) in bytes.chunks(row_length).enumerate() {
+                    let offset = index * self.buffer_size
+                        + (region.y as usize + row) * self.stride
+                        + region.x as usize * 4;
+                    self.file.write_all_at(pixels, offset as u64)?;
+                }
             }
-            Ok(())
         }
-        Err(err) => {
-            let kind = err.kind();
-            panic!(
-                "Error while trying to create the wayland pool file. Error '{err:?}' with ErrorKind '{kind}'"
+        self.presented.clear();
+        self.presented.extend_from_slice(canvas.pixels());
+        Ok(damage)
+    }
+
+    fn destroy(self) {
+        // "A buffer will keep a reference to the pool it was created from so it is valid to
+        // destroy the pool immediately after creating a buffer from it."
+        self.pool.destroy();
+        for buffer in self.buffers {
+            buffer.destroy();
+        }
+    }
+}
+
+impl Renderer for Buffers {
+    fn transform(&self) -> wl_output::Transform {
+        self.transform
+    }
+
+    /*
+     * uploads the canvas into a released buffer and attaches it with the changed parts damaged,
+     * returns false if the compositor still uses every buffer
+     */
+    fn present(
+        &mut self,
+        canvas: &Canvas,
+        data: &mut State,
+        qh: &QueueHandle<State>,
+        wl_surface: &wl_surface::WlSurface,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(index) = self.released(&data.buffers_busy, qh)? else {
+            return Ok(false);
+        };
+        let damage = self.upload(canvas, index)?;
+
+        wl_surface.attach(Some(&self.buffers[index]), 0, 0);
+        if let Some(damage) = damage {
+            let damage = canvas.buffer_rect(self.transform, damage);
+            wl_surface.damage_buffer(
+                damage.x as i32,
+                damage.y as i32,
+                damage.width as i32,
+                damage.height as i32,
             );
         }
+        data.buffers_busy[index] = true;
+        Ok(true)
+    }
+
+    /*
+     * kept for the next break, the pool is only recreated when the overlay's size changes
+     */
+    fn release(self: Box<Self>, data: &mut State) {
+        data.overlay_buffers = Some(*self);
+    }
+}
+
+/*
+ * an alpha channel is needed to fade the overlay out, without one it fades to black instead
+ */
+fn choose_format(formats: &[WEnum<Format>]) -> Format {
+    if formats.contains(&WEnum::Value(Format::Argb8888)) {
+        Format::Argb8888
+    } else if formats.contains(&WEnum::Value(Format::Xrgb8888)) {
+        Format::Xrgb8888
+    } else {
+        error!("Neither Argb8888 nor Xrgb8888 are supported");
+        Format::Xbgr8888
     }
 }
 
-pub(crate) fn check_for_globals(data: &State) -> Result<(), &'static str> {
+/*
+ * connects to the compositor and binds all globals it advertises
+ */
+pub(crate) fn connect(
+    cursor_mode: CursorMode,
+) -> Result<(EventQueue<State>, State), Box<dyn std::error::Error>> {
+    let connection = Connection::connect_to_env()?;
+    let display = connection.display();
+    let mut event_queue: EventQueue<State> = connection.new_event_queue();
+    let qh = event_queue.handle();
+    let _registry = display.get_registry(&qh, ());
+
+    let mut data = State {
+        wl_shm: None,
+        surface_size: None,
+        accepted_formats: Vec::new(),
+        compositor: None,
+        base: None,
+        layer_shell: None,
+        seat: None,
+        pointer: None,
+        cursor_shape_manager: None,
+        cursor_shape_device: None,
+        cursor_mode,
+        touch: None,
+        keyboard: None,
+        shortcuts_inhibit_manager: None,
+        viewporter: None,
+        render_scale: 1.0,
+        tap_to_skip: false,
+        inhibit_shortcuts: false,
+        popup_on_top: false,
+        extend_on_activity: false,
+        skip_requested: false,
+        escape_held_since: None,
+        frame_ready: true,
+        buffers_busy: [false; MAX_BUFFERS],
+        overlay_buffers: None,
+        #[cfg(feature = "gpu")]
+        gpu: Gpu::default(),
+        prepared_frame: None,
+        shown_overlay: None,
+        outputs: Vec::new(),
+        output_transforms: Vec::new(),
+        popup_output: None,
+        gamma_manager: None,
+        gamma_controls: Vec::new(),
+        toplevel_manager_global: None,
+        toplevel_manager: None,
+        toplevels: Vec::new(),
+    };
+
+    // waiting on compositor to advertise globals
+    event_queue.blocking_dispatch(&mut data)?;
+
+    // make sure all necessary globals have been bound
+    check_for_globals(&data)?;
+
+    Ok((event_queue, data))
+}
+
+fn check_for_globals(data: &State) -> Result<(), &'static str> {
     if data.compositor.is_none() {
         return Err("no compositor");
     }
@@ -0,0 +1,351 @@
+// the overlay for X11 sessions, e.g. the same dotfiles on a machine without Wayland
+//
+// an override-redirect window covers the whole screen, so the window manager neither decorates nor
+// moves it, and the keyboard is grabbed for Esc. it shows the same frames as the Wayland overlay,
+// however without the fades (X has no per-window opacity without a compositing manager) and
+// centered on the whole screen, across the monitors of multi-head setups
+use jiff::Zoned;
+use log::info;
+use rustix::{
+    event::{PollFd, PollFlags, Timespec, poll},
+    io::Errno,
+};
+use std::{
+    env,
+    os::unix::net::UnixDatagram,
+    thread,
+    time::{Duration, Instant},
+};
+use wlbreaktime::duration::Seconds;
+use x11rb::{
+    CURRENT_TIME,
+    connection::{Connection, RequestConnection},
+    protocol::{
+        Event,
+        xproto::{
+            ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, GrabMode, GrabStatus,
+            ImageFormat, ImageOrder, Window, WindowClass,
+        },
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+use crate::{
+    BreakView,
+    layout::Layout,
+    render::{Canvas, Rect},
+    skip, wait_until_work,
+};
+
+// the keycode of Esc on servers with the evdev driver, which offsets the evdev codes by 8
+const KEYCODE_ESC: u8 = 9;
+const HOLD_TO_SKIP: Duration = Duration::from_secs(3);
+// the keyboard can't be grabbed before the window is mapped
+const GRAB_ATTEMPTS: u32 = 10;
+const GRAB_INTERVAL: Duration = Duration::from_millis(50);
+// the header of a PutImage request, the rest of it are the pixels
+const PUT_IMAGE_HEADER: usize = 28;
+
+pub(crate) struct X11 {
+    connection: RustConnection,
+    screen: usize,
+}
+
+/*
+ * whether there is an X server to connect to, XWayland included
+ */
+pub(crate) fn session() -> bool {
+    env::var_os("DISPLAY").is_some()
+}
+
+/*
+ * connects to the X server of $DISPLAY, the frames are copied as they are, so only servers with
+ * 32 bits per pixel in the canvas' byte order are supported
+ */
+pub(crate) fn connect() -> Result<X11, Box<dyn std::error::Error>> {
+    let (connection, screen) = RustConnection::connect(None)?;
+    let setup = connection.setup();
+    let depth = setup.roots[screen].root_depth;
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|format| format.depth == depth)
+        .map(|format| format.bits_per_pixel);
+    if !matches!(depth, 24 | 32)
+        || bits_per_pixel != Some(32)
+        || setup.image_byte_order != ImageOrder::LSB_FIRST
+    {
+        return Err(format!("the screen's depth of {depth} bits is not supported").into());
+    }
+    Ok(X11 { connection, screen })
+}
+
+/*
+ * shows the overlay until the break is over, returns true if it was skipped -- it's removed right
+ * away afterwards, there is no grace period
+ */
+pub(crate) fn show_popup(
+    x11: &X11,
+    socket: &mut UnixDatagram,
+    break_duration: Seconds,
+    tap_to_skip: bool,
+    skip: &skip::Policy,
+    layout: &mut Layout,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let connection = &x11.connection;
+    let screen = &connection.setup().roots[x11.screen];
+    let (width, height) = (screen.width_in_pixels, screen.height_in_pixels);
+
+    let window = connection.generate_id()?;
+    connection.create_window(
+        screen.root_depth,
+        window,
+        screen.root,
+        0,
+        0,
+        width,
+        height,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        screen.root_visual,
+        &CreateWindowAux::new()
+            .override_redirect(1)
+            .background_pixel(screen.black_pixel)
+            .event_mask(
+                EventMask::KEY_PRESS
+                    | EventMask::KEY_RELEASE
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::EXPOSURE,
+            ),
+    )?;
+    let gc = connection.generate_id()?;
+    connection.create_gc(gc, window, &CreateGCAux::new())?;
+    connection.map_window(window)?;
+    connection.flush()?;
+    grab_keyboard(connection, window)?;
+
+    layout.set_clock(&Zoned::now());
+    let mut canvas = Canvas::new(width.into(), height.into(), 0xFF000000);
+    layout.render(&mut canvas, break_duration.as_secs());
+    let mut overlay = Overlay {
+        connection,
+        window,
+        gc,
+        depth: screen.root_depth,
+        canvas,
+        presented: Vec::new(),
+        layout,
+        rendered: break_duration,
+        tap_to_skip,
+        escape_held_since: None,
+        skip_requested: false,
+    };
+    overlay.present()?;
+    info!("Mapped the X11 overlay");
+
+    // the input is only watched through Wayland, so the break is never extended
+    let skipped = wait_until_work(socket, break_duration, false, skip, Some(&mut overlay));
+    // the window goes away even if the break failed, the grab with it
+    connection.destroy_window(window)?;
+    connection.free_gc(gc)?;
+    connection.sync()?;
+    skipped
+}
+
+/*
+ * the window manager might hold the keyboard for a moment, e.g. while a key binding is released
+ */
+fn grab_keyboard(
+    connection: &RustConnection,
+    window: Window,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for _ in 0..GRAB_ATTEMPTS {
+        let status = connection
+            .grab_keyboard(
+                false,
+                window,
+                CURRENT_TIME,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .reply()?
+            .status;
+        if status == GrabStatus::SUCCESS {
+            return Ok(());
+        }
+        thread::sleep(GRAB_INTERVAL);
+    }
+    println!("The keyboard could not be grabbed, Esc doesn't reach the overlay.");
+    Ok(())
+}
+
+/*
+ * the overlay during the break, it's redrawn whenever the countdown or the hold progress changed
+ */
+struct Overlay<'a> {
+    connection: &'a RustConnection,
+    window: Window,
+    gc: u32,
+    depth: u8,
+    canvas: Canvas,
+    // the canvas as it was presented the last time
+    presented: Vec<u32>,
+    layout: &'a mut Layout,
+    rendered: Seconds,
+    tap_to_skip: bool,
+    escape_held_since: Option<Instant>,
+    skip_requested: bool,
+}
+
+impl Overlay<'_> {
+    /*
+     * copies the rows that changed since the last frame to the window, in as many requests as the
+     * server's request size needs
+     */
+    fn present(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(rect) = self.canvas.changed_rect(&self.presented) else {
+            return Ok(());
+        };
+        self.put_rows(rect)?;
+        self.presented.clear();
+        self.presented.extend_from_slice(self.canvas.pixels());
+        Ok(())
+    }
+
+    fn put_rows(&self, rect: Rect) -> Result<(), Box<dyn std::error::Error>> {
+        let width = self.canvas.width as usize;
+        let stride = width * 4;
+        let maximum = self.connection.maximum_request_bytes() - PUT_IMAGE_HEADER;
+        let rows_per_request = (maximum / stride).max(1) as u32;
+        let mut row = rect.y;
+        while row < rect.y + rect.height {
+            let rows = rows_per_request.min(rect.y + rect.height - row);
+            let start = row as usize * width;
+            let bytes: Vec<u8> = self.canvas.pixels()[start..start + rows as usize * width]
+                .iter()
+                .flat_map(|pixel| pixel.to_le_bytes())
+                .collect();
+            self.connection.put_image(
+                ImageFormat::Z_PIXMAP,
+                self.window,
+                self.gc,
+                self.canvas.width as u16,
+                rows as u16,
+                0,
+                row as i16,
+                0,
+                self.depth,
+                &bytes,
+            )?;
+            row += rows;
+        }
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    /*
+     * handles the events that arrived, a key release directly followed by a press at the same time
+     * is the key repeating, so holding Esc isn't interrupted by it
+     */
+    fn dispatch(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut events = Vec::new();
+        while let Some(event) = self.connection.poll_for_event()? {
+            events.push(event);
+        }
+        let mut events = events.into_iter().peekable();
+        while let Some(event) = events.next() {
+            match event {
+                Event::KeyPress(press) if press.detail == KEYCODE_ESC && self.tap_to_skip => {
+                    self.escape_held_since.get_or_insert_with(Instant::now);
+                }
+                Event::KeyRelease(release) if release.detail == KEYCODE_ESC => {
+                    let repeated = matches!(
+                        events.peek(),
+                        Some(Event::KeyPress(press))
+                            if press.detail == release.detail && press.time == release.time
+                    );
+                    if repeated {
+                        events.next();
+                    } else {
+                        self.escape_held_since = None;
+                    }
+                }
+                Event::ButtonPress(_) => {
+                    if self.tap_to_skip {
+                        info!("Overlay was clicked, skipping the break");
+                        self.skip_requested = true;
+                    } else {
+                        info!("Overlay was clicked, but this break can't be skipped by clicking");
+                    }
+                }
+                // e.g. a screensaver was shown above it, everything is copied again
+                Event::Expose(_) => self.presented.clear(),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BreakView for Overlay<'_> {
+    fn update(&mut self, remaining: Seconds) -> Result<bool, Box<dyn std::error::Error>> {
+        let hold_progress = self.escape_held_since.map_or(0.0, |since| {
+            since.elapsed().as_secs_f32() / HOLD_TO_SKIP.as_secs_f32()
+        });
+        if hold_progress >= 1.0 {
+            info!("Esc was held, skipping the break");
+            self.escape_held_since = None;
+            return Ok(true);
+        }
+
+        let changed = remaining != self.rendered
+            || hold_progress > 0.0
+            || self.layout.hold_progress > 0.0
+            || self.presented.is_empty();
+        if changed {
+            self.layout.hold_progress = hold_progress;
+            self.layout.set_clock(&Zoned::now());
+            self.layout.render(&mut self.canvas, remaining.as_secs());
+            self.present()?;
+            self.rendered = remaining;
+        }
+        Ok(std::mem::take(&mut self.skip_requested))
+    }
+
+    fn wait(
+        &mut self,
+        socket: &UnixDatagram,
+        timeout: Duration,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        // the events that were read along with replies are only in the connection's buffer
+        self.dispatch()?;
+        // while Esc is held, the progress is drawn on every frame
+        let timeout = if self.escape_held_since.is_some() {
+            timeout.min(Duration::from_millis(30))
+        } else {
+            timeout
+        };
+
+        let (socket_readable, events_readable) = {
+            let mut fds = [
+                PollFd::new(socket, PollFlags::IN),
+                PollFd::new(self.connection.stream(), PollFlags::IN),
+            ];
+            match poll(&mut fds, Some(&Timespec::try_from(timeout)?)) {
+                Ok(_) => (
+                    fds[0].revents().contains(PollFlags::IN),
+                    !fds[1].revents().is_empty(),
+                ),
+                // e.g. the system woke up from suspension
+                Err(Errno::INTR) => (false, false),
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        if events_readable {
+            self.dispatch()?;
+        }
+        Ok(socket_readable)
+    }
+}
@@ -0,0 +1,104 @@
+// property tests of the protocol: whatever is sent to the daemon's socket, parsing it must not
+// panic -- the fuzz target in fuzz/ feeds it arbitrary bytes for longer than a test can
+use proptest::prelude::*;
+use wlbreaktime::{
+    duration::{Minutes, Seconds},
+    protocol::{self, BATCH_SEPARATOR, MAX_DURATION},
+};
+
+// the commands the helper sends, with and without arguments
+const COMMANDS: [&str; 11] = [
+    "break", "skip", "reset", "get", "status", "stats", "hello", "diag", "set", "focus", "timer",
+];
+
+/*
+ * a command as the helper sends it, optionally with an argument that may well be malformed
+ */
+fn command() -> impl Strategy<Value = String> {
+    (
+        proptest::sample::select(COMMANDS.to_vec()),
+        proptest::option::of("[0-9a-z+-]{1,24}"),
+    )
+        .prop_map(|(command, argument)| match argument {
+            Some(argument) => format!("{command} {argument}"),
+            None => command.to_string(),
+        })
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_do_not_panic(bytes in proptest::collection::vec(any::<u8>(), 0..protocol::MAX_REQUEST_SIZE)) {
+        if let Ok((_, commands)) = protocol::parse_request(&bytes) {
+            for command in commands {
+                let argument = command.split_once(' ').map_or("", |(_, argument)| argument);
+                let _ = protocol::parse_duration(argument);
+                let _ = protocol::parse_seconds(argument);
+            }
+        }
+    }
+
+    #[test]
+    fn requests_are_parsed_back(
+        id in any::<u32>(),
+        commands in proptest::collection::vec(command(), 1..8),
+        acknowledged in any::<bool>(),
+    ) {
+        let command = commands.join(BATCH_SEPARATOR);
+        let message = if acknowledged {
+            protocol::acknowledged_request(id, &command)
+        } else {
+            protocol::request(id, &command)
+        };
+        let (parsed_id, parsed) = protocol::parse_request(message.as_bytes()).unwrap();
+        prop_assert_eq!(parsed_id, id);
+        prop_assert_eq!(parsed, commands);
+        prop_assert_eq!(protocol::wants_acknowledgment(message.as_bytes()), acknowledged);
+    }
+
+    #[test]
+    fn chunks_are_put_back_together(
+        id in any::<u32>(),
+        offset in 0..4usize,
+        character in any::<char>(),
+        count in 0..protocol::CHUNK_SIZE,
+    ) {
+        // the offset moves multibyte characters across the chunks' borders
+        let payload = format!("{}{}", "a".repeat(offset), character.to_string().repeat(count));
+        let mut received = String::new();
+        let chunks = protocol::response_chunks(id, &payload);
+        for (index, chunk) in chunks.iter().enumerate() {
+            let (chunk_id, more, part) = protocol::split_chunk(chunk).unwrap();
+            prop_assert_eq!(chunk_id, id);
+            prop_assert_eq!(more, index + 1 < chunks.len());
+            prop_assert!(part.len() <= protocol::CHUNK_SIZE);
+            received.push_str(part);
+        }
+        prop_assert_eq!(received, payload);
+    }
+
+    #[test]
+    fn durations_never_exceed_the_maximum(argument in "\\PC{0,24}") {
+        for seconds in [protocol::parse_duration(&argument), protocol::parse_seconds(&argument)]
+            .into_iter()
+            .flatten()
+        {
+            prop_assert!(seconds <= MAX_DURATION);
+        }
+    }
+
+    #[test]
+    fn durations_keep_their_unit(number in 0..=MAX_DURATION.as_secs() / 60) {
+        let minutes = Some(Seconds::from(Minutes::new(number)));
+        prop_assert_eq!(protocol::parse_duration(&number.to_string()), minutes);
+        prop_assert_eq!(protocol::parse_duration(&format!("{number}m")), minutes);
+        prop_assert_eq!(protocol::parse_duration(&format!("{number}s")), Some(Seconds::new(number)));
+        prop_assert_eq!(protocol::parse_seconds(&number.to_string()), Some(Seconds::new(number)));
+    }
+
+    #[test]
+    fn overflowing_durations_are_refused(number in MAX_DURATION.as_secs() + 1..) {
+        prop_assert_eq!(protocol::parse_seconds(&number.to_string()), None);
+        prop_assert_eq!(protocol::parse_duration(&format!("{number}s")), None);
+        prop_assert_eq!(protocol::parse_duration(&number.to_string()), None);
+    }
+}
@@ -0,0 +1,174 @@
+// golden images of the overlay: every case is rendered into a canvas, like for a compositor, and
+// compared to the PNG in tests/snapshots/ -- small differences (e.g. from rasterizing the icon on
+// another CPU) are tolerated, anything else fails and leaves the actual image in the target dir
+//
+// after an intended change of the drawing code, run `UPDATE_SNAPSHOTS=1 cargo test --test render`
+// and look at the written images before committing them
+use std::{env, path::PathBuf};
+
+use jiff::Zoned;
+use resvg::tiny_skia::{IntSize, Pixmap};
+
+// the daemon is a binary, so the modules that draw the overlay are included directly
+#[allow(dead_code)]
+#[path = "../src/config.rs"]
+mod config;
+#[allow(dead_code)]
+#[path = "../src/fonts.rs"]
+mod fonts;
+#[allow(dead_code)]
+#[path = "../src/layout.rs"]
+mod layout;
+#[allow(dead_code)]
+#[path = "../src/palette.rs"]
+mod palette;
+#[allow(dead_code)]
+#[path = "../src/render.rs"]
+mod render;
+#[allow(dead_code)]
+#[path = "../src/resources.rs"]
+mod resources;
+
+use config::{Align, Config, PhasePalette, Theme};
+use layout::Layout;
+use render::Canvas;
+
+// how far a channel may be off before the pixel counts as different
+const CHANNEL_TOLERANCE: u8 = 8;
+// how many pixels may be different, in thousandths
+const DIFFERENT_PIXELS_PER_MILLE: usize = 5;
+const REMAINING_SECONDS: u64 = 80;
+
+/*
+ * the overlay of the configuration, rendered like the first frame of a break
+ */
+fn render(config: &Config, width: u32, height: u32, stats: Option<(u64, u64)>) -> Canvas {
+    let mut layout = Layout::new(config, true);
+    if let Some((worked, taken_today)) = stats {
+        layout.set_stats(worked, taken_today);
+    }
+    let mut canvas = Canvas::new(width, height, 0xFF000000);
+    layout.render(&mut canvas, REMAINING_SECONDS);
+    canvas
+}
+
+/*
+ * the canvas as RGBA, the overlay is opaque, so it doesn't matter that tiny-skia expects
+ * premultiplied colors
+ */
+fn to_pixmap(canvas: &Canvas) -> Pixmap {
+    let data = canvas
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let [alpha, red, green, blue] = pixel.to_be_bytes();
+            [red, green, blue, alpha]
+        })
+        .collect();
+    Pixmap::from_vec(data, IntSize::from_wh(canvas.width, canvas.height).unwrap()).unwrap()
+}
+
+fn assert_snapshot(name: &str, canvas: &Canvas) {
+    let actual = to_pixmap(canvas);
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("tests/snapshots/{name}.png"));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        actual.save_png(&path).unwrap();
+        return;
+    }
+
+    let expected = Pixmap::load_png(&path).unwrap_or_else(|err| {
+        panic!(
+            "The snapshot '{}' could not be read ({err}), run with UPDATE_SNAPSHOTS=1 to write it!",
+            path.display()
+        )
+    });
+    let actual_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.png"));
+    if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+        actual.save_png(&actual_path).unwrap();
+        panic!(
+            "The snapshot '{name}' is {}x{}, but the overlay was rendered at {}x{}! See '{}'.",
+            expected.width(),
+            expected.height(),
+            actual.width(),
+            actual.height(),
+            actual_path.display()
+        );
+    }
+
+    let different = expected
+        .data()
+        .chunks_exact(4)
+        .zip(actual.data().chunks_exact(4))
+        .filter(|(expected, actual)| {
+            expected
+                .iter()
+                .zip(actual.iter())
+                .any(|(expected, actual)| expected.abs_diff(*actual) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let pixels = (actual.width() * actual.height()) as usize;
+    if different * 1000 > pixels * DIFFERENT_PIXELS_PER_MILLE {
+        actual.save_png(&actual_path).unwrap();
+        panic!(
+            "{different} of {pixels} pixels differ from the snapshot '{name}'! See '{}'.",
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn countdown() {
+    let config = config::defaults();
+    assert_snapshot("countdown", &render(&config, 640, 360, None));
+}
+
+#[test]
+fn high_contrast() {
+    let mut config = config::defaults();
+    config.high_contrast = true;
+    assert_snapshot("high_contrast", &render(&config, 640, 360, None));
+}
+
+#[test]
+fn light_theme() {
+    let mut config = config::defaults();
+    config.theme = Theme::Light;
+    assert_snapshot("light_theme", &render(&config, 640, 360, Some((1860, 3))));
+}
+
+#[test]
+fn stats_aligned_left() {
+    let mut config = config::defaults();
+    config.popup_align = Align::Left;
+    config.phase_palette = PhasePalette::OkabeIto;
+    assert_snapshot(
+        "stats_aligned_left",
+        &render(&config, 640, 360, Some((1860, 3))),
+    );
+}
+
+// the texts shrink and the icon is left out when there is no room for it
+#[test]
+fn small_output() {
+    let config = config::defaults();
+    assert_snapshot("small_output", &render(&config, 240, 135, Some((1860, 3))));
+}
+
+#[test]
+fn clock() {
+    let mut config = config::defaults();
+    config.popup_clock = Some("%H:%M".to_string());
+    let mut layout = Layout::new(&config, true);
+    layout.set_clock(&"2026-03-14T15:09:00[UTC]".parse::<Zoned>().unwrap());
+    let mut canvas = Canvas::new(640, 360, 0xFF000000);
+    layout.render(&mut canvas, REMAINING_SECONDS);
+    assert_snapshot("clock", &canvas);
+}
+
+#[test]
+fn qr_code() {
+    let mut config = config::defaults();
+    config.popup_qr_url = Some("https://example.com/stretches".to_string());
+    assert_snapshot("qr_code", &render(&config, 1280, 720, None));
+}